@@ -83,6 +83,17 @@ class AutotaggerConfig {
     albumTagging: boolean = false;
     albumTaggingRatio: number = 0.5;
     coverFilename: string | undefined = undefined;
+    dryRun: boolean = false;
+    dryRunReport?: 'json' | 'csv';
+    httpCache: HttpCacheConfig = { enabled: false, ttl: 604800, maxSize: 512 };
+    writeProvenance: boolean = false;
+    // { tag: [platform1, platform2...] }
+    tagPriority: Record<string, string[]> = {};
+    matchScoring: MatchScoringConfig = { enabled: false, title: 1.0, artists: 0.5, remixers: 0.2, version: 0.3, 
+        duration: 0.3, album: 0.2, label: 0.1, releaseYear: 0.1, isrc: 1.0 };
+    review: ReviewConfig = { enabled: false, threshold: 0.9, candidates: 5 };
+    // { ID3 frame: RIFF INFO id }, default mapping if undefined
+    wavInfoMapping?: Record<string, string>;
 
     spotify?: SpotifyConfig;
 
@@ -100,6 +111,66 @@ class AutotaggerConfig {
     }
 }
 
+interface HttpCacheConfig {
+    enabled: boolean;
+    // seconds
+    ttl: number;
+    // MB
+    maxSize: number;
+}
+
+interface MatchScoringConfig {
+    enabled: boolean;
+    title: number;
+    artists: number;
+    remixers: number;
+    version: number;
+    duration: number;
+    album: number;
+    label: number;
+    releaseYear: number;
+    isrc: number;
+}
+
+interface ReviewConfig {
+    enabled: boolean;
+    threshold: number;
+    candidates: number;
+}
+
+/// Similarity of each component of the match (0 - 1), missing if it couldn't be compared
+interface MatchScore {
+    title?: number;
+    artists?: number;
+    remixers?: number;
+    version?: number;
+    duration?: number;
+    album?: number;
+    label?: number;
+    releaseYear?: number;
+    isrc?: number;
+    total: number;
+}
+
+const SCORE_COMPONENTS: { key: keyof MatchScore, label: string }[] = [
+    { key: 'title', label: 'Title' },
+    { key: 'artists', label: 'Artists' },
+    { key: 'remixers', label: 'Remixers' },
+    { key: 'version', label: 'Version' },
+    { key: 'duration', label: 'Duration' },
+    { key: 'album', label: 'Album' },
+    { key: 'label', label: 'Label' },
+    { key: 'releaseYear', label: 'Release year' },
+    { key: 'isrc', label: 'ISRC' },
+];
+
+/// Get lines of the score breakdown
+function scoreBreakdown(score: MatchScore): string[] {
+    return SCORE_COMPONENTS
+        .filter(c => score[c.key] !== undefined && score[c.key] !== null)
+        .map(c => `${c.label}: ${(score[c.key]! * 100).toFixed(0)}%`);
+}
+
 interface SpotifyConfig {
     clientId: string;
     clientSecret: string;
@@ -138,7 +209,22 @@ interface TaggingStatus {
     path: 'string',
     message?: string;
     accuracy?: number;
-    usedShazam: boolean
+    usedShazam: boolean;
+    diff?: TagDiff;
+    score?: MatchScore;
+}
+
+interface TagDiff {
+    fields: { tag: string, before?: string[], after?: string[] }[];
+    pictures: { kind: string, before?: PictureInfo, after?: PictureInfo }[];
+}
+
+interface PictureInfo {
+    kind: string;
+    mime: string;
+    description: string;
+    size: number;
+    hash: number;
 }
 
 enum SupportedTag {
@@ -260,5 +346,5 @@ interface RepoPlatform {
     versions: { [key: string]: number };
 }
 
-export type { AutotaggerPlatform, PlatformInfo, AutotaggerProfile, TaggingStatusWrap, Track, ConfigCallbackResponse, RepoPlatform };
-export { AutotaggerConfig, TaggerStatus, SupportedTag, SUPPORTED_TAGS };
\ No newline at end of file
+export type { AutotaggerPlatform, PlatformInfo, AutotaggerProfile, TaggingStatusWrap, TagDiff, Track, ConfigCallbackResponse, RepoPlatform, MatchScore };
+export { AutotaggerConfig, TaggerStatus, SupportedTag, SUPPORTED_TAGS, SCORE_COMPONENTS, scoreBreakdown };
\ No newline at end of file
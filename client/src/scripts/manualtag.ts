@@ -1,4 +1,4 @@
-import { AutotaggerConfig, Track } from "./autotagger";
+import { AutotaggerConfig, MatchScore, Track } from "./autotagger";
 import { get1t } from "./onetagger";
 import { wsUrl } from "./utils";
 
@@ -98,6 +98,14 @@ interface TrackMatch {
     accuracy: number;
     track: Track;
     reason: string;
+    score?: MatchScore;
+}
+
+/// File with low confidence matches waiting for review
+interface ReviewItem {
+    path: string;
+    added: number;
+    candidates: TrackMatch[];
 }
 
 interface ManualTagError {
@@ -105,5 +113,5 @@ interface ManualTagError {
     error: string;
 }
 
-export type { TrackMatch };
+export type { TrackMatch, ReviewItem };
 export { ManualTag };
@@ -5,7 +5,7 @@ import { Player } from './player';
 import { QTTrack, QuickTag, QuickTagFile } from './quicktag';
 import { Settings } from './settings';
 import { Keybind, Playlist, Spotify, wsUrl } from './utils';
-import { ManualTag } from './manualtag';
+import { ManualTag, ReviewItem } from './manualtag';
 import ExitDialog from '../components/ExitDialog.vue';
 import router from './router';
 import DevToolsVue from '../components/DevTools.vue';
@@ -27,6 +27,7 @@ class OneTagger {
     taggerStatus: Ref<TaggerStatus> = ref(new TaggerStatus());
     autoTaggerPlaylist: Ref<Playlist> = ref({});
     manualTag: Ref<ManualTag> = ref(new ManualTag());
+    reviewQueue: Ref<ReviewItem[]> = ref([]);
 
     // Websocket
     private ws!: WebSocket;
@@ -269,10 +270,37 @@ class OneTagger {
             case 'folderBrowser':
                 this.onFolderBrowserEvent(json);
                 break;
+            // Undo finished
+            case 'undoTagging':
+                Notify.create({
+                    position: 'top-right',
+                    timeout: 5000,
+                    progress: true,
+                    message: `Restored ${json.restored} files` + (json.failed.length > 0 ? `, failed: ${json.failed.length}` : '')
+                });
+                break;
             // Manual tag applied tags
             case 'manualTagApplied':
                 this.manualTag.value._resolveSaving!(json);
                 break;
+            // ReplayGain written
+            case 'replayGain':
+                Notify.create({
+                    position: 'top-right',
+                    timeout: 5000,
+                    progress: true,
+                    message: `ReplayGain written to ${json.results.length} files`
+                });
+                break;
+            // Low confidence matches
+            case 'reviewQueue':
+                this.reviewQueue.value = json.items;
+                break;
+            case 'reviewResolved':
+                if (json.status == 'error')
+                    this.onError(json.error);
+                this.send('reviewQueue');
+                break;
 
             // Callback from config
             case 'configCallback':
@@ -73,7 +73,7 @@ interface QuickTagCustomValue {
 
 interface QuickTagFile {
     path: string;
-    format: 'flac' | 'aiff' | 'mp3' | 'mp4' | 'wav' | 'ogg';
+    format: 'flac' | 'aiff' | 'mp3' | 'mp4' | 'wav' | 'ogg' | 'wv' | 'ape' | 'mpc' | 'dsf' | 'dff';
     title: string;
     artists: string[];
     genres: string[];
@@ -82,6 +82,16 @@ interface QuickTagFile {
     tags: Record<string, string[]>;
     year?: number;
     key?: string;
+    serato?: SeratoMarkers;
+}
+
+/// Serato cue points, loops and beatgrid
+interface SeratoMarkers {
+    cues: { index: number, position: number, color: number[], label: string }[];
+    loops: { index: number, start: number, end: number, color: number[], locked: boolean, label: string }[];
+    color?: number[];
+    bpmLock: boolean;
+    beatgrid: { position: number, beats?: number, bpm?: number }[];
 }
 
 /// Custom Tag chips
@@ -272,7 +282,7 @@ class QTMultiTrack {
 class QTTrack implements QuickTagFile {
     // QuickTagFile
     path!: string;
-    format!: "flac" | "aiff" | "mp3" | "mp4" | "wav" | "ogg";
+    format!: "flac" | "aiff" | "mp3" | "mp4" | "wav" | "ogg" | "wv" | "ape" | "mpc" | "dsf" | "dff";
     title!: string;
     artists!: string[];
     genres!: string[];
@@ -281,6 +291,7 @@ class QTTrack implements QuickTagFile {
     tags!: Record<string, string[]>;
     year?: number | undefined;
     key?: string | undefined;
+    serato?: SeratoMarkers | undefined;
 
     // QTTrack
     mood?: string;
@@ -727,5 +738,5 @@ function keyColor(key?: string) {
 }
 
 
-export type { QuickTagFile, QuickTagMood, QuickTagGenre, QuickTagCustom, CustomTagInfo };
+export type { QuickTagFile, QuickTagMood, QuickTagGenre, QuickTagCustom, CustomTagInfo, SeratoMarkers };
 export { QuickTag, QuickTagSettings, QTTrack, EnergyTag, PLACEHOLDER_IMG, CAMELOT_KEYS, KEY_COLORS, OPENKEY_KEYS, keyColor };
\ No newline at end of file
@@ -56,6 +56,8 @@ class RenamerSettings {
     copy: boolean = false;
     subfolders: boolean = true;
     overwrite: boolean = false;
+    collision?: 'suffix' | 'skip' | 'overwrite';
+    companions?: { sidecars: string[], folder: string[] };
 }
 
 class AudioFeaturesSettings {
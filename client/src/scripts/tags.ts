@@ -121,4 +121,30 @@ const ID3 = [
     "MVNM (Movement Name)"
 ];
 
-export { ABSTRACTIONS, MP4, VORBIS, ID3 };
\ No newline at end of file
+const APE = [
+    "Album",
+    "Album Artist",
+    "Comment",
+    "Composer",
+    "Conductor",
+    "Genre",
+    "Publisher",
+    "Lyrics",
+    "Subtitle",
+    "REMIXER",
+];
+
+// Vorbis names which use different APE item key, has to match `Field::ape` in onetagger-tag
+const APE_KEYS: Record<string, string> = {
+    "TITLE": "Title",
+    "ARTIST": "Artist",
+    "ALBUMARTIST": "Album Artist",
+    "ALBUM": "Album",
+    "GENRE": "Genre",
+    "PUBLISHER": "Publisher",
+    "SUBTITLE": "Subtitle",
+    "TRACKNUMBER": "Track",
+    "DISCNUMBER": "Disc",
+};
+
+export { ABSTRACTIONS, MP4, VORBIS, ID3, APE, APE_KEYS };
\ No newline at end of file
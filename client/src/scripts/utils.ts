@@ -1,3 +1,5 @@
+import { APE_KEYS } from './tags';
+
 // Returns the WebSocket server URL
 function wsUrl(): string {
     return import.meta.env.DEV ? `ws://${window.location.hostname}:36913/ws` : `ws://${window.location.host}/ws`;
@@ -48,11 +50,17 @@ class FrameName {
             case 'aif':
             case 'id3':
             case 'wav':
+            case 'dsf':
+            case 'dff':
                 return this.id3;
             case 'flac':
             case 'ogg':
             case 'vorbis':
                 return this.vorbis;
+            case 'wv':
+            case 'ape':
+            case 'mpc':
+                return APE_KEYS[this.vorbis.toUpperCase()] ?? this.vorbis;
             case 'mp4':
             case 'm4a':
                 return this.mp4;
@@ -0,0 +1,162 @@
+use std::collections::{HashMap, HashSet};
+use std::hash::Hasher;
+use std::path::{Path, PathBuf};
+use anyhow::Error;
+use serde::{Serialize, Deserialize};
+use onetagger_tagger::{TaggerConfig, TrackMatch};
+use onetagger_shared::Settings;
+use onetagger_platforms::cache::StableHasher;
+
+use crate::{TaggingStatus, TaggingState};
+
+/// Persistent progress of an Autotagger run, so it can be resumed after crash or stop
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TaggerCheckpoint {
+    pub run_id: String,
+    pub config_hash: u64,
+    /// All the files of the run
+    pub files: Vec<PathBuf>,
+    /// platform: progress
+    pub platforms: HashMap<String, PlatformProgress>,
+    /// Matches of all platforms (in platform order), which get composed by tag priority
+    #[serde(default)]
+    pub matches: HashMap<PathBuf, Vec<TrackMatch>>,
+    /// Override of the checkpoints folder
+    #[serde(skip)]
+    folder: Option<PathBuf>,
+}
+
+impl TaggerCheckpoint {
+    /// Create new empty checkpoint
+    pub fn new(run_id: &str, config: &TaggerConfig, files: &[PathBuf]) -> TaggerCheckpoint {
+        TaggerCheckpoint {
+            run_id: run_id.to_string(),
+            config_hash: Self::config_hash(config),
+            files: files.to_vec(),
+            platforms: HashMap::new(),
+            matches: HashMap::new(),
+            folder: None,
+        }
+    }
+
+    /// Load checkpoint by run ID and make sure it was created with the same config
+    pub fn load(run_id: &str, config: &TaggerConfig) -> Result<TaggerCheckpoint, Error> {
+        Self::load_from(None, run_id, config)
+    }
+
+    /// Load checkpoint from folder, `None` = default folder
+    pub fn load_from(folder: Option<&Path>, run_id: &str, config: &TaggerConfig) -> Result<TaggerCheckpoint, Error> {
+        let data = std::fs::read(Self::checkpoint_path(folder, run_id)?)?;
+        let mut checkpoint: TaggerCheckpoint = serde_json::from_slice(&data)?;
+        if checkpoint.config_hash != Self::config_hash(config) {
+            return Err(anyhow!("Config of run {run_id} doesn't match the current config!"));
+        }
+        checkpoint.folder = folder.map(|f| f.to_owned());
+        Ok(checkpoint)
+    }
+
+    /// Store the checkpoint in a different folder than the default one
+    pub fn set_folder(&mut self, folder: impl AsRef<Path>) {
+        self.folder = Some(folder.as_ref().to_owned());
+    }
+
+    /// Get path to the checkpoint file, `None` = `checkpoints` in the settings folder
+    pub fn checkpoint_path(folder: Option<&Path>, run_id: &str) -> Result<PathBuf, Error> {
+        let folder = match folder {
+            Some(folder) => folder.to_owned(),
+            None => Settings::get_folder()?.join("checkpoints"),
+        };
+        if !folder.exists() {
+            std::fs::create_dir_all(&folder)?;
+        }
+        Ok(folder.join(format!("{run_id}.json")))
+    }
+
+    /// Hash of the config. Serialized through Value to get sorted keys, the hash is stable across builds.
+    /// Path is ignored, because the files are stored in the checkpoint
+    pub fn config_hash(config: &TaggerConfig) -> u64 {
+        let json = serde_json::to_value(TaggerConfig { path: None, ..config.clone() }).map(|v| v.to_string()).unwrap_or_default();
+        let mut hasher = StableHasher::default();
+        hasher.write(json.as_bytes());
+        hasher.finish()
+    }
+
+    /// Save to disk
+    pub fn save(&self) -> Result<(), Error> {
+        let path = Self::checkpoint_path(self.folder.as_deref(), &self.run_id)?;
+        // Write into temp file first, so crash while saving doesn't corrupt it
+        let tmp = path.with_extension("json.tmp");
+        std::fs::write(&tmp, serde_json::to_vec(self)?)?;
+        std::fs::rename(&tmp, &path)?;
+        Ok(())
+    }
+
+    /// Run finished, checkpoint is no longer needed
+    pub fn remove(&self) -> Result<(), Error> {
+        let path = Self::checkpoint_path(self.folder.as_deref(), &self.run_id)?;
+        if path.exists() {
+            std::fs::remove_file(path)?;
+        }
+        Ok(())
+    }
+
+    /// Get progress of platform
+    pub fn platform(&mut self, platform: &str) -> &mut PlatformProgress {
+        self.platforms.entry(platform.to_string()).or_default()
+    }
+
+    /// Files which were tagged successfully on any platform
+    pub fn succeeded(&self) -> HashSet<PathBuf> {
+        self.platforms.values().flat_map(|p| p.succeeded.iter().cloned()).collect()
+    }
+
+    /// Files which failed on every platform they were processed by
+    pub fn failed(&self) -> HashSet<PathBuf> {
+        let succeeded = self.succeeded();
+        self.platforms.values().flat_map(|p| p.failed.iter().cloned()).filter(|f| !succeeded.contains(f)).collect()
+    }
+}
+
+/// Progress of single platform
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct PlatformProgress {
+    pub processed: HashSet<PathBuf>,
+    pub succeeded: HashSet<PathBuf>,
+    pub failed: HashSet<PathBuf>,
+    /// All files were processed
+    pub done: bool,
+}
+
+impl PlatformProgress {
+    /// Save the tagging result
+    pub fn record(&mut self, status: &TaggingStatus) {
+        self.processed.insert(status.path.to_owned());
+        match status.status {
+            TaggingState::Ok => { self.succeeded.insert(status.path.to_owned()); },
+            TaggingState::Error => { self.failed.insert(status.path.to_owned()); },
+            TaggingState::Skipped => {}
+        }
+    }
+}
+
+/// Checkpoint can be resumed only with the same config
+#[test]
+fn checkpoint_config() {
+    let config = TaggerConfig::default();
+    let other = TaggerConfig { overwrite: !config.overwrite, ..config.clone() };
+    assert_eq!(TaggerCheckpoint::config_hash(&config), TaggerCheckpoint::config_hash(&config.clone()));
+    assert_eq!(TaggerCheckpoint::config_hash(&config), TaggerCheckpoint::config_hash(&TaggerConfig { path: Some(PathBuf::from("/music")), ..config.clone() }));
+    assert_ne!(TaggerCheckpoint::config_hash(&config), TaggerCheckpoint::config_hash(&other));
+
+    let folder = std::env::temp_dir().join("onetagger-checkpoint-test");
+    let mut checkpoint = TaggerCheckpoint::new(&format!("test-{}", timestamp!()), &config, &[PathBuf::from("a.mp3")]);
+    checkpoint.set_folder(&folder);
+    checkpoint.save().unwrap();
+    assert!(folder.join(format!("{}.json", checkpoint.run_id)).exists());
+    assert_eq!(TaggerCheckpoint::load_from(Some(&folder), &checkpoint.run_id, &config).unwrap().files, checkpoint.files);
+    assert!(TaggerCheckpoint::load_from(Some(&folder), &checkpoint.run_id, &other).is_err());
+    checkpoint.remove().unwrap();
+    std::fs::remove_dir_all(&folder).ok();
+}
@@ -0,0 +1,183 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use serde::{Serialize, Deserialize};
+use onetagger_tag::{TagImpl, CoverType, Picture};
+
+/// State of the tag at some point, used for generating diffs
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TagSnapshot {
+    pub tags: HashMap<String, Vec<String>>,
+    pub pictures: Vec<PictureInfo>,
+}
+
+impl TagSnapshot {
+    /// Take snapshot of the tag
+    pub fn from_tag(tag: &dyn TagImpl) -> TagSnapshot {
+        TagSnapshot {
+            tags: tag.all_tags(),
+            pictures: tag.get_art().iter().map(PictureInfo::from_picture).collect(),
+        }
+    }
+
+    /// Generate list of changes between self and `after`
+    pub fn diff(&self, after: &TagSnapshot) -> TagDiff {
+        // Fields
+        let mut fields = vec![];
+        for (tag, value) in &after.tags {
+            let before = self.tags.get(tag);
+            if before != Some(value) {
+                fields.push(FieldChange { tag: tag.to_string(), before: before.cloned(), after: Some(value.clone()) });
+            }
+        }
+        for (tag, value) in &self.tags {
+            if !after.tags.contains_key(tag) {
+                fields.push(FieldChange { tag: tag.to_string(), before: Some(value.clone()), after: None });
+            }
+        }
+        fields.sort_by(|a, b| a.tag.cmp(&b.tag));
+
+        // Pictures, compared by type
+        let mut pictures = vec![];
+        for kind in CoverType::types() {
+            let before = self.pictures.iter().find(|p| p.kind == kind);
+            let after = after.pictures.iter().find(|p| p.kind == kind);
+            if before != after {
+                pictures.push(PictureChange { kind, before: before.cloned(), after: after.cloned() });
+            }
+        }
+
+        TagDiff { fields, pictures }
+    }
+}
+
+/// Picture metadata without the data itself
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct PictureInfo {
+    pub kind: CoverType,
+    pub mime: String,
+    pub description: String,
+    pub size: usize,
+    /// Hash of the data, to detect replaced pictures
+    pub hash: u64,
+}
+
+impl PictureInfo {
+    /// Create from picture
+    pub fn from_picture(picture: &Picture) -> PictureInfo {
+        let mut hasher = DefaultHasher::new();
+        picture.data.hash(&mut hasher);
+        PictureInfo {
+            kind: picture.kind.clone(),
+            mime: picture.mime.to_string(),
+            description: picture.description.to_string(),
+            size: picture.data.len(),
+            hash: hasher.finish()
+        }
+    }
+}
+
+/// Changes which were (or would be) made to a tag
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct TagDiff {
+    pub fields: Vec<FieldChange>,
+    pub pictures: Vec<PictureChange>,
+}
+
+impl TagDiff {
+    /// Are there any changes
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty() && self.pictures.is_empty()
+    }
+}
+
+/// Raw tag change, `None` = tag not present
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FieldChange {
+    pub tag: String,
+    pub before: Option<Vec<String>>,
+    pub after: Option<Vec<String>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PictureChange {
+    pub kind: CoverType,
+    pub before: Option<PictureInfo>,
+    pub after: Option<PictureInfo>,
+}
+
+/// Single file entry in the dry run report
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DryRunReportEntry {
+    pub path: PathBuf,
+    pub platform: String,
+    pub diff: TagDiff,
+}
+
+impl DryRunReportEntry {
+    /// Generate CSV report with one line per changed field / picture
+    pub fn to_csv(entries: &[DryRunReportEntry]) -> String {
+        let mut out = String::from("path,platform,tag,before,after\r\n");
+        for entry in entries {
+            let path = entry.path.to_string_lossy();
+            for field in &entry.diff.fields {
+                let before = field.before.as_ref().map(|v| v.join("; ")).unwrap_or_default();
+                let after = field.after.as_ref().map(|v| v.join("; ")).unwrap_or_default();
+                out.push_str(&csv_line(&[&path, &entry.platform, &field.tag, &before, &after]));
+            }
+            for picture in &entry.diff.pictures {
+                let kind = format!("PICTURE:{:?}", picture.kind);
+                let before = picture.before.as_ref().map(|p| format!("{} ({} bytes)", p.mime, p.size)).unwrap_or_default();
+                let after = picture.after.as_ref().map(|p| format!("{} ({} bytes)", p.mime, p.size)).unwrap_or_default();
+                out.push_str(&csv_line(&[&path, &entry.platform, &kind, &before, &after]));
+            }
+        }
+        out
+    }
+}
+
+/// Escape and join CSV values
+fn csv_line(values: &[&str]) -> String {
+    let values = values.iter().map(|v| {
+        if v.contains(',') || v.contains('"') || v.contains('\n') || v.contains('\r') {
+            format!("\"{}\"", v.replace('"', "\"\""))
+        } else {
+            v.to_string()
+        }
+    }).collect::<Vec<_>>();
+    format!("{}\r\n", values.join(","))
+}
+
+
+/// Diff generation test
+#[test]
+fn tag_snapshot_diff() {
+    let before = TagSnapshot {
+        tags: HashMap::from([
+            ("TITLE".to_string(), vec!["Title".to_string()]),
+            ("GENRE".to_string(), vec!["House".to_string()]),
+            ("COMMENT".to_string(), vec!["Old".to_string()]),
+        ]),
+        pictures: vec![],
+    };
+    let mut after = before.clone();
+    after.tags.insert("GENRE".to_string(), vec!["Techno".to_string()]);
+    after.tags.insert("BPM".to_string(), vec!["128".to_string()]);
+    after.tags.remove("COMMENT");
+
+    let diff = before.diff(&after);
+    assert!(diff.pictures.is_empty());
+    let tags = diff.fields.iter().map(|f| f.tag.as_str()).collect::<Vec<_>>();
+    assert_eq!(tags, vec!["BPM", "COMMENT", "GENRE"]);
+    assert!(diff.fields[0].before.is_none());
+    assert!(diff.fields[1].after.is_none());
+    assert!(before.diff(&before).is_empty());
+    assert_eq!(csv_line(&["a,b", "c\"d", "e"]), "\"a,b\",\"c\"\"d\",e\r\n");
+}
@@ -0,0 +1,330 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use anyhow::Error;
+use serde::{Serialize, Deserialize};
+use onetagger_tag::AudioFileFormat;
+use onetagger_player::fingerprint::Fingerprint;
+use onetagger_tagger::{AudioFileInfo, MatchingUtils};
+
+use crate::AudioFileInfoImpl;
+
+/// Options for finding duplicates
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct DuplicatesConfig {
+    pub path: PathBuf,
+    pub include_subfolders: bool,
+    /// Same ISRC
+    pub isrc: bool,
+    /// Same platform track ID written by the Autotagger (`{PLATFORM}_TRACK_ID`)
+    pub track_id: bool,
+    /// Same normalized artists & title
+    pub metadata: bool,
+    /// Max duration difference (in seconds) for metadata & fingerprint matches
+    pub duration_tolerance: u64,
+    /// Compare audio fingerprints (slow)
+    pub fingerprint: bool,
+    /// Min fingerprint similarity (0 - 1)
+    pub fingerprint_threshold: f64,
+}
+
+impl Default for DuplicatesConfig {
+    fn default() -> Self {
+        Self {
+            path: PathBuf::new(),
+            include_subfolders: true,
+            isrc: true,
+            track_id: true,
+            metadata: true,
+            duration_tolerance: 3,
+            fingerprint: false,
+            fingerprint_threshold: 0.9,
+        }
+    }
+}
+
+/// Why were the files grouped
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+pub enum DuplicateReason {
+    #[serde(rename = "isrc")]
+    ISRC,
+    TrackId,
+    Metadata,
+    Fingerprint
+}
+
+/// File in duplicate group
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DuplicateFile {
+    pub path: PathBuf,
+    pub format: AudioFileFormat,
+    pub title: Option<String>,
+    pub artists: Vec<String>,
+    /// In bytes
+    pub size: u64,
+    /// In seconds
+    pub duration: Option<u64>,
+    /// Average bitrate in kbps, estimated from file size
+    pub bitrate: Option<u64>,
+}
+
+impl DuplicateFile {
+    fn new(info: &AudioFileInfo) -> DuplicateFile {
+        let size = std::fs::metadata(&info.path).map(|m| m.len()).unwrap_or(0);
+        let duration = info.duration.map(|d| d.as_secs()).filter(|d| *d > 0);
+        DuplicateFile {
+            path: info.path.to_owned(),
+            format: info.format.clone(),
+            title: info.title.clone(),
+            artists: info.artists.clone(),
+            size,
+            duration,
+            bitrate: duration.map(|d| size * 8 / d / 1000),
+        }
+    }
+
+    /// Is lossless format
+    pub fn lossless(&self) -> bool {
+        matches!(self.format, AudioFileFormat::FLAC | AudioFileFormat::WAV | AudioFileFormat::AIFF | AudioFileFormat::WV | AudioFileFormat::APE
+            | AudioFileFormat::DSF | AudioFileFormat::DFF)
+    }
+
+    /// For sorting by quality, lossless first, then by bitrate
+    fn quality(&self) -> (bool, u64, u64) {
+        (self.lossless(), self.bitrate.unwrap_or(0), self.size)
+    }
+}
+
+/// Group of the same tracks, sorted by quality (first is the best copy)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DuplicateGroup {
+    pub files: Vec<DuplicateFile>,
+    pub reasons: Vec<DuplicateReason>,
+}
+
+impl DuplicateGroup {
+    /// Best quality copy
+    pub fn best(&self) -> &DuplicateFile {
+        &self.files[0]
+    }
+
+    /// All files except the best copy
+    pub fn rest(&self) -> impl Iterator<Item = &DuplicateFile> {
+        self.files.iter().skip(1)
+    }
+}
+
+pub struct Duplicates;
+
+impl Duplicates {
+    /// Find groups of duplicate files
+    pub fn find(config: &DuplicatesConfig) -> Result<Vec<DuplicateGroup>, Error> {
+        let files = AudioFileInfo::get_file_list(&config.path, config.include_subfolders);
+        info!("Looking for duplicates in {} files", files.len());
+        let infos: Vec<AudioFileInfo> = files.iter().filter_map(|path| {
+            match AudioFileInfo::load_file(path, None, None) {
+                Ok(mut info) => {
+                    info.load_duration();
+                    // Unreadable audio
+                    if info.duration.map(|d| d.is_zero()).unwrap_or(true) {
+                        warn!("Failed reading duration of {path:?}, skipping");
+                        return None;
+                    }
+                    Some(info)
+                },
+                Err(e) => {
+                    warn!("Failed loading {path:?}: {e}");
+                    None
+                }
+            }
+        }).collect();
+        Ok(Self::group(&infos, config))
+    }
+
+    /// Group already loaded files
+    pub fn group(infos: &[AudioFileInfo], config: &DuplicatesConfig) -> Vec<DuplicateGroup> {
+        let mut groups = UnionFind::new(infos.len());
+        let durations: Vec<Option<u64>> = infos.iter().map(|i| i.duration.map(|d| d.as_secs()).filter(|d| *d > 0)).collect();
+        let duration_ok = |a: usize, b: usize| match (durations[a], durations[b]) {
+            (Some(a), Some(b)) => a.abs_diff(b) <= config.duration_tolerance,
+            _ => true
+        };
+
+        // Exact keys
+        let mut keys: HashMap<(DuplicateReason, String), Vec<usize>> = HashMap::new();
+        for (i, info) in infos.iter().enumerate() {
+            if config.isrc {
+                if let Some(isrc) = info.isrc.as_ref().map(|i| i.trim().to_uppercase()).filter(|i| !i.is_empty()) {
+                    keys.entry((DuplicateReason::ISRC, isrc)).or_default().push(i);
+                }
+            }
+            if config.track_id {
+                for (tag, values) in &info.tags {
+                    let tag = tag.to_uppercase();
+                    if !tag.ends_with("_TRACK_ID") {
+                        continue;
+                    }
+                    if let Some(value) = values.first().filter(|v| !v.trim().is_empty()) {
+                        keys.entry((DuplicateReason::TrackId, format!("{tag}:{}", value.trim()))).or_default().push(i);
+                    }
+                }
+            }
+            if config.metadata {
+                if let Some(key) = Self::metadata_key(info) {
+                    keys.entry((DuplicateReason::Metadata, key)).or_default().push(i);
+                }
+            }
+        }
+        for ((reason, _), files) in keys {
+            for (n, a) in files.iter().enumerate() {
+                for b in &files[n + 1..] {
+                    if reason != DuplicateReason::Metadata || duration_ok(*a, *b) {
+                        groups.union(*a, *b, reason);
+                    }
+                }
+            }
+        }
+
+        // Fingerprints, compare only files with similar duration
+        if config.fingerprint {
+            let fingerprints: Vec<Option<Fingerprint>> = infos.iter().map(|info| {
+                match Fingerprint::from_path(&info.path) {
+                    Ok(f) => Some(f),
+                    Err(e) => {
+                        warn!("Failed fingerprinting {:?}: {e}", info.path);
+                        None
+                    }
+                }
+            }).collect();
+            let mut order: Vec<usize> = (0..infos.len()).filter(|i| fingerprints[*i].is_some() && durations[*i].is_some()).collect();
+            order.sort_by_key(|i| durations[*i]);
+            for (n, a) in order.iter().enumerate() {
+                for b in &order[n + 1..] {
+                    if !duration_ok(*a, *b) {
+                        break;
+                    }
+                    let similarity = fingerprints[*a].as_ref().unwrap().similarity(fingerprints[*b].as_ref().unwrap());
+                    if similarity >= config.fingerprint_threshold {
+                        groups.union(*a, *b, DuplicateReason::Fingerprint);
+                    }
+                }
+            }
+        }
+
+        // Collect groups
+        let mut output: HashMap<usize, DuplicateGroup> = HashMap::new();
+        for (i, info) in infos.iter().enumerate() {
+            let root = groups.find(i);
+            output.entry(root).or_insert(DuplicateGroup { files: vec![], reasons: vec![] }).files.push(DuplicateFile::new(info));
+        }
+        let mut output: Vec<DuplicateGroup> = output.into_iter().filter(|(_, g)| g.files.len() > 1).map(|(root, mut group)| {
+            group.files.sort_by(|a, b| b.quality().cmp(&a.quality()));
+            group.reasons = groups.reasons.remove(&root).unwrap_or_default();
+            group
+        }).collect();
+        output.sort_by(|a, b| a.best().path.cmp(&b.best().path));
+        output
+    }
+
+    /// Normalized artists & title
+    fn metadata_key(info: &AudioFileInfo) -> Option<String> {
+        let title = MatchingUtils::clean_title_matching(info.title.as_ref()?);
+        let mut artists: Vec<String> = info.artists.iter()
+            .map(|a| MatchingUtils::clean_title_matching(a))
+            .filter(|a| !a.is_empty())
+            .collect();
+        if title.is_empty() || artists.is_empty() {
+            return None;
+        }
+        artists.sort();
+        Some(format!("{}|{title}", artists.join(",")))
+    }
+
+    /// Move all files except the best copy of each group to trash. Returns the trashed files
+    pub fn trash(groups: &[DuplicateGroup]) -> Result<Vec<PathBuf>, Error> {
+        let paths: Vec<PathBuf> = groups.iter().flat_map(|g| g.rest().map(|f| f.path.to_owned())).collect();
+        for path in &paths {
+            info!("Moving duplicate to trash: {path:?}");
+        }
+        trash::delete_all(&paths)?;
+        Ok(paths)
+    }
+}
+
+/// Disjoint set of file indices, with the reasons of each set
+struct UnionFind {
+    parents: Vec<usize>,
+    reasons: HashMap<usize, Vec<DuplicateReason>>,
+}
+
+impl UnionFind {
+    fn new(len: usize) -> UnionFind {
+        UnionFind { parents: (0..len).collect(), reasons: HashMap::new() }
+    }
+
+    fn find(&mut self, i: usize) -> usize {
+        let mut root = i;
+        while self.parents[root] != root {
+            root = self.parents[root];
+        }
+        self.parents[i] = root;
+        root
+    }
+
+    fn union(&mut self, a: usize, b: usize, reason: DuplicateReason) {
+        let (a, b) = (self.find(a), self.find(b));
+        let mut reasons = self.reasons.remove(&a).unwrap_or_default();
+        if a != b {
+            reasons.extend(self.reasons.remove(&b).unwrap_or_default());
+            self.parents[b] = a;
+        }
+        reasons.push(reason);
+        reasons.sort_by_key(|r| *r as u8);
+        reasons.dedup();
+        self.reasons.insert(a, reasons);
+    }
+}
+
+/// Group by ISRC, track ID and metadata with duration tolerance
+#[test]
+fn group_duplicates() {
+    use std::time::Duration;
+    use onetagger_tagger::FileTaggedStatus;
+
+    let file = |path: &str, title: &str, artist: &str, duration: u64, isrc: Option<&str>, tags: &[(&str, &str)]| AudioFileInfo {
+        title: Some(title.to_string()),
+        artists: vec![artist.to_string()],
+        format: AudioFileFormat::MP3,
+        path: PathBuf::from(path),
+        isrc: isrc.map(String::from),
+        duration: Some(Duration::from_secs(duration)),
+        track_number: None,
+        tagged: FileTaggedStatus::Untagged,
+        tags: tags.iter().map(|(k, v)| (k.to_string(), vec![v.to_string()])).collect(),
+        provenance: HashMap::new(),
+        properties: None,
+        rating: None,
+        date: None,
+    };
+    let infos = vec![
+        file("a.mp3", "Song (Original Mix)", "Artist", 300, None, &[]),
+        file("b.mp3", "song", "ARTIST", 301, None, &[]),
+        // Too long
+        file("c.mp3", "Song", "Artist", 420, Some("usabc1234567"), &[]),
+        file("d.mp3", "Other", "Someone", 200, Some("USABC1234567"), &[("BEATPORT_TRACK_ID", "1")]),
+        file("e.mp3", "Different", "Name", 100, None, &[("beatport_track_id", "1")]),
+        file("f.mp3", "Unique", "Artist", 300, None, &[]),
+    ];
+    let groups = Duplicates::group(&infos, &DuplicatesConfig::default());
+    let paths: Vec<Vec<&str>> = groups.iter().map(|g| {
+        let mut p: Vec<&str> = g.files.iter().map(|f| f.path.to_str().unwrap()).collect();
+        p.sort();
+        p
+    }).collect();
+    assert_eq!(paths, vec![vec!["a.mp3", "b.mp3"], vec!["c.mp3", "d.mp3", "e.mp3"]]);
+    assert_eq!(groups[0].reasons, vec![DuplicateReason::Metadata]);
+    assert_eq!(groups[1].reasons, vec![DuplicateReason::ISRC, DuplicateReason::TrackId]);
+}
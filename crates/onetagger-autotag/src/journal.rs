@@ -0,0 +1,325 @@
+use std::collections::{HashMap, HashSet};
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use anyhow::Error;
+use base64::Engine;
+use serde::{Serialize, Deserialize};
+use onetagger_tag::{Tag, TagImpl, TagDate, CoverType};
+use onetagger_tag::id3::ID3SyncedLyrics;
+use onetagger_shared::Settings;
+
+/// Journal of original tags of files modified during an Autotagger run, used for undo
+pub struct UndoJournal {
+    path: PathBuf,
+    file: Mutex<File>,
+    recorded: Mutex<HashSet<PathBuf>>,
+}
+
+impl UndoJournal {
+    /// Create new journal file for run
+    pub fn create(run_id: &str) -> Result<UndoJournal, Error> {
+        let path = Self::journal_path(run_id)?;
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        Ok(UndoJournal {
+            path,
+            file: Mutex::new(file),
+            recorded: Mutex::new(HashSet::new()),
+        })
+    }
+
+    /// Get path to journal file of run
+    pub fn journal_path(run_id: &str) -> Result<PathBuf, Error> {
+        let folder = Settings::get_folder()?.join("runs");
+        if !folder.exists() {
+            std::fs::create_dir_all(&folder)?;
+        }
+        Ok(folder.join(format!("journal-{run_id}.jsonl")))
+    }
+
+    /// Path to the journal file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Save original state of the file before it gets modified. Only the first state of each file is saved
+    pub fn backup(&self, path: impl AsRef<Path>) -> Result<(), Error> {
+        if !self.recorded.lock().unwrap().insert(path.as_ref().to_owned()) {
+            return Ok(());
+        }
+        let entry = JournalEntry::from_file(&path)?;
+        self.write(&JournalRecord::Backup(entry))
+    }
+
+    /// File next to the audio file (LRC, cover) is about to be written. Only the first state of each file is saved
+    pub fn file(&self, audio: impl AsRef<Path>, path: impl AsRef<Path>) -> Result<(), Error> {
+        if !self.recorded.lock().unwrap().insert(path.as_ref().to_owned()) {
+            return Ok(());
+        }
+        let data = match path.as_ref().exists() {
+            true => Some(base64::engine::general_purpose::STANDARD.encode(std::fs::read(&path)?)),
+            false => None
+        };
+        let file = JournalFile { path: path.as_ref().to_owned(), data };
+        self.write(&JournalRecord::File { audio: audio.as_ref().to_owned(), file })
+    }
+
+    /// File was moved after tagging
+    pub fn moved(&self, from: impl AsRef<Path>, to: impl AsRef<Path>) -> Result<(), Error> {
+        if !self.recorded.lock().unwrap().contains(from.as_ref()) {
+            return Ok(());
+        }
+        self.write(&JournalRecord::Moved { from: from.as_ref().to_owned(), to: to.as_ref().to_owned() })
+    }
+
+    /// Append record to file
+    fn write(&self, record: &JournalRecord) -> Result<(), Error> {
+        let mut line = serde_json::to_string(record)?;
+        line.push('\n');
+        let mut file = self.file.lock().unwrap();
+        file.write_all(line.as_bytes())?;
+        file.flush()?;
+        Ok(())
+    }
+
+    /// Load entries from journal file (or run ID), paths are updated for moved files
+    pub fn load(journal: &str) -> Result<Vec<JournalEntry>, Error> {
+        let path = match Path::new(journal).exists() {
+            true => PathBuf::from(journal),
+            false => Self::journal_path(journal)?
+        };
+        let reader = BufReader::new(File::open(&path)?);
+        let mut entries: Vec<JournalEntry> = vec![];
+        for line in reader.lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            // Last line might be incomplete after crash
+            let record = match serde_json::from_str(&line) {
+                Ok(r) => r,
+                Err(e) => {
+                    warn!("Invalid journal record in {}: {e}", path.display());
+                    continue;
+                }
+            };
+            match record {
+                // Resumed runs append to the same journal, keep only the original state
+                JournalRecord::Backup(entry) => if !entries.iter().any(|e| e.path == entry.path) {
+                    entries.push(entry)
+                },
+                JournalRecord::Moved { from, to } => {
+                    if let Some(entry) = entries.iter_mut().find(|e| e.path == from) {
+                        entry.path = to;
+                    }
+                },
+                JournalRecord::File { audio, file } => {
+                    if entries.iter().any(|e| e.files.iter().any(|f| f.path == file.path)) {
+                        continue;
+                    }
+                    match entries.iter_mut().find(|e| e.path == audio) {
+                        Some(entry) => entry.files.push(file),
+                        None => warn!("Journal record of {:?} without backup of {:?}", file.path, audio),
+                    }
+                },
+            }
+        }
+        Ok(entries)
+    }
+
+    /// Restore all files (or only the selected ones) from journal
+    pub fn restore(journal: &str, files: Option<&[PathBuf]>) -> Result<Vec<(PathBuf, Result<(), Error>)>, Error> {
+        let entries = Self::load(journal)?;
+        let mut results = vec![];
+        for entry in entries {
+            if let Some(files) = files {
+                if !files.contains(&entry.path) {
+                    continue;
+                }
+            }
+            info!("Restoring tags of: {}", entry.path.display());
+            let result = entry.restore();
+            if let Err(e) = &result {
+                error!("Failed restoring tags of: {}: {e}", entry.path.display());
+            }
+            results.push((entry.path.to_owned(), result));
+        }
+        Ok(results)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", tag = "type")]
+enum JournalRecord {
+    Backup(JournalEntry),
+    Moved { from: PathBuf, to: PathBuf },
+    File { audio: PathBuf, file: JournalFile },
+}
+
+/// Original tag state of a file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JournalEntry {
+    pub path: PathBuf,
+    pub tags: HashMap<String, Vec<String>>,
+    pub pictures: Vec<JournalPicture>,
+    pub rating: Option<u8>,
+    pub date: Option<TagDate>,
+    /// ID3 version, because it can be changed by the Autotagger
+    pub id3v24: Option<bool>,
+    /// ID3 SYLT frames, not included in the text tags
+    #[serde(default)]
+    pub synced_lyrics: Vec<ID3SyncedLyrics>,
+    /// Files written next to the audio file
+    #[serde(default)]
+    pub files: Vec<JournalFile>,
+}
+
+impl JournalEntry {
+    /// Load current state of file
+    pub fn from_file(path: impl AsRef<Path>) -> Result<JournalEntry, Error> {
+        let tag_wrap = Tag::load_file(&path, true)?;
+        let (id3v24, synced_lyrics) = match &tag_wrap {
+            Tag::ID3(t) => (Some(t.id3v24), t.get_synced_lyrics()),
+            _ => (None, vec![])
+        };
+        let tag = tag_wrap.tag();
+        Ok(JournalEntry {
+            path: path.as_ref().to_owned(),
+            tags: tag.all_tags(),
+            pictures: tag.get_art().into_iter().map(|p| JournalPicture {
+                kind: p.kind,
+                mime: p.mime,
+                description: p.description,
+                data: base64::engine::general_purpose::STANDARD.encode(&p.data)
+            }).collect(),
+            rating: tag.get_rating(),
+            date: tag.get_date(),
+            id3v24,
+            synced_lyrics,
+            files: vec![],
+        })
+    }
+
+    /// Write the original state back to file
+    pub fn restore(&self) -> Result<(), Error> {
+        let mut tag_wrap = Tag::load_file(&self.path, true)?;
+        if let (Tag::ID3(t), Some(id3v24)) = (&mut tag_wrap, self.id3v24) {
+            t.set_id3v24(id3v24);
+            t.set_synced_lyrics(self.synced_lyrics.clone());
+        }
+        if let Tag::MP4(mp4) = &mut tag_wrap {
+            mp4.remove_all_artworks();
+        }
+        let tag = tag_wrap.tag_mut();
+
+        // Rating first, because it also writes some raw tags. Without rating
+        // leave it alone, resetting would wipe POPM frames of other apps
+        if let Some(rating) = self.rating {
+            if tag.get_rating() != Some(rating) {
+                tag.set_rating(rating, true);
+            }
+        }
+
+        // Remove new tags, restore original
+        for t in tag.all_tags().keys() {
+            if !self.tags.contains_key(t) {
+                tag.remove_raw(t);
+            }
+        }
+        for (t, value) in &self.tags {
+            tag.set_raw(t, value.to_owned(), true);
+        }
+
+        // Date might be stored in non-text frames
+        if let Some(date) = &self.date {
+            if tag.get_date().as_ref() != Some(date) {
+                tag.set_date(date, true);
+            }
+        }
+
+        // Pictures
+        for kind in CoverType::types() {
+            tag.remove_art(kind);
+        }
+        for picture in &self.pictures {
+            let data = base64::engine::general_purpose::STANDARD.decode(&picture.data)?;
+            tag.set_art(picture.kind.clone(), &picture.mime, Some(&picture.description), data);
+        }
+
+        tag.save_file(&self.path)?;
+
+        // Created files are removed, overwritten restored
+        for file in &self.files {
+            match &file.data {
+                Some(data) => std::fs::write(&file.path, base64::engine::general_purpose::STANDARD.decode(data)?)?,
+                None => if file.path.exists() {
+                    std::fs::remove_file(&file.path)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JournalPicture {
+    pub kind: CoverType,
+    pub mime: String,
+    pub description: String,
+    /// Base64
+    pub data: String,
+}
+
+/// Original state of file written next to the audio file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JournalFile {
+    pub path: PathBuf,
+    /// Base64 content, `None` if the file didn't exist
+    pub data: Option<String>,
+}
+
+#[test]
+fn journal_restore_files() {
+    let dir = std::env::temp_dir().join(format!("onetagger-journal-{}", onetagger_shared::timestamp!()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let (audio, lrc, cover) = (dir.join("a.mp3"), dir.join("a.lrc"), dir.join("cover.jpg"));
+    std::fs::write(&audio, []).unwrap();
+    std::fs::write(&cover, "old").unwrap();
+    let sylt = ID3SyncedLyrics { lang: "eng".to_string(), description: "Lyrics".to_string(), mpeg: false, content_type: 1, content: vec![(1000, "Line".to_string())] };
+    let mut tag = Tag::load_file(&audio, true).unwrap();
+    if let Tag::ID3(t) = &mut tag {
+        t.set_synced_lyrics(vec![sylt.clone()]);
+    }
+    tag.tag_mut().save_file(&audio).unwrap();
+
+    let path = dir.join("journal.jsonl");
+    let journal = UndoJournal {
+        file: Mutex::new(OpenOptions::new().create(true).append(true).open(&path).unwrap()),
+        path,
+        recorded: Mutex::new(HashSet::new()),
+    };
+    journal.backup(&audio).unwrap();
+    journal.file(&audio, &lrc).unwrap();
+    journal.file(&audio, &cover).unwrap();
+    let mut tag = Tag::load_file(&audio, true).unwrap();
+    if let Tag::ID3(t) = &mut tag {
+        t.set_synced_lyrics(vec![]);
+    }
+    tag.tag_mut().save_file(&audio).unwrap();
+    std::fs::write(&lrc, "[00:01.00]Line").unwrap();
+    std::fs::write(&cover, "new").unwrap();
+
+    let results = UndoJournal::restore(&journal.path().to_string_lossy(), None).unwrap();
+    assert!(results.iter().all(|(_, r)| r.is_ok()));
+    match Tag::load_file(&audio, false).unwrap() {
+        Tag::ID3(t) => assert_eq!(t.get_synced_lyrics(), vec![sylt]),
+        _ => unreachable!()
+    }
+    assert!(!lrc.exists());
+    assert_eq!(std::fs::read_to_string(&cover).unwrap(), "old");
+    std::fs::remove_dir_all(&dir).ok();
+}
@@ -5,6 +5,8 @@
 use std::collections::HashMap;
 use anyhow::Error;
 use onetagger_renamer::{Renamer, RenamerConfig, TemplateParser};
+use onetagger_playlist::{PlaylistFormat, get_files_from_playlist_file};
+use onetagger_playlist::cue::CueSheet;
 use rand::seq::SliceRandom;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
@@ -15,7 +17,7 @@ use std::default::Default;
 use std::io::prelude::*;
 use chrono::Local;
 use execute::Execute;
-use onetagger_tagger::{FileTaggedStatus, LyricsExt, MatchReason, MatchingUtils, SupportedTag, TrackMatch};
+use onetagger_tagger::{DryRunReportFormat, FileTaggedStatus, LyricsExt, MatchReason, MatchScore, MatchingUtils, SupportedTag, TrackMatch, TagProvenance, PROVENANCE_TAG};
 use regex::Regex;
 use reqwest::StatusCode;
 use walkdir::WalkDir;
@@ -25,12 +27,24 @@ use crossbeam_channel::{unbounded, Sender, Receiver};
 use onetagger_tag::{AudioFileFormat, Tag, Field, TagDate, CoverType, TagImpl, EXTENSIONS};
 use onetagger_shared::Settings;
 use onetagger_player::AudioSources;
+use onetagger_platforms::cache::HttpCache;
+use onetagger_platforms::fixtures::HttpFixtures;
 use onetagger_tagger::{Track, AudioFileInfo, TaggerConfig, StylesOptions, AutotaggerSource, AutotaggerSourceBuilder};
 
 use crate::shazam::Shazam;
+use crate::diff::{TagSnapshot, TagDiff, DryRunReportEntry};
+use crate::journal::UndoJournal;
+use crate::checkpoint::TaggerCheckpoint;
+use crate::review::ReviewQueue;
 mod shazam;
 
 pub mod repo;
+pub mod diff;
+pub mod journal;
+pub mod duplicates;
+pub mod replaygain;
+pub mod checkpoint;
+pub mod review;
 pub mod platforms;
 pub mod audiofeatures;
 
@@ -67,15 +81,61 @@ impl TaggerConfigExt for TaggerConfig {
 } 
 
 
+/// Match(es) the written track comes from, used for provenance
+#[derive(Debug, Clone)]
+pub struct TrackSource {
+    pub accuracy: f64,
+    pub reason: MatchReason,
+    /// Sources of individual tags if the track was composed from multiple matches
+    pub tags: HashMap<SupportedTag, TagProvenance>,
+}
+
+impl TrackSource {
+    /// Whole track comes from single match
+    pub fn new(accuracy: f64, reason: MatchReason) -> TrackSource {
+        TrackSource { accuracy, reason, tags: HashMap::new() }
+    }
+
+    /// Track composed from `matches` using `Track::compose`
+    pub fn composed(matches: &[TrackMatch], sources: &HashMap<SupportedTag, usize>) -> TrackSource {
+        let mut source = TrackSource::new(matches[0].accuracy, matches[0].reason);
+        for (tag, i) in sources {
+            let m = &matches[*i];
+            source.tags.insert(*tag, TagProvenance {
+                platform: m.track.platform.to_string(),
+                track_id: m.track.track_id.clone(),
+                release_id: m.track.release_id.clone(),
+                accuracy: m.accuracy,
+                reason: m.reason,
+                timestamp: String::new()
+            });
+        }
+        source
+    }
+}
+
 pub trait TrackImpl {
-    fn write_to_file(&self, path: impl AsRef<Path>, config: &TaggerConfig) -> Result<(), Error>;
+    fn write_to_file(&self, path: impl AsRef<Path>, config: &TaggerConfig, source: &TrackSource, journal: Option<&UndoJournal>) -> Result<Option<TagDiff>, Error>;
     fn download_art(&self, url: &str) -> Result<Option<Vec<u8>>, Error>;
     fn merge_styles(self, option: &StylesOptions) -> Self;
 }
 
 impl TrackImpl for Track {
-    // Write tags to file
-    fn write_to_file(&self, path: impl AsRef<Path>, config: &TaggerConfig) -> Result<(), Error> {        
+    // Write tags to file, in dry run mode only returns the changes
+    fn write_to_file(&self, path: impl AsRef<Path>, config: &TaggerConfig, source: &TrackSource, journal: Option<&UndoJournal>) -> Result<Option<TagDiff>, Error> {
+        // Audio analysis only provides BPM & key, other tags of the file are kept
+        let analyzer_config;
+        let config = match self.platform == "analyzer" {
+            true => {
+                analyzer_config = TaggerConfig {
+                    tags: config.tags.iter().filter(|t| matches!(t, SupportedTag::BPM | SupportedTag::Key)).copied().collect(),
+                    ..config.clone()
+                };
+                &analyzer_config
+            },
+            false => config
+        };
+
         // Get tag
         let mut tag_wrap = Tag::load_file(&path, true)?;
         tag_wrap.set_separators(&config.separators);
@@ -89,7 +149,22 @@ impl TrackImpl for Track {
                     t.set_comm_lang(lang.to_string());
                 }
             }
+            if let Some(mapping) = config.wav_info_mapping.as_ref() {
+                t.set_info_mapping(mapping.iter().map(|(f, i)| (f.to_string(), i.to_string())).collect());
+            }
         }
+        // Original state for dry run
+        let snapshot = match config.dry_run {
+            true => Some(TagSnapshot::from_tag(*tag_wrap.tag())),
+            false => None
+        };
+
+        // Tags which already have a value, for provenance when not overwriting
+        let existing_tags = match config.write_provenance {
+            true => config.tags.iter().filter(|t| !tag_is_empty(&**tag_wrap.tag(), **t)).copied().collect::<Vec<_>>(),
+            false => vec![]
+        };
+
         // MP4 Album art override
         if let Tag::MP4(mp4) = &mut tag_wrap {
             // Has art
@@ -306,15 +381,30 @@ impl TrackImpl for Track {
             tag.set_raw("1T_TAGGEDDATE", vec![format!("{}_AT", time.format("%Y-%m-%d %H:%M:%S"))], true);
         }
 
+        // Which platform supplied which tag
+        if config.write_provenance {
+            write_provenance(self, &mut **tag, config, &existing_tags, source);
+        }
+
+        // Dry run, don't write anything
+        if let Some(snapshot) = snapshot {
+            return Ok(Some(snapshot.diff(&TagSnapshot::from_tag(&**tag))));
+        }
+
         // LRC
         if config.write_lrc && self.lyrics.is_some() {
-            let path = path.as_ref().with_extension("lrc");
-            if !path.exists() {
+            let lrc_path = path.as_ref().with_extension("lrc");
+            if !lrc_path.exists() {
                 if let Some(lrc) = self.lyrics.as_ref().unwrap().generate_lrc(Some(&self), config.enhanced_lrc) {
                     info!("Writing LRC");
-                    match std::fs::write(&path, lrc) {
+                    if let Some(journal) = journal {
+                        if let Err(e) = journal.file(&path, &lrc_path) {
+                            warn!("Failed saving LRC file to undo journal: {e}");
+                        }
+                    }
+                    match std::fs::write(&lrc_path, lrc) {
                         Ok(_) => {}
-                        Err(e) => warn!("Failed writing .LRC file to {:?} {}", path, e),
+                        Err(e) => warn!("Failed writing .LRC file to {:?} {}", lrc_path, e),
                     }
                 }
             }
@@ -328,6 +418,11 @@ impl TrackImpl for Track {
             match AudioFileInfo::load_file(&path, None, None) {
                 Ok(info) => {
                     let cover_path = get_cover_path(&info, path.as_ref().parent().unwrap(), config);
+                    if let Some(journal) = journal {
+                        if let Err(e) = journal.file(&path, &cover_path) {
+                            warn!("Failed saving original cover file to undo journal: {e}");
+                        }
+                    }
                     match std::fs::write(&cover_path, cover_data) {
                         Ok(_) => debug!("Cover written to: {}", cover_path.display()),
                         Err(e) => error!("Failed to write cover file: {e}"),
@@ -339,23 +434,21 @@ impl TrackImpl for Track {
             }
         }
 
-        Ok(())
+        Ok(None)
     }
 
     // Download album art, None if invalid album art
     fn download_art(&self, url: &str) -> Result<Option<Vec<u8>>, Error> {
-        let response = reqwest::blocking::get(url)?;
-        if response.status() != StatusCode::OK {
+        let (status, data) = HttpFixtures::download("art", reqwest::blocking::Client::new().get(url))?;
+        if status != StatusCode::OK {
             return Ok(None);
         }
         // Too small, most likely a text response
-        if let Some(cl) = response.content_length() {
-            if cl < 4096 {
-                return Ok(None);
-            }
+        if data.len() < 4096 {
+            return Ok(None);
         }
-       
-        Ok(Some(response.bytes()?.to_vec()))
+
+        Ok(Some(data))
     }
 
     /// Merge styles by config
@@ -390,6 +483,70 @@ impl TrackImpl for Track {
 
 }
 
+/// Add score breakdown to matches which don't have it
+fn score_matches(matches: Vec<TrackMatch>, info: &AudioFileInfo, config: &TaggerConfig) -> Vec<TrackMatch> {
+    matches.into_iter().map(|m| match m.score {
+        Some(_) => m,
+        None => MatchingUtils::score_match(info, m, config)
+    }).collect()
+}
+
+/// Is the tag missing in file. Tags which can't be checked are considered empty
+fn tag_is_empty(tag: &dyn TagImpl, supported: SupportedTag) -> bool {
+    let field = match supported {
+        SupportedTag::ReleaseDate => return tag.get_date().is_none(),
+        SupportedTag::AlbumArt => return tag.get_art().is_empty(),
+        SupportedTag::Title => Field::Title,
+        SupportedTag::Artist => Field::Artist,
+        SupportedTag::Album => Field::Album,
+        SupportedTag::AlbumArtist => Field::AlbumArtist,
+        SupportedTag::Key => Field::Key,
+        SupportedTag::BPM => Field::BPM,
+        SupportedTag::Genre => Field::Genre,
+        SupportedTag::Style => Field::Style,
+        SupportedTag::Label => Field::Label,
+        SupportedTag::ISRC => Field::ISRC,
+        SupportedTag::CatalogNumber => Field::CatalogNumber,
+        SupportedTag::Version => Field::Version,
+        SupportedTag::TrackNumber => Field::TrackNumber,
+        SupportedTag::TrackTotal => Field::TrackTotal,
+        SupportedTag::Duration => Field::Duration,
+        SupportedTag::Remixer => Field::Remixer,
+        SupportedTag::Mood => Field::Mood,
+        SupportedTag::DiscNumber => Field::DiscNumber,
+        _ => return true
+    };
+    tag.get_field(field).map(|v| v.iter().all(|v| v.trim().is_empty())).unwrap_or(true)
+}
+
+/// Update `1T_PROVENANCE` with the tags written from this track
+fn write_provenance(track: &Track, tag: &mut dyn TagImpl, config: &TaggerConfig, existing_tags: &[SupportedTag], source: &TrackSource) {
+    let mut provenance = tag.get_raw(PROVENANCE_TAG).map(|v| TagProvenance::parse(&v)).unwrap_or_default();
+    let timestamp = Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
+    for t in &config.tags {
+        if !track.has_tag(*t) {
+            continue;
+        }
+        // Original value was kept
+        if !config.overwrite_tag(*t) && existing_tags.contains(t) {
+            continue;
+        }
+        let mut p = source.tags.get(t).cloned().unwrap_or_else(|| TagProvenance {
+            platform: track.platform.to_string(),
+            track_id: track.track_id.clone(),
+            release_id: track.release_id.clone(),
+            accuracy: source.accuracy,
+            reason: source.reason,
+            timestamp: String::new()
+        });
+        p.timestamp = timestamp.to_string();
+        provenance.insert(*t, p);
+    }
+    if !provenance.is_empty() {
+        tag.set_raw(PROVENANCE_TAG, TagProvenance::serialize(&provenance), true);
+    }
+}
+
 /// Get path to cover file
 fn get_cover_path(info: &AudioFileInfo, folder: impl AsRef<Path>, config: &TaggerConfig) -> PathBuf {
     let mut path = folder.as_ref().join("cover.jpg");
@@ -455,6 +612,18 @@ impl AudioFileInfoImpl for AudioFileInfo {
             }
         }
 
+        // CUE sheet in the same folder
+        if title.is_none() || artists.is_none() {
+            if let Some((cue_title, cue_performer)) = CueSheet::track_info(&path) {
+                if title.is_none() {
+                    title = cue_title;
+                }
+                if artists.is_none() {
+                    artists = cue_performer.map(|p| AudioFileInfo::parse_artist_tag(vec![p.trim()]));
+                }
+            }
+        }
+
         // Get tagging status
         let tagged = match tag.get_raw("1T_TAGGEDDATE").map(|t| t.first().map(String::from)).flatten() {
             Some(val) => {
@@ -486,7 +655,11 @@ impl AudioFileInfoImpl for AudioFileInfo {
             duration: None,
             track_number,
             tagged,
-            tags: tag.all_tags()
+            tags: tag.all_tags(),
+            provenance: tag.get_raw(PROVENANCE_TAG).map(|v| TagProvenance::parse(&v)).unwrap_or_default(),
+            properties: None,
+            rating: tag.get_rating(),
+            date: tag.get_date(),
         })
     }
 
@@ -539,6 +712,10 @@ impl AudioFileInfoImpl for AudioFileInfo {
                     track_number: None,
                     tagged: FileTaggedStatus::Untagged,
                     tags: Default::default(),
+                    provenance: Default::default(),
+                    properties: None,
+                    rating: None,
+                    date: None,
                 });
             },
             // Mark as failed
@@ -555,6 +732,17 @@ impl AudioFileInfoImpl for AudioFileInfo {
             return vec![];
         }
 
+        // Playlist
+        if path.as_ref().is_file() && PlaylistFormat::from_path(&path).is_some() {
+            return match get_files_from_playlist_file(&path) {
+                Ok(files) => files,
+                Err(e) => {
+                    warn!("Failed loading playlist {:?}: {e}", path.as_ref());
+                    vec![]
+                }
+            };
+        }
+
         if subfolders {
             WalkDir::new(path)
                 .into_iter()
@@ -603,7 +791,14 @@ pub struct TaggingStatus {
     pub accuracy: Option<f64>,
     pub used_shazam: bool,
     pub release_id: Option<String>,
-    pub reason: Option<MatchReason>
+    pub reason: Option<MatchReason>,
+    /// Changes which would be made in dry run mode
+    pub diff: Option<TagDiff>,
+    /// Breakdown of the accuracy
+    pub score: Option<MatchScore>,
+    /// Matched track which is written after all platforms finish (tag priority)
+    #[serde(skip)]
+    pub track: Option<TrackMatch>,
 }
 
 // Wrap for sending into UI
@@ -630,29 +825,91 @@ impl Tagger {
 
     // Returtns progress receiver, and file count
     pub fn tag_files(cfg: &TaggerConfig, mut files: Vec<PathBuf>, finished: Arc<Mutex<Option<TaggerFinishedData>>>) -> Receiver<TaggingStatusWrap> {
-        STOP_TAGGING.store(false, Ordering::SeqCst);
-
         // Shuffle so album tag is more "efficient"
         if cfg.album_tagging {
             let mut rng = rand::rng();
             files.shuffle(&mut rng);
         }
-        
-        // let original_files = files.clone();
-        let mut succesful_files = vec![];
-        let mut failed_files = vec![];
+
+        let checkpoint = TaggerCheckpoint::new(&timestamp!().to_string(), cfg, &files);
+        Self::run(cfg, files, finished, checkpoint)
+    }
+
+    /// Continue a stopped or crashed run from its checkpoint
+    pub fn resume_files(cfg: &TaggerConfig, run_id: &str, finished: Arc<Mutex<Option<TaggerFinishedData>>>) -> Result<Receiver<TaggingStatusWrap>, Error> {
+        let checkpoint = TaggerCheckpoint::load(run_id, cfg)?;
+        let files = checkpoint.files.clone();
+        info!("Resuming run: {run_id}");
+        Ok(Self::run(cfg, files, finished, checkpoint))
+    }
+
+    /// Start tagging thread
+    fn run(cfg: &TaggerConfig, mut files: Vec<PathBuf>, finished: Arc<Mutex<Option<TaggerFinishedData>>>, mut checkpoint: TaggerCheckpoint) -> Receiver<TaggingStatusWrap> {
+        STOP_TAGGING.store(false, Ordering::SeqCst);
+        HttpCache::configure(&cfg.http_cache);
+
+        // With tag priority, files are written only after all platforms are done
+        let compose = cfg.uses_tag_priority();
+
+        // Restore from checkpoint
+        let mut succesful_files = match compose {
+            true => vec![],
+            false => checkpoint.succeeded().into_iter().collect::<Vec<_>>()
+        };
+        let mut failed_files = checkpoint.failed().into_iter().collect::<Vec<_>>();
+        let mut dry_run_report = vec![];
         let total_files = files.len();
-        info!("Starting tagger with: {} files!", total_files);
+        let run_id = checkpoint.run_id.to_string();
+        info!("Starting tagger with: {} files! Run ID: {run_id}", total_files);
+        // So the run can be resumed even if it crashes on the first file
+        if !cfg.dry_run {
+            if let Err(e) = checkpoint.save() {
+                warn!("Failed saving checkpoint: {e}");
+            }
+        }
+
+        // Journal for undo
+        let journal = match cfg.dry_run {
+            true => None,
+            false => match UndoJournal::create(&run_id) {
+                Ok(j) => Some(Arc::new(j)),
+                Err(e) => {
+                    warn!("Failed creating undo journal, undo won't be available: {e}");
+                    None
+                }
+            }
+        };
 
         // Create thread
         let (tx, rx) = unbounded();
         let config = cfg.clone();
         std::thread::spawn(move || {
+            // Platforms only in tag priority have to be queried as well
+            let mut platforms = config.platforms.clone();
+            for platform in config.tag_priority.values().flatten() {
+                if !platforms.contains(platform) {
+                    platforms.push(platform.to_string());
+                }
+            }
+            // Writing composed tracks is the last step
+            let steps = platforms.len() + compose as usize;
+
             // Tag
-            for (platform_index, platform) in config.platforms.iter().enumerate() {
+            for (platform_index, platform) in platforms.iter().enumerate() {
+                // Already processed files from checkpoint
+                let progress = checkpoint.platform(platform).clone();
                 // For progress
-                let mut processed = 0;
+                let mut processed = progress.processed.len() as i64;
                 let total = files.len();
+                // Fallback, remove files which were already tagged by this platform
+                let batch = files.iter().filter(|f| !progress.processed.contains(*f)).cloned().collect::<Vec<_>>();
+                if !config.multiplatform && !compose {
+                    files.retain(|f| !progress.succeeded.contains(f));
+                }
+                if progress.done {
+                    info!("Skipping {platform}, already processed");
+                    continue;
+                }
                 // No more files
                 if files.is_empty() {
                     info!("All tagged succesfully!");
@@ -678,7 +935,7 @@ impl Tagger {
                 if platform_info.max_threads > 0 && platform_info.max_threads < config.threads {
                     threads = platform_info.max_threads;
                 }
-                let rx = match Tagger::tag_batch(&files, tagger, &config, threads) {
+                let rx = match Tagger::tag_batch(&batch, tagger, &config, threads, journal.clone()) {
                     Some(t) => t,
                     None => {
                         error!("Failed creating platform: {platform:?}, skipping...");
@@ -687,13 +944,31 @@ impl Tagger {
                 };
                 // Start tagging
                 info!("Starting {platform}");
-                for status in rx {
+                for mut status in rx {
                     info!("[{platform}] State: {:?}, Accuracy: {:?}, Path: {:?}", status.status, status.accuracy, status.path);
                     processed += 1;
                     // Send to UI
-                    tx.send(TaggingStatusWrap::wrap(&platform_info.name, &status, platform_index, config.platforms.len(), processed, total)).ok();
+                    tx.send(TaggingStatusWrap::wrap(&platform_info.name, &status, platform_index, steps, processed, total)).ok();
+
+                    // Save match for composing
+                    if let Some(track) = status.track.take() {
+                        checkpoint.matches.entry(status.path.to_owned()).or_default().push(track);
+                    }
+
+                    // Dry run changes
+                    if let Some(diff) = &status.diff {
+                        dry_run_report.push(DryRunReportEntry {
+                            path: status.path.to_owned(),
+                            platform: platform_info.name.to_string(),
+                            diff: diff.to_owned()
+                        });
+                    }
 
-                    if status.status == TaggingState::Ok {
+                    if status.status == TaggingState::Ok && !compose {
+                        // Tagged by other platform, no longer needs review
+                        if config.review.enabled && !config.dry_run {
+                            ReviewQueue::open().and_then(|q| q.remove(&status.path)).ok();
+                        }
                         // Save good files
                         if !succesful_files.contains(&status.path) {
                             succesful_files.push(status.path.to_owned());
@@ -714,15 +989,79 @@ impl Tagger {
                         failed_files.push(status.path.to_owned());
                     }
 
+                    // Save checkpoint once in a while
+                    checkpoint.platform(platform).record(&status);
+                    if processed % 25 == 0 && !config.dry_run {
+                        if let Err(e) = checkpoint.save() {
+                            warn!("Failed saving checkpoint: {e}");
+                        }
+                    }
+                }
+
+                // Platform finished
+                if !STOP_TAGGING.load(Ordering::SeqCst) {
+                    checkpoint.platform(platform).done = true;
                 }
+                if !config.dry_run {
+                    if let Err(e) = checkpoint.save() {
+                        warn!("Failed saving checkpoint: {e}");
+                    }
+                }
+            }
+
+            // Compose matches of all platforms and write
+            if compose && !STOP_TAGGING.load(Ordering::SeqCst) {
+                let matched = checkpoint.files.iter().filter_map(|f| checkpoint.matches.get(f).map(|m| (f, m))).collect::<Vec<_>>();
+                info!("Writing {} composed tracks", matched.len());
+                for (i, (path, matches)) in matched.iter().enumerate() {
+                    if STOP_TAGGING.load(Ordering::SeqCst) {
+                        break;
+                    }
+                    let status = Self::write_composed(path, matches, &config, journal.as_deref());
+                    info!("[merged] State: {:?}, Path: {:?}", status.status, status.path);
+                    tx.send(TaggingStatusWrap::wrap("Merged", &status, platforms.len(), steps, i as i64 + 1, matched.len())).ok();
+
+                    if let Some(diff) = &status.diff {
+                        dry_run_report.push(DryRunReportEntry {
+                            path: status.path.to_owned(),
+                            platform: "Merged".to_string(),
+                            diff: diff.to_owned()
+                        });
+                    }
+                    match status.status {
+                        TaggingState::Ok => {
+                            if config.review.enabled && !config.dry_run {
+                                ReviewQueue::open().and_then(|q| q.remove(&status.path)).ok();
+                            }
+                            succesful_files.push(status.path.to_owned());
+                            failed_files.retain(|f| f != &status.path);
+                        },
+                        TaggingState::Error => if !failed_files.contains(&status.path) {
+                            failed_files.push(status.path.to_owned());
+                        },
+                        TaggingState::Skipped => {}
+                    }
+                }
+            }
+
+            // Keep checkpoint if stopped, so the run can be resumed
+            if STOP_TAGGING.load(Ordering::SeqCst) && !config.dry_run {
+                info!("Tagging stopped, can be resumed using run ID: {run_id}");
+            } else if let Err(e) = checkpoint.remove() {
+                warn!("Failed removing checkpoint: {e}");
             }
 
             // Move files
             let mut successful_paths = vec![];
             for file in &succesful_files {
-                if config.move_success && config.move_success_path.is_some() {
+                if config.move_success && config.move_success_path.is_some() && !config.dry_run {
                     match Self::move_file(file, &config.move_success_path.as_ref().unwrap()) {
-                        Ok(p) => successful_paths.push(p),
+                        Ok(p) => {
+                            if let Some(journal) = &journal {
+                                journal.moved(file, &p).ok();
+                            }
+                            successful_paths.push(p)
+                        },
                         Err(e) => warn!("Failed moving file: {file:?} {e}"),
                     }
                 } else {
@@ -731,9 +1070,14 @@ impl Tagger {
             }
             let mut failed_paths = vec![];
             for file in &failed_files {
-                if config.move_failed && config.move_failed_path.is_some() {
+                if config.move_failed && config.move_failed_path.is_some() && !config.dry_run {
                     match Self::move_file(file, &config.move_failed_path.as_ref().unwrap()) {
-                        Ok(p) => failed_paths.push(p),
+                        Ok(p) => {
+                            if let Some(journal) = &journal {
+                                journal.moved(file, &p).ok();
+                            }
+                            failed_paths.push(p)
+                        },
                         Err(e) => warn!("Failed moving file: {file:?} {e}"),
                     }
                 } else {
@@ -745,12 +1089,12 @@ impl Tagger {
             std::mem::drop(files);
 
             // Tagging ended, save lists of files
-            match Self::write_results(successful_paths, failed_paths, &config) {
-                Ok((failed, success)) => {
-                    info!("Written failed songs to: {}, successful to: {}", failed, success);
-                    *finished.lock().unwrap() = Some(TaggerFinishedData {
-                        failed_file: failed, success_file: success
-                    });
+            match Self::write_results(successful_paths, failed_paths, &dry_run_report, &config) {
+                Ok(mut data) => {
+                    data.journal_file = journal.as_ref().map(|j| j.path().to_string_lossy().to_string());
+                    data.run_id = run_id;
+                    info!("Written failed songs to: {}, successful to: {}", data.failed_file, data.success_file);
+                    *finished.lock().unwrap() = Some(data);
                 },
                 Err(e) => warn!("Failed writing failed songs to file! {}", e)
             };
@@ -761,8 +1105,8 @@ impl Tagger {
         rx
     }
 
-    /// Write playlists & execute command
-    fn write_results(successful_paths: Vec<PathBuf>, failed_paths: Vec<PathBuf>, config: &TaggerConfig) -> Result<(String, String), Error> {
+    /// Write playlists, dry run report & execute command
+    fn write_results(successful_paths: Vec<PathBuf>, failed_paths: Vec<PathBuf>, dry_run_report: &[DryRunReportEntry], config: &TaggerConfig) -> Result<TaggerFinishedData, Error> {
         let time = timestamp!();
         let folder = PathBuf::from(Settings::get_folder()?.to_str().unwrap().to_string()).join("runs");
         if !folder.exists() {
@@ -788,10 +1132,24 @@ impl Tagger {
                 .collect();
             file.write_all(files.join("\r\n").as_bytes())?;
         }
+
+        // Dry run report
+        let report_file = match (config.dry_run, config.dry_run_report) {
+            (true, Some(format)) => {
+                let (path, data) = match format {
+                    DryRunReportFormat::Json => (folder.join(format!("dryrun-{}.json", time)), serde_json::to_string_pretty(dry_run_report)?),
+                    DryRunReportFormat::Csv => (folder.join(format!("dryrun-{}.csv", time)), DryRunReportEntry::to_csv(dry_run_report)),
+                };
+                std::fs::write(&path, data)?;
+                info!("Written dry run report to: {}", path.display());
+                Some(path.to_string_lossy().to_string())
+            },
+            _ => None
+        };
         
-        // Run command
+        // Run command (nothing was changed in dry run)
         let (failed_file, success_file) = (failed_file.to_str().unwrap().to_string(), success_file.to_str().unwrap().to_string());
-        if let Some(command) = &config.post_command {
+        if let Some(command) = config.post_command.as_ref().filter(|_| !config.dry_run) {
             if !command.trim().is_empty() {
                 let command = command
                     .replace("$failed", &failed_file)
@@ -805,7 +1163,7 @@ impl Tagger {
             }
         }
 
-        Ok((failed_file, success_file))
+        Ok(TaggerFinishedData { failed_file, success_file, report_file, journal_file: None, run_id: String::new() })
 
     }
 
@@ -819,7 +1177,10 @@ impl Tagger {
             message: None,
             used_shazam: false,
             release_id: None,
-            reason: None
+            reason: None,
+            diff: None,
+            score: None,
+            track: None,
         };
 
         // Filename template
@@ -891,7 +1252,7 @@ impl Tagger {
     }
 
     /// Tag single track
-    pub fn tag_track<T>(path: impl AsRef<Path>, tagger: &mut Box<T>, config: &TaggerConfig) -> TaggingStatus 
+    pub fn tag_track<T>(path: impl AsRef<Path>, tagger: &mut Box<T>, config: &TaggerConfig, journal: Option<&UndoJournal>) -> TaggingStatus 
     where T: AutotaggerSource + ?Sized
     {
         info!("Tagging: {:?}", path.as_ref());
@@ -904,7 +1265,7 @@ impl Tagger {
        
         // Match track
         let result = tagger.match_track(&info, &config);
-        let mut tracks = match result {
+        let tracks = match result {
             Ok(o) => {
                 if o.is_empty() {
                     out.message = Some("No match!".to_owned());
@@ -920,8 +1281,27 @@ impl Tagger {
             }
         };
 
+        // Score matches which weren't scored by the platform
+        let mut tracks = score_matches(tracks, &info, config);
+
         // Get & extend track
         MatchingUtils::sort_tracks(&mut tracks, config);
+
+        // Low confidence, let the user decide later
+        if config.review.enabled && tracks[0].reason == MatchReason::Fuzzy && tracks[0].accuracy < config.review.threshold {
+            info!("Low confidence match ({:.2}), adding to review queue: {:?}", tracks[0].accuracy, path.as_ref());
+            out.status = TaggingState::Skipped;
+            out.accuracy = Some(tracks[0].accuracy);
+            out.score = tracks[0].score.clone();
+            out.message = Some("Low confidence match, added to the review queue".to_string());
+            if !config.dry_run {
+                if let Err(e) = ReviewQueue::open().and_then(|q| q.add(&info.path, tracks, config.review.candidates)) {
+                    warn!("Failed adding file to review queue: {e}");
+                }
+            }
+            return out;
+        }
+
         let mut track = tracks.remove(0);
         drop(tracks);
         match tagger.extend_track(&mut track.track, config) {
@@ -932,10 +1312,25 @@ impl Tagger {
         // Save
         out.release_id = track.track.release_id.clone();
         out.reason = Some(track.reason);
-        match track.track.merge_styles(&config.styles_options).write_to_file(&info.path, &config) {
-            Ok(_) => {
+        out.score = track.score.clone();
+        // Composed from matches of all platforms later
+        if config.uses_tag_priority() {
+            out.accuracy = Some(track.accuracy);
+            out.status = TaggingState::Ok;
+            out.track = Some(track);
+            return out;
+        }
+        if let Some(journal) = journal {
+            if let Err(e) = journal.backup(&info.path) {
+                warn!("Failed saving original tags to undo journal: {e}");
+            }
+        }
+        let source = TrackSource::new(track.accuracy, track.reason);
+        match track.track.merge_styles(&config.styles_options).write_to_file(&info.path, &config, &source, journal) {
+            Ok(diff) => {
                 out.accuracy = Some(track.accuracy);
                 out.status = TaggingState::Ok;
+                out.diff = diff;
             },
             Err(e) => {
                 error!("Failed writing tags to file: {e}");
@@ -946,8 +1341,53 @@ impl Tagger {
         out
     }
 
+    /// Compose the final track from matches of all platforms by the tag priority and write it
+    fn write_composed(path: &Path, matches: &[TrackMatch], config: &TaggerConfig, journal: Option<&UndoJournal>) -> TaggingStatus {
+        let mut out = TaggingStatus {
+            status: TaggingState::Error,
+            path: path.to_owned(),
+            accuracy: None,
+            message: None,
+            used_shazam: false,
+            release_id: None,
+            reason: None,
+            diff: None,
+            score: None,
+            track: None,
+        };
+        let tracks = matches.iter().map(|m| m.track.clone()).collect::<Vec<_>>();
+        let (track, sources) = match Track::compose(&tracks, &config.tags, &config.tag_priority) {
+            Some(r) => r,
+            None => {
+                out.message = Some("No match!".to_owned());
+                return out;
+            }
+        };
+        out.release_id = track.release_id.clone();
+        out.reason = Some(matches[0].reason);
+        out.score = matches[0].score.clone();
+
+        if let Some(journal) = journal {
+            if let Err(e) = journal.backup(path) {
+                warn!("Failed saving original tags to undo journal: {e}");
+            }
+        }
+        match track.merge_styles(&config.styles_options).write_to_file(path, config, &TrackSource::composed(matches, &sources), journal) {
+            Ok(diff) => {
+                out.accuracy = Some(matches[0].accuracy);
+                out.status = TaggingState::Ok;
+                out.diff = diff;
+            },
+            Err(e) => {
+                error!("Failed writing tags to file: {e}");
+                out.message = Some(format!("Failed writing tags to file: {}", e));
+            }
+        }
+        out
+    }
+
     // Tag all files with threads specified in config
-    pub fn tag_batch(files: &Vec<PathBuf>, tagger: &mut Box<dyn AutotaggerSourceBuilder + Send + Sync>, config: &TaggerConfig, threads: u16) -> Option<Receiver<TaggingStatus>> {
+    pub fn tag_batch(files: &Vec<PathBuf>, tagger: &mut Box<dyn AutotaggerSourceBuilder + Send + Sync>, config: &TaggerConfig, threads: u16, journal: Option<Arc<UndoJournal>>) -> Option<Receiver<TaggingStatus>> {
         info!("Starting tagging: {} files, {} threads!", files.len(), threads);
         let (tx, rx) = unbounded();
         let (file_tx, file_rx): (Sender<PathBuf>, Receiver<PathBuf>) = unbounded();
@@ -966,6 +1406,7 @@ impl Tagger {
             let config = config.clone();
             let finished_tx = finished_tx.clone();
             let album_tagging = album_tagging.clone();
+            let journal = journal.clone();
             let mut source = match tagger.get_source(&config) {
                 Ok(s) => s,
                 Err(e) => {
@@ -987,7 +1428,7 @@ impl Tagger {
                     }
 
                     // Tag
-                    let res = Tagger::tag_track(&f, &mut source, &config);
+                    let res = Tagger::tag_track(&f, &mut source, &config, journal.as_deref());
                     if config.album_tagging {
                         album_tagging.lock().unwrap().process(&res, &config);
                     }
@@ -1014,7 +1455,7 @@ impl Tagger {
                             }
 
                             // Tag
-                            match Self::tag_album(path, &stats.get_album_id().unwrap(), &mut source, &config) {
+                            match Self::tag_album(path, &stats.get_album_id().unwrap(), &mut source, &config, journal.as_deref()) {
                                 Ok(statuses) => {
                                     for status in statuses {
                                         tx.send(status).ok();
@@ -1043,7 +1484,7 @@ impl Tagger {
     }
 
     /// Tag an album by ID
-    pub fn tag_album(path: impl AsRef<Path>, release_id: &str, source: &mut Box<dyn AutotaggerSource>, config: &TaggerConfig) -> Result<Vec<TaggingStatus>, Error> {
+    pub fn tag_album(path: impl AsRef<Path>, release_id: &str, source: &mut Box<dyn AutotaggerSource>, config: &TaggerConfig, journal: Option<&UndoJournal>) -> Result<Vec<TaggingStatus>, Error> {
         info!("Album tagging release: {release_id} in {}", path.as_ref().display());
 
         // Change strictness since we're working in context of album, and just care about most likely match
@@ -1079,17 +1520,35 @@ impl Tagger {
             MatchingUtils::sort_tracks(&mut tracks, &config);
             let track = tracks.remove(0);
             
-            // TODO: Extend track if needed (?)
-            if let Err(e) = track.track.merge_styles(&config.styles_options).write_to_file(&info.path, &config) {
-                status.status = TaggingState::Error;
-                error!("Album tag writing tags failed: {e} ({})", file.display());
-            } else {
-                status.status = TaggingState::Ok;
-            }
-
             // Save status
             status.accuracy = Some(1.0);
             status.reason = Some(MatchReason::Album);
+
+            // Composed from matches of all platforms later
+            if config.uses_tag_priority() {
+                status.status = TaggingState::Ok;
+                status.track = Some(TrackMatch { reason: MatchReason::Album, ..track });
+                statuses.push(status);
+                continue;
+            }
+
+            // TODO: Extend track if needed (?)
+            if let Some(journal) = journal {
+                if let Err(e) = journal.backup(&info.path) {
+                    warn!("Failed saving original tags to undo journal: {e}");
+                }
+            }
+            let source = TrackSource::new(track.accuracy, MatchReason::Album);
+            match track.track.merge_styles(&config.styles_options).write_to_file(&info.path, &config, &source, journal) {
+                Ok(diff) => {
+                    status.status = TaggingState::Ok;
+                    status.diff = diff;
+                },
+                Err(e) => {
+                    status.status = TaggingState::Error;
+                    error!("Album tag writing tags failed: {e} ({})", file.display());
+                }
+            }
             statuses.push(status);
         }
 
@@ -1213,13 +1672,21 @@ impl AlbumTagFolderStats {
 #[serde(rename_all = "camelCase")]
 pub struct TaggerFinishedData {
     pub failed_file: String,
-    pub success_file: String
+    pub success_file: String,
+    /// Dry run report
+    pub report_file: Option<String>,
+    /// Undo journal
+    pub journal_file: Option<String>,
+    /// For resuming / undo
+    pub run_id: String,
 }
 
 
 /// Start manual tagging mode
 /// Return: receiver with results for every platform
 pub fn manual_tagger(path: impl AsRef<Path>, config: &TaggerConfig) -> Result<Receiver<(String, Result<Vec<TrackMatch>, Error>)>, Error> {
+    HttpCache::configure(&config.http_cache);
+
     // Get filename template
     let filename_template = config.filename_template.as_ref().map(|template| {
         match AudioFileInfo::parse_template(template) {
@@ -1275,7 +1742,7 @@ pub fn manual_tagger(path: impl AsRef<Path>, config: &TaggerConfig) -> Result<Re
         let platform = platform.to_string();
         platforms.push(std::thread::spawn(move || {
             // Match
-            let r = s.match_track(&info, &config).map(|mut m| { m.dedup(); m });
+            let r = s.match_track(&info, &config).map(|mut m| { m.dedup(); score_matches(m, &info, &config) });
             tx.send((platform, r)).ok();
         }));
     }
@@ -1315,13 +1782,11 @@ pub fn manual_tagger_apply(mut matches: Vec<TrackMatch>, path: impl AsRef<Path>,
         }
     }
 
-    // Merge
-    let mut track = matches.remove(0).track;
-    for t in matches {
-        track = track.merge(t.track);
-    }
+    // Merge, tags with priority are taken from the preferred platform
+    let tracks = matches.iter().map(|m| m.track.clone()).collect::<Vec<_>>();
+    let (track, sources) = Track::compose(&tracks, &config.tags, &config.tag_priority).ok_or(anyhow!("No tracks"))?;
 
     // Save
-    track.merge_styles(&config.styles_options).write_to_file(&path, &config)?;
+    track.merge_styles(&config.styles_options).write_to_file(&path, &config, &TrackSource::composed(&matches, &sources), None)?;
     Ok(())
 }
\ No newline at end of file
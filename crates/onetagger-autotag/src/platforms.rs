@@ -6,7 +6,7 @@ use std::io::Cursor;
 use std::sync::{Arc, Mutex};
 use base64::Engine;
 use libloading::{Library, Symbol};
-use onetagger_platforms::{beatport, junodownload, spotify, traxsource, discogs, itunes, musicbrainz, beatsource, bpmsupreme, bandcamp, deezer, musixmatch};
+use onetagger_platforms::{beatport, junodownload, spotify, traxsource, discogs, itunes, musicbrainz, acoustid, beatsource, bpmsupreme, bandcamp, deezer, musixmatch, analyzer};
 use image::{ImageFormat, ImageReader};
 use onetagger_shared::Settings;
 use onetagger_tagger::custom::MatchTrackResult;
@@ -36,12 +36,14 @@ impl AutotaggerPlatforms {
         AutotaggerPlatforms::add_builtin::<junodownload::JunoDownloadBuilder>(&mut output);
         AutotaggerPlatforms::add_builtin::<spotify::SpotifyBuilder>(&mut output);
         AutotaggerPlatforms::add_builtin::<musicbrainz::MusicBrainzBuilder>(&mut output);
+        AutotaggerPlatforms::add_builtin::<acoustid::AcoustIDBuilder>(&mut output);
         AutotaggerPlatforms::add_builtin::<discogs::DiscogsBuilder>(&mut output);
         AutotaggerPlatforms::add_builtin::<itunes::ITunesBuilder>(&mut output);
         AutotaggerPlatforms::add_builtin::<bpmsupreme::BPMSupremeBuilder>(&mut output);
         AutotaggerPlatforms::add_builtin::<bandcamp::BandcampBuilder>(&mut output);
         AutotaggerPlatforms::add_builtin::<deezer::DeezerBuilder>(&mut output);
         AutotaggerPlatforms::add_builtin::<musixmatch::MusixmatchBuilder>(&mut output);
+        AutotaggerPlatforms::add_builtin::<analyzer::AudioAnalyzerBuilder>(&mut output);
 
         AutotaggerPlatforms { platforms: output }
     }
@@ -0,0 +1,203 @@
+use std::collections::BTreeMap;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use anyhow::Error;
+use serde::{Serialize, Deserialize};
+use onetagger_tag::{AudioFileFormat, FrameName, Tag};
+use onetagger_player::loudness::Loudness;
+use onetagger_tagger::AudioFileInfo;
+
+use crate::AudioFileInfoImpl;
+
+/// ReplayGain 2.0 reference loudness
+const REPLAYGAIN_REFERENCE: f64 = -18.0;
+/// Opus R128 gains are relative to EBU R128
+const R128_REFERENCE: f64 = -23.0;
+
+/// Options for loudness analysis
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ReplayGainConfig {
+    pub path: PathBuf,
+    pub include_subfolders: bool,
+    /// Treat every folder as an album
+    pub album: bool,
+    /// Recalculate folders where every file already has the track gain
+    pub overwrite: bool,
+}
+
+impl Default for ReplayGainConfig {
+    fn default() -> Self {
+        Self {
+            path: PathBuf::new(),
+            include_subfolders: true,
+            album: true,
+            overwrite: false,
+        }
+    }
+}
+
+/// Written values of a file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReplayGainResult {
+    pub path: PathBuf,
+    /// Integrated loudness in LUFS
+    pub loudness: f64,
+    /// In dB
+    pub gain: f64,
+    pub peak: f64,
+    pub album_gain: Option<f64>,
+    pub album_peak: Option<f64>,
+}
+
+pub struct ReplayGain;
+
+impl ReplayGain {
+    /// Analyze and tag all files, returns the written files
+    pub fn run(config: &ReplayGainConfig) -> Result<Vec<ReplayGainResult>, Error> {
+        // Group by folder
+        let mut folders: BTreeMap<PathBuf, Vec<PathBuf>> = BTreeMap::new();
+        for file in AudioFileInfo::get_file_list(&config.path, config.include_subfolders) {
+            folders.entry(file.parent().map(|p| p.to_owned()).unwrap_or_default()).or_default().push(file);
+        }
+
+        let mut output = vec![];
+        for (folder, mut files) in folders {
+            files.sort();
+            if !config.overwrite && files.iter().all(|f| Self::has_gain(f)) {
+                debug!("Skipping already analyzed folder: {folder:?}");
+                continue;
+            }
+            info!("Calculating loudness of {} files in {folder:?}", files.len());
+            let tracks: Vec<(PathBuf, Loudness)> = files.into_iter().filter_map(|path| {
+                match Loudness::from_path(&path) {
+                    Ok(loudness) if loudness.integrated.is_some() => Some((path, loudness)),
+                    Ok(_) => {
+                        warn!("Skipping silent file: {path:?}");
+                        None
+                    }
+                    Err(e) => {
+                        warn!("Failed calculating loudness of {path:?}: {e}");
+                        None
+                    }
+                }
+            }).collect();
+            let album = match config.album {
+                true => Some(Loudness::album(&tracks.iter().map(|(_, l)| l.clone()).collect::<Vec<_>>())),
+                false => None
+            };
+
+            for (path, track) in tracks {
+                match Self::write(&path, &track, album.as_ref()) {
+                    Ok(r) => output.push(r),
+                    Err(e) => warn!("Failed writing ReplayGain to {path:?}: {e}"),
+                }
+            }
+        }
+        Ok(output)
+    }
+
+    /// Write the gain tags into file
+    pub fn write(path: impl AsRef<Path>, track: &Loudness, album: Option<&Loudness>) -> Result<ReplayGainResult, Error> {
+        let loudness = track.integrated.ok_or(anyhow!("Missing loudness"))?;
+        let album = album.and_then(|a| a.integrated.map(|l| (l, a.true_peak)));
+        let mut tag_wrap = Tag::load_file(&path, true)?;
+        let format = tag_wrap.format();
+        let tag = tag_wrap.tag_mut();
+        for (name, value) in Self::tag_values(&format, Self::is_opus(&path), (loudness, track.true_peak), album) {
+            match value {
+                Some(value) => tag.set_raw(&name, vec![value], true),
+                None => tag.remove_raw(&name)
+            }
+        }
+        tag.save_file(path.as_ref())?;
+
+        Ok(ReplayGainResult {
+            path: path.as_ref().to_owned(),
+            loudness,
+            gain: REPLAYGAIN_REFERENCE - loudness,
+            peak: track.true_peak,
+            album_gain: album.map(|(l, _)| REPLAYGAIN_REFERENCE - l),
+            album_peak: album.map(|(_, p)| p),
+        })
+    }
+
+    /// Tag names & values for format, `None` = remove. Loudness & peak of track and album
+    fn tag_values(format: &AudioFileFormat, opus: bool, track: (f64, f64), album: Option<(f64, f64)>) -> Vec<(String, Option<String>)> {
+        let gain = |l: f64| format!("{:.2} dB", REPLAYGAIN_REFERENCE - l);
+        let peak = |p: f64| format!("{p:.6}");
+        let mut output = vec![];
+        // Opus uses Q7.8 gains relative to -23 LUFS, ReplayGain tags shouldn't be present
+        if opus {
+            let r128 = |l: f64| (((R128_REFERENCE - l) * 256.0).round() as i64).clamp(i16::MIN as i64, i16::MAX as i64).to_string();
+            output.push(("R128_TRACK_GAIN".to_string(), Some(r128(track.0))));
+            output.push(("R128_ALBUM_GAIN".to_string(), album.map(|(l, _)| r128(l))));
+            for name in ["TRACK_GAIN", "TRACK_PEAK", "ALBUM_GAIN", "ALBUM_PEAK"] {
+                output.push((format!("REPLAYGAIN_{name}"), None));
+            }
+            return output;
+        }
+
+        let values = [
+            ("TRACK_GAIN", Some(gain(track.0))),
+            ("TRACK_PEAK", Some(peak(track.1))),
+            ("ALBUM_GAIN", album.map(|(l, _)| gain(l))),
+            ("ALBUM_PEAK", album.map(|(_, p)| peak(p))),
+        ];
+        for (name, value) in values {
+            let frame = Self::frame_name(name).by_format(format);
+            output.push((frame, value));
+        }
+        output
+    }
+
+    /// TXXX for ID3, freeform for MP4
+    fn frame_name(name: &str) -> FrameName {
+        FrameName::new(
+            &format!("REPLAYGAIN_{name}"),
+            &format!("REPLAYGAIN_{name}"),
+            &format!("iTunes:replaygain_{}", name.to_lowercase())
+        )
+    }
+
+    /// File already has the track gain
+    fn has_gain(path: &Path) -> bool {
+        let tag_wrap = match Tag::load_file(path, false) {
+            Ok(t) => t,
+            Err(_) => return false
+        };
+        let name = match Self::is_opus(path) {
+            true => "R128_TRACK_GAIN".to_string(),
+            false => Self::frame_name("TRACK_GAIN").by_format(&tag_wrap.format())
+        };
+        tag_wrap.tag().get_raw(&name).map(|v| !v.is_empty()).unwrap_or(false)
+    }
+
+    /// Check the Ogg identification header
+    fn is_opus(path: impl AsRef<Path>) -> bool {
+        let mut header = [0u8; 64];
+        let read = std::fs::File::open(path).and_then(|mut f| f.read(&mut header)).unwrap_or(0);
+        header[..read].starts_with(b"OggS") && header[..read].windows(8).any(|w| w == b"OpusHead")
+    }
+}
+
+#[test]
+fn replaygain_tag_values() {
+    let values = ReplayGain::tag_values(&AudioFileFormat::MP3, false, (-8.25, 0.95), Some((-9.0, 1.02)));
+    assert_eq!(values, vec![
+        ("REPLAYGAIN_TRACK_GAIN".to_string(), Some("-9.75 dB".to_string())),
+        ("REPLAYGAIN_TRACK_PEAK".to_string(), Some("0.950000".to_string())),
+        ("REPLAYGAIN_ALBUM_GAIN".to_string(), Some("-9.00 dB".to_string())),
+        ("REPLAYGAIN_ALBUM_PEAK".to_string(), Some("1.020000".to_string())),
+    ]);
+    let values = ReplayGain::tag_values(&AudioFileFormat::MP4, false, (-20.0, 0.5), None);
+    assert_eq!(values[0], ("iTunes:replaygain_track_gain".to_string(), Some("2.00 dB".to_string())));
+    assert_eq!(values[2], ("iTunes:replaygain_album_gain".to_string(), None));
+
+    // -23 - -8.25 = -14.75 dB * 256
+    let values = ReplayGain::tag_values(&AudioFileFormat::OGG, true, (-8.25, 0.95), None);
+    assert_eq!(values[0], ("R128_TRACK_GAIN".to_string(), Some("-3776".to_string())));
+    assert_eq!(values[1], ("R128_ALBUM_GAIN".to_string(), None));
+    assert!(values[2..].iter().all(|(n, v)| n.starts_with("REPLAYGAIN_") && v.is_none()));
+}
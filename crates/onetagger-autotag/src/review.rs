@@ -0,0 +1,146 @@
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use anyhow::Error;
+use serde::{Serialize, Deserialize};
+use onetagger_tagger::{TaggerConfig, TrackMatch};
+use onetagger_shared::Settings;
+
+lazy_static::lazy_static! {
+    /// The queue file is shared by all tagging threads
+    static ref REVIEW_LOCK: Mutex<()> = Mutex::new(());
+}
+
+/// Files with low confidence matches, waiting to be resolved manually
+pub struct ReviewQueue {
+    path: PathBuf
+}
+
+impl ReviewQueue {
+    /// Queue stored in file
+    pub fn new(path: impl AsRef<Path>) -> ReviewQueue {
+        ReviewQueue { path: path.as_ref().to_owned() }
+    }
+
+    /// Queue in the settings folder
+    pub fn open() -> Result<ReviewQueue, Error> {
+        Ok(ReviewQueue::new(Settings::get_folder()?.join("review.json")))
+    }
+
+    /// Get all the items in queue
+    pub fn list(&self) -> Result<Vec<ReviewItem>, Error> {
+        let _lock = REVIEW_LOCK.lock().unwrap();
+        self.load()
+    }
+
+    /// Add candidates of file, merged with candidates from other platforms
+    pub fn add(&self, path: impl AsRef<Path>, candidates: Vec<TrackMatch>, max_candidates: usize) -> Result<(), Error> {
+        let _lock = REVIEW_LOCK.lock().unwrap();
+        let mut items = self.load()?;
+        let index = match items.iter().position(|i| i.path == path.as_ref()) {
+            Some(i) => i,
+            None => {
+                items.push(ReviewItem { path: path.as_ref().to_owned(), added: timestamp!() as u64, candidates: vec![] });
+                items.len() - 1
+            }
+        };
+        let item = &mut items[index];
+        item.candidates.extend(candidates);
+        item.candidates.sort_by(|a, b| b.accuracy.partial_cmp(&a.accuracy).unwrap_or(std::cmp::Ordering::Equal));
+        item.candidates.dedup_by(|a, b| a.track.platform == b.track.platform && a.track.url == b.track.url);
+        item.candidates.truncate(max_candidates.max(1));
+        self.save(&items)
+    }
+
+    /// Remove file from queue. Returns whether it was queued
+    pub fn remove(&self, path: impl AsRef<Path>) -> Result<bool, Error> {
+        let _lock = REVIEW_LOCK.lock().unwrap();
+        let mut items = self.load()?;
+        let len = items.len();
+        items.retain(|i| i.path != path.as_ref());
+        if items.len() == len {
+            return Ok(false);
+        }
+        self.save(&items)?;
+        Ok(true)
+    }
+
+    /// Write the selected candidate into the file and remove it from queue
+    pub fn accept(&self, path: impl AsRef<Path>, candidate: usize, config: &TaggerConfig) -> Result<(), Error> {
+        let item = self.list()?.into_iter().find(|i| i.path == path.as_ref()).ok_or(anyhow!("File is not in the review queue"))?;
+        let candidate = item.candidates.get(candidate).ok_or(anyhow!("Invalid candidate: {candidate}"))?.to_owned();
+        info!("Accepted review candidate from {} for: {}", candidate.track.platform, path.as_ref().display());
+        crate::manual_tagger_apply(vec![candidate], &path, config)?;
+        self.remove(&path)?;
+        Ok(())
+    }
+
+    /// Leave the file untagged
+    pub fn skip(&self, path: impl AsRef<Path>) -> Result<(), Error> {
+        if !self.remove(&path)? {
+            return Err(anyhow!("File is not in the review queue"));
+        }
+        Ok(())
+    }
+
+    fn load(&self) -> Result<Vec<ReviewItem>, Error> {
+        if !self.path.exists() {
+            return Ok(vec![]);
+        }
+        Ok(serde_json::from_slice(&std::fs::read(&self.path)?)?)
+    }
+
+    fn save(&self, items: &[ReviewItem]) -> Result<(), Error> {
+        let tmp = self.path.with_extension("json.tmp");
+        std::fs::write(&tmp, serde_json::to_vec(items)?)?;
+        std::fs::rename(&tmp, &self.path)?;
+        Ok(())
+    }
+}
+
+/// File waiting for review
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReviewItem {
+    pub path: PathBuf,
+    /// When was the file added, UNIX timestamp in ms
+    pub added: u64,
+    /// Best candidates, sorted by accuracy
+    pub candidates: Vec<TrackMatch>,
+}
+
+/// Enqueue, persistence, skip and accept
+#[test]
+fn review_queue() {
+    use onetagger_tagger::{Track, SupportedTag};
+    use onetagger_tag::{Tag, Field};
+
+    let dir = std::env::temp_dir().join(format!("onetagger-review-{}", timestamp!()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let (a, b) = (dir.join("a.mp3"), dir.join("b.mp3"));
+    std::fs::write(&a, []).unwrap();
+    std::fs::write(&b, []).unwrap();
+    let candidate = |platform: &str, title: &str, accuracy: f64| TrackMatch::new(accuracy, Track {
+        platform: platform.to_string(), title: title.to_string(), url: format!("https://{platform}/{title}"), ..Default::default()
+    });
+
+    let queue = ReviewQueue::new(dir.join("review.json"));
+    queue.add(&a, vec![candidate("test", "A1", 0.6), candidate("test", "A1", 0.6)], 2).unwrap();
+    // Merged with other platform, sorted and truncated
+    queue.add(&a, vec![candidate("other", "A2", 0.7), candidate("other", "A3", 0.5)], 2).unwrap();
+    queue.add(&b, vec![candidate("test", "B", 0.5)], 2).unwrap();
+
+    let items = ReviewQueue::new(dir.join("review.json")).list().unwrap();
+    assert_eq!(items.len(), 2);
+    assert_eq!(items[0].candidates.iter().map(|c| c.track.title.as_str()).collect::<Vec<_>>(), vec!["A2", "A1"]);
+
+    queue.skip(&b).unwrap();
+    assert!(queue.skip(&b).is_err());
+    let config = TaggerConfig { tags: vec![SupportedTag::Title], ..Default::default() };
+    assert!(queue.accept(&a, 5, &config).is_err());
+    queue.accept(&a, 1, &config).unwrap();
+    assert!(queue.list().unwrap().is_empty());
+    let tag = Tag::load_file(&a, false).unwrap();
+    assert_eq!(tag.tag().get_field(Field::Title), Some(vec!["A1".to_string()]));
+
+    std::fs::remove_dir_all(&dir).ok();
+}
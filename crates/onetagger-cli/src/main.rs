@@ -4,16 +4,25 @@
 use anyhow::Error;
 use onetagger_ui::StartContext;
 use std::fs::File;
+use std::io::Write;
+use std::collections::HashMap;
 use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
-use clap::{Parser, Subcommand};
+use clap::{Parser, Subcommand, ValueEnum};
 use convert_case::{Casing, Case};
 use onetagger_platforms::spotify::Spotify;
-use onetagger_renamer::{RenamerConfig, Renamer, TemplateParser};
+use onetagger_platforms::cache::HttpCache;
+use onetagger_platforms::analyzer::AudioAnalyzer;
+use onetagger_renamer::{RenamerConfig, Renamer, TemplateParser, RenameLog, CollisionPolicy, CompanionFiles, FilenameParser, FilenameTagsConfig};
 use onetagger_shared::{VERSION, COMMIT};
 use onetagger_autotag::audiofeatures::{AudioFeaturesConfig, AudioFeatures};
-use onetagger_autotag::{Tagger, TaggerConfigExt, AudioFileInfoImpl};
+use onetagger_autotag::journal::UndoJournal;
+use onetagger_autotag::duplicates::{Duplicates, DuplicatesConfig};
+use onetagger_autotag::replaygain::{ReplayGain, ReplayGainConfig};
+use onetagger_autotag::{Tagger, TaggerConfigExt, AudioFileInfoImpl, TaggingState};
 use onetagger_tagger::{TaggerConfig, AudioFileInfo, SupportedTag};
+use onetagger_tag::serato::SeratoMarkers;
+use serde_json::{json, Value};
 
 fn main() {
     let cli = Cli::parse();
@@ -35,30 +44,129 @@ fn main() {
         return;
     }
 
-    // Setup logging
-    onetagger_shared::setup();
+    // Setup logging, stdout is used for output in structured mode
+    match &cli.action {
+        Some(Actions::Autotagger { output: Some(_), .. }) => onetagger_shared::setup_stderr(),
+        _ => onetagger_shared::setup()
+    }
     info!("\n\nStarting OneTagger v{VERSION} Commit: {COMMIT} OS: {}\n\n", std::env::consts::OS);
 
 
+    let fail_exit_code = cli.fail_exit_code;
     let action = cli.action.unwrap();
     match &action {
-        Actions::Autotagger { path, .. } => {
+        Actions::Autotagger { path, resume, output, .. } => {
             let config = action.get_at_config().expect("Failed loading config file!");
             debug!("{:?}", config);
 
-            // Get files
-            let files = if path.is_file() {
-                onetagger_playlist::get_files_from_playlist_file(path).expect("Not a valid playlist file")
-            } else {
-                AudioFileInfo::get_file_list(&path, config.include_subfolders)
+            let finished = Arc::new(Mutex::new(None));
+            let rx = match resume {
+                // Continue previous run
+                Some(run_id) => Tagger::resume_files(&config, run_id, finished.clone()).expect("Failed resuming run!"),
+                None => {
+                    // Get files
+                    let path = path.as_ref().unwrap();
+                    let files = if path.is_file() {
+                        onetagger_playlist::get_files_from_playlist_file(path).expect("Not a valid playlist file")
+                    } else {
+                        AudioFileInfo::get_file_list(path, config.include_subfolders)
+                    };
+                    Tagger::tag_files(&config, files, finished.clone())
+                }
             };
-
-            let rx = Tagger::tag_files(&config, files, Arc::new(Mutex::new(None)));
             let start = timestamp!();
+            // Overall state of file across platforms
+            let mut states: HashMap<PathBuf, TaggingState> = HashMap::new();
+            let mut first = true;
+            if *output == Some(OutputFormat::Json) {
+                println!("[");
+            }
             for status in rx {
                 debug!("{status:?}");
+                let state = states.entry(status.status.path.clone()).or_insert(TaggingState::Error);
+                if state_priority(&status.status.status) > state_priority(state) {
+                    *state = status.status.status.clone();
+                }
+                if let Some(output) = output {
+                    output.print(&json!({
+                        "type": "status",
+                        "path": status.status.path,
+                        "platform": status.platform,
+                        "state": status.status.status,
+                        "accuracy": status.status.accuracy,
+                        "reason": status.status.reason,
+                        "releaseId": status.status.release_id,
+                        "message": status.status.message,
+                        "usedShazam": status.status.used_shazam,
+                        "diff": status.status.diff,
+                        "score": status.status.score,
+                    }), first);
+                    first = false;
+                }
+                // Print planned changes
+                if let Some(diff) = &status.status.diff {
+                    for field in &diff.fields {
+                        info!("[DRY RUN] {:?} {}: {:?} -> {:?}", status.status.path, field.tag, field.before, field.after);
+                    }
+                    for picture in &diff.pictures {
+                        info!("[DRY RUN] {:?} Picture {:?}: {:?} -> {:?}", status.status.path, picture.kind, 
+                            picture.before.as_ref().map(|p| p.size), picture.after.as_ref().map(|p| p.size));
+                    }
+                }
             }
             info!("Tagging finished, took: {} seconds.", (timestamp!() - start) / 1000);
+            if let Some(report) = finished.lock().unwrap().as_ref().map(|f| f.report_file.clone()).flatten() {
+                info!("Dry run report: {report}");
+            }
+            if let Some(journal) = finished.lock().unwrap().as_ref().map(|f| f.journal_file.clone()).flatten() {
+                info!("Undo journal: {journal}");
+            }
+
+            // Summary
+            let count = |state: TaggingState| states.values().filter(|s| **s == state).count();
+            let failed = count(TaggingState::Error);
+            if let Some(output) = output {
+                output.print(&json!({
+                    "type": "summary",
+                    "total": states.len(),
+                    "ok": count(TaggingState::Ok),
+                    "failed": failed,
+                    "skipped": count(TaggingState::Skipped),
+                    "elapsed": (timestamp!() - start) / 1000,
+                    "finished": finished.lock().unwrap().clone(),
+                }), first);
+                if *output == OutputFormat::Json {
+                    println!("\n]");
+                }
+            }
+            // Structured output always reports failures in exit code
+            exit_on_failure(fail_exit_code || output.is_some(), failed);
+        },
+        // Restore original tags
+        Actions::Undo { journal, files } => {
+            let files = match files.is_empty() {
+                true => None,
+                false => Some(files.as_slice())
+            };
+            let results = UndoJournal::restore(journal, files).expect("Failed loading undo journal!");
+            let failed = results.iter().filter(|(_, r)| r.is_err()).count();
+            info!("Restored {} files, failed: {failed}", results.len() - failed);
+            exit_on_failure(fail_exit_code, failed);
+        },
+        // Inspect or purge HTTP cache
+        Actions::Cache { purge, platform, expired } => {
+            if *purge || *expired {
+                let removed = HttpCache::purge(platform.as_deref(), *expired).expect("Failed purging cache!");
+                info!("Removed {removed} cache entries");
+                return;
+            }
+            let stats = HttpCache::stats().expect("Failed reading cache!");
+            let mut platforms = stats.iter().filter(|(p, _)| platform.as_ref().map(|platform| platform == *p).unwrap_or(true)).collect::<Vec<_>>();
+            platforms.sort_by(|a, b| a.0.cmp(b.0));
+            for (p, s) in platforms {
+                println!("{p}: {} entries ({} expired), {:.2} MB", s.entries, s.expired, s.size as f64 / 1024.0 / 1024.0);
+            }
+            println!("Cache folder: {}", HttpCache::folder(None).map(|p| p.display().to_string()).unwrap_or_default());
         },
         Actions::Audiofeatures { path, config, client_id, client_secret, no_subfolders } => {
             let file = File::open(config).expect("Failed reading config file!");
@@ -86,6 +194,93 @@ fn main() {
             }
             info!("Tagging finished, took: {} seconds.", (timestamp!() - start) / 1000);
         },
+        // Offline BPM & key detection
+        Actions::Analyze { path, overwrite, camelot, no_bpm, no_key, no_subfolders } => {
+            let files = if path.is_file() {
+                onetagger_playlist::get_files_from_playlist_file(path).expect("Not a valid playlist file")
+            } else {
+                AudioFileInfo::get_file_list(&path, !*no_subfolders)
+            };
+            let start = timestamp!();
+            let mut failed = 0;
+            for file in &files {
+                match AudioAnalyzer::analyze_file(file, !*no_bpm, !*no_key, *overwrite, *camelot) {
+                    Ok(analysis) => info!("{file:?}: BPM: {:?}, Key: {:?} ({:?})", analysis.bpm, analysis.key,
+                        analysis.key.as_ref().map(|k| onetagger_tagger::to_camelot(k))),
+                    Err(e) => {
+                        error!("Failed analyzing {file:?}: {e}");
+                        failed += 1;
+                    }
+                }
+            }
+            info!("Analyzed {} files, failed: {failed}, took: {} seconds.", files.len() - failed, (timestamp!() - start) / 1000);
+        },
+        // Duplicate finder
+        Actions::Duplicates { path, no_subfolders, no_isrc, no_track_id, no_metadata, duration_tolerance, fingerprint, trash, json } => {
+            let mut config = DuplicatesConfig {
+                path: path.to_owned(),
+                include_subfolders: !*no_subfolders,
+                isrc: !*no_isrc,
+                track_id: !*no_track_id,
+                metadata: !*no_metadata,
+                fingerprint: *fingerprint,
+                ..Default::default()
+            };
+            if let Some(tolerance) = duration_tolerance {
+                config.duration_tolerance = *tolerance;
+            }
+            let groups = Duplicates::find(&config).expect("Failed finding duplicates!");
+            if *json {
+                println!("{}", serde_json::to_string_pretty(&groups).expect("Failed serializing groups!"));
+            } else {
+                for (i, group) in groups.iter().enumerate() {
+                    println!("{}. {:?}", i + 1, group.reasons);
+                    for (j, file) in group.files.iter().enumerate() {
+                        println!("  {} {:?} ({:?}, {} kbps)", if j == 0 { "*" } else { "-" }, file.path, file.format, file.bitrate.unwrap_or(0));
+                    }
+                }
+            }
+            info!("Found {} duplicate groups", groups.len());
+            if *trash {
+                let trashed = Duplicates::trash(&groups).expect("Failed moving duplicates to trash!");
+                info!("Moved {} files to trash", trashed.len());
+            }
+        },
+        // Loudness analysis
+        Actions::Replaygain { path, no_subfolders, no_album, overwrite } => {
+            let config = ReplayGainConfig {
+                path: path.to_owned(),
+                include_subfolders: !*no_subfolders,
+                album: !*no_album,
+                overwrite: *overwrite,
+            };
+            let start = timestamp!();
+            let results = ReplayGain::run(&config).expect("Failed calculating ReplayGain!");
+            for r in &results {
+                info!("{:?}: {:.2} LUFS, gain: {:.2} dB, peak: {:.6}, album gain: {}", r.path, r.loudness, r.gain, r.peak,
+                    r.album_gain.map(|g| format!("{g:.2} dB")).unwrap_or("-".to_string()));
+            }
+            info!("Written ReplayGain to {} files, took: {} seconds.", results.len(), (timestamp!() - start) / 1000);
+        },
+        Actions::ExportPlaylist { path, output, no_subfolders } => {
+            if !onetagger_playlist::has_playlist_extension(output) {
+                error!("Unsupported playlist format, supported: {}", onetagger_playlist::PLAYLIST_EXTENSIONS.join(", "));
+                std::process::exit(1);
+            }
+            let files = if path.is_file() {
+                onetagger_playlist::get_files_from_playlist_file(path).expect("Not a valid playlist file")
+            } else {
+                AudioFileInfo::get_file_list(&path, !*no_subfolders)
+            };
+            onetagger_playlist::export_playlist(&files, output).expect("Failed exporting playlist!");
+            info!("Exported {} files to {output:?}", files.len());
+        },
+        Actions::CopyCues { source, target } => {
+            match SeratoMarkers::copy(source, target).expect("Failed copying cue points!") {
+                true => info!("Copied Serato cue points, loops and beatgrid to {target:?}"),
+                false => warn!("{source:?} has no Serato markers"),
+            }
+        },
         // Spotify OAuth flow
         Actions::AuthorizeSpotify { client_id, client_secret, prompt, expose } => {
             let (auth_url, client) = Spotify::generate_auth_url(&client_id, &client_secret).expect("Failed generating auth URL!");
@@ -116,7 +311,17 @@ fn main() {
             std::process::exit(0);
         },
         // Renamer
-        Actions::Renamer { path, output, template, copy, no_subfolders, preview, overwrite, separator, keep_subfolders } => {
+        Actions::Renamer { path, output, template, copy, no_subfolders, preview, overwrite, separator, keep_subfolders, collision, sidecar, folder_file, undo } => {
+            // Revert previous run
+            if let Some(undo) = undo {
+                let results = RenameLog::undo(undo).expect("Failed loading rename log!");
+                let failed = results.iter().filter(|(_, r)| r.is_err()).count();
+                info!("Reverted {} files, failed: {failed}", results.len() - failed);
+                exit_on_failure(fail_exit_code, failed);
+                return;
+            }
+
+            let (path, template) = (path.as_ref().unwrap(), template.as_ref().unwrap());
             let config = RenamerConfig {
                 path: path.to_owned(),
                 out_dir: output.to_owned(),
@@ -126,8 +331,10 @@ fn main() {
                 overwrite: *overwrite,
                 separator: separator.to_string(),
                 keep_subfolders: *keep_subfolders,
+                collision: *collision,
+                companions: CompanionFiles { sidecars: sidecar.clone(), folder: folder_file.clone() },
             };
-            let mut renamer = Renamer::new(TemplateParser::parse(&template));
+            let mut renamer = Renamer::new(TemplateParser::parse(template));
             let files = AudioFileInfo::load_files_iter(&config.path, config.subfolders, None, None);
             let names = renamer.generate(files, &config).expect("Failed generating filenames!");
 
@@ -136,10 +343,50 @@ fn main() {
                 for (i, (from, to)) in names.iter().enumerate() {
                     println!("{}. {:?} -> {:?}", i + 1, from, to);
                 }
+                let (plan, _) = Renamer::plan(&names, &config);
+                for companion in config.companions.resolve(&plan.renamed(&names)) {
+                    println!("   {:?} -> {:?}{}", companion.from, companion.to, if companion.copy { " (copy)" } else { "" });
+                }
+                for collision in &plan.collisions {
+                    match &collision.resolved {
+                        Some(resolved) => println!("Collision ({:?}): {:?} -> {:?}, renamed to {:?}", collision.kind, collision.from, collision.to, resolved),
+                        None => println!("Collision ({:?}): {:?} -> {:?}, skipped", collision.kind, collision.from, collision.to),
+                    }
+                }
                 return;
             }
 
-            renamer.rename(&names, &config).expect("Failed renaming!");
+            let report = renamer.rename(&names, &config).expect("Failed renaming!");
+            if let Some(run_id) = report.run_id {
+                info!("Renamed {} files, can be reverted using: renamer --undo {run_id}", report.renamed);
+            }
+        },
+        // Tags from filename
+        Actions::FilenameTags { path, template, no_subfolders, preview, overwrite, separator } => {
+            let parser = FilenameParser::new(template).expect("Invalid template!");
+            let files = AudioFileInfo::get_file_list(path, !*no_subfolders);
+
+            // Only preview
+            if *preview {
+                for (i, file) in parser.preview(&files).into_iter().enumerate() {
+                    match file.values {
+                        Some(values) => println!("{}. {:?}: {}", i + 1, file.path, values.iter().map(|(k, v)| format!("{k} = {v}")).collect::<Vec<_>>().join(", ")),
+                        None => println!("{}. {:?}: doesn't match", i + 1, file.path),
+                    }
+                }
+                return;
+            }
+
+            let config = FilenameTagsConfig {
+                path: path.to_owned(),
+                template: template.to_string(),
+                subfolders: !*no_subfolders,
+                overwrite: *overwrite,
+                separator: separator.to_owned(),
+            };
+            let results = parser.write_files(&files, &config);
+            let failed = results.iter().filter(|(_, r)| r.is_err()).count();
+            info!("Written tags to {} files, failed: {failed}", results.len() - failed);
         },
         // Server mode
         Actions::Server { expose, path, browser } => {
@@ -168,15 +415,19 @@ struct Cli {
     /// Prints the default Audio Features config and exits
     #[clap(long)]
     audiofeatures_config: bool,
+
+    /// Exit with code 1 if any file failed (Autotagger, undo), always enabled with Autotagger --output
+    #[clap(long, global = true)]
+    fail_exit_code: bool,
 }
 
 #[derive(Subcommand, Debug, Clone)]
 enum Actions {
     /// Start Autotagger in CLI mode
     Autotagger {
-        /// Path to music files (overrides config)
-        #[clap(short, long)]
-        path: PathBuf,
+        /// Path to music files (overrides config), not needed with --resume
+        #[clap(short, long, required_unless_present = "resume")]
+        path: Option<PathBuf>,
 
         /// Specify a path to config file
         #[clap(short, long)]
@@ -265,6 +516,58 @@ enum Actions {
         /// Tag on multiple platforms instead of the default fallback mode
         #[clap(long)]
         multiplatform: bool,
+
+        /// Don't write any tags, only report the changes which would be made
+        #[clap(long)]
+        dry_run: bool,
+
+        /// Write which platform supplied each tag into the 1T_PROVENANCE tag
+        #[clap(long)]
+        write_provenance: bool,
+
+        /// Use the weighted match score (title, artists, version, duration, album, label, year, ISRC) as accuracy
+        #[clap(long)]
+        match_scoring: bool,
+
+        /// Write the dry run changes into a report file in the runs folder. Options: json, csv
+        #[clap(long)]
+        dry_run_report: Option<String>,
+
+        /// Cache the platform API responses on disk, so repeated runs don't make the same requests
+        #[clap(long)]
+        http_cache: bool,
+
+        /// Resume a stopped or crashed run by its run ID. Requires the same config and options
+        #[clap(long)]
+        resume: Option<String>,
+
+        /// Print the tagging statuses and summary to stdout (logs go to stderr). Exits with code 1 if any file failed
+        #[clap(long, value_enum)]
+        output: Option<OutputFormat>,
+    },
+    /// Restore the original tags from an Autotagger run
+    Undo {
+        /// Run ID (timestamp) or path to the journal file
+        #[clap(short, long)]
+        journal: String,
+
+        /// Restore only these files (can be used multiple times)
+        #[clap(short, long)]
+        files: Vec<PathBuf>,
+    },
+    /// Show the platform HTTP cache statistics or purge the cache
+    Cache {
+        /// Remove the cached responses
+        #[clap(long)]
+        purge: bool,
+
+        /// Only this platform
+        #[clap(short, long)]
+        platform: Option<String>,
+
+        /// Remove only expired responses
+        #[clap(long)]
+        expired: bool,
     },
     /// Start Audio Features in CLI mode
     Audiofeatures {
@@ -288,6 +591,112 @@ enum Actions {
         #[clap(long)]
         no_subfolders: bool,
     },
+    /// Detect BPM & key from the audio and write them into tags
+    Analyze {
+        /// Path to music files
+        #[clap(short, long)]
+        path: PathBuf,
+
+        /// Overwrite the existing BPM & key tags
+        #[clap(long)]
+        overwrite: bool,
+
+        /// Write the key tag in CAMELOT format
+        #[clap(long)]
+        camelot: bool,
+
+        /// Don't detect BPM
+        #[clap(long)]
+        no_bpm: bool,
+
+        /// Don't detect key
+        #[clap(long)]
+        no_key: bool,
+
+        /// Don't include subfolders
+        #[clap(long)]
+        no_subfolders: bool,
+    },
+    /// Find duplicate tracks
+    Duplicates {
+        /// Path to music files
+        #[clap(short, long)]
+        path: PathBuf,
+
+        /// Don't include subfolders
+        #[clap(long)]
+        no_subfolders: bool,
+
+        /// Don't group by ISRC
+        #[clap(long)]
+        no_isrc: bool,
+
+        /// Don't group by platform track IDs
+        #[clap(long)]
+        no_track_id: bool,
+
+        /// Don't group by artists & title
+        #[clap(long)]
+        no_metadata: bool,
+
+        /// Max duration difference in seconds, Default: 3
+        #[clap(long)]
+        duration_tolerance: Option<u64>,
+
+        /// Compare audio fingerprints (slow)
+        #[clap(long)]
+        fingerprint: bool,
+
+        /// Keep the best quality copy and move the rest to trash
+        #[clap(long)]
+        trash: bool,
+
+        /// Print the groups as JSON to stdout
+        #[clap(long)]
+        json: bool,
+    },
+    /// Calculate EBU R128 loudness and write ReplayGain tags (R128 for Opus)
+    Replaygain {
+        /// Path to music files
+        #[clap(short, long)]
+        path: PathBuf,
+
+        /// Don't include subfolders
+        #[clap(long)]
+        no_subfolders: bool,
+
+        /// Don't calculate album gain (every folder is an album)
+        #[clap(long)]
+        no_album: bool,
+
+        /// Recalculate folders which already have ReplayGain
+        #[clap(long)]
+        overwrite: bool,
+    },
+    /// Export folder or playlist as M3U, PLS, XSPF, Rekordbox XML or Traktor NML (by output extension)
+    ExportPlaylist {
+        /// Path to music files or playlist
+        #[clap(short, long)]
+        path: PathBuf,
+
+        /// Output file, format by extension (.m3u, .m3u8, .pls, .xspf, .xml for Rekordbox or .nml for Traktor)
+        #[clap(short, long)]
+        output: PathBuf,
+
+        /// Don't include subfolders
+        #[clap(long)]
+        no_subfolders: bool,
+    },
+    /// Copy Serato cue points, loops and beatgrid between files
+    CopyCues {
+        /// File to copy the markers from
+        #[clap(short, long)]
+        source: PathBuf,
+
+        /// File to write the markers to
+        #[clap(short, long)]
+        target: PathBuf,
+    },
     /// Authorize Spotify and cache the token
     AuthorizeSpotify {
         /// Spotify Client ID
@@ -308,16 +717,16 @@ enum Actions {
     },
     Renamer {
         /// Path to input files
-        #[clap(long, short)]
-        path: PathBuf,
+        #[clap(long, short, required_unless_present = "undo")]
+        path: Option<PathBuf>,
 
         /// Output directory
         #[clap(long, short)]
         output: Option<PathBuf>,
 
         /// New filename template
-        #[clap(long, short)]
-        template: String,
+        #[clap(long, short, required_unless_present = "undo")]
+        template: Option<String>,
 
         /// Copy files instead of moving
         #[clap(long)]
@@ -342,6 +751,48 @@ enum Actions {
         /// Keep original subfolders
         #[clap(long)]
         keep_subfolders: bool,
+
+        /// What to do when the target exists or multiple files get the same name: suffix, skip or overwrite
+        #[clap(long)]
+        collision: Option<CollisionPolicy>,
+
+        /// Also rename files with the same name and this extension, e.g. `lrc` (can be used multiple times)
+        #[clap(long)]
+        sidecar: Vec<String>,
+
+        /// Also move this per-folder file, filename or `*.ext`, e.g. `cover.jpg` (can be used multiple times)
+        #[clap(long)]
+        folder_file: Vec<String>,
+
+        /// Revert a previous rename, run ID (timestamp) or path to the rename log
+        #[clap(long, conflicts_with_all = ["path", "template", "preview"])]
+        undo: Option<String>,
+    },
+    /// Write tags parsed from the filename and folders
+    FilenameTags {
+        /// Path to input files
+        #[clap(long, short)]
+        path: PathBuf,
+
+        /// Template using the renamer variables, folders separated by `/`, e.g. `%albumartist%/%year% - %album%/%track%. %artist% - %title%`
+        #[clap(long, short)]
+        template: String,
+
+        /// Exclude subfolders
+        #[clap(long)]
+        no_subfolders: bool,
+
+        /// Don't write anything, only print the parsed values
+        #[clap(long)]
+        preview: bool,
+
+        /// Overwrite existing tags
+        #[clap(long)]
+        overwrite: bool,
+
+        /// Split artists and genres using this separator
+        #[clap(long)]
+        separator: Option<String>,
     },
     /// Start OneTagger server mode
     Server {
@@ -357,6 +808,42 @@ enum Actions {
     }
 }
 
+/// Machine-readable output format
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+enum OutputFormat {
+    /// Single JSON array, streamed element by element
+    Json,
+    /// One JSON object per line
+    Ndjson
+}
+
+impl OutputFormat {
+    /// Print single output object
+    fn print(&self, value: &Value, first: bool) {
+        match self {
+            OutputFormat::Json => print!("{}{value}", if first { "" } else { ",\n" }),
+            OutputFormat::Ndjson => println!("{value}"),
+        }
+        std::io::stdout().flush().ok();
+    }
+}
+
+/// Exit with code 1 if enabled and any file failed
+fn exit_on_failure(enabled: bool, failed: usize) {
+    if enabled && failed > 0 {
+        std::process::exit(1);
+    }
+}
+
+/// Used for getting the overall state of file tagged by multiple platforms
+fn state_priority(state: &TaggingState) -> u8 {
+    match state {
+        TaggingState::Error => 0,
+        TaggingState::Skipped => 1,
+        TaggingState::Ok => 2,
+    }
+}
+
 /// For easily generating CLI -> config
 macro_rules! config_option {
     ($target:expr, $t:tt) => {
@@ -376,7 +863,7 @@ impl Actions {
             Actions::Autotagger { path, config, platforms, tags, id3v24, 
                 overwrite, threads, strictness, album_art_file, merge_genres, camelot, 
                 short_title, match_duration, max_duration_difference, match_by_id, enable_shazam, force_shazam, 
-                skip_tagged, parse_filename, filename_template, no_subfolders, only_year, multiplatform } => {
+                skip_tagged, parse_filename, filename_template, no_subfolders, only_year, multiplatform, dry_run, dry_run_report, write_provenance, match_scoring, http_cache, .. } => {
 
                 // Load config
                 let mut config = if let Some(config_path) = config {
@@ -387,7 +874,9 @@ impl Actions {
                 };
 
                 // Overrides
-                config.path = Some(path.to_owned());
+                if let Some(path) = path {
+                    config.path = Some(path.to_owned());
+                }
                 if let Some(platforms) = platforms {
                     config.platforms = platforms.split(",").map(String::from).collect();
                 }
@@ -409,7 +898,13 @@ impl Actions {
                 }
                 // Boolean options
                 config_option!(config, id3v24, overwrite, album_art_file, merge_genres, camelot, short_title, match_duration,
-                    match_by_id, enable_shazam, force_shazam, skip_tagged, parse_filename, only_year, multiplatform);
+                    match_by_id, enable_shazam, force_shazam, skip_tagged, parse_filename, only_year, multiplatform, dry_run, write_provenance);
+                if *match_scoring {
+                    config.match_scoring.enabled = true;
+                }
+                if *http_cache {
+                    config.http_cache.enabled = true;
+                }
                 // Remaining options
                 if let Some(threads) = threads {
                     config.threads = *threads;
@@ -430,6 +925,12 @@ impl Actions {
                 if *no_subfolders {
                     config.include_subfolders = false;
                 }
+                if let Some(format) = dry_run_report {
+                    match serde_json::from_str(&format!("\"{}\"", format.to_lowercase())) {
+                        Ok(format) => config.dry_run_report = Some(format),
+                        Err(_) => warn!("Invalid dry run report format: {format}"),
+                    }
+                }
                 return Ok(config);
             },
             _ => unreachable!()
@@ -0,0 +1,206 @@
+use anyhow::Error;
+use std::sync::Mutex;
+use std::time::Duration;
+use reqwest::blocking::Client;
+use serde::{Deserialize, Serialize};
+use onetagger_player::fingerprint::Fingerprint;
+use onetagger_tagger::{Track, AutotaggerSource, AudioFileInfo, TaggerConfig, AutotaggerSourceBuilder, PlatformInfo, supported_tags, TrackMatch, PlatformCustomOptions, PlatformCustomOptionValue, FrameName};
+
+use crate::cache::HttpCache;
+use crate::musicbrainz::MusicBrainz;
+
+/// Max recordings to fetch from MusicBrainz per file
+const MAX_RECORDINGS: usize = 5;
+/// Min delay between requests in ms, AcoustID allows max 3 requests per second
+const REQUEST_INTERVAL: u128 = 350;
+
+lazy_static::lazy_static! {
+    /// Timestamp of the last request, shared by all threads
+    static ref LAST_REQUEST: Mutex<u128> = Mutex::new(0);
+}
+
+pub struct AcoustID {
+    client: Client,
+    musicbrainz: MusicBrainz,
+    config: AcoustIDConfig,
+}
+
+impl AcoustID {
+    pub fn new(config: AcoustIDConfig) -> AcoustID {
+        AcoustID {
+            client: Client::builder()
+                .user_agent("OneTagger/1.0")
+                .build()
+                .unwrap(),
+            musicbrainz: MusicBrainz::new(),
+            config
+        }
+    }
+
+    /// Tag with the fingerprint
+    pub fn fingerprint_tag() -> FrameName {
+        FrameName::new("Acoustid Fingerprint", "ACOUSTID_FINGERPRINT", "iTunes:Acoustid Fingerprint")
+    }
+
+    /// Tag with the AcoustID track ID
+    pub fn id_tag() -> FrameName {
+        FrameName::new("Acoustid Id", "ACOUSTID_ID", "iTunes:Acoustid Id")
+    }
+
+    /// Get fingerprint and duration (in seconds) from tag, or calculate it
+    fn fingerprint(info: &AudioFileInfo) -> Result<(String, u32), Error> {
+        let tag = Self::fingerprint_tag().by_format(&info.format);
+        if let (Some(fingerprint), Some(duration)) = (info.tags.get(&tag).and_then(|v| v.first()), info.duration) {
+            if !fingerprint.is_empty() {
+                return Ok((fingerprint.to_string(), duration.as_secs() as u32));
+            }
+        }
+        let fingerprint = Fingerprint::from_path(&info.path)?;
+        Ok((fingerprint.encode(), fingerprint.duration))
+    }
+
+    /// Lookup fingerprint
+    pub fn lookup(&self, fingerprint: &str, duration: u32) -> Result<LookupResponse, Error> {
+        debug!("AcoustID lookup: {} {duration}s", self.config.base_url);
+        let duration = duration.to_string();
+        let request = self.client.get(format!("{}/v2/lookup", self.config.base_url.trim_end_matches('/')))
+            .query(&[
+                ("client", self.config.api_key.as_str()),
+                ("meta", "recordingids"),
+                ("format", "json"),
+                ("duration", &duration),
+                ("fingerprint", fingerprint)
+            ]);
+        let response: LookupResponse = HttpCache::send_with("acoustid", request, |r| {
+            Self::rate_limit();
+            Ok(r.send()?)
+        })?.json()?;
+        if response.status != "ok" {
+            return Err(anyhow!("AcoustID error: {:?}", response.error));
+        }
+        Ok(response)
+    }
+
+    /// Wait until the next request is allowed, the lock is held while waiting so threads go one by one
+    fn rate_limit() {
+        let mut last = LAST_REQUEST.lock().unwrap_or_else(|e| e.into_inner());
+        let diff = timestamp!().saturating_sub(*last);
+        if diff < REQUEST_INTERVAL {
+            std::thread::sleep(Duration::from_millis((REQUEST_INTERVAL - diff) as u64));
+        }
+        *last = timestamp!();
+    }
+}
+
+impl AutotaggerSource for AcoustID {
+    fn match_track(&mut self, info: &AudioFileInfo, config: &TaggerConfig) -> Result<Vec<TrackMatch>, Error> {
+        let (fingerprint, duration) = Self::fingerprint(info)?;
+        let mut results = self.lookup(&fingerprint, duration)?.results;
+        results.sort_by(|a, b| b.score.total_cmp(&a.score));
+
+        // Resolve recordings on MusicBrainz
+        let mut matches: Vec<TrackMatch> = vec![];
+        for result in results.iter().filter(|r| r.score >= config.strictness) {
+            for recording in &result.recordings {
+                if matches.len() >= MAX_RECORDINGS {
+                    break;
+                }
+                if matches.iter().any(|m| m.track.track_id.as_ref() == Some(&recording.id)) {
+                    continue;
+                }
+                let recording = match self.musicbrainz.recording(&recording.id) {
+                    Ok(r) => r,
+                    Err(e) => {
+                        warn!("Failed fetching MusicBrainz recording {}: {e}", recording.id);
+                        continue;
+                    }
+                };
+                let mut track: Track = recording.into();
+                track.platform = "acoustid".to_string();
+                if self.config.write_fingerprint {
+                    track.other.push((Self::fingerprint_tag(), vec![fingerprint.to_string()]));
+                    track.other.push((Self::id_tag(), vec![result.id.to_string()]));
+                }
+                matches.push(TrackMatch::new(result.score, track));
+            }
+        }
+        Ok(matches)
+    }
+
+    fn extend_track(&mut self, track: &mut Track, _config: &TaggerConfig) -> Result<(), Error> {
+        let releases = self.musicbrainz.full_release(track.track_id.as_ref().unwrap())?;
+        MusicBrainz::extend_track(track, releases);
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LookupResponse {
+    pub status: String,
+    #[serde(default)]
+    pub results: Vec<LookupResult>,
+    pub error: Option<LookupError>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LookupResult {
+    pub id: String,
+    pub score: f64,
+    #[serde(default)]
+    pub recordings: Vec<LookupRecording>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LookupRecording {
+    pub id: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LookupError {
+    pub code: i32,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AcoustIDConfig {
+    pub api_key: String,
+    pub base_url: String,
+    pub write_fingerprint: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct AcoustIDBuilder;
+
+impl AutotaggerSourceBuilder for AcoustIDBuilder {
+    fn new() -> AcoustIDBuilder {
+        AcoustIDBuilder
+    }
+
+    fn get_source(&mut self, config: &TaggerConfig) -> Result<Box<dyn AutotaggerSource>, Error> {
+        let config: AcoustIDConfig = config.get_custom("acoustid")?;
+        Ok(Box::new(AcoustID::new(config)))
+    }
+
+    fn info(&self) -> PlatformInfo {
+        PlatformInfo {
+            id: "acoustid".to_string(),
+            name: "AcoustID".to_string(),
+            description: "Identifies tracks by audio fingerprint, metadata from MusicBrainz. Requires an API key".to_string(),
+            icon: include_bytes!("../assets/musicbrainz.png"),
+            max_threads: 2,
+            version: "1.0.0".to_string(),
+            requires_auth: true,
+            supported_tags: supported_tags!(Title, Artist, AlbumArtist, Album, URL, ReleaseId, TrackId, Duration, ISRC, Label, CatalogNumber, TrackNumber, Genre, OtherTags),
+            custom_options: PlatformCustomOptions::new()
+                .add_tooltip("api_key", "API key", "Application API key from acoustid.org", PlatformCustomOptionValue::String {
+                    value: String::new(), hidden: Some(true)
+                })
+                .add_tooltip("base_url", "API URL", "Change to use a different lookup server", PlatformCustomOptionValue::String {
+                    value: "https://api.acoustid.org".to_string(), hidden: Some(false)
+                })
+                .add_tooltip("write_fingerprint", "Write fingerprint", "Save the fingerprint and AcoustID into tags (requires Other tags enabled)", PlatformCustomOptionValue::Boolean {
+                    value: true
+                }),
+        }
+    }
+}
@@ -0,0 +1,95 @@
+use anyhow::Error;
+use std::path::Path;
+use onetagger_player::analysis::AudioAnalysis;
+use onetagger_tag::{Tag, Field};
+use onetagger_tagger::{Track, AutotaggerSource, AudioFileInfo, TaggerConfig, AutotaggerSourceBuilder, PlatformInfo, supported_tags, TrackMatch, SupportedTag, MatchScore};
+
+/// Offline BPM & key detection, for files which no platform knows
+pub struct AudioAnalyzer;
+
+impl AudioAnalyzer {
+    /// Analyze file and write the BPM and key tags. Existing tags are kept unless `overwrite`
+    pub fn analyze_file(path: impl AsRef<Path>, bpm: bool, key: bool, overwrite: bool, camelot: bool) -> Result<AudioAnalysis, Error> {
+        let mut tag_wrap = Tag::load_file(&path, true)?;
+        let tag = tag_wrap.tag_mut();
+        let missing = |field: Field| tag.get_field(field).map(|v| v.iter().all(|v| v.trim().is_empty())).unwrap_or(true);
+        let bpm = bpm && (overwrite || missing(Field::BPM));
+        let key = key && (overwrite || missing(Field::Key));
+        if !bpm && !key {
+            return Ok(AudioAnalysis { bpm: None, key: None });
+        }
+
+        let analysis = AudioAnalysis::from_path(&path, bpm, key)?;
+        if let Some(bpm) = analysis.bpm {
+            tag.set_field(Field::BPM, vec![(bpm.round() as i64).to_string()], overwrite);
+        }
+        if let Some(key) = &analysis.key {
+            let value = match camelot {
+                true => onetagger_tagger::to_camelot(key),
+                false => key.as_str()
+            };
+            tag.set_field(Field::Key, vec![value.to_string()], overwrite);
+        }
+        tag.save_file(path.as_ref())?;
+        Ok(analysis)
+    }
+}
+
+impl AutotaggerSource for AudioAnalyzer {
+    fn match_track(&mut self, info: &AudioFileInfo, config: &TaggerConfig) -> Result<Vec<TrackMatch>, Error> {
+        // Analyze only what would be written
+        let needed = |tag: SupportedTag, field: Field| config.tag_enabled(tag) && (config.overwrite_tag(tag) || info.tag_value(field).is_none());
+        let bpm = needed(SupportedTag::BPM, Field::BPM);
+        let key = needed(SupportedTag::Key, Field::Key);
+        if !bpm && !key {
+            return Ok(vec![]);
+        }
+
+        let analysis = AudioAnalysis::from_path(&info.path, bpm, key)?;
+        debug!("Audio analysis of {:?}: {analysis:?}", info.path);
+        if analysis.bpm.is_none() && analysis.key.is_none() {
+            return Ok(vec![]);
+        }
+        // Only BPM & key get written for this platform
+        let track = Track {
+            platform: "analyzer".to_string(),
+            bpm: analysis.bpm.map(|b| b.round() as i64),
+            key: analysis.key,
+            ..Default::default()
+        };
+        // Analysis of the file itself, nothing to score against the file tags
+        let score = MatchScore { total: 1.0, ..Default::default() };
+        Ok(vec![TrackMatch { score: Some(score), ..TrackMatch::new(1.0, track) }])
+    }
+
+    fn extend_track(&mut self, _track: &mut Track, _config: &TaggerConfig) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct AudioAnalyzerBuilder;
+
+impl AutotaggerSourceBuilder for AudioAnalyzerBuilder {
+    fn new() -> AudioAnalyzerBuilder {
+        AudioAnalyzerBuilder
+    }
+
+    fn get_source(&mut self, _config: &TaggerConfig) -> Result<Box<dyn AutotaggerSource>, Error> {
+        Ok(Box::new(AudioAnalyzer))
+    }
+
+    fn info(&self) -> PlatformInfo {
+        PlatformInfo {
+            id: "analyzer".to_string(),
+            name: "Audio Analysis".to_string(),
+            description: "Detects BPM & key from the audio, offline. Use as the last platform".to_string(),
+            icon: include_bytes!("../assets/analyzer.png"),
+            max_threads: 0,
+            version: "1.0.0".to_string(),
+            custom_options: Default::default(),
+            requires_auth: false,
+            supported_tags: supported_tags!(BPM, Key),
+        }
+    }
+}
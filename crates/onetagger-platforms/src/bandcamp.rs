@@ -7,6 +7,8 @@ use scraper::{Html, Selector};
 use serde_json::{json, Value};
 use serde::{Serialize, Deserialize};
 
+use crate::cache::HttpCache;
+
 pub struct Bandcamp {
     client: Client
 }
@@ -25,14 +27,14 @@ impl Bandcamp {
 
     /// Search for tracks
     fn search_tracks(&self, query: &str) -> Result<Vec<BandcampSearchResult>, Error> {
-        let response = self.client.post("https://bandcamp.com/api/bcsearch_public_api/1/autocomplete_elastic")
+        let request = self.client.post("https://bandcamp.com/api/bcsearch_public_api/1/autocomplete_elastic")
             .json(&json!({
                 "fan_id": null,
                 "full_page": false,
                 "search_filter": "t",
                 "search_text": query
-            }))
-            .send()?;
+            }));
+        let response = HttpCache::send("bandcamp", request)?;
         // No rate limit
         if response.status().is_success() {
             let r: Value = response.json()?;
@@ -48,7 +50,7 @@ impl Bandcamp {
     /// Get data from track page
     fn track_page(&self, url: &str) -> Result<BandcampTrack, Error> {
         // Fetch with rate limit
-        let response = self.client.get(url).send()?;
+        let response = HttpCache::send("bandcamp", self.client.get(url))?;
         if response.status().is_client_error() {
             warn!("Bandcamp track page rate limit... Waiting for few seconds");
             std::thread::sleep(Duration::from_secs(3));
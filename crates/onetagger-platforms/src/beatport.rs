@@ -11,6 +11,9 @@ use onetagger_tag::FrameName;
 use onetagger_tagger::{supported_tags, Album, AudioFileInfo, AutotaggerSource, AutotaggerSourceBuilder, MatchingUtils, PlatformCustomOptionValue, PlatformCustomOptions, PlatformInfo, SupportedTag, TaggerConfig, Track, TrackMatch, TrackNumber};
 use serde_json::{json, Value};
 
+use crate::cache::HttpCache;
+use crate::fixtures::HttpFixtures;
+
 const INVALID_ART: &'static str = "ab2d1d04-233d-4b08-8234-9782b34dcab8";
 
 
@@ -35,14 +38,13 @@ impl Beatport {
     /// Search for tracks on beatport
     pub fn search(&self, query: &str, page: i32, results_per_page: usize) -> Result<BeatportTrackResults, Error> {
         let query = Self::clear_search_query(query);
-        let response = self.client.get("https://www.beatport.com/search/tracks")
+        let request = self.client.get("https://www.beatport.com/search/tracks")
             .query(&[
                 ("q", &query), 
                 ("page", &page.to_string()),
                 ("per-page", &results_per_page.to_string())
-            ])
-            .send()?
-            .text()?;
+            ]);
+        let response = HttpCache::send("beatport", request)?.text()?;
         
         // Parse JSON
         let results: BeatportTrackResults = self.get_next_data(&response)?;
@@ -64,14 +66,13 @@ impl Beatport {
         // Fetch new if doesn't exist
         if (*token).is_none() {
             // Taken from https://embed.beatport.com/?id=4418593&type=release
-            let mut response: BeatportOAuth = self.client.post("https://account.beatport.com/o/token/")
+            let request = self.client.post("https://account.beatport.com/o/token/")
                 .form(&json!({
                     "client_id": "2tiTbKxmQFwnbFjMONU4k7njMRZmV3ZMwRBndiZs",
                     "client_secret": "RDUJyAk4zFEGtQ8rsTmylDSfxmALRNBn3D1BsRr7MKi3oa1TL9Mq9QxqUPK7loiumXolEWbJcWa4IGAhtwnTz1cSXClGJ1tkkNCNWwRwjxIKTZJKOJxbwaNt0Rm3WG0v",
                     "grant_type": "client_credentials"
-                }))
-                .send()?
-                .json()?;
+                }));
+            let mut response: BeatportOAuth = HttpFixtures::send_auth("beatport", request)?.json()?;
             response.expires_in = response.expires_in * 1000 + timestamp!() - 10_000;
             *token = Some(response);
             debug!("OAuth: {:?}", token);
@@ -88,9 +89,9 @@ impl Beatport {
     /// Fetch track using API
     pub fn track(&self, id: i64) -> Result<Option<BeatportTrack>, Error> {
         let token = self.update_token()?;
-        let response = self.client.get(&format!("https://api.beatport.com/v4/catalog/tracks/{}", id))
-            .bearer_auth(token)
-            .send()?;
+        let request = self.client.get(&format!("https://api.beatport.com/v4/catalog/tracks/{}", id))
+            .bearer_auth(token);
+        let response = HttpCache::send("beatport", request)?;
 
         // Restricted / deleted track
         if response.status() == StatusCode::FORBIDDEN {
@@ -103,18 +104,18 @@ impl Beatport {
     /// Fetch release using API
     pub fn release(&self, id: i64) -> Result<BeatportRelease, Error> {
         let token = self.update_token()?;
-        let response = self.client.get(&format!("https://api.beatport.com/v4/catalog/releases/{}", id))
-            .bearer_auth(token)
-            .send()?.json()?;
+        let request = self.client.get(&format!("https://api.beatport.com/v4/catalog/releases/{}", id))
+            .bearer_auth(token);
+        let response = HttpCache::send("beatport", request)?.json()?;
         Ok(response)
     }
 
     /// Get tracks from release
     pub fn release_tracks(&self, id: i64) -> Result<Vec<BeatportTrack>, Error> {
         let token = self.update_token()?;
-        let response: BeatportPagination<BeatportTrack> = self.client.get(&format!("https://api.beatport.com/v4/catalog/releases/{}/tracks?per_page=200", id))
-            .bearer_auth(token)
-            .send()?.json()?;
+        let request = self.client.get(&format!("https://api.beatport.com/v4/catalog/releases/{}/tracks?per_page=200", id))
+            .bearer_auth(token);
+        let response: BeatportPagination<BeatportTrack> = HttpCache::send("beatport", request)?.json()?;
         Ok(response.results)
     }
 
@@ -9,6 +9,8 @@ use serde::{Serialize, Deserialize};
 
 use onetagger_tagger::{AutotaggerSource, Track, TaggerConfig, AudioFileInfo, MatchingUtils, AutotaggerSourceBuilder, PlatformInfo, PlatformCustomOptions, PlatformCustomOptionValue, supported_tags, TrackMatch};
 
+use crate::fixtures::HttpFixtures;
+
 pub struct Beatsource {
     client: Client,
     token_manager: BeatsourceTokenManager
@@ -28,16 +30,15 @@ impl Beatsource {
 
     /// Search for tracks
     pub fn search(&self, query: &str) -> Result<BeatsourceSearchResponse, Error> {
-        let res: BeatsourceSearchResponse = self.client.get("https://api.beatsource.com/v4/catalog/search")
+        let request = self.client.get("https://api.beatsource.com/v4/catalog/search")
             .query(&[
                 ("pubper_page", "100"),
                 ("page", "1"),
                 ("type", "tracks"),
                 ("q", query)
             ])
-            .bearer_auth(self.token_manager.token()?)
-            .send()?
-            .json()?;
+            .bearer_auth(self.token_manager.token()?);
+        let res: BeatsourceSearchResponse = HttpFixtures::send("beatsource", request)?.json()?;
         Ok(res)
     }
 }
@@ -207,7 +208,7 @@ impl BeatsourceTokenManager {
     /// Fetch token from homepage
     fn fetch_token(&self) -> Result<BeatsourceToken, Error> {
         debug!("Updating Beatsource token!");
-        let body = self.client.get("https://www.beatsource.com").send()?.text()?;
+        let body = HttpFixtures::send("beatsource", self.client.get("https://www.beatsource.com"))?.text()?;
         let document = Html::parse_document(&body);
         let selector = Selector::parse("script#__NEXT_DATA__").unwrap();
         let elem = document.select(&selector).next().ok_or(anyhow!("Missing __NEXT_DATA__"))?;
@@ -10,6 +10,9 @@ use serde::de::DeserializeOwned;
 use serde::{Serialize, Deserialize};
 use serde_json::json;
 
+use crate::cache::HttpCache;
+use crate::fixtures::HttpFixtures;
+
 struct BPMSupreme {
     client: Client,
     library: BPMMusicLibrary
@@ -36,7 +39,7 @@ impl BPMSupreme {
             .user_agent("Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/101.0.4951.67 Safari/537.36.")
             .build()
             .unwrap();
-        let res: BPMSupremeResponse<BPMSupremeUser> = client.post("https://api.bpmsupreme.com/v4/login")
+        let request = client.post("https://api.bpmsupreme.com/v4/login")
             .json(&json!({
                 "device": {
                     "app_type": "web_landing",
@@ -51,8 +54,8 @@ impl BPMSupreme {
                 "email": email,
                 "password": password,
                 "from": "global-login"
-            }))
-            .send()?
+            }));
+        let res: BPMSupremeResponse<BPMSupremeUser> = HttpFixtures::send_auth("bpmsupreme", request)?
             .error_for_status()?
             .json()?;
         debug!("User: {:?}", res.data);
@@ -61,13 +64,15 @@ impl BPMSupreme {
 
     /// Wrapper for GET request with rate limit
     fn get<T: DeserializeOwned>(&self, url: &str, query: &[(&str, &str)]) -> Result<T, Error> {
-        let res = self.client.get(url)
-            .query(query)
-            .send()?;
+        let mut delay = 3;
+        let res = HttpCache::send_with("bpmsupreme", self.client.get(url).query(query), |request| {
+            let res = request.send()?;
+            delay = res.headers().get("retry-after").map(|h| h.to_str().unwrap().parse().ok()).flatten().unwrap_or(3);
+            Ok(res)
+        })?;
 
         // Rate limit
         if res.status() == StatusCode::TOO_MANY_REQUESTS {
-            let delay = res.headers().get("retry-after").map(|h| h.to_str().unwrap().parse().ok()).flatten().unwrap_or(3);
             warn!("BPM Supreme rate limited, waiting for: {delay}s");
             std::thread::sleep(Duration::from_secs(delay));
             return self.get(url, query);
@@ -0,0 +1,408 @@
+use std::collections::HashMap;
+use std::hash::Hasher;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+use anyhow::Error;
+use reqwest::StatusCode;
+use reqwest::blocking::{RequestBuilder, Response};
+use serde::{Serialize, Deserialize};
+use serde::de::DeserializeOwned;
+use onetagger_shared::Settings;
+use onetagger_tagger::HttpCacheConfig;
+
+use crate::fixtures::HttpFixtures;
+
+/// How often (in writes) to check the size of the cache
+const CLEANUP_INTERVAL: usize = 100;
+
+lazy_static::lazy_static! {
+    static ref CACHE_CONFIG: Mutex<HttpCacheConfig> = Mutex::new(HttpCacheConfig::default());
+    static ref CACHE_WRITES: AtomicUsize = AtomicUsize::new(0);
+    /// Override of the cache folder
+    static ref CACHE_FOLDER: Mutex<Option<PathBuf>> = Mutex::new(None);
+}
+
+/// Persistent on-disk cache of platform API responses
+pub struct HttpCache;
+
+impl HttpCache {
+    /// Update the cache config
+    pub fn configure(config: &HttpCacheConfig) {
+        *CACHE_CONFIG.lock().unwrap() = config.clone();
+    }
+
+    /// Get the current config
+    pub fn config() -> HttpCacheConfig {
+        CACHE_CONFIG.lock().unwrap().clone()
+    }
+
+    /// Use different cache folder, `None` = `cache/http` in the settings folder
+    pub fn set_folder(folder: Option<PathBuf>) {
+        *CACHE_FOLDER.lock().unwrap() = folder;
+    }
+
+    /// Get cache folder (or platform's subfolder)
+    pub fn folder(platform: Option<&str>) -> Result<PathBuf, Error> {
+        let folder = CACHE_FOLDER.lock().unwrap().clone();
+        let mut folder = match folder {
+            Some(folder) => folder,
+            None => Settings::get_folder()?.join("cache").join("http"),
+        };
+        if let Some(platform) = platform {
+            folder = folder.join(platform);
+        }
+        if !folder.exists() {
+            std::fs::create_dir_all(&folder)?;
+        }
+        Ok(folder)
+    }
+
+    /// Send the request or return the cached response
+    pub fn send(platform: &str, request: RequestBuilder) -> Result<CachedResponse, Error> {
+        Self::send_with(platform, request, |r| Ok(r.send()?))
+    }
+
+    /// Same as `send`, but the request is sent using `f` (for rate limiting etc.), which is called only on cache miss
+    pub fn send_with<F>(platform: &str, request: RequestBuilder, f: F) -> Result<CachedResponse, Error>
+    where
+        F: FnOnce(RequestBuilder) -> Result<Response, Error>
+    {
+        // Fixtures bypass the cache
+        if HttpFixtures::enabled() {
+            return HttpFixtures::send_with(platform, request, f);
+        }
+
+        let config = Self::config();
+        let key = match config.enabled {
+            true => Self::key(platform, &request, true),
+            false => None
+        };
+
+        // Cache hit
+        if let Some((key, url)) = &key {
+            match Self::load(platform, key, config.ttl) {
+                Ok(Some(response)) => {
+                    debug!("HTTP cache hit: {platform} {url}");
+                    return Ok(response);
+                },
+                Ok(None) => {},
+                Err(e) => warn!("Failed reading HTTP cache entry {platform} {url}: {e}"),
+            }
+        }
+
+        let response = CachedResponse::from_response(f(request)?)?;
+        if let Some((key, url)) = key {
+            if response.status.is_success() {
+                if let Err(e) = Self::save(platform, &key, &url, &response) {
+                    warn!("Failed saving HTTP cache entry {platform} {url}: {e}");
+                }
+            }
+        }
+        Ok(response)
+    }
+
+    /// Remove cached response of request, for responses which are successful but contain an error
+    pub fn remove(platform: &str, request: &RequestBuilder) -> Result<(), Error> {
+        if let Some((key, _)) = Self::key(platform, request, true) {
+            let path = Self::folder(Some(platform))?.join(format!("{key}.json"));
+            if path.exists() {
+                std::fs::remove_file(path)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Generate key from platform, method, URL (including query) and body. Returns (key, url)
+    pub(crate) fn key(platform: &str, request: &RequestBuilder, include_body: bool) -> Option<(String, String)> {
+        Self::key_without(platform, request, include_body, &[])
+    }
+
+    /// Same as `key`, but the `ignored` query parameters (timestamps, tokens) are not part of the key nor URL
+    pub(crate) fn key_without(platform: &str, request: &RequestBuilder, include_body: bool, ignored: &[&str]) -> Option<(String, String)> {
+        let request = request.try_clone()?.build().ok()?;
+        let mut url = request.url().clone();
+        if !ignored.is_empty() && url.query().is_some() {
+            let query = url.query_pairs().filter(|(k, _)| !ignored.contains(&k.as_ref())).map(|(k, v)| (k.to_string(), v.to_string())).collect::<Vec<_>>();
+            url.query_pairs_mut().clear().extend_pairs(query);
+        }
+        let mut hasher = StableHasher::default();
+        hasher.write_part(platform.as_bytes());
+        hasher.write_part(request.method().as_str().as_bytes());
+        hasher.write_part(url.as_str().as_bytes());
+        if let Some(body) = request.body().and_then(|b| b.as_bytes()).filter(|_| include_body) {
+            hasher.write_part(body);
+        }
+        Some((format!("{:016x}", hasher.finish()), url.to_string()))
+    }
+
+    /// Load cached response if valid
+    fn load(platform: &str, key: &str, ttl: u64) -> Result<Option<CachedResponse>, Error> {
+        let path = Self::folder(Some(platform))?.join(format!("{key}.json"));
+        if !path.exists() {
+            return Ok(None);
+        }
+        let entry: CacheEntry = serde_json::from_slice(&std::fs::read(&path)?)?;
+        if entry.expired(ttl) {
+            std::fs::remove_file(&path).ok();
+            return Ok(None);
+        }
+        Ok(Some(CachedResponse { status: StatusCode::from_u16(entry.status)?, body: entry.body }))
+    }
+
+    /// Write response into cache
+    fn save(platform: &str, key: &str, url: &str, response: &CachedResponse) -> Result<(), Error> {
+        let path = Self::folder(Some(platform))?.join(format!("{key}.json"));
+        let entry = CacheEntry {
+            url: url.to_string(),
+            created: now(),
+            status: response.status.as_u16(),
+            body: response.body.to_string(),
+        };
+        // Temp file, because the same entry might be read by other thread
+        let tmp = path.with_extension("json.tmp");
+        std::fs::write(&tmp, serde_json::to_vec(&entry)?)?;
+        std::fs::rename(&tmp, &path)?;
+
+        if CACHE_WRITES.fetch_add(1, Ordering::Relaxed).is_multiple_of(CLEANUP_INTERVAL) {
+            if let Err(e) = Self::cleanup() {
+                warn!("Failed cleaning up HTTP cache: {e}");
+            }
+        }
+        Ok(())
+    }
+
+    /// Get all cache files of all platforms with their (platform, size, modified time)
+    fn files() -> Result<Vec<(PathBuf, String, u64, SystemTime)>, Error> {
+        let mut out = vec![];
+        for platform in std::fs::read_dir(Self::folder(None)?)? {
+            let platform = platform?;
+            if !platform.file_type()?.is_dir() {
+                continue;
+            }
+            let name = platform.file_name().to_string_lossy().to_string();
+            for file in std::fs::read_dir(platform.path())? {
+                let file = file?;
+                let meta = file.metadata()?;
+                if !meta.is_file() {
+                    continue;
+                }
+                out.push((file.path(), name.to_string(), meta.len(), meta.modified().unwrap_or(UNIX_EPOCH)));
+            }
+        }
+        Ok(out)
+    }
+
+    /// Remove oldest entries if the cache is over the size limit
+    fn cleanup() -> Result<(), Error> {
+        let max_size = Self::config().max_size * 1024 * 1024;
+        let mut files = Self::files()?;
+        let mut size: u64 = files.iter().map(|(_, _, s, _)| s).sum();
+        if size <= max_size {
+            return Ok(());
+        }
+        info!("HTTP cache over limit ({size} bytes), removing old entries");
+        files.sort_by_key(|(_, _, _, modified)| *modified);
+        // Leave some space, so it doesn't have to be cleaned on every write
+        for (path, _, s, _) in files {
+            if size <= max_size / 10 * 9 {
+                break;
+            }
+            std::fs::remove_file(path)?;
+            size -= s;
+        }
+        Ok(())
+    }
+
+    /// Get statistics per platform
+    pub fn stats() -> Result<HashMap<String, HttpCacheStats>, Error> {
+        let ttl = Self::config().ttl;
+        let mut stats: HashMap<String, HttpCacheStats> = HashMap::new();
+        for (path, platform, size, _) in Self::files()? {
+            let s = stats.entry(platform).or_default();
+            s.entries += 1;
+            s.size += size;
+            if Self::expired(&path, ttl) {
+                s.expired += 1;
+            }
+        }
+        Ok(stats)
+    }
+
+    /// Is the entry older than TTL (by the stored creation time), invalid entries are expired
+    fn expired(path: &Path, ttl: u64) -> bool {
+        std::fs::read(path).ok()
+            .and_then(|data| serde_json::from_slice::<CacheEntry>(&data).ok())
+            .map(|e| e.expired(ttl))
+            .unwrap_or(true)
+    }
+
+    /// Remove entries of all platforms (or only a single one). Returns number of removed entries
+    pub fn purge(platform: Option<&str>, expired_only: bool) -> Result<usize, Error> {
+        let ttl = Self::config().ttl;
+        let mut removed = 0;
+        for (path, p, _, _) in Self::files()? {
+            if platform.map(|platform| platform != p).unwrap_or(false) {
+                continue;
+            }
+            if expired_only && !Self::expired(&path, ttl) {
+                continue;
+            }
+            std::fs::remove_file(path)?;
+            removed += 1;
+        }
+        Ok(removed)
+    }
+}
+
+/// Cached (or fresh) response, has the subset of `Response` API used by platforms
+#[derive(Debug, Clone)]
+pub struct CachedResponse {
+    status: StatusCode,
+    body: String,
+}
+
+impl CachedResponse {
+    pub(crate) fn new(status: StatusCode, body: String) -> CachedResponse {
+        CachedResponse { status, body }
+    }
+
+    /// Read the whole response
+    pub fn from_response(response: Response) -> Result<CachedResponse, Error> {
+        Ok(CachedResponse {
+            status: response.status(),
+            body: response.text()?
+        })
+    }
+
+    /// HTTP status code
+    pub fn status(&self) -> StatusCode {
+        self.status
+    }
+
+    /// Get response body
+    pub fn text(self) -> Result<String, Error> {
+        Ok(self.body)
+    }
+
+    /// Parse response body as JSON
+    pub fn json<T: DeserializeOwned>(self) -> Result<T, Error> {
+        Ok(serde_json::from_str(&self.body)?)
+    }
+
+    /// Return error if the status is not successful
+    pub fn error_for_status(self) -> Result<CachedResponse, Error> {
+        if self.status.is_client_error() || self.status.is_server_error() {
+            return Err(anyhow!("HTTP status error: {}", self.status));
+        }
+        Ok(self)
+    }
+}
+
+/// Statistics of a single platform cache
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct HttpCacheStats {
+    pub entries: usize,
+    pub expired: usize,
+    /// In bytes
+    pub size: u64,
+}
+
+/// Cache file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CacheEntry {
+    url: String,
+    /// UNIX timestamp in seconds
+    created: u64,
+    status: u16,
+    body: String,
+}
+
+impl CacheEntry {
+    /// Is older than TTL
+    fn expired(&self, ttl: u64) -> bool {
+        now().saturating_sub(self.created) > ttl
+    }
+}
+
+/// FNV-1a hasher, unlike `DefaultHasher` it is stable across Rust versions and platforms, so the keys can be used for fixtures
+pub struct StableHasher(u64);
+
+impl Default for StableHasher {
+    fn default() -> Self {
+        Self(0xcbf29ce484222325)
+    }
+}
+
+impl StableHasher {
+    /// Write bytes with separator
+    fn write_part(&mut self, bytes: &[u8]) {
+        self.write(bytes);
+        self.write(&[0xff]);
+    }
+}
+
+impl Hasher for StableHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for b in bytes {
+            self.0 ^= *b as u64;
+            self.0 = self.0.wrapping_mul(0x100000001b3);
+        }
+    }
+}
+
+/// Current UNIX timestamp in seconds
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+/// Keys must not change between builds, fixtures depend on them
+#[test]
+fn stable_hasher() {
+    let mut hasher = StableHasher::default();
+    hasher.write(b"a");
+    assert_eq!(hasher.finish(), 0xaf63dc4c8601ec8c);
+}
+
+/// Hits, misses, expiry and statistics
+#[test]
+fn http_cache() {
+    // Fixtures bypass the cache
+    let _lock = crate::fixtures::TEST_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let folder = std::env::temp_dir().join(format!("onetagger-http-cache-{}", SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos()));
+    HttpCache::set_folder(Some(folder.clone()));
+    let platform = "test".to_string();
+    let request = |q: &str| reqwest::blocking::Client::new().get("https://example.invalid/search").query(&[("q", q)]);
+    let miss = |_: RequestBuilder| -> Result<Response, Error> { Err(anyhow!("miss")) };
+
+    // Disabled by default
+    let (key, url) = HttpCache::key(&platform, &request("a"), true).unwrap();
+    HttpCache::save(&platform, &key, &url, &CachedResponse::new(StatusCode::OK, "a".to_string())).unwrap();
+    assert!(HttpCache::send_with(&platform, request("a"), miss).is_err());
+
+    HttpCache::configure(&HttpCacheConfig { enabled: true, ttl: 60, max_size: 512 });
+    assert_eq!(HttpCache::send_with(&platform, request("a"), miss).unwrap().text().unwrap(), "a");
+    assert!(HttpCache::send_with(&platform, request("b"), miss).is_err());
+
+    // Expired entry
+    let (key, url) = HttpCache::key(&platform, &request("b"), true).unwrap();
+    let path = HttpCache::folder(Some(&platform)).unwrap().join(format!("{key}.json"));
+    let entry = CacheEntry { url, created: now() - 120, status: 200, body: "b".to_string() };
+    std::fs::write(&path, serde_json::to_vec(&entry).unwrap()).unwrap();
+    let stats = HttpCache::stats().unwrap();
+    assert_eq!((stats[&platform].entries, stats[&platform].expired), (2, 1));
+    assert!(HttpCache::send_with(&platform, request("b"), miss).is_err());
+    assert!(!path.exists());
+
+    assert_eq!(HttpCache::purge(Some(&platform), false).unwrap(), 1);
+    assert!(folder.join(&platform).exists());
+    HttpCache::configure(&HttpCacheConfig::default());
+    HttpCache::set_folder(None);
+    std::fs::remove_dir_all(&folder).ok();
+}
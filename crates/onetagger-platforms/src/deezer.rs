@@ -7,6 +7,8 @@ use reqwest::blocking::Client;
 use serde::de::DeserializeOwned;
 use serde::{Serialize, Deserialize};
 
+use crate::cache::HttpCache;
+
 /// Rate limit error code
 const RATE_LIMIT_CODE: i32 = 4;
 
@@ -33,9 +35,13 @@ impl Deezer {
 
     /// GET with rate limit wrap
     fn get<D: DeserializeOwned>(&self, path: &str, query: &[(&str, &str)]) -> Result<D, Error> {
-        let r: DeezerResponse<D> = self.client.get(format!("https://api.deezer.com{path}"))
-            .query(query)
-            .send()?.json()?;
+        let request = self.client.get(format!("https://api.deezer.com{path}"))
+            .query(query);
+        let r: DeezerResponse<D> = HttpCache::send("deezer", request.try_clone().unwrap())?.json()?;
+        // Errors are returned with 200
+        if r.error_code().is_some() {
+            HttpCache::remove("deezer", &request)?;
+        }
         if r.error_code() == Some(RATE_LIMIT_CODE) {
             warn!("Deezer Rate Limit, sleeping for 3s...");
             std::thread::sleep(Duration::from_secs(3));
@@ -5,12 +5,14 @@ use std::time::Duration;
 use regex::Regex;
 use chrono::NaiveDate;
 use reqwest::StatusCode;
-use reqwest::blocking::{Client, Response};
+use reqwest::blocking::{Client, RequestBuilder, Response};
 use serde_json::Value;
 use serde::{Serialize, Deserialize};
 use onetagger_tag::FrameName;
 use onetagger_tagger::{Track, AutotaggerSource, TaggerConfig, AudioFileInfo, MatchingUtils, TrackNumber, 
     AutotaggerSourceBuilder, PlatformInfo, PlatformCustomOptions, PlatformCustomOptionValue, supported_tags, SupportedTag, TrackMatch};
+use crate::cache::{HttpCache, CachedResponse};
+use crate::fixtures::HttpFixtures;
 
 pub struct Discogs {
     client: Client,
@@ -49,9 +51,10 @@ impl Discogs {
         self.rate_limit = 60;
     }
 
-    // Check if token is valid
+    // Check if token is valid, bypasses the cache
     pub fn validate_token(&mut self) -> bool {
-        match self.get("https://api.discogs.com/database/search", vec![("q", "test")]) {
+        let request = self.request("https://api.discogs.com/database/search", vec![("q", "test")]);
+        match HttpFixtures::send_with("discogs", request, |request| self.send(request)) {
             Ok(res) => if res.status() == StatusCode::OK { true } else { 
                 error!("Failed validating Discogs token: {}", res.status());
                 debug!("{:?}", res.text());
@@ -64,9 +67,24 @@ impl Discogs {
         }
     }
 
-    // Get request wrapper with rate limit
-    fn get(&mut self, url: &str, query: Vec<(&str, &str)>) -> Result<Response, Error> {
+    // Create request with auth header
+    fn request(&self, url: &str, query: Vec<(&str, &str)>) -> RequestBuilder {
+        let mut request = self.client.get(url).query(&query);
+        if self.token.is_some() {
+            request = request.header("Authorization", format!("Discogs token={}", self.token.as_ref().unwrap()));
+        }
+        request
+    }
+
+    // Cached get request
+    fn get(&mut self, url: &str, query: Vec<(&str, &str)>) -> Result<CachedResponse, Error> {
         debug!("Discogs GET {}", url);
+        let request = self.request(url, query);
+        HttpCache::send_with("discogs", request, |request| self.send(request))
+    }
+
+    // Send request with rate limit
+    fn send(&mut self, request: RequestBuilder) -> Result<Response, Error> {
         // Rate limit
         if self.last_request > 0 && self.rate_limit != -1 {
             let diff = timestamp!() - self.last_request;
@@ -77,12 +95,7 @@ impl Discogs {
                 sleep(Duration::from_millis(-wait as u64));
             }
         }
-        // Create request
-        let mut request = self.client.get(url).query(&query);
-        if self.token.is_some() {
-            request = request.header("Authorization", format!("Discogs token={}", self.token.as_ref().unwrap()));
-        }
-        let response = request.send()?;
+        let response = request.try_clone().ok_or(anyhow!("Invalid Discogs request"))?.send()?;
         
         // Rate limit
         let rate_limit_remaining = response.headers().get("X-Discogs-Ratelimit-Remaining")
@@ -91,7 +104,7 @@ impl Discogs {
         if rate_limit_remaining < 1 {
             warn!("Discogs rate limit hit! Waiting 10s...");
             sleep(Duration::from_secs(10));
-            return self.get(url, query);
+            return self.send(request);
         }
 
         // Save last reqeust time for rate limitting
@@ -0,0 +1,255 @@
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+use anyhow::Error;
+use reqwest::StatusCode;
+use reqwest::blocking::{RequestBuilder, Response};
+use serde::{Serialize, Deserialize};
+
+use crate::cache::{HttpCache, CachedResponse};
+
+/// Env variable with path to the fixtures folder
+pub const FIXTURES_ENV: &str = "ONETAGGER_HTTP_FIXTURES";
+/// Env variable with the mode (`record` or `replay`), default is replay
+pub const FIXTURES_MODE_ENV: &str = "ONETAGGER_HTTP_FIXTURES_MODE";
+
+lazy_static::lazy_static! {
+    static ref FIXTURES: RwLock<Option<(FixturesMode, PathBuf)>> = RwLock::new(HttpFixtures::from_env());
+}
+
+/// Fixtures are global, so tests which replay them can't run in parallel
+#[cfg(test)]
+pub(crate) static TEST_LOCK: std::sync::Mutex<()> = std::sync::Mutex::new(());
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum FixturesMode {
+    /// Do live requests and save them
+    Record,
+    /// Serve all requests from fixtures, without network
+    Replay
+}
+
+/// Record / replay layer for platform HTTP requests, so the Autotagger can run deterministically without network.
+/// Fixtures are stored as `{folder}/{platform}/{key}.json`. Spotify is not supported
+pub struct HttpFixtures;
+
+impl HttpFixtures {
+    /// Load mode from env variables
+    fn from_env() -> Option<(FixturesMode, PathBuf)> {
+        let path = std::env::var(FIXTURES_ENV).ok().filter(|p| !p.is_empty())?;
+        let mode = match std::env::var(FIXTURES_MODE_ENV).unwrap_or_default().to_lowercase().as_str() {
+            "record" => FixturesMode::Record,
+            "" | "replay" => FixturesMode::Replay,
+            m => {
+                warn!("Invalid HTTP fixtures mode: {m}, using replay");
+                FixturesMode::Replay
+            }
+        };
+        info!("HTTP fixtures: {mode:?} {path}");
+        Some((mode, PathBuf::from(path)))
+    }
+
+    /// Record all requests into folder
+    pub fn record(folder: impl AsRef<Path>) {
+        *FIXTURES.write().unwrap() = Some((FixturesMode::Record, folder.as_ref().to_owned()));
+    }
+
+    /// Serve all requests from folder
+    pub fn replay(folder: impl AsRef<Path>) {
+        *FIXTURES.write().unwrap() = Some((FixturesMode::Replay, folder.as_ref().to_owned()));
+    }
+
+    /// Back to live requests
+    pub fn disable() {
+        *FIXTURES.write().unwrap() = None;
+    }
+
+    /// Get current mode and folder
+    pub fn mode() -> Option<(FixturesMode, PathBuf)> {
+        FIXTURES.read().unwrap().clone()
+    }
+
+    /// Is recording or replaying
+    pub fn enabled() -> bool {
+        FIXTURES.read().unwrap().is_some()
+    }
+
+    /// Send request through the fixtures layer
+    pub fn send(platform: &str, request: RequestBuilder) -> Result<CachedResponse, Error> {
+        Self::send_with(platform, request, |r| Ok(r.send()?))
+    }
+
+    /// Send authentication request. Body is not part of the key, so fixtures don't depend on the credentials
+    pub fn send_auth(platform: &str, request: RequestBuilder) -> Result<CachedResponse, Error> {
+        Self::send_inner(platform, request, false, &[], |r| Ok(r.send()?))
+    }
+
+    /// Send request, the `ignored` query parameters (timestamps, tokens) are not part of the key
+    pub fn send_ignoring(platform: &str, request: RequestBuilder, ignored: &[&str]) -> Result<CachedResponse, Error> {
+        Self::send_inner(platform, request, true, ignored, |r| Ok(r.send()?))
+    }
+
+    /// Send request using `f`, which is called only if not replaying
+    pub fn send_with<F>(platform: &str, request: RequestBuilder, f: F) -> Result<CachedResponse, Error>
+    where
+        F: FnOnce(RequestBuilder) -> Result<Response, Error>
+    {
+        Self::send_inner(platform, request, true, &[], f)
+    }
+
+    /// Download binary data (album art), returns status and body. The data is stored next to the fixture as `{key}.bin`
+    pub fn download(platform: &str, request: RequestBuilder) -> Result<(StatusCode, Vec<u8>), Error> {
+        let (mode, path, url) = match Self::fixture(platform, &request, true, &[])? {
+            Some(f) => f,
+            None => {
+                let response = request.send()?;
+                return Ok((response.status(), response.bytes()?.to_vec()));
+            }
+        };
+        let data_path = path.with_extension("bin");
+
+        match mode {
+            FixturesMode::Replay => {
+                debug!("HTTP fixture replay: {platform} {url}");
+                let fixture = Fixture::load(&path, &url)?;
+                let data = std::fs::read(&data_path).map_err(|e| anyhow!("Missing HTTP fixture data {} for {url}: {e}", data_path.display()))?;
+                Ok((StatusCode::from_u16(fixture.status)?, data))
+            },
+            FixturesMode::Record => {
+                let method = request.try_clone().and_then(|r| r.build().ok()).map(|r| r.method().to_string()).unwrap_or_default();
+                let response = request.send()?;
+                let status = response.status();
+                let data = response.bytes()?.to_vec();
+                if status == StatusCode::TOO_MANY_REQUESTS || status == StatusCode::SERVICE_UNAVAILABLE {
+                    return Ok((status, data));
+                }
+                debug!("HTTP fixture record: {platform} {url}");
+                Fixture { method, url, status: status.as_u16(), body: String::new(), binary: true }.save(&path)?;
+                std::fs::write(&data_path, &data)?;
+                Ok((status, data))
+            }
+        }
+    }
+
+    /// Get mode, fixture path and URL of request, `None` if disabled
+    fn fixture(platform: &str, request: &RequestBuilder, include_body: bool, ignored: &[&str]) -> Result<Option<(FixturesMode, PathBuf, String)>, Error> {
+        let (mode, folder) = match Self::mode() {
+            Some(m) => m,
+            None => return Ok(None)
+        };
+        let (key, url) = HttpCache::key_without(platform, request, include_body, ignored).ok_or(anyhow!("Invalid request"))?;
+        Ok(Some((mode, folder.join(platform).join(format!("{key}.json")), url)))
+    }
+
+    fn send_inner<F>(platform: &str, request: RequestBuilder, include_body: bool, ignored: &[&str], f: F) -> Result<CachedResponse, Error>
+    where
+        F: FnOnce(RequestBuilder) -> Result<Response, Error>
+    {
+        let (mode, path, url) = match Self::fixture(platform, &request, include_body, ignored)? {
+            Some(f) => f,
+            None => return CachedResponse::from_response(f(request)?)
+        };
+
+        match mode {
+            FixturesMode::Replay => {
+                debug!("HTTP fixture replay: {platform} {url}");
+                let fixture = Fixture::load(&path, &url)?;
+                Ok(CachedResponse::new(StatusCode::from_u16(fixture.status)?, fixture.body))
+            },
+            FixturesMode::Record => {
+                let method = request.try_clone().and_then(|r| r.build().ok()).map(|r| r.method().to_string()).unwrap_or_default();
+                let response = CachedResponse::from_response(f(request)?)?;
+                // Don't record rate limits, they would be replayed forever
+                if response.status() == StatusCode::TOO_MANY_REQUESTS || response.status() == StatusCode::SERVICE_UNAVAILABLE {
+                    return Ok(response);
+                }
+                debug!("HTTP fixture record: {platform} {url}");
+                Fixture { method, url, status: response.status().as_u16(), body: response.clone().text()?, binary: false }.save(&path)?;
+                Ok(response)
+            }
+        }
+    }
+
+    /// Save fixture of request, for tests which replay platform responses
+    #[cfg(test)]
+    pub(crate) fn save_fixture(folder: &Path, platform: &str, request: RequestBuilder, ignored: &[&str], status: u16, body: &[u8]) {
+        let (key, url) = HttpCache::key_without(platform, &request, true, ignored).unwrap();
+        let path = folder.join(platform).join(format!("{key}.json"));
+        let binary = std::str::from_utf8(body).is_err();
+        let text = match binary {
+            true => String::new(),
+            false => String::from_utf8_lossy(body).to_string()
+        };
+        Fixture { method: "GET".to_string(), url, status, body: text, binary }.save(&path).unwrap();
+        if binary {
+            std::fs::write(path.with_extension("bin"), body).unwrap();
+        }
+    }
+}
+
+/// Fixture file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Fixture {
+    method: String,
+    url: String,
+    status: u16,
+    body: String,
+    /// Body is stored in a separate `.bin` file
+    #[serde(default)]
+    binary: bool,
+}
+
+impl Fixture {
+    fn load(path: &Path, url: &str) -> Result<Fixture, Error> {
+        let data = std::fs::read(path).map_err(|e| anyhow!("Missing HTTP fixture {} for {url}: {e}", path.display()))?;
+        Ok(serde_json::from_slice(&data)?)
+    }
+
+    fn save(&self, path: &Path) -> Result<(), Error> {
+        std::fs::create_dir_all(path.parent().unwrap())?;
+        std::fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+}
+
+
+/// Replay previously recorded response
+#[test]
+fn replay_fixture() {
+    let _lock = TEST_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let folder = std::env::temp_dir().join("onetagger-fixtures-test");
+    let request = || reqwest::blocking::Client::new().get("https://example.invalid/search").query(&[("q", "test")]);
+    let (key, url) = HttpCache::key("test", &request(), true).unwrap();
+    std::fs::create_dir_all(folder.join("test")).unwrap();
+    let fixture = Fixture { method: "GET".to_string(), url, status: 200, body: "{\"ok\":true}".to_string(), binary: false };
+    std::fs::write(folder.join("test").join(format!("{key}.json")), serde_json::to_string(&fixture).unwrap()).unwrap();
+
+    HttpFixtures::replay(&folder);
+    let response: serde_json::Value = HttpCache::send("test", request()).unwrap().json().unwrap();
+    assert_eq!(response["ok"], true);
+    // Unknown request fails instead of hitting network
+    assert!(HttpCache::send("test", request().query(&[("page", "2")])).is_err());
+    HttpFixtures::disable();
+    std::fs::remove_dir_all(&folder).ok();
+}
+
+/// Binary data and requests with ignored query parameters
+#[test]
+fn replay_binary_fixture() {
+    let _lock = TEST_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let folder = std::env::temp_dir().join("onetagger-fixtures-binary-test");
+    let client = reqwest::blocking::Client::new();
+    let data = vec![0xff, 0xd8, 0xff, 0xe0, 0x00];
+    HttpFixtures::save_fixture(&folder, "test", client.get("https://example.invalid/art.jpg"), &[], 200, &data);
+    let request = |t: &str| client.get("https://example.invalid/api").query(&[("q", "test"), ("t", t)]);
+    HttpFixtures::save_fixture(&folder, "test", request("1"), &["t"], 200, b"{\"ok\":true}");
+
+    HttpFixtures::replay(&folder);
+    let (status, body) = HttpFixtures::download("test", client.get("https://example.invalid/art.jpg")).unwrap();
+    assert_eq!((status, body), (StatusCode::OK, data));
+    let response: serde_json::Value = HttpFixtures::send_ignoring("test", request("2"), &["t"]).unwrap().json().unwrap();
+    assert_eq!(response["ok"], true);
+    assert!(HttpFixtures::send("test", request("2")).is_err());
+    HttpFixtures::disable();
+    std::fs::remove_dir_all(&folder).ok();
+}
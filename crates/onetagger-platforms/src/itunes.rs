@@ -2,10 +2,12 @@ use anyhow::Error;
 use std::time::Duration;
 use std::thread::sleep;
 use chrono::NaiveDate;
-use reqwest::blocking::{Client, Response};
+use reqwest::blocking::Client;
 use serde::{Serialize, Deserialize};
 use onetagger_tagger::{AutotaggerSource, AudioFileInfo, TaggerConfig, Track, MatchingUtils, AutotaggerSourceBuilder, PlatformInfo, PlatformCustomOptions, PlatformCustomOptionValue, supported_tags, TrackMatch};
 
+use crate::cache::{HttpCache, CachedResponse};
+
 pub struct ITunes {
     client: Client,
     // Requests per minute
@@ -26,26 +28,27 @@ impl ITunes {
         }
     }
 
-    /// Make get request to API
-    fn get(&mut self, path: &str, query: &[(&str, &str)]) -> Result<Response, Error> {
+    /// Make cached get request to API
+    fn get(&mut self, path: &str, query: &[(&str, &str)]) -> Result<CachedResponse, Error> {
         debug!("iTunes GET: {} {:?}", path, query);
-        // Rate limit
-        if self.last_request > 0 && self.rate_limit != -1 {
-            let diff = timestamp!() - self.last_request;
-            let req_ms = 1000_f64 / (self.rate_limit as f64 / 60_f64);
-            let wait = diff as f64 - req_ms;
-            if wait < 0_f64 {
-                info!("iTunes rate limit delay: {}", -wait);
-                sleep(Duration::from_millis(-wait as u64));
+        let request = self.client.get(&format!("https://itunes.apple.com{}", path)).query(query);
+        HttpCache::send_with("itunes", request, |request| {
+            // Rate limit
+            if self.last_request > 0 && self.rate_limit != -1 {
+                let diff = timestamp!() - self.last_request;
+                let req_ms = 1000_f64 / (self.rate_limit as f64 / 60_f64);
+                let wait = diff as f64 - req_ms;
+                if wait < 0_f64 {
+                    info!("iTunes rate limit delay: {}", -wait);
+                    sleep(Duration::from_millis(-wait as u64));
+                }
             }
-        }
 
-        // Do request
-        let res = self.client.get(&format!("https://itunes.apple.com{}", path))
-            .query(query)
-            .send()?;
-        self.last_request = timestamp!();
-        Ok(res)
+            // Do request
+            let res = request.send()?;
+            self.last_request = timestamp!();
+            Ok(res)
+        })
     }
 
     /// Search the iTunes API
@@ -8,6 +8,8 @@ use std::time::Duration;
 use anyhow::Error;
 use onetagger_tagger::{Track, AutotaggerSource, AudioFileInfo, TaggerConfig, MatchingUtils, TrackNumber, AutotaggerSourceBuilder, PlatformInfo, supported_tags, TrackMatch};
 
+use crate::cache::HttpCache;
+
 pub struct JunoDownload {
     client: Client
 }
@@ -27,11 +29,11 @@ impl JunoDownload {
 
     // Search releases, generate tracks
     pub fn search(&self, query: &str) -> Result<Vec<Track>, Error> {
-        let response = self.client
+        let request = self.client
             .get("https://www.junodownload.com/search/")
             .query(&[("q[all][]", query), ("solrorder", "relevancy"), ("items_per_page", "50")])
-            .header("Referer", "https://www.junodownload.com/")
-            .send()?;
+            .header("Referer", "https://www.junodownload.com/");
+        let response = HttpCache::send("junodownload", request)?;
 
         // Rate limitting
         if response.status() == StatusCode::TOO_MANY_REQUESTS {
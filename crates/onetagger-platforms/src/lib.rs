@@ -2,6 +2,8 @@
 #[macro_use] extern crate anyhow;
 #[macro_use] extern crate onetagger_shared;
 
+pub mod cache;
+pub mod fixtures;
 pub mod beatport;
 pub mod traxsource;
 pub mod discogs;
@@ -9,6 +11,8 @@ pub mod junodownload;
 pub mod spotify;
 pub mod itunes;
 pub mod musicbrainz;
+pub mod acoustid;
+pub mod analyzer;
 pub mod beatsource;
 pub mod bpmsupreme;
 pub mod deezer;
@@ -7,6 +7,8 @@ use reqwest::blocking::Client;
 use serde::{Deserialize, Serialize, de::DeserializeOwned};
 use onetagger_tagger::{Track, AutotaggerSource, AudioFileInfo, TaggerConfig, MatchingUtils, TrackNumber, AutotaggerSourceBuilder, PlatformInfo, supported_tags, TrackMatch};
 
+use crate::cache::HttpCache;
+
 pub struct MusicBrainz {
     client: Client
 }
@@ -27,9 +29,9 @@ impl MusicBrainz {
         new_query.push(("fmt", "json"));
         debug!("MusicBrainz GET: {} {:?}", path, new_query);
 
-        let response = self.client.get(&format!("https://musicbrainz.org/ws/2{}", path))
-            .query(&new_query)
-            .send()?;
+        let request = self.client.get(&format!("https://musicbrainz.org/ws/2{}", path))
+            .query(&new_query);
+        let response = HttpCache::send("musicbrainz", request)?;
         if response.status() == StatusCode::SERVICE_UNAVAILABLE {
             warn!("MusicBrainz rate limit hit! Waiting...");
             // Use random rate limit delay because threading
@@ -48,6 +50,14 @@ impl MusicBrainz {
         Ok(results)
     }
 
+    /// Get recording by ID
+    pub fn recording(&self, recording_id: &str) -> Result<Recording, Error> {
+        let recording: Recording = self.get(&format!("/recording/{recording_id}"), &[
+            ("inc", "artist-credits releases release-groups isrcs")
+        ])?;
+        Ok(recording)
+    }
+
     /// Get full release for recording
     pub fn full_release(&self, recording_id: &str) -> Result<BrowseReleases, Error> {
         let results: BrowseReleases = self.get("/release", &[
@@ -9,6 +9,8 @@ use serde::de::DeserializeOwned;
 use serde_json::Value;
 use onetagger_tagger::{AutotaggerSource, AudioFileInfo, TaggerConfig, Track, Lyrics, LyricsLine, LyricsLinePart, AutotaggerSourceBuilder, PlatformInfo, PlatformCustomOptions, supported_tags, TrackMatch};
 
+use crate::fixtures::HttpFixtures;
+
 #[derive(Debug, Clone)]
 pub struct Musixmatch {
     client: Client,
@@ -64,11 +66,9 @@ impl Musixmatch {
         // Timestamp
         let t = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis().to_string();
         query.push(("t", t.as_str()));
-        // Make request
-        let o = self.client.get(&format!("https://apic-desktop.musixmatch.com/ws/1.1/{action}"))
-            .query(&query)
-            .send()?
-            .json()?;
+        // Make request, token and timestamp are not part of the fixture key
+        let request = self.client.get(&format!("https://apic-desktop.musixmatch.com/ws/1.1/{action}")).query(&query);
+        let o = HttpFixtures::send_ignoring("musixmatch", request, &["usertoken", "t"])?.json()?;
         Ok(o)
     }
 
@@ -336,3 +336,29 @@ impl AutotaggerSourceBuilder for MusixmatchBuilder {
     }
 }
 
+
+/// Token & lyrics requests are replayed from fixtures, regardless of token and timestamp
+#[test]
+fn test_musixmatch_replay() {
+    let _lock = crate::fixtures::TEST_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let folder = std::env::temp_dir().join("onetagger-fixtures-musixmatch");
+    let url = |action: &str| format!("https://apic-desktop.musixmatch.com/ws/1.1/{action}");
+    let client = Client::new();
+    let token = client.get(url("token.get")).query(&[("user_language", "en"), ("app_id", "web-desktop-app-v1.0"), ("t", "0")]);
+    HttpFixtures::save_fixture(&folder, "musixmatch", token, &["t"], 200, br#"{"message":{"header":{"status_code":200},"body":{"user_token":"token"}}}"#);
+    let lyrics = client.get(url("macro.subtitles.get")).query(&[
+        ("format", "json"), ("namespace", "lyrics_richsynced"), ("optional_calls", "track.richsync"), ("subtitle_format", "lrc"),
+        ("q_artist", "Artist"), ("q_track", "Title"), ("app_id", "web-desktop-app-v1.0")
+    ]);
+    let body = br#"{"message":{"header":{"status_code":200},"body":{"macro_calls":{"track.lyrics.get":{"message":{"header":{"status_code":200},"body":{"lyrics":{"lyrics_id":1,"lyrics_body":"Line","lyrics_language":"en","lyrics_language_description":"English"}}}}}}}}"#;
+    HttpFixtures::save_fixture(&folder, "musixmatch", lyrics, &["usertoken", "t"], 200, body);
+
+    HttpFixtures::replay(&folder);
+    let result = Musixmatch::new().fetch_lyrics("Title", "Artist", 0);
+    HttpFixtures::disable();
+    std::fs::remove_dir_all(&folder).ok();
+    match result.unwrap().macro_calls["track.lyrics.get"].message.body.as_ref().unwrap() {
+        MusixmatchBody::Lyrics { lyrics } => assert_eq!(lyrics.lyrics_body, "Line"),
+        b => panic!("Invalid body: {b:?}")
+    }
+}
@@ -4,6 +4,9 @@ use chrono::NaiveDate;
 use scraper::{Html, Selector};
 use onetagger_tagger::{Track, AudioFileInfo, TaggerConfig, AutotaggerSource, MatchingUtils, TrackNumber, AutotaggerSourceBuilder, PlatformInfo, supported_tags, TrackMatch, SupportedTag};
 
+use crate::cache::HttpCache;
+use crate::fixtures::HttpFixtures;
+
 pub struct Traxsource {
     client: Client
 }
@@ -23,10 +26,9 @@ impl Traxsource {
     pub fn search_tracks(&self, query: &str) -> Result<Vec<Track>, Error> {
         // Fetch
         debug!("Q: {}", query);
-        let data = self.client.get("https://www.traxsource.com/search/tracks")
-            .query(&[("term", query)])
-            .send()?
-            .text()?;
+        let request = self.client.get("https://www.traxsource.com/search/tracks")
+            .query(&[("term", query)]);
+        let data = HttpCache::send("traxsource", request)?.text()?;
 
         // Minify and parse
         let data = String::from_utf8(minify_html::minify(&data.as_bytes(), &minify_html::Cfg::new()))?;
@@ -122,9 +124,7 @@ impl Traxsource {
     // Tracks in search don't have album name and art
     pub fn extend_track_traxsource(&self, track: &mut Track, album_meta: bool, album_art: bool) -> Result<(), Error> {
         // Fetch
-        let data = self.client.get(&track.url)
-            .send()?
-            .text()?;
+        let data = HttpCache::send("traxsource", self.client.get(&track.url))?.text()?;
         
         // Minify and parse
         let data = String::from_utf8(minify_html::minify(data.as_bytes(), &minify_html::Cfg::new()))?;
@@ -145,9 +145,7 @@ impl Traxsource {
         if !album_meta { 
             return Ok(());
         }
-        let data = self.client.get(format!("https://www.traxsource.com{}", album_url))
-            .send()?
-            .text()?;
+        let data = HttpCache::send("traxsource", self.client.get(format!("https://www.traxsource.com{}", album_url)))?.text()?;
         // Minify and parse
         let data = String::from_utf8(minify_html::minify(data.as_bytes(), &minify_html::Cfg::new()))?;
         let document = Html::parse_document(&data);
@@ -192,7 +190,7 @@ impl Traxsource {
 
         // Pre fetch album art since traxsource changed something
         if album_art {
-            self.client.get(art_url).header("Referer", "https://www.traxsource.com/").send().ok();
+            HttpFixtures::download("traxsource", self.client.get(art_url).header("Referer", "https://www.traxsource.com/")).ok();
         }
 
         Ok(())
@@ -245,4 +243,42 @@ impl AutotaggerSourceBuilder for TraxsourceBuilder {
             supported_tags: supported_tags!(Version, Artist, BPM, Key, Title, URL, Label, ReleaseDate, Genre, TrackId, Duration, Album, ReleaseId, CatalogNumber, AlbumArtist, TrackNumber, TrackTotal, AlbumArt)
         }
     }
-}
\ No newline at end of file
+}
+/// Search, track, album and album art requests are replayed from fixtures
+#[test]
+fn test_traxsource_replay() {
+    let _lock = crate::fixtures::TEST_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let folder = std::env::temp_dir().join("onetagger-fixtures-traxsource");
+    let t = Traxsource::new();
+    let search = r#"<div id="searchTrackList"><div class="trk-row"><div class="thumb"><img src="https://img.example.invalid/thumb.jpg"></div>
+        <div class="title"><a href="/track/123/title">Title</a><span class="version">Extended Mix <span class="duration">6:00</span></span></div>
+        <div class="artists"><a href="/artist/1/artist">Artist</a></div><div class="label"><a href="/label/1/label">Label</a></div>
+        <div class="key-bpm">Amin<br>124</div><div class="genre"><a href="/genre/1/house">House</a></div><div class="r-date">2020-01-02</div></div></div>"#;
+    let track = r#"<div class="ttl-info ellip"><a href="/title/555/album">Album</a></div>"#;
+    let album = r#"<div class="cat-rdate">CAT001 | 2020-01-02</div><h1 class="artists">A, B</h1>
+        <div class="trk-row play-trk ptk-123"><div class="tnum">1</div></div><div class="t-image"><img src="https://img.example.invalid/art.jpg"></div>"#;
+    HttpFixtures::save_fixture(&folder, "traxsource", t.client.get("https://www.traxsource.com/search/tracks").query(&[("term", "Artist Title")]), &[], 200, search.as_bytes());
+    HttpFixtures::save_fixture(&folder, "traxsource", t.client.get("https://www.traxsource.com/track/123/title"), &[], 200, track.as_bytes());
+    HttpFixtures::save_fixture(&folder, "traxsource", t.client.get("https://www.traxsource.com/title/555/album"), &[], 200, album.as_bytes());
+    HttpFixtures::save_fixture(&folder, "traxsource", t.client.get("https://img.example.invalid/art.jpg"), &[], 200, &[0xff, 0xd8, 0xff, 0xe0]);
+
+    HttpFixtures::replay(&folder);
+    let result = t.search_tracks("Artist Title").and_then(|mut tracks| {
+        t.extend_track_traxsource(&mut tracks[0], true, true)?;
+        Ok(tracks)
+    });
+    let art = HttpFixtures::download("traxsource", t.client.get("https://img.example.invalid/art.jpg"));
+    HttpFixtures::disable();
+    std::fs::remove_dir_all(&folder).ok();
+
+    let tracks = result.unwrap();
+    assert_eq!(tracks.len(), 1);
+    assert_eq!(tracks[0].title, "Title");
+    assert_eq!(tracks[0].version.as_deref(), Some("Extended Mix"));
+    assert_eq!(tracks[0].artists, vec!["Artist".to_string()]);
+    assert_eq!(tracks[0].bpm, Some(124));
+    assert_eq!(tracks[0].album.as_deref(), Some("Album"));
+    assert_eq!(tracks[0].catalog_number.as_deref(), Some("CAT001"));
+    assert_eq!(tracks[0].art.as_deref(), Some("https://img.example.invalid/art.jpg"));
+    assert_eq!(art.unwrap().1.len(), 4);
+}
@@ -0,0 +1,209 @@
+use anyhow::Error;
+use std::f64::consts::PI;
+use std::path::Path;
+use crate::fingerprint::fft;
+use crate::{AudioSources, AudioSource};
+
+/// Sample rate used for analysis
+const SAMPLE_RATE: u32 = 11025;
+/// Analyze only first 10 minutes
+const MAX_DURATION: u32 = 600;
+
+/// Onset detection frame size and step
+const ONSET_FRAME: usize = 1024;
+const ONSET_STEP: usize = 128;
+const MIN_BPM: f64 = 60.0;
+const MAX_BPM: f64 = 200.0;
+
+/// Chroma frame size (for better low frequency resolution) and step
+const CHROMA_FRAME: usize = 8192;
+const CHROMA_STEP: usize = 4096;
+const CHROMA_MIN_FREQ: f64 = 55.0;
+const CHROMA_MAX_FREQ: f64 = 2000.0;
+
+/// Krumhansl-Schmuckler key profiles
+const MAJOR_PROFILE: [f64; 12] = [6.35, 2.23, 3.48, 2.33, 4.38, 4.09, 2.52, 5.19, 2.39, 3.66, 2.29, 2.88];
+const MINOR_PROFILE: [f64; 12] = [6.33, 2.68, 3.52, 5.38, 2.60, 3.53, 2.54, 4.75, 3.98, 2.69, 3.34, 3.17];
+const MAJOR_KEYS: [&str; 12] = ["C", "Db", "D", "Eb", "E", "F", "F#", "G", "Ab", "A", "Bb", "B"];
+const MINOR_KEYS: [&str; 12] = ["Cm", "C#m", "Dm", "Ebm", "Em", "Fm", "F#m", "Gm", "G#m", "Am", "Bbm", "Bm"];
+
+/// Offline tempo & key estimation
+#[derive(Debug, Clone, PartialEq)]
+pub struct AudioAnalysis {
+    pub bpm: Option<f64>,
+    /// Standard notation (`Am`, `F#`), use `to_camelot` for Camelot
+    pub key: Option<String>,
+}
+
+impl AudioAnalysis {
+    /// Analyze file, skip the BPM or key detection if not needed
+    pub fn from_path(path: impl AsRef<Path>, bpm: bool, key: bool) -> Result<AudioAnalysis, Error> {
+        let source = AudioSources::from_path(path)?;
+        let samples = source.mono_samples(SAMPLE_RATE, Some(MAX_DURATION))?;
+        Ok(AudioAnalysis {
+            bpm: bpm.then(|| Self::detect_bpm(&samples)).flatten(),
+            key: key.then(|| Self::detect_key(&samples)).flatten(),
+        })
+    }
+
+    /// Estimate tempo from mono 11025Hz samples
+    pub fn detect_bpm(samples: &[i16]) -> Option<f64> {
+        let fps = SAMPLE_RATE as f64 / ONSET_STEP as f64;
+        let window = hann(ONSET_FRAME);
+
+        // Spectral flux of log magnitudes
+        let mut envelope = vec![];
+        let mut last: Vec<f64> = vec![0.0; ONSET_FRAME / 2];
+        for frame in frames(samples, ONSET_FRAME, ONSET_STEP) {
+            let spectrum = magnitudes(frame, &window);
+            let spectrum: Vec<f64> = spectrum.iter().map(|m| (1.0 + 1000.0 * m).ln()).collect();
+            envelope.push(spectrum.iter().zip(&last).map(|(m, l)| (m - l).max(0.0)).sum::<f64>());
+            last = spectrum;
+        }
+        if envelope.len() < 2 {
+            return None;
+        }
+        // First frame is compared against silence
+        envelope[0] = 0.0;
+
+        // Remove local mean (~0.5s), so only the peaks remain
+        let half = (fps / 4.0) as usize;
+        let envelope: Vec<f64> = (0..envelope.len()).map(|i| {
+            let part = &envelope[i.saturating_sub(half)..(i + half + 1).min(envelope.len())];
+            (envelope[i] - part.iter().sum::<f64>() / part.len() as f64).max(0.0)
+        }).collect();
+
+        // Autocorrelation up to 4 beats of the slowest tempo
+        let max_lag = ((fps * 60.0 / MIN_BPM) * 4.0).ceil() as usize + 1;
+        let acf: Vec<f64> = (0..max_lag.min(envelope.len())).map(|lag| {
+            envelope.iter().zip(&envelope[lag..]).map(|(a, b)| a * b).sum::<f64>()
+        }).collect();
+        if acf.is_empty() || acf[0] <= 0.0 {
+            return None;
+        }
+        let interpolate = |lag: f64| -> f64 {
+            let i = lag.floor() as usize;
+            match i + 1 < acf.len() {
+                true => acf[i] + (acf[i + 1] - acf[i]) * (lag - i as f64),
+                false => 0.0
+            }
+        };
+
+        // Score tempos by the average of the beat period multiples, with prior around 120 BPM
+        let mut best: Option<(f64, f64)> = None;
+        let mut bpm = MIN_BPM;
+        while bpm <= MAX_BPM {
+            let period = fps * 60.0 / bpm;
+            let multiples = ((acf.len() - 2) as f64 / period) as usize;
+            let score = (1..=multiples).map(|k| interpolate(period * k as f64)).sum::<f64>() / multiples as f64 / acf[0];
+            let prior = (-0.5 * (bpm / 120.0).log2().powi(2)).exp();
+            if best.map(|(_, s)| score * prior > s).unwrap_or(true) {
+                best = Some((bpm, score * prior));
+            }
+            bpm += 0.05;
+        }
+        best.filter(|(_, s)| *s > 0.0).map(|(bpm, _)| (bpm * 10.0).round() / 10.0)
+    }
+
+    /// Estimate musical key from mono 11025Hz samples
+    pub fn detect_key(samples: &[i16]) -> Option<String> {
+        let window = hann(CHROMA_FRAME);
+        // Pitch class of each FFT bin
+        let classes: Vec<Option<usize>> = (0..CHROMA_FRAME / 2).map(|i| {
+            let freq = i as f64 * SAMPLE_RATE as f64 / CHROMA_FRAME as f64;
+            if !(CHROMA_MIN_FREQ..=CHROMA_MAX_FREQ).contains(&freq) {
+                return None;
+            }
+            // A4 = 440Hz = pitch class 9
+            let note = (12.0 * (freq / 440.0).log2()).round() as i64 + 9;
+            Some(note.rem_euclid(12) as usize)
+        }).collect();
+
+        let mut chroma = [0.0; 12];
+        for frame in frames(samples, CHROMA_FRAME, CHROMA_STEP) {
+            let mut frame_chroma = [0.0; 12];
+            for (m, class) in magnitudes(frame, &window).iter().zip(&classes) {
+                if let Some(class) = class {
+                    frame_chroma[*class] += m;
+                }
+            }
+            // Every frame has the same weight, so loud parts don't dominate
+            let max = frame_chroma.iter().cloned().fold(0.0, f64::max);
+            if max > 0.0 {
+                for (c, f) in chroma.iter_mut().zip(frame_chroma) {
+                    *c += f / max;
+                }
+            }
+        }
+        if chroma.iter().all(|c| *c == 0.0) {
+            return None;
+        }
+
+        // Best correlating profile
+        let mut best = (f64::MIN, "");
+        for tonic in 0..12 {
+            let rotated: Vec<f64> = (0..12).map(|i| chroma[(tonic + i) % 12]).collect();
+            for (profile, keys) in [(MAJOR_PROFILE, MAJOR_KEYS), (MINOR_PROFILE, MINOR_KEYS)] {
+                let r = correlation(&rotated, &profile);
+                if r > best.0 {
+                    best = (r, keys[tonic]);
+                }
+            }
+        }
+        Some(best.1.to_string())
+    }
+}
+
+/// Hann window
+fn hann(size: usize) -> Vec<f64> {
+    (0..size).map(|i| 0.5 - 0.5 * (2.0 * PI * i as f64 / (size as f64 - 1.0)).cos()).collect()
+}
+
+/// Iterate over full frames
+fn frames(samples: &[i16], size: usize, step: usize) -> impl Iterator<Item = &[i16]> {
+    (0..samples.len().saturating_sub(size - 1)).step_by(step).map(move |i| &samples[i..i + size])
+}
+
+/// Magnitude spectrum of windowed frame
+fn magnitudes(frame: &[i16], window: &[f64]) -> Vec<f64> {
+    let mut data: Vec<(f64, f64)> = frame.iter().zip(window).map(|(s, w)| (*s as f64 / i16::MAX as f64 * w, 0.0)).collect();
+    fft(&mut data);
+    data[..frame.len() / 2].iter().map(|(re, im)| (re * re + im * im).sqrt()).collect()
+}
+
+/// Pearson correlation
+fn correlation(a: &[f64], b: &[f64]) -> f64 {
+    let mean_a = a.iter().sum::<f64>() / a.len() as f64;
+    let mean_b = b.iter().sum::<f64>() / b.len() as f64;
+    let cov = a.iter().zip(b).map(|(x, y)| (x - mean_a) * (y - mean_b)).sum::<f64>();
+    let var_a = a.iter().map(|x| (x - mean_a).powi(2)).sum::<f64>();
+    let var_b = b.iter().map(|y| (y - mean_b).powi(2)).sum::<f64>();
+    cov / (var_a * var_b).sqrt().max(f64::EPSILON)
+}
+
+#[test]
+fn analysis_synthetic() {
+    let rate = SAMPLE_RATE as f64;
+    let tone = |freqs: &[f64], seconds: f64| -> Vec<i16> {
+        (0..(rate * seconds) as usize).map(|i| {
+            let t = i as f64 / rate;
+            (freqs.iter().map(|f| (2.0 * PI * f * t).sin()).sum::<f64>() / freqs.len() as f64 * 8000.0) as i16
+        }).collect()
+    };
+    // A minor and C major triads
+    assert_eq!(AudioAnalysis::detect_key(&tone(&[220.0, 261.63, 329.63], 5.0)).as_deref(), Some("Am"));
+    assert_eq!(AudioAnalysis::detect_key(&tone(&[261.63, 329.63, 392.0], 5.0)).as_deref(), Some("C"));
+
+    // Clicks at 128 BPM
+    let beat = rate * 60.0 / 128.0;
+    let clicks: Vec<i16> = (0..(rate * 20.0) as usize).map(|i| {
+        let pos = i as f64 % beat;
+        match pos < 200.0 {
+            true => ((2.0 * PI * 1000.0 * i as f64 / rate).sin() * 16000.0 * (1.0 - pos / 200.0)) as i16,
+            false => 0
+        }
+    }).collect();
+    let bpm = AudioAnalysis::detect_bpm(&clicks).unwrap();
+    assert!((bpm - 128.0).abs() < 0.5, "{bpm}");
+    assert_eq!(AudioAnalysis::detect_bpm(&vec![0; SAMPLE_RATE as usize * 5]), None);
+}
@@ -0,0 +1,46 @@
+use std::time::Duration;
+use anyhow::Error;
+use lofty::file::AudioFile;
+use std::path::{PathBuf, Path};
+use rodio::Source;
+
+use crate::AudioSource;
+use crate::ffmpeg::FFmpegDecoder;
+
+/// WavPack, Monkey's Audio and Musepack. Rodio has no decoders for these,
+/// so they are decoded using ffmpeg (has to be installed and in PATH)
+pub struct APESource {
+    path: PathBuf,
+    duration: Duration,
+    channels: u16,
+    sample_rate: u32,
+}
+
+impl APESource {
+    pub fn new(path: impl AsRef<Path>) -> Result<APESource, Error> {
+        if !FFmpegDecoder::available() {
+            return Err(anyhow!("Playing WavPack, Monkey's Audio and Musepack files requires ffmpeg"));
+        }
+
+        // Get duration
+        let file = lofty::read_from_path(&path)?;
+        let properties = file.properties();
+
+        Ok(APESource {
+            duration: properties.duration(),
+            channels: properties.channels().unwrap_or(2) as u16,
+            sample_rate: properties.sample_rate().unwrap_or(44100),
+            path: path.as_ref().into()
+        })
+    }
+}
+
+impl AudioSource for APESource {
+    fn duration(&self) -> u128 {
+        self.duration.as_millis()
+    }
+
+    fn get_source(&self) -> Result<Box<dyn Source<Item = i16> + Send>, Error> {
+        Ok(Box::new(FFmpegDecoder::new(&self.path, self.channels, self.sample_rate, Some(self.duration))?))
+    }
+}
@@ -0,0 +1,30 @@
+use anyhow::Error;
+use std::path::{PathBuf, Path};
+use rodio::Source;
+
+use crate::AudioSource;
+
+/// DSF & DFF, duration from header only, there is no DSD decoder
+pub struct DSDSource {
+    path: PathBuf,
+    duration: u128,
+}
+
+impl DSDSource {
+    pub fn new(path: impl AsRef<Path>) -> Result<DSDSource, Error> {
+        Ok(DSDSource {
+            duration: onetagger_tag::dsd::duration(&path)?.as_millis(),
+            path: path.as_ref().to_owned()
+        })
+    }
+}
+
+impl AudioSource for DSDSource {
+    fn duration(&self) -> u128 {
+        self.duration
+    }
+
+    fn get_source(&self) -> Result<Box<dyn Source<Item = i16> + Send>, Error> {
+        Err(anyhow!("Decoding {:?} is not supported", self.path.extension().unwrap_or_default()))
+    }
+}
@@ -0,0 +1,134 @@
+use std::io::{BufReader, Read};
+use std::path::Path;
+use std::process::{Child, ChildStdout, Command, Stdio};
+use std::sync::OnceLock;
+use std::time::Duration;
+use anyhow::Error;
+use rodio::Source;
+
+/// Size of a single read from ffmpeg's stdout
+const CHUNK_SIZE: usize = 16 * 1024;
+
+/// Decodes to 16 bit PCM using ffmpeg from PATH, for formats without a native decoder.
+/// ffmpeg isn't bundled, it has to be installed separately
+pub struct FFmpegDecoder {
+    child: Child,
+    stdout: BufReader<ChildStdout>,
+    buffer: Vec<u8>,
+    position: usize,
+    channels: u16,
+    sample_rate: u32,
+    duration: Option<Duration>,
+}
+
+impl FFmpegDecoder {
+    pub fn new(path: impl AsRef<Path>, channels: u16, sample_rate: u32, duration: Option<Duration>) -> Result<FFmpegDecoder, Error> {
+        if !FFmpegDecoder::available() {
+            return Err(anyhow!("Decoding {:?} requires ffmpeg, which wasn't found in PATH", path.as_ref().extension().unwrap_or_default()));
+        }
+        let channels = channels.clamp(1, 2);
+        let mut child = FFmpegDecoder::command()
+            .args(["-v", "error", "-nostdin", "-i"])
+            .arg(path.as_ref())
+            .args(["-f", "s16le", "-acodec", "pcm_s16le", "-ac", &channels.to_string(), "-ar", &sample_rate.to_string(), "-"])
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()?;
+        let stdout = BufReader::with_capacity(CHUNK_SIZE, child.stdout.take().ok_or(anyhow!("Missing ffmpeg stdout"))?);
+        Ok(FFmpegDecoder { child, stdout, buffer: vec![], position: 0, channels, sample_rate, duration })
+    }
+
+    /// Check (once) whether ffmpeg can be executed
+    pub fn available() -> bool {
+        static AVAILABLE: OnceLock<bool> = OnceLock::new();
+        *AVAILABLE.get_or_init(|| {
+            let available = FFmpegDecoder::command()
+                .arg("-version")
+                .stdin(Stdio::null())
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .status()
+                .map(|s| s.success())
+                .unwrap_or(false);
+            if !available {
+                warn!("ffmpeg not found in PATH, WavPack, Monkey's Audio and Musepack playback is unavailable");
+            }
+            available
+        })
+    }
+
+    /// ffmpeg command without a console window
+    fn command() -> Command {
+        #[allow(unused_mut)]
+        let mut command = Command::new("ffmpeg");
+        #[cfg(target_os = "windows")]
+        {
+            use std::os::windows::process::CommandExt;
+            const CREATE_NO_WINDOW: u32 = 0x08000000;
+            command.creation_flags(CREATE_NO_WINDOW);
+        }
+        command
+    }
+
+    /// Read next chunk of samples, returns false on EOF
+    fn fill_buffer(&mut self) -> bool {
+        // Keep the odd byte of a split sample
+        self.buffer.drain(..self.position);
+        self.position = 0;
+        let start = self.buffer.len();
+        self.buffer.resize(start + CHUNK_SIZE, 0);
+        let read = loop {
+            match self.stdout.read(&mut self.buffer[start..]) {
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+                Err(e) => {
+                    warn!("Failed reading ffmpeg output: {e}");
+                    break 0;
+                }
+                Ok(read) => break read,
+            }
+        };
+        self.buffer.truncate(start + read);
+        read > 0
+    }
+}
+
+impl Iterator for FFmpegDecoder {
+    type Item = i16;
+
+    fn next(&mut self) -> Option<i16> {
+        while self.buffer.len() - self.position < 2 {
+            if !self.fill_buffer() {
+                return None;
+            }
+        }
+        let sample = i16::from_le_bytes([self.buffer[self.position], self.buffer[self.position + 1]]);
+        self.position += 2;
+        Some(sample)
+    }
+}
+
+impl Source for FFmpegDecoder {
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        self.channels
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.duration
+    }
+}
+
+impl Drop for FFmpegDecoder {
+    fn drop(&mut self) {
+        self.child.kill().ok();
+        self.child.wait().ok();
+    }
+}
@@ -0,0 +1,311 @@
+use anyhow::Error;
+use std::f64::consts::PI;
+use std::path::Path;
+use base64::Engine;
+use crate::{AudioSources, AudioSource};
+
+/// Sample rate used for fingerprinting
+const SAMPLE_RATE: u32 = 11025;
+const FRAME_SIZE: usize = 4096;
+const FRAME_STEP: usize = FRAME_SIZE / 3;
+const MIN_FREQ: f64 = 28.0;
+const MAX_FREQ: f64 = 3520.0;
+const BANDS: usize = 12;
+const CHROMA_FILTER: [f64; 5] = [0.25, 0.75, 1.0, 0.75, 0.25];
+/// Same as `fpcalc`
+const MAX_DURATION: u32 = 120;
+/// Algorithm ID of Chromaprint's default (TEST2) algorithm
+const ALGORITHM: u8 = 1;
+
+/// Filter type, y (band), height (bands), width (frames)
+type Filter = (u8, usize, usize, usize);
+
+/// Filters and quantizer thresholds of Chromaprint TEST2 algorithm
+const CLASSIFIERS: [(Filter, [f64; 3]); 16] = [
+    ((0, 4, 3, 15), [1.98215, 2.35817, 2.63523]),
+    ((4, 4, 6, 15), [-1.03809, -0.651211, -0.282167]),
+    ((1, 0, 4, 16), [-0.298702, 0.119262, 0.558497]),
+    ((3, 8, 2, 12), [-0.105439, 0.0153946, 0.135898]),
+    ((3, 4, 4, 8), [-0.142891, 0.0258736, 0.200632]),
+    ((4, 0, 3, 5), [-0.826319, -0.590612, -0.368214]),
+    ((1, 2, 2, 9), [-0.557409, -0.233035, 0.0534525]),
+    ((2, 7, 3, 4), [-0.0646826, 0.00620476, 0.0784847]),
+    ((2, 6, 2, 16), [-0.192387, -0.029699, 0.215855]),
+    ((2, 1, 3, 2), [-0.0397818, -0.00568076, 0.0292026]),
+    ((5, 10, 1, 15), [-0.53823, -0.369934, -0.190235]),
+    ((3, 6, 2, 10), [-0.124877, 0.0296483, 0.139239]),
+    ((2, 1, 1, 14), [-0.101475, 0.0225617, 0.231971]),
+    ((3, 5, 6, 4), [-0.0799915, -0.00729616, 0.063262]),
+    ((1, 9, 2, 12), [-0.272556, 0.019424, 0.302559]),
+    ((3, 4, 2, 14), [-0.164292, -0.0321188, 0.0846339]),
+];
+const MAX_FILTER_WIDTH: usize = 16;
+/// Max offset (in items) when comparing fingerprints
+const MAX_OFFSET: isize = 3;
+
+/// Chromaprint compatible audio fingerprint
+#[derive(Debug, Clone, PartialEq)]
+pub struct Fingerprint {
+    /// Duration of the whole file in seconds
+    pub duration: u32,
+    pub raw: Vec<u32>,
+}
+
+impl Fingerprint {
+    /// Calculate fingerprint of file (first 2 minutes)
+    pub fn from_path(path: impl AsRef<Path>) -> Result<Fingerprint, Error> {
+        let source = AudioSources::from_path(path)?;
+        let duration = (source.duration() / 1000) as u32;
+        let samples = source.mono_samples(SAMPLE_RATE, Some(MAX_DURATION))?;
+
+        let raw = Self::calculate(&samples);
+        if raw.is_empty() {
+            return Err(anyhow!("Audio too short for fingerprinting"));
+        }
+        Ok(Fingerprint { duration, raw })
+    }
+
+    /// Calculate raw fingerprint from mono 11025Hz samples
+    pub fn calculate(samples: &[i16]) -> Vec<u32> {
+        let window: Vec<f64> = (0..FRAME_SIZE)
+            .map(|i| (0.54 - 0.46 * (2.0 * PI * i as f64 / (FRAME_SIZE as f64 - 1.0)).cos()) / i16::MAX as f64)
+            .collect();
+        let (min_index, max_index, notes) = chroma_notes();
+
+        // Chroma features
+        let mut chroma = vec![];
+        let mut offset = 0;
+        while offset + FRAME_SIZE <= samples.len() {
+            let mut frame: Vec<(f64, f64)> = samples[offset..offset + FRAME_SIZE].iter().zip(&window)
+                .map(|(s, w)| (*s as f64 * w, 0.0))
+                .collect();
+            fft(&mut frame);
+            let mut features = [0.0; BANDS];
+            for i in min_index..max_index {
+                let (re, im) = frame[i];
+                features[notes[i]] += re * re + im * im;
+            }
+            chroma.push(features);
+            offset += FRAME_STEP;
+        }
+
+        // Smoothen and normalize
+        let image: Vec<[f64; BANDS]> = chroma.windows(CHROMA_FILTER.len()).map(|frames| {
+            let mut features = [0.0; BANDS];
+            for (frame, coef) in frames.iter().zip(CHROMA_FILTER) {
+                for b in 0..BANDS {
+                    features[b] += frame[b] * coef;
+                }
+            }
+            let norm = features.iter().map(|f| f * f).sum::<f64>().sqrt();
+            for f in features.iter_mut() {
+                *f = match norm < 0.01 {
+                    true => 0.0,
+                    false => *f / norm
+                };
+            }
+            features
+        }).collect();
+        if image.len() < MAX_FILTER_WIDTH {
+            return vec![];
+        }
+
+        let integral = IntegralImage::new(&image);
+        (0..=image.len() - MAX_FILTER_WIDTH).map(|x| {
+            CLASSIFIERS.iter().fold(0u32, |bits, (filter, thresholds)| {
+                let value = integral.filter(*filter, x);
+                let q = thresholds.iter().filter(|t| value >= **t).count();
+                // Gray code
+                (bits << 2) | [0, 1, 3, 2][q]
+            })
+        }).collect()
+    }
+
+    /// Similarity (0 - 1) of two fingerprints, by matching bits with small offsets (encoder delay etc.)
+    pub fn similarity(&self, other: &Fingerprint) -> f64 {
+        let mut best = 0.0;
+        for offset in -MAX_OFFSET..=MAX_OFFSET {
+            let (a, b) = match offset < 0 {
+                true => (&self.raw[..], other.raw.get(offset.unsigned_abs()..).unwrap_or_default()),
+                false => (self.raw.get(offset as usize..).unwrap_or_default(), &other.raw[..])
+            };
+            let len = a.len().min(b.len());
+            if len == 0 {
+                continue;
+            }
+            let errors: u32 = a.iter().zip(b).map(|(a, b)| (a ^ b).count_ones()).sum();
+            best = f64::max(best, 1.0 - errors as f64 / (len * 32) as f64);
+        }
+        best
+    }
+
+    /// Compress and encode into the format used by `fpcalc` and AcoustID
+    pub fn encode(&self) -> String {
+        base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(compress(&self.raw, ALGORITHM))
+    }
+}
+
+/// Get FFT bin range and bin => note mapping
+fn chroma_notes() -> (usize, usize, Vec<usize>) {
+    let freq_to_index = |f: f64| (FRAME_SIZE as f64 * f / SAMPLE_RATE as f64).round() as usize;
+    let min_index = freq_to_index(MIN_FREQ).max(1);
+    let max_index = freq_to_index(MAX_FREQ).min(FRAME_SIZE / 2);
+    let notes = (0..FRAME_SIZE / 2).map(|i| {
+        let freq = i as f64 * SAMPLE_RATE as f64 / FRAME_SIZE as f64;
+        let octave = (freq / (440.0 / 16.0)).log2();
+        (BANDS as f64 * (octave - octave.floor())) as usize
+    }).collect();
+    (min_index, max_index, notes)
+}
+
+/// In-place radix-2 FFT
+pub(crate) fn fft(data: &mut [(f64, f64)]) {
+    let n = data.len();
+    // Bit reversal
+    let mut j = 0;
+    for i in 1..n {
+        let mut bit = n >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+        if i < j {
+            data.swap(i, j);
+        }
+    }
+    let mut len = 2;
+    while len <= n {
+        let angle = -2.0 * PI / len as f64;
+        for start in (0..n).step_by(len) {
+            for k in 0..len / 2 {
+                let (wr, wi) = ((angle * k as f64).cos(), (angle * k as f64).sin());
+                let (ar, ai) = data[start + k];
+                let (br, bi) = data[start + k + len / 2];
+                let (tr, ti) = (br * wr - bi * wi, br * wi + bi * wr);
+                data[start + k] = (ar + tr, ai + ti);
+                data[start + k + len / 2] = (ar - tr, ai - ti);
+            }
+        }
+        len <<= 1;
+    }
+}
+
+/// Summed area table of the chroma image (rows = time, columns = bands)
+struct IntegralImage {
+    data: Vec<[f64; BANDS + 1]>
+}
+
+impl IntegralImage {
+    fn new(image: &[[f64; BANDS]]) -> IntegralImage {
+        let mut data = vec![[0.0; BANDS + 1]; image.len() + 1];
+        for (r, row) in image.iter().enumerate() {
+            for c in 0..BANDS {
+                data[r + 1][c + 1] = row[c] + data[r][c + 1] + data[r + 1][c] - data[r][c];
+            }
+        }
+        IntegralImage { data }
+    }
+
+    /// Sum of rows x1..x2 and columns y1..y2
+    fn area(&self, x1: usize, y1: usize, x2: usize, y2: usize) -> f64 {
+        self.data[x2][y2] - self.data[x1][y2] - self.data[x2][y1] + self.data[x1][y1]
+    }
+
+    /// Apply Chromaprint filter at time offset x
+    fn filter(&self, (kind, y, h, w): Filter, x: usize) -> f64 {
+        let (a, b) = match kind {
+            0 => (self.area(x, y, x + w, y + h), 0.0),
+            1 => (
+                self.area(x, y + h / 2, x + w, y + h),
+                self.area(x, y, x + w, y + h / 2)
+            ),
+            2 => (
+                self.area(x + w / 2, y, x + w, y + h),
+                self.area(x, y, x + w / 2, y + h)
+            ),
+            3 => (
+                self.area(x, y + h / 2, x + w / 2, y + h) + self.area(x + w / 2, y, x + w, y + h / 2),
+                self.area(x, y, x + w / 2, y + h / 2) + self.area(x + w / 2, y + h / 2, x + w, y + h)
+            ),
+            4 => (
+                self.area(x, y + h / 3, x + w, y + 2 * h / 3),
+                self.area(x, y, x + w, y + h / 3) + self.area(x, y + 2 * h / 3, x + w, y + h)
+            ),
+            _ => (
+                self.area(x + w / 3, y, x + 2 * w / 3, y + h),
+                self.area(x, y, x + w / 3, y + h) + self.area(x + 2 * w / 3, y, x + w, y + h)
+            ),
+        };
+        (1.0 + a).ln() - (1.0 + b).ln()
+    }
+}
+
+/// Chromaprint fingerprint compression
+fn compress(raw: &[u32], algorithm: u8) -> Vec<u8> {
+    let mut normal = vec![];
+    let mut exceptional = vec![];
+    let mut last = 0;
+    for x in raw {
+        // Encode positions of changed bits
+        let mut value = x ^ last;
+        last = *x;
+        let (mut bit, mut last_bit) = (1, 0);
+        while value != 0 {
+            if value & 1 != 0 {
+                let delta = bit - last_bit;
+                normal.push(delta.min(7));
+                if delta >= 7 {
+                    exceptional.push(delta - 7);
+                }
+                last_bit = bit;
+            }
+            value >>= 1;
+            bit += 1;
+        }
+        normal.push(0);
+    }
+
+    let len = raw.len();
+    let mut out = vec![algorithm, (len >> 16) as u8, (len >> 8) as u8, len as u8];
+    out.extend(pack_bits(&normal, 3));
+    out.extend(pack_bits(&exceptional, 5));
+    out
+}
+
+/// Pack values into LSB first bit stream
+fn pack_bits(values: &[u32], bits: usize) -> Vec<u8> {
+    let mut out = vec![0u8; (values.len() * bits).div_ceil(8)];
+    for (i, value) in values.iter().enumerate() {
+        for b in 0..bits {
+            if value & (1 << b) != 0 {
+                let pos = i * bits + b;
+                out[pos / 8] |= 1 << (pos % 8);
+            }
+        }
+    }
+    out
+}
+
+#[test]
+fn fingerprint_compression() {
+    assert_eq!(compress(&[1], 0), vec![0, 0, 0, 1, 1]);
+    assert_eq!(compress(&[7], 0), vec![0, 0, 0, 1, 0o111, 0]);
+    assert_eq!(compress(&[1 << 6], 0), vec![0, 0, 0, 1, 7, 0]);
+    // Silence
+    let raw = Fingerprint::calculate(&vec![0; SAMPLE_RATE as usize * 5]);
+    assert!(!raw.is_empty());
+    assert_eq!(Fingerprint { duration: 5, raw }.encode().get(0..4), Some("AQAA"));
+    // Shifted copy
+    let a = Fingerprint { duration: 1, raw: (0..100u32).map(|i| i.wrapping_mul(2654435761)).collect() };
+    let b = Fingerprint { duration: 1, raw: a.raw[2..].to_vec() };
+    assert_eq!(a.similarity(&b), 1.0);
+}
+
+/// Known output of Chromaprint (`fpcalc`) for 130 * 1024 samples of 44.1kHz silence
+#[test]
+fn fingerprint_chromaprint_vector() {
+    let raw = Fingerprint::calculate(&vec![0; 130 * 1024 / 4]);
+    assert_eq!(raw, vec![627964279; 3]);
+    assert_eq!(Fingerprint { duration: 3, raw }.encode(), "AQAAA0mUaEkSRZEGAA");
+}
@@ -8,6 +8,8 @@ use std::sync::mpsc::{Sender, Receiver, channel};
 use std::thread;
 use hound::{WavSpec, SampleFormat, WavWriter};
 use rodio::{Sink, Source};
+use rodio::buffer::SamplesBuffer;
+use rodio::source::UniformSourceIterator;
 
 pub mod mp3;
 pub mod mp4;
@@ -16,6 +18,12 @@ pub mod ogg;
 pub mod alac;
 pub mod flac;
 pub mod aiff;
+pub mod ape;
+pub mod dsd;
+pub mod ffmpeg;
+pub mod fingerprint;
+pub mod analysis;
+pub mod loudness;
 
 /// Re-Export to prevent dependency issues
 pub use rodio;
@@ -170,6 +178,14 @@ impl AudioSources {
         if p == "ogg" || p == "opus" || p == "oga" || p == "spx" {
             return Ok(Box::new(ogg::OGGSource::new(path)?));
         }
+        // WavPack, Monkey's Audio, Musepack
+        if p == "wv" || p == "ape" || p == "mpc" {
+            return Ok(Box::new(ape::APESource::new(path)?));
+        }
+        // DSD
+        if p == "dsf" || p == "dff" {
+            return Ok(Box::new(dsd::DSDSource::new(path)?));
+        }
 
         Err(anyhow!("Unsupported format!").into())
     }
@@ -239,4 +255,18 @@ pub trait AudioSource {
         }
         Ok(buf)
     }
+
+    /// Decode into mono samples with the given sample rate, optionally only the first `max_duration` seconds
+    fn mono_samples(&self, sample_rate: u32, max_duration: Option<u32>) -> Result<Vec<i16>, Error> {
+        let source = self.get_source()?;
+        let channels = source.channels().max(1) as usize;
+        let source_rate = source.sample_rate();
+        let max_samples = max_duration.map(|d| (source_rate * d) as usize * channels).unwrap_or(usize::MAX);
+        // Downmix manually, because the converter would use only the first channel
+        let samples: Vec<i16> = source.take(max_samples).collect();
+        let mono: Vec<i16> = samples.chunks(channels)
+            .map(|c| (c.iter().map(|s| *s as i32).sum::<i32>() / c.len() as i32) as i16)
+            .collect();
+        Ok(UniformSourceIterator::new(SamplesBuffer::new(1, source_rate, mono), 1, sample_rate).collect())
+    }
 }
\ No newline at end of file
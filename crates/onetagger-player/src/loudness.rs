@@ -0,0 +1,244 @@
+use anyhow::Error;
+use std::collections::VecDeque;
+use std::f64::consts::PI;
+use std::path::Path;
+use crate::{AudioSources, AudioSource};
+
+/// Gating block is 400ms with 75% overlap, so the step is 100ms
+const BLOCK_STEPS: usize = 4;
+const ABSOLUTE_GATE: f64 = -70.0;
+const RELATIVE_GATE: f64 = -10.0;
+/// Taps of the true peak interpolation filter
+const PEAK_TAPS: usize = 12;
+
+/// EBU R128 / ITU-R BS.1770 loudness measurement
+#[derive(Debug, Clone, Default)]
+pub struct Loudness {
+    /// Integrated loudness in LUFS, None if silent
+    pub integrated: Option<f64>,
+    /// Linear true peak (can be over 1.0)
+    pub true_peak: f64,
+    /// Mean square of all gating blocks, for album loudness
+    blocks: Vec<f64>,
+}
+
+impl Loudness {
+    /// Decode and measure the whole file
+    pub fn from_path(path: impl AsRef<Path>) -> Result<Loudness, Error> {
+        let source = AudioSources::from_path(path)?.get_source()?;
+        let (channels, sample_rate) = (source.channels(), source.sample_rate());
+        Ok(Self::calculate(source, channels, sample_rate))
+    }
+
+    /// Measure interleaved samples
+    pub fn calculate(samples: impl Iterator<Item = i16>, channels: u16, sample_rate: u32) -> Loudness {
+        let channels = channels.max(1) as usize;
+        let step = (sample_rate as usize / 10).max(1);
+        let weights: Vec<f64> = (0..channels).map(|c| channel_weight(c, channels)).collect();
+        let mut filters = vec![KWeighting::new(sample_rate); channels];
+        let mut peaks = vec![TruePeak::new(sample_rate); channels];
+
+        let mut blocks = vec![];
+        // Weighted sum of squares of the last steps
+        let mut steps: VecDeque<f64> = VecDeque::with_capacity(BLOCK_STEPS);
+        let mut current = 0.0;
+        let mut position = 0;
+        let mut channel = 0;
+        for sample in samples {
+            let sample = sample as f64 / 32768.0;
+            peaks[channel].process(sample);
+            let filtered = filters[channel].process(sample);
+            current += filtered * filtered * weights[channel];
+
+            channel += 1;
+            if channel < channels {
+                continue;
+            }
+            channel = 0;
+            position += 1;
+            if position < step {
+                continue;
+            }
+            position = 0;
+            if steps.len() == BLOCK_STEPS {
+                steps.pop_front();
+            }
+            steps.push_back(current);
+            current = 0.0;
+            if steps.len() == BLOCK_STEPS {
+                blocks.push(steps.iter().sum::<f64>() / (step * BLOCK_STEPS) as f64);
+            }
+        }
+
+        Loudness {
+            integrated: gated_loudness(&blocks),
+            true_peak: peaks.iter().map(|p| p.peak).fold(0.0, f64::max),
+            blocks
+        }
+    }
+
+    /// Loudness of all the tracks together
+    pub fn album(tracks: &[Loudness]) -> Loudness {
+        let blocks: Vec<f64> = tracks.iter().flat_map(|t| t.blocks.iter().copied()).collect();
+        Loudness {
+            integrated: gated_loudness(&blocks),
+            true_peak: tracks.iter().map(|t| t.true_peak).fold(0.0, f64::max),
+            blocks
+        }
+    }
+}
+
+/// BS.1770 channel weights (5.1: L, R, C, LFE, Ls, Rs)
+fn channel_weight(channel: usize, channels: usize) -> f64 {
+    match (channels, channel) {
+        (6, 3) => 0.0,
+        (5 | 6, c) if c >= channels - 2 => 1.41,
+        _ => 1.0
+    }
+}
+
+fn energy_to_loudness(energy: f64) -> f64 {
+    -0.691 + 10.0 * energy.log10()
+}
+
+/// Apply the absolute and relative gates
+fn gated_loudness(blocks: &[f64]) -> Option<f64> {
+    let mean = |blocks: &[f64]| blocks.iter().sum::<f64>() / blocks.len() as f64;
+    let blocks: Vec<f64> = blocks.iter().copied().filter(|b| *b > 0.0 && energy_to_loudness(*b) > ABSOLUTE_GATE).collect();
+    if blocks.is_empty() {
+        return None;
+    }
+    let threshold = energy_to_loudness(mean(&blocks)) + RELATIVE_GATE;
+    let blocks: Vec<f64> = blocks.into_iter().filter(|b| energy_to_loudness(*b) > threshold).collect();
+    Some(energy_to_loudness(mean(&blocks)))
+}
+
+/// Direct form I biquad
+#[derive(Debug, Clone)]
+struct Biquad {
+    b: [f64; 3],
+    a: [f64; 3],
+    x: [f64; 2],
+    y: [f64; 2],
+}
+
+impl Biquad {
+    fn process(&mut self, x: f64) -> f64 {
+        let y = self.b[0] * x + self.b[1] * self.x[0] + self.b[2] * self.x[1] - self.a[1] * self.y[0] - self.a[2] * self.y[1];
+        self.x = [x, self.x[0]];
+        self.y = [y, self.y[0]];
+        y
+    }
+}
+
+/// K-weighting filter (high shelf + high pass), coefficients for any sample rate
+#[derive(Debug, Clone)]
+struct KWeighting {
+    shelf: Biquad,
+    highpass: Biquad,
+}
+
+impl KWeighting {
+    fn new(sample_rate: u32) -> KWeighting {
+        let rate = sample_rate as f64;
+        // High shelf
+        let (f0, gain, q) = (1681.974450955533, 3.999843853973347, 0.7071752369554196);
+        let k = (PI * f0 / rate).tan();
+        let vh = 10f64.powf(gain / 20.0);
+        let vb = vh.powf(0.4996667741545416);
+        let a0 = 1.0 + k / q + k * k;
+        let shelf = Biquad {
+            b: [(vh + vb * k / q + k * k) / a0, 2.0 * (k * k - vh) / a0, (vh - vb * k / q + k * k) / a0],
+            a: [1.0, 2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+            x: [0.0; 2], y: [0.0; 2]
+        };
+        // High pass
+        let (f0, q) = (38.13547087602444, 0.5003270373238773);
+        let k = (PI * f0 / rate).tan();
+        let a0 = 1.0 + k / q + k * k;
+        let highpass = Biquad {
+            b: [1.0, -2.0, 1.0],
+            a: [1.0, 2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+            x: [0.0; 2], y: [0.0; 2]
+        };
+        KWeighting { shelf, highpass }
+    }
+
+    fn process(&mut self, x: f64) -> f64 {
+        self.highpass.process(self.shelf.process(x))
+    }
+}
+
+/// True peak by oversampling (4x below 96kHz) with windowed sinc interpolation
+#[derive(Debug, Clone)]
+struct TruePeak {
+    /// Filter coefficients of each intermediate phase
+    phases: Vec<[f64; PEAK_TAPS]>,
+    history: VecDeque<f64>,
+    peak: f64,
+}
+
+impl TruePeak {
+    fn new(sample_rate: u32) -> TruePeak {
+        let factor = match sample_rate {
+            0..=95999 => 4,
+            96000..=191999 => 2,
+            _ => 1
+        };
+        let center = (PEAK_TAPS / 2 - 1) as f64;
+        let phases = (1..factor).map(|p| {
+            let offset = p as f64 / factor as f64;
+            let mut taps = [0.0; PEAK_TAPS];
+            for (j, tap) in taps.iter_mut().enumerate() {
+                let t = center + offset - j as f64;
+                let sinc = (PI * t).sin() / (PI * t);
+                let window = 0.5 + 0.5 * (PI * t / (PEAK_TAPS / 2) as f64).cos();
+                *tap = sinc * window;
+            }
+            taps
+        }).collect();
+        TruePeak { phases, history: VecDeque::with_capacity(PEAK_TAPS), peak: 0.0 }
+    }
+
+    fn process(&mut self, x: f64) {
+        self.peak = self.peak.max(x.abs());
+        if self.history.len() == PEAK_TAPS {
+            self.history.pop_front();
+        }
+        self.history.push_back(x);
+        if self.history.len() < PEAK_TAPS {
+            return;
+        }
+        for taps in &self.phases {
+            let value: f64 = self.history.iter().zip(taps).map(|(x, t)| x * t).sum();
+            self.peak = self.peak.max(value.abs());
+        }
+    }
+}
+
+/// 1kHz sine with -23dBFS amplitude on both channels is -23 LUFS (EBU Tech 3341)
+#[test]
+fn loudness_sine() {
+    let sine = |channels: usize, amplitude: f64, seconds: usize| -> Vec<i16> {
+        (0..48000 * seconds).flat_map(|i| {
+            let s = ((2.0 * PI * 1000.0 * i as f64 / 48000.0).sin() * amplitude * 32767.0) as i16;
+            std::iter::repeat_n(s, channels)
+        }).collect()
+    };
+    let amplitude = 10f64.powf(-23.0 / 20.0);
+    let stereo = Loudness::calculate(sine(2, amplitude, 10).into_iter(), 2, 48000);
+    assert!((stereo.integrated.unwrap() + 23.0).abs() < 0.1, "{:?}", stereo.integrated);
+    assert!((stereo.true_peak - amplitude).abs() < 0.01, "{}", stereo.true_peak);
+    // Inter-sample peak, samples are only at 0.707 of the amplitude
+    let peak: Vec<i16> = (0..48000).map(|i| ((PI / 2.0 * i as f64 + PI / 4.0).sin() * 0.5 * 32767.0) as i16).collect();
+    let peak = Loudness::calculate(peak.into_iter(), 1, 48000).true_peak;
+    assert!((peak - 0.5).abs() < 0.02, "{peak}");
+    let mono = Loudness::calculate(sine(1, amplitude, 10).into_iter(), 1, 48000);
+    assert!((mono.integrated.unwrap() + 26.0).abs() < 0.1, "{:?}", mono.integrated);
+
+    // Silence is gated out of the album
+    let silence = Loudness::calculate(vec![0; 48000 * 10].into_iter(), 1, 48000);
+    assert_eq!(silence.integrated, None);
+    let album = Loudness::album(&[mono.clone(), silence]);
+    assert!((album.integrated.unwrap() - mono.integrated.unwrap()).abs() < 0.01);
+}
@@ -0,0 +1,194 @@
+use anyhow::Error;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
+
+/// Folder modification time & parsed CUE sheets
+type CachedSheets = (Option<SystemTime>, Vec<CueSheet>);
+
+/// Max folders in the CUE cache
+const CUE_CACHE_SIZE: usize = 64;
+
+lazy_static::lazy_static! {
+    /// Parsed CUE sheets by folder, invalidated when the folder is modified
+    static ref CUE_CACHE: Mutex<HashMap<PathBuf, CachedSheets>> = Mutex::new(HashMap::new());
+}
+
+/// Parsed CUE sheet
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CueSheet {
+    pub title: Option<String>,
+    pub performer: Option<String>,
+    pub files: Vec<CueFile>,
+}
+
+/// `FILE` entry of CUE sheet
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CueFile {
+    pub path: PathBuf,
+    pub tracks: Vec<CueTrack>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CueTrack {
+    pub number: u32,
+    pub title: Option<String>,
+    pub performer: Option<String>,
+    /// `INDEX 01` position within the file
+    pub start: Option<Duration>,
+}
+
+impl CueSheet {
+    /// Parse CUE sheet, relative file paths are joined with `base_path`
+    pub fn parse(cue: &str, base_path: Option<&Path>) -> CueSheet {
+        let mut sheet = CueSheet::default();
+        for line in cue.trim_start_matches('\u{feff}').lines() {
+            let args = split_args(line);
+            let (command, args) = match args.split_first() {
+                Some((c, a)) => (c.to_uppercase(), a),
+                None => continue
+            };
+            let arg = args.first().cloned();
+            let track = sheet.files.last_mut().and_then(|f| f.tracks.last_mut());
+            match (command.as_str(), track) {
+                ("FILE", _) => if let Some(file) = arg {
+                    let path = match base_path {
+                        Some(base) if Path::new(&file).is_relative() => base.join(file),
+                        _ => PathBuf::from(file),
+                    };
+                    sheet.files.push(CueFile { path, tracks: vec![] });
+                },
+                ("TRACK", _) => if let Some(file) = sheet.files.last_mut() {
+                    file.tracks.push(CueTrack { number: arg.and_then(|n| n.parse().ok()).unwrap_or_default(), ..Default::default() });
+                },
+                ("TITLE", Some(track)) => track.title = arg,
+                ("TITLE", None) => sheet.title = arg,
+                ("PERFORMER", Some(track)) => track.performer = arg,
+                ("PERFORMER", None) => sheet.performer = arg,
+                ("INDEX", Some(track)) if arg.as_deref().and_then(|a| a.parse::<u32>().ok()) == Some(1) => {
+                    track.start = args.get(1).and_then(|t| parse_time(t));
+                },
+                _ => {}
+            }
+        }
+        sheet
+    }
+
+    /// Load from file, paths are relative to the CUE sheet
+    pub fn from_path(path: impl AsRef<Path>) -> Result<CueSheet, Error> {
+        // Older CUE sheets are usually not UTF-8
+        let data = std::fs::read(&path)?;
+        Ok(CueSheet::parse(&String::from_utf8_lossy(&data), path.as_ref().parent()))
+    }
+
+    /// All referenced files
+    pub fn files(&self) -> Vec<PathBuf> {
+        self.files.iter().map(|f| f.path.clone()).collect()
+    }
+
+    /// Find title & performer of audio file in CUE sheets of the same folder.
+    /// If the file contains multiple tracks only the sheet performer is used
+    pub fn track_info(path: impl AsRef<Path>) -> Option<(Option<String>, Option<String>)> {
+        let path = path.as_ref();
+        let filename = path.file_name()?;
+        let sheets = CueSheet::from_folder(path.parent()?);
+        for sheet in sheets {
+            let file = match sheet.files.iter().find(|f| f.path.file_name() == Some(filename)) {
+                Some(file) => file,
+                None => continue
+            };
+            return Some(match &file.tracks[..] {
+                [track] => (
+                    track.title.clone().or(sheet.title.clone()),
+                    track.performer.clone().or(sheet.performer.clone())
+                ),
+                _ => (None, sheet.performer.clone())
+            });
+        }
+        None
+    }
+
+    /// All CUE sheets in folder, cached
+    fn from_folder(folder: &Path) -> Vec<CueSheet> {
+        let modified = std::fs::metadata(folder).and_then(|m| m.modified()).ok();
+        if let Some((m, sheets)) = CUE_CACHE.lock().unwrap().get(folder) {
+            if *m == modified {
+                return sheets.clone();
+            }
+        }
+
+        let mut sheets = vec![];
+        for entry in std::fs::read_dir(folder).into_iter().flatten().filter_map(|e| e.ok()) {
+            if entry.path().extension().map(|e| !e.eq_ignore_ascii_case("cue")).unwrap_or(true) {
+                continue;
+            }
+            match CueSheet::from_path(entry.path()) {
+                Ok(sheet) => sheets.push(sheet),
+                Err(e) => warn!("Failed reading CUE sheet {:?}: {e}", entry.path()),
+            }
+        }
+        let mut cache = CUE_CACHE.lock().unwrap();
+        // Files are usually processed folder by folder, so old entries are unlikely to be hit again
+        if cache.len() >= CUE_CACHE_SIZE {
+            cache.clear();
+        }
+        cache.insert(folder.to_owned(), (modified, sheets.clone()));
+        sheets
+    }
+}
+
+/// Split line into arguments, respecting quotes
+fn split_args(line: &str) -> Vec<String> {
+    let mut out = vec![];
+    let mut current = String::new();
+    let mut quoted = false;
+    for c in line.trim().chars() {
+        match c {
+            '"' => quoted = !quoted,
+            c if c.is_whitespace() && !quoted => if !current.is_empty() {
+                out.push(std::mem::take(&mut current));
+            },
+            c => current.push(c),
+        }
+    }
+    if !current.is_empty() {
+        out.push(current);
+    }
+    out
+}
+
+/// Parse `mm:ss:ff`, 75 frames per second
+fn parse_time(time: &str) -> Option<Duration> {
+    let parts = time.split(':').map(|p| p.parse::<u64>().ok()).collect::<Option<Vec<_>>>()?;
+    match parts[..] {
+        [m, s, f] => Some(Duration::from_millis((m * 60 + s) * 1000 + f * 1000 / 75)),
+        _ => None
+    }
+}
+
+#[test]
+fn cue_parse() {
+    let cue = "\u{feff}REM GENRE House\r\nPERFORMER \"Various\"\r\nTITLE \"Mix\"\r\nFILE \"01 Intro.flac\" WAVE\r\n  TRACK 01 AUDIO\r\n    TITLE \"Intro\"\r\n    PERFORMER \"DJ A\"\r\n    INDEX 01 00:00:00\r\n\
+        FILE \"/abs/mix.wav\" WAVE\r\n  TRACK 02 AUDIO\r\n    TITLE \"Two\"\r\n    INDEX 00 01:59:00\r\n    INDEX 01 02:00:37\r\n  TRACK 03 AUDIO\r\n";
+    let sheet = CueSheet::parse(cue, Some(Path::new("/music")));
+    assert_eq!(sheet.title.as_deref(), Some("Mix"));
+    assert_eq!(sheet.performer.as_deref(), Some("Various"));
+    assert_eq!(sheet.files(), vec![PathBuf::from("/music/01 Intro.flac"), PathBuf::from("/abs/mix.wav")]);
+    assert_eq!(sheet.files[0].tracks, vec![CueTrack { number: 1, title: Some("Intro".to_string()), performer: Some("DJ A".to_string()), start: Some(Duration::ZERO) }]);
+    assert_eq!(sheet.files[1].tracks.len(), 2);
+    assert_eq!(sheet.files[1].tracks[0].start, Some(Duration::from_millis(120_493)));
+}
+
+#[test]
+fn cue_track_info() {
+    let dir = std::env::temp_dir().join("onetagger_cue_track_info");
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("album.cue"), "PERFORMER \"Various\"\nTITLE \"Mix\"\nFILE \"01.flac\" WAVE\n  TRACK 01 AUDIO\n    TITLE \"Intro\"\n\
+        FILE \"mix.wav\" WAVE\n  TRACK 02 AUDIO\n    TITLE \"Two\"\n  TRACK 03 AUDIO\n    TITLE \"Three\"\n").unwrap();
+    assert_eq!(CueSheet::track_info(dir.join("01.flac")), Some((Some("Intro".to_string()), Some("Various".to_string()))));
+    assert_eq!(CueSheet::track_info(dir.join("mix.wav")), Some((None, Some("Various".to_string()))));
+    assert_eq!(CueSheet::track_info(dir.join("other.flac")), None);
+    assert!(CUE_CACHE.lock().unwrap().contains_key(&dir));
+    std::fs::remove_dir_all(&dir).ok();
+}
@@ -9,7 +9,27 @@ use serde::{Serialize, Deserialize};
 use base64::Engine;
 use onetagger_tag::EXTENSIONS;
 
-pub const PLAYLIST_EXTENSIONS: [&str; 2] = ["m3u", "m3u8"];
+use crate::cue::CueSheet;
+use crate::library::LibraryTrack;
+use crate::xml::XmlNode;
+
+mod xml;
+pub mod library;
+pub mod rekordbox;
+pub mod traktor;
+pub mod pls;
+pub mod xspf;
+pub mod cue;
+
+pub const PLAYLIST_EXTENSIONS: [&str; 7] = ["m3u", "m3u8", "pls", "xspf", "cue", "xml", "nml"];
+
+/// Does the path have one of the playlist extensions (case insensitive)
+pub fn has_playlist_extension(path: impl AsRef<Path>) -> bool {
+    match path.as_ref().extension() {
+        Some(ext) => PLAYLIST_EXTENSIONS.iter().any(|e| ext.eq_ignore_ascii_case(e)),
+        None => false
+    }
+}
 
 // Playlist info from UI
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -22,13 +42,19 @@ pub struct UIPlaylist {
 
 impl UIPlaylist {
     pub fn get_files(&self) -> Result<Vec<PathBuf>, Error> {
+        // Decode base64 from JS
+        let bytes = base64::engine::general_purpose::STANDARD.decode(self.data[self.data.find(';').ok_or(anyhow!("Invalid data!"))? + 8..].trim())?;
+        let data = match self.format {
+            PlaylistFormat::CUE => String::from_utf8_lossy(&bytes).to_string(),
+            _ => String::from_utf8(bytes)?
+        };
         let files = match self.format {
-            PlaylistFormat::M3U => {
-                // Decode base64 from JS
-                let bytes = base64::engine::general_purpose::STANDARD.decode(self.data[self.data.find(';').ok_or(anyhow!("Invalid data!"))? + 8..].trim())?;
-                let m3u = String::from_utf8(bytes)?;
-                get_files_from_m3u(&m3u, None)
-            }
+            PlaylistFormat::M3U => get_files_from_m3u(&data, None),
+            PlaylistFormat::PLS => pls::get_files_from_pls(&data, None),
+            PlaylistFormat::XSPF => xspf::get_files_from_xspf(&data, None)?,
+            PlaylistFormat::CUE => CueSheet::parse(&data, None).files(),
+            PlaylistFormat::Rekordbox => rekordbox::read_rekordbox_xml(&data)?.files(),
+            PlaylistFormat::Traktor => traktor::read_traktor_nml(&data)?.files(),
         };
         // Filter extensions
         let out = files
@@ -39,30 +65,83 @@ impl UIPlaylist {
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum PlaylistFormat {
-    M3U
+    M3U,
+    PLS,
+    XSPF,
+    /// CUE sheet, read only
+    CUE,
+    /// Rekordbox XML
+    Rekordbox,
+    /// Traktor NML
+    Traktor
+}
+
+impl PlaylistFormat {
+    /// Get format of existing playlist file, XML files have to be Rekordbox (`DJ_PLAYLISTS` root)
+    pub fn from_path(path: impl AsRef<Path>) -> Option<PlaylistFormat> {
+        match PlaylistFormat::from_extension(&path)? {
+            PlaylistFormat::Rekordbox => match XmlNode::root_name(&path) {
+                Ok(root) if root == "DJ_PLAYLISTS" => Some(PlaylistFormat::Rekordbox),
+                _ => None
+            },
+            format => Some(format)
+        }
+    }
+
+    /// Get format from file extension only
+    pub fn from_extension(path: impl AsRef<Path>) -> Option<PlaylistFormat> {
+        match path.as_ref().extension()?.to_string_lossy().to_lowercase().as_str() {
+            "m3u" | "m3u8" => Some(PlaylistFormat::M3U),
+            "pls" => Some(PlaylistFormat::PLS),
+            "xspf" => Some(PlaylistFormat::XSPF),
+            "cue" => Some(PlaylistFormat::CUE),
+            "xml" => Some(PlaylistFormat::Rekordbox),
+            "nml" => Some(PlaylistFormat::Traktor),
+            _ => None
+        }
+    }
+
+    /// File extensions of this format
+    pub fn extensions(&self) -> &'static [&'static str] {
+        match self {
+            PlaylistFormat::M3U => &["m3u", "m3u8"],
+            PlaylistFormat::PLS => &["pls"],
+            PlaylistFormat::XSPF => &["xspf"],
+            PlaylistFormat::CUE => &["cue"],
+            PlaylistFormat::Rekordbox => &["xml"],
+            PlaylistFormat::Traktor => &["nml"],
+        }
+    }
 }
 
 
 /// Get files from any playlist format
 pub fn get_files_from_playlist_file(path: impl AsRef<Path>) -> Result<Vec<PathBuf>, Error> {
     // Validate extension
-    if !PLAYLIST_EXTENSIONS.iter().any(|e| &&path.as_ref().extension().unwrap_or_default().to_string_lossy().to_lowercase() == e) {
-        return Err(anyhow!("Unsupported playlist!").into());
-    };
-    
+    let format = PlaylistFormat::from_path(&path).ok_or(anyhow!("Unsupported playlist!"))?;
+
     // Load file
     let mut file = File::open(&path)?;
     let mut buf = vec![];
     file.read_to_end(&mut buf)?;
 
-    // TODO: Check format if multiple
-
-    // M3U
-    let data = String::from_utf8(buf)?;
-    Ok(get_files_from_m3u(&data, Some(path.as_ref().parent().unwrap().to_owned())))
+    // CUE sheets are often in legacy encodings
+    let data = match format {
+        PlaylistFormat::CUE => String::from_utf8_lossy(&buf).to_string(),
+        _ => String::from_utf8(buf)?
+    };
+    let base_path = path.as_ref().parent().map(|p| p.to_owned());
+    match format {
+        PlaylistFormat::M3U => Ok(get_files_from_m3u(&data, base_path)),
+        PlaylistFormat::PLS => Ok(pls::get_files_from_pls(&data, base_path)),
+        PlaylistFormat::XSPF => xspf::get_files_from_xspf(&data, base_path),
+        PlaylistFormat::CUE => Ok(CueSheet::parse(&data, path.as_ref().parent()).files()),
+        PlaylistFormat::Rekordbox => Ok(rekordbox::read_rekordbox_xml(&data)?.files()),
+        PlaylistFormat::Traktor => Ok(traktor::read_traktor_nml(&data)?.files()),
+    }
 }
 
 
@@ -101,3 +180,18 @@ pub fn create_m3u_playlist(paths: &Vec<PathBuf>) -> String {
     }
     playlist
 }
+
+/// Save playlist of files, format by output extension. Name of the playlist is the filename
+pub fn export_playlist(paths: &[PathBuf], output: impl AsRef<Path>) -> Result<(), Error> {
+    let name = output.as_ref().file_stem().unwrap_or_default().to_string_lossy().to_string();
+    let data = match PlaylistFormat::from_extension(&output).unwrap_or(PlaylistFormat::M3U) {
+        PlaylistFormat::M3U => create_m3u_playlist(&paths.to_vec()),
+        PlaylistFormat::PLS => pls::create_pls_playlist(&paths.iter().map(LibraryTrack::load).collect::<Vec<_>>()),
+        PlaylistFormat::XSPF => xspf::create_xspf_playlist(&name, &paths.iter().map(LibraryTrack::load).collect::<Vec<_>>()),
+        PlaylistFormat::CUE => return Err(anyhow!("CUE sheets can't be exported")),
+        PlaylistFormat::Rekordbox => rekordbox::create_rekordbox_xml(&name, &paths.iter().map(LibraryTrack::load).collect::<Vec<_>>()),
+        PlaylistFormat::Traktor => traktor::create_traktor_nml(&name, &paths.iter().map(LibraryTrack::load).collect::<Vec<_>>()),
+    };
+    std::fs::write(output, data)?;
+    Ok(())
+}
@@ -0,0 +1,124 @@
+use anyhow::Error;
+use std::path::{Path, PathBuf};
+use onetagger_tag::{Field, FrameName, Tag};
+
+/// Track metadata for DJ library exports
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LibraryTrack {
+    pub path: PathBuf,
+    pub title: Option<String>,
+    pub artists: Vec<String>,
+    pub album: Option<String>,
+    pub genres: Vec<String>,
+    pub bpm: Option<f64>,
+    pub key: Option<String>,
+    /// 0 - 5 stars
+    pub rating: u8,
+    pub comment: Option<String>,
+    pub label: Option<String>,
+    pub year: Option<i32>,
+    /// In seconds
+    pub duration: Option<u64>,
+}
+
+impl LibraryTrack {
+    /// Load from tag
+    pub fn from_path(path: impl AsRef<Path>) -> Result<LibraryTrack, Error> {
+        let tag_wrap = Tag::load_file(&path, false)?;
+        let tag = tag_wrap.tag();
+        let first = |field: Field| tag.get_field(field).and_then(|v| v.into_iter().find(|v| !v.trim().is_empty()));
+        let comment = FrameName::new("COMM", "COMMENT", "©cmt").by_format(&tag_wrap.format());
+        Ok(LibraryTrack {
+            path: path.as_ref().to_owned(),
+            title: first(Field::Title),
+            artists: tag.get_field(Field::Artist).unwrap_or_default(),
+            album: first(Field::Album),
+            genres: tag.get_field(Field::Genre).unwrap_or_default(),
+            bpm: first(Field::BPM).and_then(|b| b.trim().parse().ok()),
+            key: first(Field::Key),
+            rating: tag.get_rating().unwrap_or(0),
+            comment: tag.get_raw(&comment).and_then(|v| v.into_iter().find(|v| !v.trim().is_empty())),
+            label: first(Field::Label),
+            year: tag.get_date().map(|d| d.year),
+            // Stored in ms
+            duration: first(Field::Duration).and_then(|d| d.trim().parse::<u64>().ok()).map(|d| d / 1000),
+        })
+    }
+
+    /// Load or fallback to only path
+    pub fn load(path: impl AsRef<Path>) -> LibraryTrack {
+        match LibraryTrack::from_path(&path) {
+            Ok(track) => track,
+            Err(e) => {
+                warn!("Failed loading tags of {:?}: {e}", path.as_ref());
+                LibraryTrack { path: path.as_ref().to_owned(), ..Default::default() }
+            }
+        }
+    }
+
+    /// Title or filename
+    pub fn name(&self) -> String {
+        self.title.clone().unwrap_or(self.path.file_stem().unwrap_or_default().to_string_lossy().to_string())
+    }
+}
+
+/// Collection & playlists of DJ library
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DJLibrary {
+    pub collection: Vec<PathBuf>,
+    pub playlists: Vec<(String, Vec<PathBuf>)>,
+}
+
+impl DJLibrary {
+    /// Files of all playlists, or the whole collection if there are no playlists
+    pub fn files(&self) -> Vec<PathBuf> {
+        if self.playlists.is_empty() {
+            return self.collection.clone();
+        }
+        let mut out: Vec<PathBuf> = vec![];
+        for file in self.playlists.iter().flat_map(|(_, files)| files) {
+            if !out.contains(file) {
+                out.push(file.to_owned());
+            }
+        }
+        out
+    }
+}
+
+/// Path to `file://` URL, prefix = `file://` + host
+pub(crate) fn path_to_url(path: &Path, prefix: &str) -> String {
+    let path = path.to_string_lossy().replace('\\', "/");
+    let encoded = path.split('/').map(|segment| match segment.len() == 2 && segment.ends_with(':') {
+        // Windows drive
+        true => segment.to_string(),
+        false => urlencoding::encode(segment).to_string(),
+    }).collect::<Vec<_>>().join("/");
+    match encoded.starts_with('/') {
+        true => format!("{prefix}{encoded}"),
+        false => format!("{prefix}/{encoded}"),
+    }
+}
+
+/// `file://` URL or plain path, relative paths are joined with `base_path`
+pub(crate) fn url_to_path(url: &str, base_path: Option<&Path>) -> Option<PathBuf> {
+    let url = url.trim();
+    if url.is_empty() || url.starts_with("http://") || url.starts_with("https://") {
+        return None;
+    }
+    let path = match url.strip_prefix("file://") {
+        Some(path) => {
+            let path = path.strip_prefix("localhost").unwrap_or(path);
+            urlencoding::decode(path).ok()?.to_string()
+        },
+        None => url.to_string(),
+    };
+    // Windows drive
+    let bytes = path.as_bytes();
+    if bytes.len() > 3 && bytes[0] == b'/' && bytes[2] == b':' {
+        return Some(PathBuf::from(&path[1..]));
+    }
+    match (base_path, Path::new(&path).is_relative() && !(bytes.len() > 1 && bytes[1] == b':')) {
+        (Some(base), true) => Some(base.join(path)),
+        _ => Some(PathBuf::from(path))
+    }
+}
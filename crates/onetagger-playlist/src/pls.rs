@@ -0,0 +1,57 @@
+use std::path::PathBuf;
+use crate::library::{LibraryTrack, url_to_path};
+
+/// Generate PLS playlist
+pub fn create_pls_playlist(tracks: &[LibraryTrack]) -> String {
+    let mut playlist = "[playlist]\r\n".to_string();
+    for (i, track) in tracks.iter().enumerate() {
+        let n = i + 1;
+        let title = match track.artists.is_empty() {
+            true => track.name(),
+            false => format!("{} - {}", track.artists.join(", "), track.name()),
+        };
+        playlist.push_str(&format!("File{n}={}\r\n", track.path.to_string_lossy()));
+        playlist.push_str(&format!("Title{n}={title}\r\n"));
+        playlist.push_str(&format!("Length{n}={}\r\n", track.duration.map(|d| d as i64).unwrap_or(-1)));
+    }
+    playlist.push_str(&format!("NumberOfEntries={}\r\nVersion=2\r\n", tracks.len()));
+    playlist
+}
+
+/// Get file list from PLS playlist, ordered by entry number
+pub fn get_files_from_pls(pls: &str, base_path: Option<PathBuf>) -> Vec<PathBuf> {
+    let mut entries = vec![];
+    for line in pls.lines() {
+        let (key, value) = match line.trim().split_once('=') {
+            Some(kv) => kv,
+            None => continue
+        };
+        let n: usize = match key.trim().to_lowercase().strip_prefix("file").and_then(|n| n.parse().ok()) {
+            Some(n) => n,
+            None => continue
+        };
+        if let Some(path) = url_to_path(value, base_path.as_deref()) {
+            entries.push((n, path));
+        }
+    }
+    entries.sort_by_key(|(n, _)| *n);
+    entries.into_iter().map(|(_, path)| path).collect()
+}
+
+#[test]
+fn pls_roundtrip() {
+    let tracks = vec![
+        LibraryTrack { path: PathBuf::from("/Music/a.mp3"), title: Some("A".to_string()), artists: vec!["X".to_string()], duration: Some(120), ..Default::default() },
+        LibraryTrack { path: PathBuf::from("/Music/b.flac"), ..Default::default() },
+    ];
+    let pls = create_pls_playlist(&tracks);
+    assert!(pls.contains("Title1=X - A\r\nLength1=120"));
+    assert!(pls.contains("Title2=b\r\nLength2=-1"));
+    assert_eq!(get_files_from_pls(&pls, None), vec![PathBuf::from("/Music/a.mp3"), PathBuf::from("/Music/b.flac")]);
+
+    let pls = "[playlist]\nFile2=b.mp3\nfile1=file:///Music/a%20b.mp3\nFile3=http://stream\n";
+    assert_eq!(get_files_from_pls(pls, Some(PathBuf::from("/base"))), vec![PathBuf::from("/Music/a b.mp3"), PathBuf::from("/base/b.mp3")]);
+
+    assert!(crate::has_playlist_extension("/Music/Set.PLS"));
+    assert!(!crate::has_playlist_extension("/Music/mypls"));
+}
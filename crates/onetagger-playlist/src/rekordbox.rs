@@ -0,0 +1,127 @@
+use anyhow::Error;
+use std::collections::HashMap;
+use crate::library::{DJLibrary, LibraryTrack, path_to_url, url_to_path};
+use crate::xml::XmlNode;
+
+const LOCATION_PREFIX: &str = "file://localhost";
+
+/// Generate Rekordbox `DJ_PLAYLISTS` XML with single playlist
+pub fn create_rekordbox_xml(name: &str, tracks: &[LibraryTrack]) -> String {
+    let mut collection = XmlNode::new("COLLECTION").attr("Entries", tracks.len());
+    let mut playlist = XmlNode::new("NODE")
+        .attr("Name", name)
+        .attr("Type", 1)
+        .attr("KeyType", 0)
+        .attr("Entries", tracks.len());
+    for (i, track) in tracks.iter().enumerate() {
+        let id = i + 1;
+        let mut node = XmlNode::new("TRACK")
+            .attr("TrackID", id)
+            .attr("Name", track.name())
+            .attr("Artist", track.artists.join(", "))
+            .attr("Album", track.album.as_deref().unwrap_or_default())
+            .attr("Genre", track.genres.join(", "))
+            .attr("Kind", format!("{} File", track.path.extension().unwrap_or_default().to_string_lossy().to_uppercase()))
+            .attr("Rating", track.rating.min(5) as u32 * 51)
+            .attr("Comments", track.comment.as_deref().unwrap_or_default())
+            .attr("Label", track.label.as_deref().unwrap_or_default())
+            .attr("Tonality", track.key.as_deref().unwrap_or_default())
+            .attr("Location", path_to_url(&track.path, LOCATION_PREFIX));
+        if let Some(bpm) = track.bpm {
+            node = node.attr("AverageBpm", format!("{bpm:.2}"));
+        }
+        if let Some(year) = track.year {
+            node = node.attr("Year", year);
+        }
+        if let Some(duration) = track.duration {
+            node = node.attr("TotalTime", duration);
+        }
+        collection = collection.child(node);
+        playlist = playlist.child(XmlNode::new("TRACK").attr("Key", id));
+    }
+
+    XmlNode::new("DJ_PLAYLISTS")
+        .attr("Version", "1.0.0")
+        .child(XmlNode::new("PRODUCT").attr("Name", "OneTagger").attr("Version", env!("CARGO_PKG_VERSION")).attr("Company", "OneTagger"))
+        .child(collection)
+        .child(XmlNode::new("PLAYLISTS").child(
+            XmlNode::new("NODE").attr("Type", 0).attr("Name", "ROOT").attr("Count", 1).child(playlist)
+        ))
+        .to_document()
+}
+
+/// Parse Rekordbox XML
+pub fn read_rekordbox_xml(xml: &str) -> Result<DJLibrary, Error> {
+    let root = XmlNode::parse(xml)?;
+    if root.name != "DJ_PLAYLISTS" {
+        return Err(anyhow!("Not a Rekordbox XML"));
+    }
+    // TrackID to path
+    let mut tracks = HashMap::new();
+    let mut library = DJLibrary::default();
+    for track in root.first("COLLECTION").map(|c| c.children("TRACK").collect::<Vec<_>>()).unwrap_or_default() {
+        let path = match track.get("Location").and_then(|l| url_to_path(l, None)) {
+            Some(path) => path,
+            None => continue
+        };
+        if let Some(id) = track.get("TrackID") {
+            tracks.insert(id.to_string(), path.clone());
+        }
+        library.collection.push(path);
+    }
+
+    // Playlists (Type 1), KeyType 0 = TrackID, 1 = Location
+    for node in root.first("PLAYLISTS").map(|p| p.descendants("NODE")).unwrap_or_default() {
+        if node.get("Type") != Some("1") {
+            continue;
+        }
+        let files = node.children("TRACK").filter_map(|t| {
+            let key = t.get("Key")?;
+            match node.get("KeyType") {
+                Some("1") => url_to_path(key, None),
+                _ => tracks.get(key).cloned(),
+            }
+        }).collect();
+        library.playlists.push((node.get("Name").unwrap_or_default().to_string(), files));
+    }
+    Ok(library)
+}
+
+#[test]
+fn rekordbox_roundtrip() {
+    use std::path::PathBuf;
+
+    let tracks = vec![
+        LibraryTrack {
+            path: PathBuf::from("/Music/Artist & Co/01 <Intro>.mp3"),
+            title: Some("Intro \"Edit\"".to_string()),
+            artists: vec!["A".to_string(), "B".to_string()],
+            bpm: Some(124.0),
+            key: Some("Am".to_string()),
+            rating: 3,
+            ..Default::default()
+        },
+        LibraryTrack { path: PathBuf::from("C:\\Music\\track 2.flac"), ..Default::default() },
+    ];
+    let xml = create_rekordbox_xml("Set", &tracks);
+    assert!(xml.contains("Location=\"file://localhost/Music/Artist%20%26%20Co/01%20%3CIntro%3E.mp3\""));
+    assert!(xml.contains("Location=\"file://localhost/C:/Music/track%202.flac\""));
+    assert!(xml.contains("Name=\"Intro &quot;Edit&quot;\""));
+    assert!(xml.contains("Rating=\"153\""));
+
+    let library = read_rekordbox_xml(&xml).unwrap();
+    assert_eq!(library.collection, vec![PathBuf::from("/Music/Artist & Co/01 <Intro>.mp3"), PathBuf::from("C:/Music/track 2.flac")]);
+    assert_eq!(library.playlists, vec![("Set".to_string(), library.collection.clone())]);
+
+    // DOCTYPE with internal subset
+    let xml = xml.replacen("<DJ_PLAYLISTS", "<!DOCTYPE DJ_PLAYLISTS [<!ELEMENT DJ_PLAYLISTS ANY><!ATTLIST DJ_PLAYLISTS Version CDATA #IMPLIED>]>\n<DJ_PLAYLISTS", 1);
+    assert_eq!(read_rekordbox_xml(&xml).unwrap().collection.len(), 2);
+
+    // Only Rekordbox XML files are playlists
+    let path = std::env::temp_dir().join("onetagger-rekordbox-test.xml");
+    std::fs::write(&path, &xml).unwrap();
+    assert_eq!(crate::PlaylistFormat::from_path(&path), Some(crate::PlaylistFormat::Rekordbox));
+    std::fs::write(&path, "<?xml version=\"1.0\"?><plist version=\"1.0\"><dict/></plist>").unwrap();
+    assert_eq!(crate::PlaylistFormat::from_path(&path), None);
+    std::fs::remove_file(&path).ok();
+}
@@ -0,0 +1,196 @@
+use anyhow::Error;
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+use crate::library::{DJLibrary, LibraryTrack};
+use crate::xml::XmlNode;
+
+/// Traktor path separator
+const SEPARATOR: &str = "/:";
+
+/// Generate Traktor `collection.nml` with single playlist
+pub fn create_traktor_nml(name: &str, tracks: &[LibraryTrack]) -> String {
+    let mut collection = XmlNode::new("COLLECTION").attr("ENTRIES", tracks.len());
+    let mut playlist = XmlNode::new("PLAYLIST")
+        .attr("ENTRIES", tracks.len())
+        .attr("TYPE", "LIST")
+        .attr("UUID", Uuid::new_v4().simple().to_string());
+    for track in tracks {
+        let location = TraktorLocation::from_path(&track.path);
+        let mut info = XmlNode::new("INFO")
+            .attr("GENRE", track.genres.join(", "))
+            .attr("LABEL", track.label.as_deref().unwrap_or_default())
+            .attr("COMMENT", track.comment.as_deref().unwrap_or_default())
+            .attr("KEY", track.key.as_deref().unwrap_or_default())
+            .attr("RANKING", track.rating.min(5) as u32 * 51);
+        if let Some(duration) = track.duration {
+            info = info.attr("PLAYTIME", duration);
+        }
+        if let Some(year) = track.year {
+            info = info.attr("RELEASE_DATE", format!("{year}/1/1"));
+        }
+        let mut entry = XmlNode::new("ENTRY")
+            .attr("TITLE", track.name())
+            .attr("ARTIST", track.artists.join(", "))
+            .child(XmlNode::new("LOCATION")
+                .attr("DIR", &location.dir)
+                .attr("FILE", &location.file)
+                .attr("VOLUME", &location.volume)
+                .attr("VOLUMEID", &location.volume)
+            )
+            .child(XmlNode::new("ALBUM").attr("TITLE", track.album.as_deref().unwrap_or_default()))
+            .child(info);
+        if let Some(bpm) = track.bpm {
+            entry = entry.child(XmlNode::new("TEMPO").attr("BPM", format!("{bpm:.6}")).attr("BPM_QUALITY", "100.000000"));
+        }
+        collection = collection.child(entry);
+        playlist = playlist.child(XmlNode::new("ENTRY").child(
+            XmlNode::new("PRIMARYKEY").attr("TYPE", "TRACK").attr("KEY", location.key())
+        ));
+    }
+
+    XmlNode::new("NML")
+        .attr("VERSION", 19)
+        .child(XmlNode::new("HEAD").attr("COMPANY", "www.native-instruments.com").attr("PROGRAM", "Traktor"))
+        .child(collection)
+        .child(XmlNode::new("PLAYLISTS").child(
+            XmlNode::new("NODE").attr("TYPE", "FOLDER").attr("NAME", "$ROOT").child(
+                XmlNode::new("SUBNODES").attr("COUNT", 1).child(
+                    XmlNode::new("NODE").attr("TYPE", "PLAYLIST").attr("NAME", name).child(playlist)
+                )
+            )
+        ))
+        .to_document()
+}
+
+/// Parse Traktor NML
+pub fn read_traktor_nml(nml: &str) -> Result<DJLibrary, Error> {
+    let root = XmlNode::parse(nml)?;
+    if root.name != "NML" {
+        return Err(anyhow!("Not a Traktor NML"));
+    }
+    let mut library = DJLibrary::default();
+    for entry in root.first("COLLECTION").map(|c| c.children("ENTRY").collect::<Vec<_>>()).unwrap_or_default() {
+        if let Some(location) = entry.first("LOCATION") {
+            let location = TraktorLocation {
+                volume: location.get("VOLUME").unwrap_or_default().to_string(),
+                dir: location.get("DIR").unwrap_or_default().to_string(),
+                file: location.get("FILE").unwrap_or_default().to_string(),
+            };
+            library.collection.push(location.to_path());
+        }
+    }
+    for node in root.first("PLAYLISTS").map(|p| p.descendants("NODE")).unwrap_or_default() {
+        if node.get("TYPE") != Some("PLAYLIST") {
+            continue;
+        }
+        let files = node.descendants("PRIMARYKEY").into_iter()
+            .filter(|k| k.get("TYPE") == Some("TRACK"))
+            .filter_map(|k| Some(TraktorLocation::from_key(k.get("KEY")?).to_path()))
+            .collect();
+        library.playlists.push((node.get("NAME").unwrap_or_default().to_string(), files));
+    }
+    Ok(library)
+}
+
+/// Traktor splits the path into volume, `/:` separated directory and filename
+#[derive(Debug, Clone, PartialEq)]
+struct TraktorLocation {
+    volume: String,
+    dir: String,
+    file: String,
+}
+
+impl TraktorLocation {
+    fn from_path(path: &Path) -> TraktorLocation {
+        let path = path.to_string_lossy().replace('\\', "/");
+        let mut parts: Vec<&str> = path.split('/').collect();
+        let file = parts.pop().unwrap_or_default().to_string();
+        // Windows drive or macOS external volume
+        let volume = match parts.first() {
+            Some(drive) if drive.len() == 2 && drive.ends_with(':') => parts.remove(0).to_string(),
+            _ if parts.len() > 2 && parts[1] == "Volumes" => {
+                parts.drain(0..2);
+                parts.remove(0).to_string()
+            },
+            // System volume
+            Some(&"") => SYSTEM_VOLUME.clone(),
+            _ => String::new()
+        };
+        let dirs: Vec<&str> = parts.into_iter().filter(|p| !p.is_empty()).collect();
+        TraktorLocation {
+            volume,
+            dir: format!("{SEPARATOR}{}", dirs.iter().map(|d| format!("{d}{SEPARATOR}")).collect::<String>()),
+            file
+        }
+    }
+
+    /// Parse `PRIMARYKEY`, which is volume + dir + file
+    fn from_key(key: &str) -> TraktorLocation {
+        let (volume, rest) = match key.find(SEPARATOR) {
+            Some(i) => (&key[..i], &key[i..]),
+            None => ("", key)
+        };
+        let (dir, file) = match rest.rfind(SEPARATOR) {
+            Some(i) => (&rest[..i + SEPARATOR.len()], &rest[i + SEPARATOR.len()..]),
+            None => ("", rest)
+        };
+        TraktorLocation { volume: volume.to_string(), dir: dir.to_string(), file: file.to_string() }
+    }
+
+    fn key(&self) -> String {
+        format!("{}{}{}", self.volume, self.dir, self.file)
+    }
+
+    fn to_path(&self) -> PathBuf {
+        let path = format!("{}{}", self.dir.replace(SEPARATOR, "/"), self.file);
+        if self.volume.len() == 2 && self.volume.ends_with(':') {
+            return PathBuf::from(format!("{}{path}", self.volume));
+        }
+        // Volume of the system drive is usually the disk name
+        if !self.volume.is_empty() && !Path::new(&path).exists() {
+            let external = PathBuf::from(format!("/Volumes/{}{path}", self.volume));
+            if external.exists() {
+                return external;
+            }
+        }
+        PathBuf::from(path)
+    }
+}
+
+lazy_static::lazy_static! {
+    /// Name of the macOS system volume, Traktor uses it for files outside of `/Volumes`
+    static ref SYSTEM_VOLUME: String = system_volume();
+}
+
+/// The system volume is symlinked to `/` in `/Volumes`
+#[cfg(target_os = "macos")]
+fn system_volume() -> String {
+    let root = Path::new("/").canonicalize().ok();
+    std::fs::read_dir("/Volumes").into_iter().flatten().flatten()
+        .find(|e| e.path().canonicalize().ok() == root)
+        .map(|e| e.file_name().to_string_lossy().to_string())
+        .unwrap_or(String::from("Macintosh HD"))
+}
+
+#[cfg(not(target_os = "macos"))]
+fn system_volume() -> String {
+    String::new()
+}
+
+#[test]
+fn traktor_roundtrip() {
+    let tracks = vec![
+        LibraryTrack { path: PathBuf::from("/home/dj/Music/a&b.mp3"), bpm: Some(128.0), rating: 5, ..Default::default() },
+        LibraryTrack { path: PathBuf::from("C:\\Music\\Sets\\b.flac"), ..Default::default() },
+    ];
+    let nml = create_traktor_nml("Set", &tracks);
+    assert!(nml.contains(&format!("DIR=\"/:home/:dj/:Music/:\" FILE=\"a&amp;b.mp3\" VOLUME=\"{}\"", *SYSTEM_VOLUME)));
+    assert!(nml.contains("KEY=\"C:/:Music/:Sets/:b.flac\""));
+    assert!(nml.contains("RANKING=\"255\""));
+
+    let library = read_traktor_nml(&nml).unwrap();
+    let paths = vec![PathBuf::from("/home/dj/Music/a&b.mp3"), PathBuf::from("C:/Music/Sets/b.flac")];
+    assert_eq!(library.collection, paths);
+    assert_eq!(library.playlists, vec![("Set".to_string(), paths)]);
+    assert_eq!(TraktorLocation::from_path(Path::new("/Volumes/USB/Music/c.wav")).key(), "USB/:Music/:c.wav");
+}
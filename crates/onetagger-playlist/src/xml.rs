@@ -0,0 +1,175 @@
+use anyhow::Error;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+use quick_xml::Reader;
+use quick_xml::events::{BytesStart, Event};
+
+/// Minimal XML element, enough for the playlist & DJ library formats (mixed content is joined)
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct XmlNode {
+    pub name: String,
+    pub attributes: Vec<(String, String)>,
+    pub children: Vec<XmlNode>,
+    pub text: String,
+}
+
+impl XmlNode {
+    pub fn new(name: &str) -> XmlNode {
+        XmlNode { name: name.to_string(), ..Default::default() }
+    }
+
+    /// Builder style attribute
+    pub fn attr(mut self, name: &str, value: impl ToString) -> XmlNode {
+        self.attributes.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    pub fn child(mut self, child: XmlNode) -> XmlNode {
+        self.children.push(child);
+        self
+    }
+
+    /// Child element with only text
+    pub fn text_child(self, name: &str, text: impl ToString) -> XmlNode {
+        let mut child = XmlNode::new(name);
+        child.text = text.to_string();
+        self.child(child)
+    }
+
+    /// Text of first child by name
+    pub fn child_text(&self, name: &str) -> Option<&str> {
+        self.first(name).map(|c| c.text.as_str()).filter(|t| !t.is_empty())
+    }
+
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.attributes.iter().find(|(n, _)| n == name).map(|(_, v)| v.as_str())
+    }
+
+    /// First child by name
+    pub fn first(&self, name: &str) -> Option<&XmlNode> {
+        self.children.iter().find(|c| c.name == name)
+    }
+
+    pub fn children<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a XmlNode> + 'a {
+        self.children.iter().filter(move |c| c.name == name)
+    }
+
+    /// All descendants by name
+    pub fn descendants<'a>(&'a self, name: &str) -> Vec<&'a XmlNode> {
+        let mut out = vec![];
+        for child in &self.children {
+            if child.name == name {
+                out.push(child);
+            }
+            out.extend(child.descendants(name));
+        }
+        out
+    }
+
+    /// Parse document and return the root element
+    pub fn parse(xml: &str) -> Result<XmlNode, Error> {
+        let mut reader = Reader::from_str(xml.trim_start_matches('\u{feff}'));
+        reader.config_mut().trim_text(true);
+        let mut stack: Vec<XmlNode> = vec![];
+        let mut root = None;
+        loop {
+            match reader.read_event()? {
+                Event::Start(e) => stack.push(XmlNode::from_start(&e)?),
+                Event::Empty(e) => {
+                    let node = XmlNode::from_start(&e)?;
+                    match stack.last_mut() {
+                        Some(parent) => parent.children.push(node),
+                        None => root = Some(node),
+                    }
+                },
+                Event::End(_) => {
+                    let node = stack.pop().ok_or(anyhow!("Unexpected closing tag"))?;
+                    match stack.last_mut() {
+                        Some(parent) => parent.children.push(node),
+                        None => root = Some(node),
+                    }
+                },
+                Event::Text(t) => if let Some(node) = stack.last_mut() {
+                    // Keep unknown entities as is
+                    match t.unescape() {
+                        Ok(text) => node.text.push_str(&text),
+                        Err(_) => node.text.push_str(&String::from_utf8_lossy(&t)),
+                    }
+                },
+                Event::CData(c) => if let Some(node) = stack.last_mut() {
+                    node.text.push_str(&String::from_utf8_lossy(&c.into_inner()));
+                },
+                Event::Eof => break,
+                // Declaration, comment, doctype, processing instruction
+                _ => {}
+            }
+        }
+        if !stack.is_empty() {
+            return Err(anyhow!("Unclosed element: {}", stack[0].name));
+        }
+        root.ok_or(anyhow!("Missing root element"))
+    }
+
+    /// Element with attributes, without children
+    fn from_start(e: &BytesStart) -> Result<XmlNode, Error> {
+        let mut node = XmlNode::new(&String::from_utf8_lossy(e.name().as_ref()));
+        for attribute in e.attributes() {
+            let attribute = attribute?;
+            node.attributes.push((String::from_utf8_lossy(attribute.key.as_ref()).to_string(), attribute.unescape_value()?.to_string()));
+        }
+        Ok(node)
+    }
+
+    /// Name of the root element, reads only the start of the file
+    pub fn root_name(path: impl AsRef<Path>) -> Result<String, Error> {
+        let mut reader = Reader::from_reader(BufReader::new(File::open(path)?));
+        let mut buf = vec![];
+        loop {
+            match reader.read_event_into(&mut buf)? {
+                Event::Start(e) | Event::Empty(e) => return Ok(String::from_utf8_lossy(e.name().as_ref()).to_string()),
+                Event::Eof => return Err(anyhow!("Missing root element")),
+                _ => buf.clear(),
+            }
+        }
+    }
+
+    /// Serialize with declaration
+    pub fn to_document(&self) -> String {
+        let mut out = "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n".to_string();
+        self.write(&mut out, 0);
+        out
+    }
+
+    fn write(&self, out: &mut String, depth: usize) {
+        out.push_str(&"  ".repeat(depth));
+        out.push('<');
+        out.push_str(&self.name);
+        for (name, value) in &self.attributes {
+            out.push_str(&format!(" {name}=\"{}\"", escape(value)));
+        }
+        if self.children.is_empty() && self.text.is_empty() {
+            out.push_str("/>\n");
+            return;
+        }
+        if self.children.is_empty() {
+            out.push_str(&format!(">{}</{}>\n", escape(&self.text), self.name));
+            return;
+        }
+        out.push_str(">\n");
+        for child in &self.children {
+            child.write(out, depth + 1);
+        }
+        out.push_str(&format!("{}</{}>\n", "  ".repeat(depth), self.name));
+    }
+}
+
+pub(crate) fn escape(value: &str) -> String {
+    value.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+        .replace('\n', "&#10;")
+        .replace('\r', "&#13;")
+}
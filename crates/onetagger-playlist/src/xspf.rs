@@ -0,0 +1,57 @@
+use anyhow::Error;
+use std::path::PathBuf;
+use crate::library::{LibraryTrack, path_to_url, url_to_path};
+use crate::xml::XmlNode;
+
+/// Generate XSPF playlist
+pub fn create_xspf_playlist(name: &str, tracks: &[LibraryTrack]) -> String {
+    let mut list = XmlNode::new("trackList");
+    for track in tracks {
+        let mut node = XmlNode::new("track")
+            .text_child("location", path_to_url(&track.path, "file://"))
+            .text_child("title", track.name());
+        if !track.artists.is_empty() {
+            node = node.text_child("creator", track.artists.join(", "));
+        }
+        if let Some(album) = &track.album {
+            node = node.text_child("album", album);
+        }
+        // In ms
+        if let Some(duration) = track.duration {
+            node = node.text_child("duration", duration * 1000);
+        }
+        list = list.child(node);
+    }
+    XmlNode::new("playlist")
+        .attr("version", 1)
+        .attr("xmlns", "http://xspf.org/ns/0/")
+        .text_child("title", name)
+        .child(list)
+        .to_document()
+}
+
+/// Get file list from XSPF playlist
+pub fn get_files_from_xspf(xspf: &str, base_path: Option<PathBuf>) -> Result<Vec<PathBuf>, Error> {
+    let root = XmlNode::parse(xspf)?;
+    if root.name != "playlist" {
+        return Err(anyhow!("Not a XSPF playlist"));
+    }
+    Ok(root.first("trackList")
+        .map(|l| l.children("track").filter_map(|t| url_to_path(t.child_text("location")?, base_path.as_deref())).collect())
+        .unwrap_or_default())
+}
+
+#[test]
+fn xspf_roundtrip() {
+    let tracks = vec![
+        LibraryTrack { path: PathBuf::from("/Music/a & b.mp3"), artists: vec!["X".to_string()], duration: Some(60), ..Default::default() },
+        LibraryTrack { path: PathBuf::from("C:\\Music\\c.flac"), ..Default::default() },
+    ];
+    let xspf = create_xspf_playlist("Set", &tracks);
+    assert!(xspf.contains("<location>file:///Music/a%20%26%20b.mp3</location>"));
+    assert!(xspf.contains("<duration>60000</duration>"));
+    assert_eq!(get_files_from_xspf(&xspf, None).unwrap(), vec![PathBuf::from("/Music/a & b.mp3"), PathBuf::from("C:/Music/c.flac")]);
+
+    let xspf = "<playlist><trackList><track><location><![CDATA[d.mp3]]></location></track></trackList></playlist>";
+    assert_eq!(get_files_from_xspf(xspf, Some(PathBuf::from("/base"))).unwrap(), vec![PathBuf::from("/base/d.mp3")]);
+}
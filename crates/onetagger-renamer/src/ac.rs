@@ -3,12 +3,14 @@ use std::cmp::Ordering;
 use pulldown_cmark::{Parser, Options};
 use serde::{Serialize, Deserialize};
 
-use crate::docs::{VARIABLES, FUNCTIONS, PROPERTIES};
+use crate::docs::{VARIABLES, FUNCTIONS, PROPERTIES, KEYWORDS};
 
 /// Separate parser for autocomplete
 pub struct Autocomplete {
     state: AutocompleteState,
     buffer: String,
+    /// First word of command, can be keyword
+    first: bool,
 }
 
 impl Autocomplete {
@@ -22,6 +24,8 @@ impl Autocomplete {
         let mut string = false;
         let mut dot_count = 0;
         let mut suggest = false;
+        let mut function = false;
+        let mut first = false;
 
         for c in input.chars() {
             match c {
@@ -29,6 +33,8 @@ impl Autocomplete {
                 '%' if !string => {
                     command = !command;
                     suggest = command;
+                    first = command;
+                    function = false;
                     dot_count = 0;
                     output.clear();
                 },
@@ -49,9 +55,18 @@ impl Autocomplete {
                 // Parameters
                 '(' if command && !string => {
                     suggest = false;
+                    function = true;
                 },
                 ')' if command && !string => {
                     output.clear();
+                    function = false;
+                }
+                // Keywords and operators separate values
+                ' ' | '!' | '=' | '~' | '<' | '>' | '?' if command && !string && !function => {
+                    output.clear();
+                    dot_count = 0;
+                    suggest = true;
+                    first = false;
                 }
                 _ => {
                     output.push(c);
@@ -64,7 +79,8 @@ impl Autocomplete {
         if !command || string || !suggest {
             return Autocomplete { 
                 state: AutocompleteState::None,
-                buffer: String::new()
+                buffer: String::new(),
+                first: false
             }
         }
         // Which part of command
@@ -73,7 +89,7 @@ impl Autocomplete {
             1 => AutocompleteState::Property,
             _ => AutocompleteState::Function
         };
-        Autocomplete { state, buffer: output }
+        Autocomplete { state, buffer: output, first }
     }
 
     /// Generate suggestions
@@ -84,7 +100,10 @@ impl Autocomplete {
 
         let text = self.buffer.to_lowercase();
         let mut matches = match self.state {
-            AutocompleteState::Variable => VARIABLES.iter().filter(|d| d.name.starts_with(&text)).collect::<Vec<&SymbolDoc>>(),
+            AutocompleteState::Variable => VARIABLES.iter()
+                .filter(|d| d.name.starts_with(&text))
+                .chain(KEYWORDS.iter().filter(|d| self.first && !text.is_empty() && d.name.starts_with(&text)))
+                .collect::<Vec<&SymbolDoc>>(),
             AutocompleteState::Property => PROPERTIES.iter()
                 .filter(|d| d.name.starts_with(&text))
                 .chain(FUNCTIONS.iter().filter(|d| d.name.starts_with(&text)))
@@ -171,6 +190,26 @@ impl SymbolDoc {
         }
     }
 
+    /// Short for creating new keyword doc
+    pub(crate) fn keyword(name: &str, doc: &str) -> SymbolDoc {
+        SymbolDoc {
+            name: name.to_string(),
+            doc: doc.to_string(),
+            kind: DocSymbolType::Keyword,
+            parameters: vec![],
+        }
+    }
+
+    /// Short for creating new operator doc
+    pub(crate) fn op(name: &str, doc: &str) -> SymbolDoc {
+        SymbolDoc {
+            name: name.to_string(),
+            doc: doc.to_string(),
+            kind: DocSymbolType::Operator,
+            parameters: vec![],
+        }
+    }
+
     // Short for creating new fn doc
     pub(crate) fn f(name: &str, doc: &str, parameters: Vec<DocParameter>) -> SymbolDoc {
         SymbolDoc {
@@ -187,7 +226,9 @@ impl SymbolDoc {
 pub enum DocSymbolType {
     Variable,
     Property,
-    Function
+    Function,
+    Keyword,
+    Operator
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
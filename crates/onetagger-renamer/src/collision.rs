@@ -0,0 +1,252 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use anyhow::Error;
+use serde::{Serialize, Deserialize};
+
+/// What to do when the target already exists or multiple files map to the same target
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum CollisionPolicy {
+    /// Append ` (n)` to the filename
+    Suffix,
+    Skip,
+    /// Overwrite existing files (the originals are kept for undo)
+    Overwrite,
+}
+
+impl FromStr for CollisionPolicy {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "suffix" => Ok(CollisionPolicy::Suffix),
+            "skip" => Ok(CollisionPolicy::Skip),
+            "overwrite" => Ok(CollisionPolicy::Overwrite),
+            _ => Err(anyhow!("Invalid collision policy: {s}, expected suffix, skip or overwrite"))
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum CollisionKind {
+    /// Multiple files map to the same target
+    Duplicate,
+    /// Target file already exists
+    Exists,
+    /// Existing file differs only in case (case-insensitive filesystem)
+    Case,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RenameCollision {
+    pub from: PathBuf,
+    pub to: PathBuf,
+    pub kind: CollisionKind,
+    /// Final target, `None` if skipped
+    pub resolved: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RenameOperation {
+    pub from: PathBuf,
+    pub to: PathBuf,
+    /// Target exists and should be replaced
+    pub overwrite: bool,
+    pub copy: bool,
+}
+
+/// Operations to execute, in order
+#[derive(Debug, Clone, Default)]
+pub struct RenamePlan {
+    pub operations: Vec<RenameOperation>,
+    pub collisions: Vec<RenameCollision>,
+}
+
+impl RenamePlan {
+    /// Detect collisions and resolve them using policy. files = output from generate
+    pub fn new(files: &[(PathBuf, PathBuf)], policy: CollisionPolicy, copy: bool) -> RenamePlan {
+        let files = files.iter().map(|(from, to)| (from.to_owned(), to.to_owned(), copy)).collect::<Vec<_>>();
+        Self::build(&files, policy, HashSet::new())
+    }
+
+    /// Add more files (from, to, copy) after the planned ones, their targets can't collide with the planned targets
+    pub fn append(&mut self, files: &[(PathBuf, PathBuf, bool)], policy: CollisionPolicy) {
+        let targets = self.operations.iter().map(|o| path_key(&o.to)).collect();
+        let plan = Self::build(files, policy, targets);
+        self.operations.extend(plan.operations);
+        self.collisions.extend(plan.collisions);
+    }
+
+    /// Final (from, to) of files which will be renamed
+    pub fn renamed(&self, files: &[(PathBuf, PathBuf)]) -> Vec<(PathBuf, PathBuf)> {
+        files.iter().filter(|(from, to)| from != to).filter_map(|(from, to)| match self.collisions.iter().find(|c| &c.from == from) {
+            Some(collision) => collision.resolved.to_owned().map(|r| (from.to_owned(), r)),
+            None => Some((from.to_owned(), to.to_owned()))
+        }).collect()
+    }
+
+    fn build(files: &[(PathBuf, PathBuf, bool)], policy: CollisionPolicy, targets: HashSet<String>) -> RenamePlan {
+        // Moved files free their original path
+        let mut sources: HashSet<String> = files.iter().filter(|(from, to, copy)| !copy && from != to).map(|(from, _, _)| path_key(from)).collect();
+        // Resolve again if some of the sources got skipped
+        let mut plan = loop {
+            let plan = Self::resolve(files, policy, &sources, targets.clone());
+            let moved = plan.operations.iter().map(|o| path_key(&o.from)).collect::<HashSet<_>>();
+            if sources.is_subset(&moved) {
+                break plan;
+            }
+            sources.retain(|s| moved.contains(s));
+        };
+        for collision in &plan.collisions {
+            info!("Collision ({:?}): {:?} -> {:?}, resolved: {:?}", collision.kind, collision.from, collision.to, collision.resolved);
+        }
+        plan.order();
+        plan
+    }
+
+    fn resolve(files: &[(PathBuf, PathBuf, bool)], policy: CollisionPolicy, sources: &HashSet<String>, mut targets: HashSet<String>) -> RenamePlan {
+        let mut plan = RenamePlan::default();
+        for (from, to, copy) in files {
+            if from == to {
+                continue;
+            }
+            let mut target = to.to_owned();
+            let mut collision: Option<RenameCollision> = None;
+            let mut n = 1;
+            let operation = loop {
+                let kind = if targets.contains(&path_key(&target)) {
+                    Some(CollisionKind::Duplicate)
+                } else if sources.contains(&path_key(&target)) {
+                    None
+                } else {
+                    match existing_path(&target) {
+                        Some(existing) if existing == target => Some(CollisionKind::Exists),
+                        // Case-only rename of the file itself
+                        Some(existing) if existing.file_name() == from.file_name() && existing.parent() == from.parent() => None,
+                        Some(_) => Some(CollisionKind::Case),
+                        None => None
+                    }
+                };
+                let kind = match kind {
+                    Some(kind) => kind,
+                    None => break Some(RenameOperation { from: from.to_owned(), to: target, overwrite: false, copy: *copy })
+                };
+                if collision.is_none() {
+                    collision = Some(RenameCollision { from: from.to_owned(), to: to.to_owned(), kind, resolved: None });
+                }
+                match (policy, kind) {
+                    (CollisionPolicy::Suffix, _) => {
+                        target = suffixed(to, n);
+                        n += 1;
+                    },
+                    // Never overwrite file from the same batch
+                    (CollisionPolicy::Skip, _) | (CollisionPolicy::Overwrite, CollisionKind::Duplicate) => break None,
+                    (CollisionPolicy::Overwrite, _) => break Some(RenameOperation { from: from.to_owned(), to: target, overwrite: true, copy: *copy }),
+                }
+            };
+
+            if let Some(operation) = &operation {
+                targets.insert(path_key(&operation.to));
+            }
+            if let Some(mut collision) = collision {
+                collision.resolved = operation.as_ref().map(|o| o.to.to_owned());
+                plan.collisions.push(collision);
+            }
+            plan.operations.extend(operation);
+        }
+        plan
+    }
+
+    /// Order moves so targets are freed before they are used, cycles go through a temporary file
+    fn order(&mut self) {
+        let mut pending = std::mem::take(&mut self.operations);
+        while !pending.is_empty() {
+            let free = (0..pending.len()).find(|i| !pending.iter().enumerate().any(|(j, o)| *i != j && !o.copy && path_key(&o.from) == path_key(&pending[*i].to)));
+            match free {
+                Some(i) => self.operations.push(pending.remove(i)),
+                None => {
+                    let tmp = temp_path(&pending[0].from);
+                    self.operations.push(RenameOperation { from: pending[0].from.to_owned(), to: tmp.to_owned(), overwrite: false, copy: false });
+                    pending[0].from = tmp;
+                }
+            }
+        }
+    }
+}
+
+/// Path used for comparison, case-insensitive on Windows and macOS
+fn path_key(path: &Path) -> String {
+    match cfg!(any(windows, target_os = "macos")) {
+        true => path.to_string_lossy().to_lowercase(),
+        false => path.to_string_lossy().to_string()
+    }
+}
+
+/// Get path of existing file with its actual case
+fn existing_path(path: &Path) -> Option<PathBuf> {
+    if !path.exists() {
+        return None;
+    }
+    let name = path.file_name()?.to_string_lossy().to_lowercase();
+    let entries = std::fs::read_dir(path.parent()?).ok()?.filter_map(|e| e.ok()).map(|e| e.path()).collect::<Vec<_>>();
+    entries.iter().find(|p| p.file_name() == path.file_name())
+        .or_else(|| entries.iter().find(|p| p.file_name().map(|n| n.to_string_lossy().to_lowercase() == name).unwrap_or(false)))
+        .map(|p| path.with_file_name(p.file_name().unwrap()))
+        .or(Some(path.to_owned()))
+}
+
+/// Add ` (n)` before extension
+fn suffixed(path: &Path, n: usize) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    match path.extension() {
+        Some(ext) => path.with_file_name(format!("{stem} ({n}).{}", ext.to_string_lossy())),
+        None => path.with_file_name(format!("{stem} ({n})")),
+    }
+}
+
+/// Temporary path in the same folder
+pub(crate) fn temp_path(path: &Path) -> PathBuf {
+    path.with_file_name(format!(".{}.{}.tmp", path.file_name().unwrap_or_default().to_string_lossy(), onetagger_shared::timestamp!()))
+}
+
+#[test]
+fn collisions() {
+    let dir = std::env::temp_dir().join(format!("onetagger-collisions-{}", onetagger_shared::timestamp!()));
+    std::fs::create_dir_all(&dir).unwrap();
+    for f in ["a.mp3", "b.mp3", "c.mp3", "taken.mp3"] {
+        std::fs::write(dir.join(f), f).unwrap();
+    }
+    let files = vec![
+        (dir.join("a.mp3"), dir.join("same.mp3")),
+        (dir.join("b.mp3"), dir.join("same.mp3")),
+        (dir.join("c.mp3"), dir.join("taken.mp3")),
+    ];
+
+    let plan = RenamePlan::new(&files, CollisionPolicy::Suffix, false);
+    let targets = plan.operations.iter().map(|o| o.to.to_owned()).collect::<Vec<_>>();
+    assert_eq!(targets, vec![dir.join("same.mp3"), dir.join("same (1).mp3"), dir.join("taken (1).mp3")]);
+    assert_eq!(plan.collisions.iter().map(|c| c.kind).collect::<Vec<_>>(), vec![CollisionKind::Duplicate, CollisionKind::Exists]);
+
+    let plan = RenamePlan::new(&files, CollisionPolicy::Skip, false);
+    assert_eq!(plan.operations.len(), 1);
+    let plan = RenamePlan::new(&files, CollisionPolicy::Overwrite, false);
+    assert_eq!(plan.operations.iter().map(|o| o.overwrite).collect::<Vec<_>>(), vec![false, true]);
+
+    // Swap goes through temporary file, moved sources are not collisions
+    let files = vec![(dir.join("a.mp3"), dir.join("b.mp3")), (dir.join("b.mp3"), dir.join("a.mp3"))];
+    let plan = RenamePlan::new(&files, CollisionPolicy::Skip, false);
+    assert!(plan.collisions.is_empty());
+    assert_eq!(plan.operations.len(), 3);
+    assert_eq!(plan.operations[2].to, dir.join("b.mp3"));
+
+    // Skipped source stays in place
+    let files = vec![(dir.join("a.mp3"), dir.join("b.mp3")), (dir.join("b.mp3"), dir.join("c.mp3"))];
+    let plan = RenamePlan::new(&files, CollisionPolicy::Skip, false);
+    assert!(plan.operations.is_empty());
+    assert_eq!(plan.collisions.len(), 2);
+
+    std::fs::remove_dir_all(&dir).ok();
+}
@@ -0,0 +1,138 @@
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use onetagger_tag::EXTENSIONS;
+use serde::{Serialize, Deserialize};
+
+/// Non-audio files which are renamed together with the audio files
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CompanionFiles {
+    /// Extensions of files with the same name as the audio file, e.g. `lrc`
+    pub sidecars: Vec<String>,
+    /// Files belonging to the whole folder, filename or `*.ext`, e.g. `cover.jpg`
+    pub folder: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CompanionFile {
+    pub from: PathBuf,
+    pub to: PathBuf,
+    /// Copy even when moving, because the folder is still used by other files
+    pub copy: bool,
+}
+
+impl CompanionFiles {
+    /// Some of the rules are set
+    pub fn is_empty(&self) -> bool {
+        self.sidecars.iter().chain(self.folder.iter()).all(|r| r.trim().is_empty())
+    }
+
+    /// Get companion files of the renamed files (from, to)
+    pub fn resolve(&self, files: &[(PathBuf, PathBuf)]) -> Vec<CompanionFile> {
+        let mut output: Vec<CompanionFile> = vec![];
+        if self.is_empty() {
+            return output;
+        }
+
+        // Same name sidecars
+        let mut folders: HashMap<PathBuf, Vec<PathBuf>> = HashMap::new();
+        for (from, to) in files.iter().filter(|(from, to)| from != to) {
+            let (from_dir, to_dir) = match (from.parent(), to.parent()) {
+                (Some(f), Some(t)) => (f, t),
+                _ => continue
+            };
+            let targets = folders.entry(from_dir.to_owned()).or_default();
+            if !targets.iter().any(|t| t == to_dir) {
+                targets.push(to_dir.to_owned());
+            }
+            for extension in self.sidecars.iter().map(|e| e.trim().trim_start_matches('.')).filter(|e| !e.is_empty()) {
+                let sidecar = match [extension.to_lowercase(), extension.to_uppercase()].iter().map(|e| from.with_extension(e)).find(|p| p.is_file()) {
+                    Some(sidecar) => sidecar,
+                    None => continue
+                };
+                if &sidecar == from {
+                    continue;
+                }
+                let to = to.with_extension(sidecar.extension().unwrap_or_default());
+                output.push(CompanionFile { from: sidecar, to, copy: false });
+            }
+        }
+
+        // Folder files, copied if the folder is split or still contains other audio files
+        let renamed = files.iter().filter(|(from, to)| from != to).map(|(from, _)| from.as_path()).collect::<HashSet<_>>();
+        for (from_dir, targets) in folders {
+            let destinations = targets.iter().filter(|t| **t != from_dir).collect::<Vec<_>>();
+            if destinations.is_empty() {
+                continue;
+            }
+            let entries = match std::fs::read_dir(&from_dir) {
+                Ok(entries) => entries.filter_map(|e| e.ok()).map(|e| e.path()).filter(|p| p.is_file()).collect::<Vec<_>>(),
+                Err(e) => {
+                    warn!("Failed listing {from_dir:?}: {e}");
+                    continue;
+                }
+            };
+            let shared = destinations.len() != targets.len() || entries.iter().any(|p| is_audio(p) && !renamed.contains(p.as_path()));
+            for path in entries.iter().filter(|p| self.is_folder_file(p)) {
+                // Already handled as sidecar
+                if output.iter().any(|c| &c.from == path) {
+                    continue;
+                }
+                for destination in &destinations {
+                    output.push(CompanionFile {
+                        from: path.to_owned(),
+                        to: destination.join(path.file_name().unwrap()),
+                        copy: shared || destinations.len() > 1
+                    });
+                }
+            }
+        }
+        output
+    }
+
+    /// Matches any of the folder rules
+    fn is_folder_file(&self, path: &Path) -> bool {
+        let name = path.file_name().unwrap_or_default().to_string_lossy().to_lowercase();
+        self.folder.iter().map(|r| r.trim().to_lowercase()).filter(|r| !r.is_empty()).any(|rule| match rule.strip_prefix("*.") {
+            Some(extension) => name.ends_with(&format!(".{extension}")),
+            None => name == rule
+        })
+    }
+}
+
+fn is_audio(path: &Path) -> bool {
+    let extension = path.extension().unwrap_or_default().to_string_lossy().to_lowercase();
+    EXTENSIONS.contains(&extension.as_str())
+}
+
+#[test]
+fn companion_files() {
+    let dir = std::env::temp_dir().join(format!("onetagger-companions-{}", onetagger_shared::timestamp!()));
+    std::fs::create_dir_all(dir.join("album")).unwrap();
+    for f in ["album/01.mp3", "album/01.lrc", "album/02.mp3", "album/cover.jpg", "album/album.CUE", "album/notes.txt"] {
+        std::fs::write(dir.join(f), f).unwrap();
+    }
+    let companions = CompanionFiles {
+        sidecars: vec!["lrc".to_string(), ".cue".to_string()],
+        folder: vec!["cover.jpg".to_string(), "*.cue".to_string()]
+    };
+    let files = vec![
+        (dir.join("album/01.mp3"), dir.join("out/A - 01.mp3")),
+        (dir.join("album/02.mp3"), dir.join("out/A - 02.mp3")),
+    ];
+    let mut resolved = companions.resolve(&files);
+    resolved.sort_by(|a, b| a.from.cmp(&b.from));
+    assert_eq!(resolved, vec![
+        CompanionFile { from: dir.join("album/01.lrc"), to: dir.join("out/A - 01.lrc"), copy: false },
+        CompanionFile { from: dir.join("album/album.CUE"), to: dir.join("out/album.CUE"), copy: false },
+        CompanionFile { from: dir.join("album/cover.jpg"), to: dir.join("out/cover.jpg"), copy: false },
+    ]);
+
+    // Other file stays, so folder files are only copied
+    let resolved = companions.resolve(&files[..1]);
+    assert_eq!(resolved.len(), 3);
+    assert!(resolved.iter().filter(|c| c.from.ends_with("cover.jpg")).all(|c| c.copy));
+
+    std::fs::remove_dir_all(&dir).ok();
+}
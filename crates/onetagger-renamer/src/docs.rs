@@ -3,7 +3,7 @@ use serde::Serialize;
 use crate::ac::{SymbolDoc, DocParameter};
 
 lazy_static! {
-    pub static ref VARIABLES: [SymbolDoc; 30] = [
+    pub static ref VARIABLES: [SymbolDoc; 44] = [
         SymbolDoc::var("title", "Get the Title frame from tag.\n\n  Used tags:<br> **MP3**: `TIT2`<br> **FLAC**: `TITLE`<br> **MP4**: `©nam`"),
         SymbolDoc::var("artist", "Get the first Artist from tag.\n\n  Used tags:<br> **MP3**: `TPE1`<br> **FLAC**: `ARTIST`<br> **MP4**: `©ART`"),
         SymbolDoc::var("artists", "Get the Artists frame from tag.\n\n  Used tags:<br> **MP3**: `TPE1`<br> **FLAC**: `ARTIST`<br> **MP4**: `©ART`"),
@@ -20,7 +20,7 @@ lazy_static! {
         SymbolDoc::var("version", "Get the Version frame from tag.\n\n  Used tags:<br> **MP3**: `TIT3`<br> **FLAC**: `SUBTITLE`<br> **MP4**: `com.apple.iTunes:SUBTITLE`"),
         SymbolDoc::var("tracknumber", "Get the Track Number frame from tag.\n\n  Used tags:<br> **MP3**: `TRCK`<br> **FLAC**: `TRACKNUMBER`<br> **MP4**: `trkn`"),
         SymbolDoc::var("track", "Get the Track Number frame from tag.\n\n  Used tags:<br> **MP3**: `TRCK`<br> **FLAC**: `TRACKNUMBER`<br> **MP4**: `trkn`"),
-        SymbolDoc::var("duration", "Get the Duration frame from tag.\n\n  Used tags:<br> **MP3**: `TLEN`<br> **FLAC**: `LENGTH`<br> **MP4**: `com.apple.iTunes:LENGTH`"),
+        SymbolDoc::var("duration", "Get the Duration frame from tag, falls back to the length of the audio in milliseconds.\n\n  Used tags:<br> **MP3**: `TLEN`<br> **FLAC**: `LENGTH`<br> **MP4**: `com.apple.iTunes:LENGTH`"),
         SymbolDoc::var("remixer", "Get the Remixer frame from tag.\n\n  Used tags:<br> **MP3**: `TPE4`<br> **FLAC**: `REMIXER`<br> **MP4**: `com.apple.iTunes:REMIXER`"),
         SymbolDoc::var("year", "Get the release year from tag.\n\n  Used tags:<br> **MP3**: `TYER` or `TDRC`<br> **FLAC**: `DATE`<br> **MP4**: `©day`"),
         SymbolDoc::var("month", "Get the release month from tag.\n\n  Used tags:<br> **MP3**: `TYER` or `TDRC`<br> **FLAC**: `DATE`<br> **MP4**: `©day`"),
@@ -34,6 +34,20 @@ lazy_static! {
         SymbolDoc::var("discnumber", "Get the Disc Number frame from tag.\n\n  Used tags:<br> **MP3**: `TPOS`<br> **FLAC**: `DISCNUMBER`<br> **MP4**: `disk`"),
         SymbolDoc::var("path", "Path to the file"),
         SymbolDoc::var("abspath", "Absolute path to the file"),
+        SymbolDoc::var("bitrate", "Bitrate of the audio in kbps"),
+        SymbolDoc::var("samplerate", "Sample rate of the audio in Hz"),
+        SymbolDoc::var("bitdepth", "Bit depth of the audio, only for lossless formats"),
+        SymbolDoc::var("channels", "Number of audio channels"),
+        SymbolDoc::var("codec", "Audio codec, for example `MP3`, `FLAC`, `AAC`, `ALAC`, `WAV`, `AIFF`, `Opus` or `DSD`"),
+        SymbolDoc::var("size", "File size in bytes"),
+        SymbolDoc::var("sizemb", "File size in MB, with one decimal place"),
+        SymbolDoc::var("rating", "Rating in stars (1 - 5)"),
+        SymbolDoc::var("camelot", "Key converted to Camelot notation"),
+        SymbolDoc::var("tagged", "How the file was tagged by OneTagger: `autotagger`, `audiofeatures` or `tagged`, empty if untagged.\n\n  Used tags: `1T_TAGGEDDATE`"),
+        SymbolDoc::var("taggeddate", "Date when the file was tagged by OneTagger, `YYYY-MM-DD`.\n\n  Used tags: `1T_TAGGEDDATE`"),
+        SymbolDoc::var("trackid", "Track ID from the platform used by Autotagger.\n\n  Used tags: `1T_PROVENANCE` or `{PLATFORM}_TRACK_ID`"),
+        SymbolDoc::var("releaseid", "Release ID from the platform used by Autotagger.\n\n  Used tags: `1T_PROVENANCE` or `{PLATFORM}_RELEASE_ID`"),
+        SymbolDoc::var("platform", "Name of the platform used by Autotagger, for example `beatport`.\n\n  Used tags: `1T_PROVENANCE` or `{PLATFORM}_TRACK_ID`"),
 
     ];
 
@@ -61,6 +75,25 @@ lazy_static! {
         SymbolDoc::f("camelot", "Convert key to camelot, or keep original", vec![DocParameter::s("value", true)]),
         SymbolDoc::f("uncamelot", "Convert key from camelot, or keep original", vec![DocParameter::s("value", true)]),
     ];
+
+    pub static ref KEYWORDS: [SymbolDoc; 4] = [
+        SymbolDoc::keyword("if", "Include the content up to `%elif%`, `%else%` or `%endif%` only if the condition is true.\n\n  Example: `%artist% - %title%%if remixer% (%remixer% Remix)%endif%`\n\n  Condition can be a value (true if it exists and is not empty), `!value` (true if it is missing or empty) or a comparison using the operators."),
+        SymbolDoc::keyword("elif", "Another condition, checked only if the previous ones were false.\n\n  Example: `%if bpm > 125%Fast%elif bpm > 110%Medium%else%Slow%endif%`"),
+        SymbolDoc::keyword("else", "Content used if none of the conditions were true."),
+        SymbolDoc::keyword("endif", "End of the `%if%` block."),
+    ];
+
+    pub static ref OPERATORS: [SymbolDoc; 9] = [
+        SymbolDoc::op("??", "Use the first value which exists and is not empty.\n\n  Example: `%remixer ?? artist%`, `%version ?? \"Original Mix\"%`"),
+        SymbolDoc::op("!", "Negate the condition. `%if !remixer%` is true if the remixer is missing or empty."),
+        SymbolDoc::op("==", "Equals. Numbers are compared as numbers, text is compared case-insensitive.\n\n  Example: `%if genre == \"House\"%`"),
+        SymbolDoc::op("!=", "Not equals. Numbers are compared as numbers, text is compared case-insensitive."),
+        SymbolDoc::op("~=", "Matches regex.\n\n  Example: `%if title ~= \"(?i)remix\"%`"),
+        SymbolDoc::op(">", "Greater than, only numbers.\n\n  Example: `%if bpm > 125%`"),
+        SymbolDoc::op("<", "Less than, only numbers."),
+        SymbolDoc::op(">=", "Greater or equal, only numbers."),
+        SymbolDoc::op("<=", "Less or equal, only numbers."),
+    ];
 }
 
 /// Holds all of the docs for sending into UI
@@ -68,13 +101,21 @@ lazy_static! {
 pub struct FullDocs {
     pub variables: Vec<SymbolDoc>,
     pub properties: Vec<SymbolDoc>,
-    pub functions: Vec<SymbolDoc>
+    pub functions: Vec<SymbolDoc>,
+    pub keywords: Vec<SymbolDoc>,
+    pub operators: Vec<SymbolDoc>
 }
 
 impl FullDocs {
     /// Get all of the docs
     pub fn get() -> FullDocs {
-        FullDocs { variables: VARIABLES.to_vec(), properties: PROPERTIES.to_vec(), functions: FUNCTIONS.to_vec() }
+        FullDocs { 
+            variables: VARIABLES.to_vec(), 
+            properties: PROPERTIES.to_vec(), 
+            functions: FUNCTIONS.to_vec(),
+            keywords: KEYWORDS.to_vec(),
+            operators: OPERATORS.to_vec()
+        }
     }
 
     /// Convert to html
@@ -82,6 +123,8 @@ impl FullDocs {
         self.variables.iter_mut().for_each(|i| i.html());
         self.properties.iter_mut().for_each(|i| i.html());
         self.functions.iter_mut().for_each(|i| i.html());
+        self.keywords.iter_mut().for_each(|i| i.html());
+        self.operators.iter_mut().for_each(|i| i.html());
         self
     }
 }
\ No newline at end of file
@@ -0,0 +1,255 @@
+use std::path::{Path, PathBuf};
+use anyhow::Error;
+use regex::Regex;
+use serde::{Serialize, Deserialize};
+use onetagger_tag::{Tag, TagDate};
+use onetagger_tagger::Field;
+
+use crate::parser::variable_field;
+
+/// Variables which can only contain numbers
+const NUMERIC_VARIABLES: [&str; 9] = ["track", "tracknumber", "total", "tracktotal", "disc", "disk", "discnumber", "disknumber", "year"];
+
+/// Parses tags from the filename and folders, inverse of the renamer template
+#[derive(Debug, Clone)]
+pub struct FilenameParser {
+    regex: Regex,
+    variables: Vec<String>,
+    /// Number of path components matched by the template
+    depth: usize,
+}
+
+impl FilenameParser {
+    /// Template uses the renamer variables, folders are separated by `/`.
+    /// Unknown variables (e.g. `%_%`) match anything and are ignored
+    pub fn new(template: &str) -> Result<FilenameParser, Error> {
+        let template = template.trim().replace('\\', "/");
+        let template = template.trim_matches('/');
+        if !template.matches('%').count().is_multiple_of(2) {
+            return Err(anyhow!("Unclosed variable in template: {template}"));
+        }
+
+        let mut pattern = String::from("^");
+        let mut variables: Vec<String> = vec![];
+        // Every other part is a variable
+        for (i, part) in template.split('%').enumerate() {
+            if i % 2 == 0 {
+                pattern.push_str(&regex::escape(part));
+                continue;
+            }
+            let name = part.trim().to_lowercase();
+            let value = match name.as_str() {
+                "bpm" => r"\d+(?:\.\d+)?",
+                n if NUMERIC_VARIABLES.contains(&n) => r"\d+",
+                _ => "[^/]+?"
+            };
+            let known = name == "year" || variable_field(&name).map(|f| f != Field::Duration).unwrap_or(false);
+            if known && !variables.contains(&name) {
+                pattern.push_str(&format!("(?P<{name}>{value})"));
+                variables.push(name);
+            } else {
+                pattern.push_str(&format!("(?:{value})"));
+            }
+        }
+        pattern.push('$');
+        if variables.is_empty() {
+            return Err(anyhow!("Template doesn't contain any tag variables"));
+        }
+        debug!("Filename tags regex: `{pattern}`");
+
+        Ok(FilenameParser {
+            regex: Regex::new(&pattern)?,
+            variables,
+            depth: template.split('/').count()
+        })
+    }
+
+    /// Get (variable, value) pairs from path, `None` if it doesn't match
+    pub fn parse(&self, path: impl AsRef<Path>) -> Option<Vec<(String, String)>> {
+        let path = path.as_ref().with_extension("");
+        let components = path.components().map(|c| c.as_os_str().to_string_lossy().to_string()).collect::<Vec<_>>();
+        let input = components[components.len().saturating_sub(self.depth)..].join("/");
+        let captures = self.regex.captures(&input)?;
+        Some(self.variables.iter()
+            .filter_map(|v| Some((v.to_string(), captures.name(v)?.as_str().trim().to_string())))
+            .filter(|(_, value)| !value.is_empty())
+            .collect())
+    }
+
+    /// Parse all files
+    pub fn preview(&self, files: &[PathBuf]) -> Vec<FilenameTags> {
+        files.iter().map(|path| FilenameTags { path: path.to_owned(), values: self.parse(path) }).collect()
+    }
+
+    /// Write the parsed values into the file tags
+    pub fn write(&self, path: impl AsRef<Path>, config: &FilenameTagsConfig) -> Result<Vec<(String, String)>, Error> {
+        let values = self.parse(&path).ok_or(anyhow!("Path doesn't match the template"))?;
+        let mut tag_wrap = Tag::load_file(&path, true)?;
+        let tag = tag_wrap.tag_mut();
+        let (mut track, mut total) = (None, None);
+        for (variable, value) in &values {
+            if variable == "year" {
+                tag.set_date(&TagDate { year: value.parse()?, month: None, day: None }, config.overwrite);
+                continue;
+            }
+            let field = match variable_field(variable) {
+                Some(field) => field,
+                None => continue
+            };
+            let value = match field {
+                // Stored together with the track number in some formats
+                Field::TrackNumber => {
+                    track = Some(value.to_string());
+                    continue;
+                },
+                Field::TrackTotal => {
+                    total = Some(value.parse::<u16>()?);
+                    continue;
+                },
+                Field::DiscNumber => {
+                    tag.set_disc_number(value.parse()?, config.overwrite);
+                    continue;
+                },
+                // Some formats only support integer BPM
+                Field::BPM => vec![value.parse::<f64>()?.round().to_string()],
+                Field::Artist | Field::AlbumArtist | Field::Genre | Field::Style | Field::Remixer if config.separator.as_ref().map(|s| !s.is_empty()).unwrap_or(false) => {
+                    value.split(config.separator.as_ref().unwrap().as_str()).map(|v| v.trim().to_string()).filter(|v| !v.is_empty()).collect()
+                },
+                _ => vec![value.to_string()]
+            };
+            tag.set_field(field, value, config.overwrite);
+        }
+        match (track, total) {
+            (Some(track), total) => tag.set_track_number(&track, total, config.overwrite),
+            // Keep the current track number
+            (None, Some(total)) => if config.overwrite || tag.get_field(Field::TrackTotal).is_none() {
+                if let Some(track) = tag.get_field(Field::TrackNumber).and_then(|t| t.first().cloned()) {
+                    tag.set_track_number(&track, Some(total), true);
+                }
+            },
+            (None, None) => {}
+        }
+        tag.save_file(path.as_ref())?;
+        Ok(values)
+    }
+
+    /// Write tags of all files
+    pub fn write_files(&self, files: &[PathBuf], config: &FilenameTagsConfig) -> Vec<(PathBuf, Result<(), Error>)> {
+        files.iter().map(|path| {
+            let result = self.write(path, config).map(|values| {
+                info!("Written tags from filename: {path:?}: {values:?}");
+            });
+            if let Err(e) = &result {
+                warn!("Failed writing tags from filename: {path:?}: {e}");
+            }
+            (path.to_owned(), result)
+        }).collect()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FilenameTagsConfig {
+    pub path: PathBuf,
+    pub template: String,
+    pub subfolders: bool,
+    /// Overwrite existing tags
+    pub overwrite: bool,
+    /// Split artists, genres etc.
+    #[serde(default)]
+    pub separator: Option<String>,
+}
+
+/// Values parsed from path
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FilenameTags {
+    pub path: PathBuf,
+    pub values: Option<Vec<(String, String)>>,
+}
+
+#[test]
+fn filename_parser() {
+    let parser = FilenameParser::new("%albumartist%/%year% - %album% [%catalognumber%]/%track%. %artist% - %title%").unwrap();
+    let values = parser.parse("/Music/Various/2020 - Best Of (Deluxe) [CAT 001]/03. A, B - Some Title (Extended Mix).flac").unwrap();
+    let expected = [
+        ("albumartist", "Various"), ("year", "2020"), ("album", "Best Of (Deluxe)"), ("catalognumber", "CAT 001"),
+        ("track", "03"), ("artist", "A, B"), ("title", "Some Title (Extended Mix)")
+    ];
+    assert_eq!(values, expected.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect::<Vec<_>>());
+
+    // Ignored variable, numbers only for track
+    let parser = FilenameParser::new("%_% - %track% - %title%").unwrap();
+    assert_eq!(parser.parse("x - 1 - y.mp3"), Some(vec![("track".to_string(), "1".to_string()), ("title".to_string(), "y".to_string())]));
+    assert_eq!(parser.parse("x - a - y.mp3"), None);
+
+    // Decimal BPM
+    let parser = FilenameParser::new("%title% - %bpm%").unwrap();
+    assert_eq!(parser.parse("a - 124.5.mp3"), Some(vec![("title".to_string(), "a".to_string()), ("bpm".to_string(), "124.5".to_string())]));
+    assert_eq!(parser.parse("a - 124.mp3"), Some(vec![("title".to_string(), "a".to_string()), ("bpm".to_string(), "124".to_string())]));
+
+    assert!(FilenameParser::new("%title").is_err());
+    assert!(FilenameParser::new("%something%").is_err());
+}
+
+#[test]
+fn filename_tags_write() {
+    let dir = std::env::temp_dir().join(format!("onetagger-filename-tags-{}", onetagger_shared::timestamp!()));
+    std::fs::create_dir_all(dir.join("2019")).unwrap();
+    let path = dir.join("2019").join("03-12 A; B - Title - 124.5.mp3");
+    std::fs::write(&path, []).unwrap();
+    let mut tag = Tag::load_file(&path, true).unwrap();
+    tag.tag_mut().set_field(Field::Title, vec!["Old".to_string()], true);
+    tag.tag_mut().set_date(&TagDate { year: 2000, month: None, day: None }, true);
+    tag.tag_mut().save_file(&path).unwrap();
+
+    let parser = FilenameParser::new("%year%/%track%-%total% %artist% - %title% - %bpm%").unwrap();
+    let mut config = FilenameTagsConfig { path: dir.clone(), template: String::new(), subfolders: true, overwrite: false, separator: Some(";".to_string()) };
+    parser.write(&path, &config).unwrap();
+    let tag = Tag::load_file(&path, false).unwrap();
+    assert_eq!(tag.tag().get_field(Field::Artist), Some(vec!["A".to_string(), "B".to_string()]));
+    assert_eq!(tag.tag().get_field(Field::BPM), Some(vec!["125".to_string()]));
+    assert_eq!(tag.tag().get_field(Field::TrackNumber), Some(vec!["3".to_string()]));
+    assert_eq!(tag.tag().get_field(Field::TrackTotal), Some(vec!["12".to_string()]));
+    assert_eq!(tag.tag().get_field(Field::Title), Some(vec!["Old".to_string()]));
+    assert_eq!(tag.tag().get_date().map(|d| d.year), Some(2000));
+
+    // Overwrite
+    config.overwrite = true;
+    parser.write(&path, &config).unwrap();
+    let tag = Tag::load_file(&path, false).unwrap();
+    assert_eq!(tag.tag().get_field(Field::Title), Some(vec!["Title".to_string()]));
+    assert_eq!(tag.tag().get_date().map(|d| d.year), Some(2019));
+
+    // MP4 stores track, total and BPM as integers
+    let mut mvhd = 108u32.to_be_bytes().to_vec();
+    mvhd.extend(b"mvhd");
+    mvhd.extend([0u8; 12]);
+    mvhd.extend(1000u32.to_be_bytes());
+    mvhd.extend([0u8; 4]);
+    mvhd.extend(0x00010000u32.to_be_bytes());
+    mvhd.extend(0x0100u16.to_be_bytes());
+    mvhd.extend([0u8; 10]);
+    for v in [0x00010000u32, 0, 0, 0, 0x00010000, 0, 0, 0, 0x40000000] {
+        mvhd.extend(v.to_be_bytes());
+    }
+    mvhd.extend([0u8; 24]);
+    mvhd.extend(1u32.to_be_bytes());
+    let mut mp4 = 20u32.to_be_bytes().to_vec();
+    mp4.extend(b"ftypM4A \0\0\0\0M4A ");
+    mp4.extend((mvhd.len() as u32 + 8).to_be_bytes());
+    mp4.extend(b"moov");
+    mp4.extend(mvhd);
+    mp4.extend(8u32.to_be_bytes());
+    mp4.extend(b"mdat");
+    let path = dir.join("2019").join("03-12 A; B - Title - 124.5.m4a");
+    std::fs::write(&path, mp4).unwrap();
+    parser.write(&path, &config).unwrap();
+    let tag = Tag::load_file(&path, false).unwrap();
+    assert_eq!(tag.tag().get_field(Field::TrackNumber), Some(vec!["3".to_string()]));
+    assert_eq!(tag.tag().get_field(Field::TrackTotal), Some(vec!["12".to_string()]));
+    assert_eq!(tag.tag().get_field(Field::BPM), Some(vec!["125".to_string()]));
+    assert_eq!(tag.tag().get_field(Field::Title), Some(vec!["Title".to_string()]));
+
+    std::fs::remove_dir_all(&dir).ok();
+}
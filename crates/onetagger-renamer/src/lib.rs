@@ -4,26 +4,42 @@
 
 use std::path::{Path, PathBuf};
 use anyhow::Error;
+use onetagger_tag::AudioProperties;
 use onetagger_tagger::AudioFileInfo;
 use serde::{Serialize, Deserialize};
 
 pub mod ac;
 pub mod docs;
+pub mod undo;
 pub mod parser;
+pub mod collision;
+pub mod companion;
+pub mod filename;
 
 // Re-export
 pub use parser::{TemplateParser, SyntaxData, SyntaxType};
+pub use collision::{CollisionPolicy, RenameCollision, RenamePlan};
+pub use undo::RenameLog;
+pub use companion::{CompanionFiles, CompanionFile};
+pub use filename::{FilenameParser, FilenameTagsConfig, FilenameTags};
 
 
 /// Renamer itself
 pub struct Renamer {
-    template: TemplateParser
+    template: TemplateParser,
+    /// Folder for undo logs, settings folder if `None`
+    log_dir: Option<PathBuf>,
 }
 
 impl Renamer {
     /// Create new instance
     pub fn new(template: TemplateParser) -> Renamer {
-        Renamer { template }
+        Renamer { template, log_dir: None }
+    }
+
+    /// Save undo logs into custom folder
+    pub fn set_log_dir(&mut self, dir: impl AsRef<Path>) {
+        self.log_dir = Some(dir.as_ref().to_owned());
     }
 
     /// Generate new filename
@@ -45,27 +61,41 @@ impl Renamer {
     where
         I: IntoIterator<Item = Result<AudioFileInfo, Error>>
     {
-        let input_path = dunce::canonicalize(&config.path)?;
+        let mut input_path = dunce::canonicalize(&config.path)?;
         if !input_path.exists() {
             return Err(anyhow!("Invalid path!"));
         }
+        // Playlist, use its folder
+        let mut base_path = config.path.to_owned();
+        if input_path.is_file() {
+            input_path = input_path.parent().ok_or(anyhow!("Invalid path!"))?.to_owned();
+            base_path = input_path.to_owned();
+        }
         
         // Get output path
-        let mut out_dir = config.out_dir.clone().unwrap_or(config.path.to_owned());
+        let mut out_dir = config.out_dir.clone().unwrap_or(base_path.to_owned());
         if out_dir.to_string_lossy().trim().is_empty() {
-            out_dir = config.path.to_owned();
+            out_dir = base_path;
         }
 
+        let properties = self.template.uses_properties();
         let mut output = vec![];
         for file in files.into_iter() {
             // Load files
-            let info = match file {
+            let mut info = match file {
                 Ok(i) => i,
                 Err(e) => {
                     warn!("Failed loading file: {e}");
                     continue;
                 },
             };
+            // Read bitrate etc only once
+            if properties && info.properties.is_none() {
+                match AudioProperties::read(&info.path) {
+                    Ok(p) => info.properties = Some(p),
+                    Err(e) => warn!("Failed reading audio properties of {:?}: {e}", info.path),
+                }
+            }
             let file = &info.path;
 
             // Get output dir
@@ -91,47 +121,44 @@ impl Renamer {
         Ok(output)
     }
 
-    /// Rename files, files = output from generate
-    pub fn rename(&mut self, files: &[(PathBuf, PathBuf)], config: &RenamerConfig) -> Result<(), Error> {
-        for (from, to) in files {
-            // Don't overwrite
-            if !config.overwrite && to.exists() {
-                info!("File exists, skipping: {to:?}");
-                continue;
-            }
+    /// Resolve collisions of files (output from generate) and their companion files.
+    /// Returns the plan and number of companion files to rename
+    pub fn plan(files: &[(PathBuf, PathBuf)], config: &RenamerConfig) -> (RenamePlan, usize) {
+        let policy = config.collision_policy();
+        let mut plan = RenamePlan::new(files, policy, config.copy);
+        let companions = config.companions.resolve(&plan.renamed(files)).into_iter()
+            .map(|c| (c.from, c.to, config.copy || c.copy))
+            .collect::<Vec<_>>();
+        let collisions = plan.collisions.len();
+        plan.append(&companions, policy);
+        let skipped = plan.collisions[collisions..].iter().filter(|c| c.resolved.is_none()).count();
+        (plan, companions.len() - skipped)
+    }
 
-            // Create dir
-            if let Some(parent) = to.parent() {
-                if let Err(e) = std::fs::create_dir_all(parent) {
-                    warn!("Failed creating dir {parent:?}: {e}");
-                }
-            }
-            // Copy mode
-            if config.copy {
-                match std::fs::copy(&from, &to) {
-                    Ok(_) => info!("Copied: {to:?}"),
-                    Err(e) => error!("Failed copying {from:?} -> {to:?}: {e}"),
-                }
-            // Move
-            } else {
-                if std::fs::rename(&from, &to).is_err() {
-                    info!("Renaming failed, might be different FS, trying to copy.");
-                    match std::fs::copy(&from, &to) {
-                        Ok(_) => {
-                            info!("Copied: {to:?}");
-                            if let Err(e) = std::fs::remove_file(&from) {
-                                warn!("Failed deleting {from:?}: {e}");
-                            }
-                        },
-                        Err(e) => error!("Failed copying {from:?} -> {to:?}: {e}"),
-                    }
-                } else {
-                    info!("Renamed: {to:?}");
-                }
-            }
+    /// Rename files, files = output from generate. Collisions are resolved up-front and
+    /// the whole batch is reverted if any of the files fails. Companion files follow the audio files
+    pub fn rename(&mut self, files: &[(PathBuf, PathBuf)], config: &RenamerConfig) -> Result<RenameReport, Error> {
+        let (plan, companions) = Renamer::plan(files, config);
+        let mut report = RenameReport {
+            renamed: plan.renamed(files).len(),
+            companions,
+            collisions: plan.collisions.clone(),
+            ..Default::default()
+        };
+        if plan.operations.is_empty() {
+            return Ok(report);
         }
 
-        Ok(())
+        let run_id = onetagger_shared::timestamp!().to_string();
+        let mut log = match &self.log_dir {
+            Some(dir) => RenameLog::open(dir.join(format!("rename-{run_id}.jsonl")))?,
+            None => RenameLog::create(&run_id)?
+        };
+        log.execute(&plan)?;
+        info!("Renamed {} files ({} companion files), skipped {}. Undo log: {}", report.renamed, report.companions, plan.collisions.iter().filter(|c| c.resolved.is_none()).count(), log.path().display());
+        report.undo_log = Some(log.path().to_owned());
+        report.run_id = Some(run_id);
+        Ok(report)
     }
 
     /// Generate html from the syntax highlighting
@@ -150,6 +177,7 @@ impl Renamer {
                 SyntaxType::Operator => "syntax_operator",
                 SyntaxType::Property => "syntax_property",
                 SyntaxType::Variable => "syntax_variable",
+                SyntaxType::Keyword => "syntax_keyword",
             };
             output.push_str(&format!("<span class=\"{prefix}{class}\">{text}</span>"));
         }
@@ -168,6 +196,10 @@ pub struct RenamerConfig {
     pub overwrite: bool,
     pub separator: String,
     pub keep_subfolders: bool,
+    #[serde(default)]
+    pub collision: Option<CollisionPolicy>,
+    #[serde(default)]
+    pub companions: CompanionFiles,
 }
 
 impl RenamerConfig {
@@ -182,8 +214,29 @@ impl RenamerConfig {
             keep_subfolders: false,
             separator: ", ".to_owned(),
             subfolders: true,
+            collision: None,
+            companions: CompanionFiles::default(),
         }
     }
+
+    /// Collision policy, falls back to the `overwrite` option
+    pub fn collision_policy(&self) -> CollisionPolicy {
+        self.collision.unwrap_or(match self.overwrite {
+            true => CollisionPolicy::Overwrite,
+            false => CollisionPolicy::Skip
+        })
+    }
+}
+
+/// Result of rename
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RenameReport {
+    pub renamed: usize,
+    pub companions: usize,
+    pub collisions: Vec<RenameCollision>,
+    pub run_id: Option<String>,
+    pub undo_log: Option<PathBuf>,
 }
 
 /// HTML generation test
@@ -194,7 +247,9 @@ fn generate_html() {
         "%track%. %artist% - %title%",
         "%artist% - %title% - %bpm% - %key%",
         "%artist% - %album%/%track% - %title%",
-        "%year% - %album%/%track% - %artist% - %title%"
+        "%year% - %album%/%track% - %artist% - %title%",
+        "%artist% - %title%%if remixer% (%remixer% Remix)%endif%",
+        "%if bpm >= 125%Fast%elif !bpm%Unknown%else%Slow%endif%/%version ?? \"Original Mix\"%"
     ];
     for i in items {
         let renamer = Renamer::new(TemplateParser::parse(i));
@@ -202,3 +257,37 @@ fn generate_html() {
         println!("{output}")
     }
 }
+
+#[test]
+fn rename_companions_undo() {
+    let dir = std::env::temp_dir().join(format!("onetagger-rename-companions-{}", onetagger_shared::timestamp!()));
+    std::fs::create_dir_all(dir.join("album")).unwrap();
+    for f in ["album/01.mp3", "album/01.lrc", "album/02.mp3", "album/cover.jpg"] {
+        std::fs::write(dir.join(f), f).unwrap();
+    }
+    let mut config = RenamerConfig::default_with_paths(dir.join("album"), "%title%");
+    config.companions = CompanionFiles { sidecars: vec!["lrc".to_string()], folder: vec!["cover.jpg".to_string()] };
+    let files = vec![(dir.join("album/01.mp3"), dir.join("out/A.mp3"))];
+
+    // Cover is copied because 02.mp3 stays in the folder
+    let mut renamer = Renamer::new(TemplateParser::parse(&config.template));
+    renamer.set_log_dir(&dir);
+    let report = renamer.rename(&files, &config).unwrap();
+    assert_eq!((report.renamed, report.companions), (1, 2));
+    assert_eq!(std::fs::read_to_string(dir.join("out/A.mp3")).unwrap(), "album/01.mp3");
+    assert_eq!(std::fs::read_to_string(dir.join("out/A.lrc")).unwrap(), "album/01.lrc");
+    assert!(dir.join("out/cover.jpg").exists());
+    assert!(dir.join("album/cover.jpg").exists());
+    assert!(!dir.join("album/01.lrc").exists());
+
+    let log = report.undo_log.unwrap();
+    let results = RenameLog::undo(&log.to_string_lossy()).unwrap();
+    assert!(results.iter().all(|(_, r)| r.is_ok()));
+    for f in ["album/01.mp3", "album/01.lrc", "album/02.mp3", "album/cover.jpg"] {
+        assert_eq!(std::fs::read_to_string(dir.join(f)).unwrap(), f);
+    }
+    assert!(!dir.join("out").exists());
+
+    assert!(log.starts_with(&dir));
+    std::fs::remove_dir_all(&dir).ok();
+}
@@ -1,6 +1,7 @@
 use std::path::Path;
-use onetagger_tag::Tag;
-use onetagger_tagger::{AudioFileInfo, Field};
+use std::sync::Mutex;
+use std::collections::HashMap;
+use onetagger_tagger::{AudioFileInfo, Field, FileTaggedStatus, SupportedTag};
 use pad::{PadStr, Alignment};
 use regex::Regex;
 
@@ -8,6 +9,20 @@ use crate::RenamerConfig;
 
 /// Illegal filename characters
 static ILLEGAL_FILENAME: &'static str = "<>:\"/\\|?*\0";
+/// Comparison operators, longer first
+static COMPARISONS: [&str; 7] = ["==", "!=", "~=", ">=", "<=", ">", "<"];
+/// Fallback operator
+static COALESCE: &str = "??";
+/// Max number of cached compiled regexes
+const REGEX_CACHE_SIZE: usize = 256;
+
+lazy_static! {
+    /// Compiled regexes of `~=` conditions, `None` if the pattern is invalid
+    static ref REGEX_CACHE: Mutex<HashMap<String, Option<Regex>>> = Mutex::new(HashMap::new());
+}
+
+/// Variables which require reading the audio properties
+pub(crate) static PROPERTY_VARIABLES: [&str; 8] = ["duration", "bitrate", "samplerate", "bitdepth", "channels", "codec", "size", "sizemb"];
 
 #[derive(Debug, Clone)]
 pub struct TemplateParser {
@@ -18,17 +33,17 @@ pub struct TemplateParser {
 impl TemplateParser {
     /// Apply template
     pub fn evaluate(&mut self, info: &AudioFileInfo, config: &RenamerConfig) -> String {
-        let mut output = String::new();
-        for token in &self.tokens {
-            if let Some(data) = token.token().get_value(None, info, config) {
-                match token {
-                    // Do not sanitize constants
-                    TokenType::Constant(_) => output.push_str(&data.to_string(&config.separator)),
-                    _ => output.push_str(&data.sanitize().to_string(&config.separator))
-                }
-            }
-        }
-        output
+        evaluate_tokens(&self.tokens, info, config)
+    }
+
+    /// Names of all used variables, lowercase
+    pub fn variables(&self) -> Vec<String> {
+        self.tokens.iter().flat_map(|t| t.variables()).map(|v| v.to_lowercase()).collect()
+    }
+
+    /// Whether the audio properties have to be loaded
+    pub fn uses_properties(&self) -> bool {
+        self.variables().iter().any(|v| PROPERTY_VARIABLES.contains(&v.as_str()))
     }
 
 
@@ -40,6 +55,8 @@ impl TemplateParser {
         let mut syntax = SyntaxBuilder::new();
         let mut escape = false;
         let mut string = false;
+        // Open if blocks
+        let mut blocks: Vec<TokenConditional> = vec![];
 
         for c in input.chars() {
             match c {
@@ -47,7 +64,22 @@ impl TemplateParser {
                     // End of command
                     if command {
                         if !string {
-                            tokens.push(TokenType::Command(TokenCommand::parse(&buffer, &mut syntax)));
+                            match Statement::parse(&buffer, &mut syntax) {
+                                Statement::Token(token) => push_token(&mut tokens, &mut blocks, token),
+                                Statement::If(condition) => blocks.push(TokenConditional { branches: vec![(Some(condition), vec![])] }),
+                                Statement::Elif(condition) => match blocks.last_mut() {
+                                    Some(block) => block.branches.push((Some(condition), vec![])),
+                                    None => error!("elif without if!")
+                                },
+                                Statement::Else => match blocks.last_mut() {
+                                    Some(block) => block.branches.push((None, vec![])),
+                                    None => error!("else without if!")
+                                },
+                                Statement::EndIf => match blocks.pop() {
+                                    Some(block) => push_token(&mut tokens, &mut blocks, TokenType::Conditional(block)),
+                                    None => error!("endif without if!")
+                                }
+                            }
                             syntax.add(1, SyntaxType::Operator);
                             buffer.clear();
                             command = false;
@@ -56,7 +88,7 @@ impl TemplateParser {
                     // Start command
                     } else {
                         if !buffer.is_empty() {
-                            tokens.push(TokenType::Constant(TokenConstant::new(&buffer)));
+                            push_token(&mut tokens, &mut blocks, TokenType::Constant(TokenConstant::new(&buffer)));
                             syntax.add(buffer.len(), SyntaxType::Text);
                             buffer.clear();
                         }
@@ -84,13 +116,288 @@ impl TemplateParser {
 
         // Leftover
         if !buffer.is_empty() {
-            tokens.push(TokenType::Constant(TokenConstant::new(&buffer)));
+            push_token(&mut tokens, &mut blocks, TokenType::Constant(TokenConstant::new(&buffer)));
             syntax.add(buffer.len(), SyntaxType::Text);
         }
+        // Close unterminated blocks
+        while let Some(block) = blocks.pop() {
+            warn!("Missing endif in template!");
+            push_token(&mut tokens, &mut blocks, TokenType::Conditional(block));
+        }
         TemplateParser { tokens, syntax: syntax.build() }
     }
 }
 
+/// Evaluate list of tokens into string
+fn evaluate_tokens(tokens: &[TokenType], info: &AudioFileInfo, config: &RenamerConfig) -> String {
+    let mut output = String::new();
+    for token in tokens {
+        if let Some(data) = token.token().get_value(None, info, config) {
+            match token {
+                // Do not sanitize constants, conditionals are already sanitized
+                TokenType::Constant(_) | TokenType::Conditional(_) => output.push_str(&data.to_string(&config.separator)),
+                _ => output.push_str(&data.sanitize().to_string(&config.separator))
+            }
+        }
+    }
+    output
+}
+
+/// Add token to the innermost open block or the root
+fn push_token(tokens: &mut Vec<TokenType>, blocks: &mut [TokenConditional], token: TokenType) {
+    match blocks.last_mut().and_then(|b| b.branches.last_mut()) {
+        Some((_, branch)) => branch.push(token),
+        None => tokens.push(token)
+    }
+}
+
+/// Content of % %
+enum Statement {
+    Token(TokenType),
+    If(Condition),
+    Elif(Condition),
+    Else,
+    EndIf
+}
+
+impl Statement {
+    fn parse(input: &str, syntax: &mut SyntaxBuilder) -> Statement {
+        let trimmed = input.trim_start();
+        let keyword = trimmed.split(char::is_whitespace).next().unwrap_or_default();
+        let statement = match keyword {
+            "if" | "elif" if trimmed.len() > keyword.len() => {
+                syntax.add(input.len() - trimmed.len(), SyntaxType::Operator);
+                syntax.add(keyword.len(), SyntaxType::Keyword);
+                let condition = Condition::parse(&trimmed[keyword.len()..], syntax);
+                return match keyword {
+                    "if" => Statement::If(condition),
+                    _ => Statement::Elif(condition)
+                };
+            },
+            "else" if trimmed.trim_end() == keyword => Statement::Else,
+            "endif" if trimmed.trim_end() == keyword => Statement::EndIf,
+            _ => return Statement::Token(parse_expression(input, syntax))
+        };
+        let end = trimmed.trim_end().len();
+        syntax.add(input.len() - trimmed.len(), SyntaxType::Operator);
+        syntax.add(end, SyntaxType::Keyword);
+        syntax.add(trimmed.len() - end, SyntaxType::Operator);
+        statement
+    }
+}
+
+/// Part of expression split by operators
+#[derive(Debug, Clone, PartialEq)]
+enum ExpressionPart<'a> {
+    Operand(&'a str),
+    Operator(&'a str)
+}
+
+/// Split by operators outside of strings and function parameters
+fn split_operators<'a>(input: &'a str, operators: &[&str]) -> Vec<ExpressionPart<'a>> {
+    let mut out = vec![];
+    let mut start = 0;
+    let mut string = false;
+    let mut escape = false;
+    let mut depth = 0;
+    let mut iter = input.char_indices();
+    while let Some((i, c)) = iter.next() {
+        match c {
+            '\\' if string => {
+                escape = !escape;
+                continue;
+            },
+            '"' if !escape => string = !string,
+            '(' if !string => depth += 1,
+            ')' if !string && depth > 0 => depth -= 1,
+            _ if !string && depth == 0 => {
+                if let Some(operator) = operators.iter().find(|o| input[i..].starts_with(*o)) {
+                    out.push(ExpressionPart::Operand(&input[start..i]));
+                    out.push(ExpressionPart::Operator(&input[i..i + operator.len()]));
+                    start = i + operator.len();
+                    // Skip rest of the operator
+                    for _ in 1..operator.chars().count() {
+                        iter.next();
+                    }
+                }
+            },
+            _ => {}
+        }
+        escape = false;
+    }
+    out.push(ExpressionPart::Operand(&input[start..]));
+    out
+}
+
+/// Parse operand with surrounding whitespace
+fn parse_operand(input: &str, syntax: &mut SyntaxBuilder) -> TokenType {
+    let trimmed = input.trim();
+    let start = input.len() - input.trim_start().len();
+    syntax.add(start, SyntaxType::Operator);
+    let number = trimmed.starts_with(|c: char| c.is_ascii_digit() || c == '-') && trimmed.parse::<f64>().is_ok();
+    let token = match number {
+        true => {
+            syntax.add(trimmed.len(), SyntaxType::Number);
+            TokenType::Constant(TokenConstant::new(trimmed))
+        },
+        false => TokenType::Command(TokenCommand::parse(trimmed, syntax))
+    };
+    syntax.add(input.len() - start - trimmed.len(), SyntaxType::Operator);
+    token
+}
+
+/// Parse command with optional `??` fallbacks
+fn parse_expression(input: &str, syntax: &mut SyntaxBuilder) -> TokenType {
+    let mut options = vec![];
+    for part in split_operators(input, &[COALESCE]) {
+        match part {
+            ExpressionPart::Operand(operand) => options.push(parse_operand(operand, syntax)),
+            ExpressionPart::Operator(operator) => syntax.add(operator.len(), SyntaxType::Operator),
+        }
+    }
+    match options.len() {
+        1 => options.remove(0),
+        _ => TokenType::Coalesce(TokenCoalesce { options })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Comparison {
+    Equals,
+    NotEquals,
+    Matches,
+    Greater,
+    Less,
+    GreaterEquals,
+    LessEquals
+}
+
+impl Comparison {
+    fn from_operator(operator: &str) -> Option<Comparison> {
+        match operator {
+            "==" => Some(Comparison::Equals),
+            "!=" => Some(Comparison::NotEquals),
+            "~=" => Some(Comparison::Matches),
+            ">" => Some(Comparison::Greater),
+            "<" => Some(Comparison::Less),
+            ">=" => Some(Comparison::GreaterEquals),
+            "<=" => Some(Comparison::LessEquals),
+            _ => None
+        }
+    }
+}
+
+/// Get compiled regex from cache or compile it, `None` if invalid
+fn cached_regex(pattern: &str) -> Option<Regex> {
+    let mut cache = REGEX_CACHE.lock().unwrap();
+    if let Some(re) = cache.get(pattern) {
+        return re.clone();
+    }
+    let re = match Regex::new(pattern) {
+        Ok(re) => Some(re),
+        Err(e) => {
+            error!("Invalid regex: {pattern}: {e}");
+            None
+        }
+    };
+    if cache.len() >= REGEX_CACHE_SIZE {
+        cache.clear();
+    }
+    cache.insert(pattern.to_string(), re.clone());
+    re
+}
+
+/// Condition of if block
+#[derive(Debug, Clone)]
+enum Condition {
+    /// Value exists and is not empty
+    Exists(TokenType),
+    Not(Box<Condition>),
+    Compare(TokenType, Comparison, TokenType),
+    Invalid
+}
+
+impl Condition {
+    fn parse(input: &str, syntax: &mut SyntaxBuilder) -> Condition {
+        let trimmed = input.trim_start();
+        // Negation
+        if trimmed.starts_with('!') && !trimmed.starts_with("!=") {
+            syntax.add(input.len() - trimmed.len() + 1, SyntaxType::Operator);
+            return Condition::Not(Box::new(Condition::parse(&trimmed[1..], syntax)));
+        }
+
+        let parts = split_operators(input, &COMPARISONS);
+        let mut operands = vec![];
+        let mut comparison = None;
+        for part in &parts {
+            match part {
+                ExpressionPart::Operand(operand) => operands.push(parse_expression(operand, syntax)),
+                ExpressionPart::Operator(operator) => {
+                    syntax.add(operator.len(), SyntaxType::Operator);
+                    comparison = comparison.or(Comparison::from_operator(operator));
+                }
+            }
+        }
+        match (operands.len(), comparison) {
+            (1, None) => Condition::Exists(operands.remove(0)),
+            (2, Some(comparison)) => {
+                let right = operands.remove(1);
+                Condition::Compare(operands.remove(0), comparison, right)
+            },
+            _ => {
+                error!("Invalid condition: {input}");
+                Condition::Invalid
+            }
+        }
+    }
+
+    /// Tokens of the operands
+    fn tokens(&self) -> Vec<&TokenType> {
+        match self {
+            Condition::Exists(token) => vec![token],
+            Condition::Not(condition) => condition.tokens(),
+            Condition::Compare(left, _, right) => vec![left, right],
+            Condition::Invalid => vec![]
+        }
+    }
+
+    /// Evaluate the condition
+    fn evaluate(&self, info: &AudioFileInfo, config: &RenamerConfig) -> bool {
+        let value = |token: &TokenType| token.token().get_value(None, info, config)
+            .map(|d| d.to_string(&config.separator).trim().to_string())
+            .filter(|v| !v.is_empty());
+
+        match self {
+            Condition::Exists(token) => value(token).is_some(),
+            Condition::Not(condition) => !condition.evaluate(info, config),
+            Condition::Compare(left, comparison, right) => {
+                let left = value(left).unwrap_or_default();
+                let right = value(right).unwrap_or_default();
+                let numbers = left.parse::<f64>().ok().zip(right.parse::<f64>().ok());
+                match comparison {
+                    Comparison::Equals | Comparison::NotEquals => {
+                        let equals = match numbers {
+                            Some((l, r)) => l == r,
+                            None => left.to_lowercase() == right.to_lowercase()
+                        };
+                        equals == (*comparison == Comparison::Equals)
+                    },
+                    Comparison::Matches => match cached_regex(&right) {
+                        Some(re) => re.is_match(&left),
+                        None => false
+                    },
+                    // Only numbers can be compared
+                    Comparison::Greater => numbers.map(|(l, r)| l > r).unwrap_or(false),
+                    Comparison::Less => numbers.map(|(l, r)| l < r).unwrap_or(false),
+                    Comparison::GreaterEquals => numbers.map(|(l, r)| l >= r).unwrap_or(false),
+                    Comparison::LessEquals => numbers.map(|(l, r)| l <= r).unwrap_or(false),
+                }
+            },
+            Condition::Invalid => false,
+        }
+    }
+}
+
 /// For syntax highlighting
 #[derive(Debug, Clone)]
 pub struct SyntaxData {
@@ -121,7 +428,9 @@ pub enum SyntaxType {
     /// Property name
     Property,
     /// Variable name
-    Variable
+    Variable,
+    /// if elif else endif
+    Keyword
 }
 
 struct SyntaxBuilder {
@@ -193,7 +502,9 @@ enum TokenType {
     Constant(TokenConstant),
     Variable(TokenVariable),
     Property(TokenProperty),
-    Function(TokenFunction)
+    Function(TokenFunction),
+    Coalesce(TokenCoalesce),
+    Conditional(TokenConditional)
 }
 
 impl TokenType {
@@ -205,6 +516,22 @@ impl TokenType {
             TokenType::Variable(t) => t,
             TokenType::Property(t) => t,
             TokenType::Function(t) => t,
+            TokenType::Coalesce(t) => t,
+            TokenType::Conditional(t) => t,
+        }
+    }
+
+    /// All variable names used in this token
+    fn variables(&self) -> Vec<&str> {
+        match self {
+            TokenType::Variable(v) => vec![v.var.as_str()],
+            TokenType::Command(c) => c.tokens.iter().flat_map(|t| t.variables()).collect(),
+            TokenType::Coalesce(c) => c.options.iter().flat_map(|t| t.variables()).collect(),
+            TokenType::Conditional(c) => c.branches.iter()
+                .flat_map(|(condition, tokens)| condition.iter().flat_map(|c| c.tokens()).chain(tokens.iter()))
+                .flat_map(|t| t.variables())
+                .collect(),
+            _ => vec![]
         }
     }
 }
@@ -367,6 +694,36 @@ impl Token for TokenCommand {
 }
 
 
+/// First non empty value of `a ?? b ?? c`
+#[derive(Debug, Clone)]
+struct TokenCoalesce {
+    options: Vec<TokenType>
+}
+
+impl Token for TokenCoalesce {
+    fn get_value(&self, _input: Option<&Data>, info: &AudioFileInfo, config: &RenamerConfig) -> Option<Data> {
+        self.options.iter()
+            .filter_map(|t| t.token().get_value(None, info, config))
+            .find(|d| !d.to_string(&config.separator).trim().is_empty())
+    }
+}
+
+/// `%if%` block, branch without condition is `else`
+#[derive(Debug, Clone)]
+struct TokenConditional {
+    branches: Vec<(Option<Condition>, Vec<TokenType>)>
+}
+
+impl Token for TokenConditional {
+    fn get_value(&self, _input: Option<&Data>, info: &AudioFileInfo, config: &RenamerConfig) -> Option<Data> {
+        let (_, tokens) = self.branches.iter().find(|(condition, _)| match condition {
+            Some(condition) => condition.evaluate(info, config),
+            None => true
+        })?;
+        Some(Data::String(evaluate_tokens(tokens, info, config)))
+    }
+}
+
 /// Constant string value
 #[derive(Debug, Clone)]
 struct TokenConstant {
@@ -386,6 +743,30 @@ impl Token for TokenConstant {
     }
 }
 
+/// Get tag field of variable
+pub(crate) fn variable_field(variable: &str) -> Option<Field> {
+    match variable {
+        "title" => Some(Field::Title),
+        "artist" | "artists" => Some(Field::Artist),
+        "album" => Some(Field::Album),
+        "albumartist" | "albumartists" => Some(Field::AlbumArtist),
+        "key" => Some(Field::Key),
+        "bpm" => Some(Field::BPM),
+        "genre" => Some(Field::Genre),
+        "style" => Some(Field::Style),
+        "label" => Some(Field::Label),
+        "isrc" => Some(Field::ISRC),
+        "catalognumber" => Some(Field::CatalogNumber),
+        "version" => Some(Field::Version),
+        "track" | "tracknumber" => Some(Field::TrackNumber),
+        "duration" => Some(Field::Duration),
+        "remixer" => Some(Field::Remixer),
+        "total" | "tracktotal" => Some(Field::TrackTotal),
+        "disc" | "disk" | "discnumber" | "disknumber" => Some(Field::DiscNumber),
+        _ => None
+    }
+}
+
 #[derive(Debug, Clone)]
 struct TokenVariable {
     var: String
@@ -400,26 +781,7 @@ impl TokenVariable {
     pub fn get_raw_value(&self, info: &AudioFileInfo) -> Option<Data> {
         // Parse field name
         let lower = self.var.to_lowercase();
-        let field = match &lower[..] {
-            "title" => Some(Field::Title),
-            "artist" | "artists" => Some(Field::Artist),
-            "album" => Some(Field::Album),
-            "albumartist" | "albumartists" => Some(Field::AlbumArtist),
-            "key" => Some(Field::Key),
-            "bpm" => Some(Field::BPM),
-            "genre" => Some(Field::Genre),
-            "style" => Some(Field::Style),
-            "label" => Some(Field::Label),
-            "isrc" => Some(Field::ISRC),
-            "catalognumber" => Some(Field::CatalogNumber),
-            "version" => Some(Field::Version),
-            "track" | "tracknumber" => Some(Field::TrackNumber),
-            "duration" => Some(Field::Duration),
-            "remixer" => Some(Field::Remixer),
-            "total" | "tracktotal" => Some(Field::TrackTotal),
-            "disc" | "disk" | "discnumber" | "disknumber" => Some(Field::DiscNumber),
-            _ => None
-        };
+        let field = variable_field(&lower);
         if let Some(field) = field {
             let tag = field.by_format(&info.format);
             if let Some(v) = info.tags.get(tag) {
@@ -436,8 +798,7 @@ impl TokenVariable {
 
         // Date
         if lower == "year" || lower == "month" || lower == "day" {
-            let tag = Tag::load_file(&info.path, false).ok()?;
-            if let Some(date) = tag.tag().get_date() {
+            if let Some(date) = &info.date {
                 let val = match &lower[..] {
                     "year" => Some(date.year),
                     "month" => date.month.map(|m| m as i32),
@@ -452,16 +813,90 @@ impl TokenVariable {
         if let Some(v) = info.tags.get(&self.var) {
             return Some(Data::Array(v.clone()));
         }
+        // Audio properties (duration if missing in tag), prefetched by the renamer
+        if PROPERTY_VARIABLES.contains(&lower.as_str()) {
+            let properties = info.properties.as_ref()?;
+            let value = match &lower[..] {
+                "duration" => Some(properties.duration.as_millis().to_string()),
+                "bitrate" => properties.bitrate.map(|b| b.to_string()),
+                "samplerate" => properties.sample_rate.map(|s| s.to_string()),
+                "bitdepth" => properties.bit_depth.map(|b| b.to_string()),
+                "channels" => properties.channels.map(|c| c.to_string()),
+                "codec" => Some(properties.codec.clone()),
+                "size" => Some(properties.size.to_string()),
+                "sizemb" => Some(format!("{:.1}", properties.size as f64 / 1024.0 / 1024.0)),
+                _ => None
+            }?;
+            return Some(Data::String(value));
+        }
+
         // Built-ins
-        match &self.var.to_lowercase()[..] {
+        match &lower[..] {
             "filename" => Some(Data::String(Path::new(&info.path).file_stem().unwrap().to_string_lossy().to_string())),
             "path" => Some(Data::String(info.path.to_string_lossy().to_string())),
             "abspath" => Some(Data::String(dunce::canonicalize(&info.path).ok()?.to_string_lossy().to_string())),
+            "rating" => Some(Data::String(info.rating?.to_string())),
+            "camelot" => {
+                let key = info.tags.get(Field::Key.by_format(&info.format))?.first()?;
+                Some(Data::String(onetagger_tagger::to_camelot(key).to_owned()))
+            },
+            "tagged" => match info.tagged {
+                FileTaggedStatus::AutoTagger => Some(Data::String("autotagger".to_string())),
+                FileTaggedStatus::AudioFeatures => Some(Data::String("audiofeatures".to_string())),
+                FileTaggedStatus::Tagged => Some(Data::String("tagged".to_string())),
+                FileTaggedStatus::Untagged => None,
+            },
+            // Date part of `%Y-%m-%d %H:%M:%S_AT`
+            "taggeddate" => {
+                let value = raw_tag(info, "1T_TAGGEDDATE")?;
+                Some(Data::String(value.split(' ').next()?.to_string()))
+            },
+            "trackid" => platform_id(info, SupportedTag::TrackId).map(|(_, id)| Data::String(id)),
+            "releaseid" => platform_id(info, SupportedTag::ReleaseId).map(|(_, id)| Data::String(id)),
+            "platform" => platform_id(info, SupportedTag::TrackId)
+                .or(platform_id(info, SupportedTag::ReleaseId))
+                .map(|(platform, _)| Data::String(platform)),
             _ => None
         }
     }
 }
 
+/// Get first value of raw tag, name can be prefixed (`com.apple.iTunes:`)
+fn raw_tag<'a>(info: &'a AudioFileInfo, name: &str) -> Option<&'a str> {
+    info.tags.iter()
+        .find(|(k, _)| k.rsplit(':').next() == Some(name))
+        .and_then(|(_, v)| v.first())
+        .map(|v| v.trim())
+        .filter(|v| !v.is_empty())
+}
+
+/// Platform and its track / release ID, from provenance or the `{PLATFORM}_TRACK_ID` tags
+fn platform_id(info: &AudioFileInfo, tag: SupportedTag) -> Option<(String, String)> {
+    if let Some(provenance) = info.provenance.get(&tag) {
+        let id = match tag {
+            SupportedTag::ReleaseId => provenance.release_id.clone(),
+            _ => provenance.track_id.clone()
+        };
+        if let Some(id) = id {
+            return Some((provenance.platform.to_lowercase(), id));
+        }
+    }
+    let suffix = match tag {
+        SupportedTag::ReleaseId => "_RELEASE_ID",
+        _ => "_TRACK_ID"
+    };
+    let mut ids = info.tags.iter().filter_map(|(k, v)| {
+        let platform = k.rsplit(':').next()?.to_uppercase().strip_suffix(suffix)?.to_lowercase();
+        let id = v.first()?.trim().replace('\0', "");
+        match platform.is_empty() || id.is_empty() {
+            true => None,
+            false => Some((platform, id))
+        }
+    }).collect::<Vec<_>>();
+    ids.sort();
+    ids.into_iter().next()
+}
+
 impl Token for TokenVariable {
     fn get_value(&self, _input: Option<&Data>, info: &AudioFileInfo, _config: &RenamerConfig) -> Option<Data> {
         Some(self.get_raw_value(info)?)
@@ -815,4 +1250,66 @@ impl Token for TokenFunction {
             }
         }
     }
-}
\ No newline at end of file
+}
+
+#[test]
+fn conditions() {
+    use std::collections::HashMap;
+    use onetagger_tag::AudioFileFormat;
+    use onetagger_tagger::FileTaggedStatus;
+
+    let tags = HashMap::from([
+        ("TIT2".to_string(), vec!["Title".to_string()]),
+        ("TPE1".to_string(), vec!["A".to_string(), "B".to_string()]),
+        ("TBPM".to_string(), vec!["128".to_string()]),
+        ("TCON".to_string(), vec!["Deep House".to_string()]),
+        ("TPE4".to_string(), vec![]),
+    ]);
+    let info = AudioFileInfo {
+        title: None, artists: vec![], format: AudioFileFormat::MP3, path: "/music/file.mp3".into(), isrc: None, duration: None, 
+        track_number: None, tagged: FileTaggedStatus::Untagged, tags, provenance: HashMap::new(), properties: None, rating: None, date: None
+    };
+    let config = RenamerConfig::default_with_paths("/music", "");
+    let evaluate = |template: &str| TemplateParser::parse(template).evaluate(&info, &config);
+
+    assert_eq!(evaluate("%title%%if remixer% (%remixer% Remix)%endif%"), "Title");
+    assert_eq!(evaluate("%if bpm >= 125%Fast/%elif !bpm%Unknown/%else%Slow/%endif%%title%"), "Fast/Title");
+    assert_eq!(evaluate("%if bpm < 100.5%Slow%elif genre == \"deep house\"%%genre.lower()%%endif%"), "deep house");
+    assert_eq!(evaluate("%if genre ~= \"^Deep\"%1%else%2%endif%%if genre != \"Techno\"%3%endif%"), "13");
+    assert_eq!(evaluate("%if title%%if artist == \"A, B\"%nested%else%no%endif%%endif%"), "nested");
+    assert_eq!(evaluate("%remixer ?? version ?? \"Original Mix\"% - %remixer ?? artists%"), "Original Mix - A");
+    assert_eq!(evaluate("%if !title%x%endif"), "");
+
+    // Syntax covers the whole template
+    let template = "%if bpm >= 125% (%remixer ?? artist.first%)%else%%endif%";
+    let syntax = TemplateParser::parse(template).syntax;
+    assert_eq!(syntax.iter().map(|s| s.length).sum::<usize>(), template.len());
+}
+
+#[test]
+fn property_variables() {
+    use std::collections::HashMap;
+    use std::time::Duration;
+    use onetagger_tag::{AudioFileFormat, AudioProperties, TagDate};
+
+    let tags = HashMap::from([
+        ("INITIALKEY".to_string(), vec!["Am".to_string()]),
+        ("1T_TAGGEDDATE".to_string(), vec!["2024-05-01 10:20:30_AT".to_string()]),
+        ("BEATPORT_TRACK_ID".to_string(), vec!["123".to_string()]),
+        ("DEEZER_RELEASE_ID".to_string(), vec!["456".to_string()]),
+    ]);
+    let properties = AudioProperties { bitrate: Some(1411), sample_rate: Some(44100), bit_depth: Some(16), channels: Some(2), codec: "FLAC".to_string(), size: 3 * 1024 * 1024, duration: Duration::from_secs(200), ..Default::default() };
+    let info = AudioFileInfo {
+        title: None, artists: vec![], format: AudioFileFormat::FLAC, path: "/music/file.flac".into(), isrc: None, duration: None, 
+        track_number: None, tagged: FileTaggedStatus::AutoTagger, tags, provenance: HashMap::new(), properties: Some(properties),
+        rating: Some(3), date: Some(TagDate { year: 2024, month: Some(5), day: None })
+    };
+    let config = RenamerConfig::default_with_paths("/music", "");
+    let template = "%codec% %bitdepth%-%samplerate% %bitrate%kbps %channels%ch %sizemb%MB %camelot% %tagged% %taggeddate% %platform%:%trackid%/%releaseid% %rating% %year%-%month% %duration%";
+    let mut parser = TemplateParser::parse(template);
+    assert!(parser.uses_properties());
+    assert!(!TemplateParser::parse("%if trackid%%title%%endif%").uses_properties());
+    assert!(TemplateParser::parse("%if bitrate > 320%lossless%endif%").uses_properties());
+    assert!(TemplateParser::parse("%duration%").uses_properties());
+    assert_eq!(parser.evaluate(&info, &config), "FLAC 16-44100 1411kbps 2ch 3.0MB 8A autotagger 2024-05-01 beatport:123/456 3 2024-5 200000");
+}
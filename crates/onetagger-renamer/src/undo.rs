@@ -0,0 +1,254 @@
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use anyhow::Error;
+use serde::{Serialize, Deserialize};
+use onetagger_shared::Settings;
+
+use crate::collision::{RenamePlan, RenameOperation, temp_path};
+
+/// Log of file operations done by the renamer, used for undo
+pub struct RenameLog {
+    path: PathBuf,
+    file: File,
+    /// Folder for overwritten files
+    backup_dir: PathBuf,
+    records: Vec<RenameRecord>,
+}
+
+impl RenameLog {
+    /// Create new log file for run
+    pub fn create(run_id: &str) -> Result<RenameLog, Error> {
+        Self::open(Self::log_path(run_id)?)
+    }
+
+    /// Create new log at path, overwritten files are moved next to it
+    pub fn open(path: impl AsRef<Path>) -> Result<RenameLog, Error> {
+        let path = path.as_ref().to_owned();
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let backup_dir = path.with_extension("backup");
+        Ok(RenameLog { path, file, backup_dir, records: vec![] })
+    }
+
+    /// Get path to log file of run
+    pub fn log_path(run_id: &str) -> Result<PathBuf, Error> {
+        let folder = Settings::get_folder()?.join("runs");
+        if !folder.exists() {
+            std::fs::create_dir_all(&folder)?;
+        }
+        Ok(folder.join(format!("rename-{run_id}.jsonl")))
+    }
+
+    /// Path to the log file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Execute the plan, on error everything done so far is reverted
+    pub fn execute(&mut self, plan: &RenamePlan) -> Result<(), Error> {
+        for (i, operation) in plan.operations.iter().enumerate() {
+            if let Err(e) = self.execute_one(i, operation) {
+                error!("Failed renaming {:?} -> {:?}: {e}, reverting", operation.from, operation.to);
+                self.revert();
+                return Err(e);
+            }
+        }
+        Ok(())
+    }
+
+    fn execute_one(&mut self, i: usize, operation: &RenameOperation) -> Result<(), Error> {
+        let (from, to) = (operation.from.as_path(), operation.to.as_path());
+        // Create missing dirs, outermost first
+        if let Some(parent) = to.parent() {
+            let mut missing = parent.ancestors().take_while(|p| !p.as_os_str().is_empty() && !p.exists()).collect::<Vec<_>>();
+            missing.reverse();
+            for dir in missing {
+                std::fs::create_dir(dir)?;
+                self.write(RenameRecord::Directory { path: dir.to_owned() })?;
+            }
+        }
+        if operation.overwrite && to.exists() {
+            if !self.backup_dir.exists() {
+                std::fs::create_dir_all(&self.backup_dir)?;
+            }
+            let backup = self.backup_dir.join(format!("{i}-{}", to.file_name().unwrap_or_default().to_string_lossy()));
+            move_file(to, &backup)?;
+            self.write(RenameRecord::Overwritten { path: to.to_owned(), backup })?;
+        } else if to.exists() && !is_case_rename(from, to) {
+            return Err(anyhow!("Target file already exists: {to:?}"));
+        }
+
+        if operation.copy {
+            std::fs::copy(from, to)?;
+            info!("Copied: {to:?}");
+            self.write(RenameRecord::Copied { from: from.to_owned(), to: to.to_owned() })
+        } else {
+            move_file(from, to)?;
+            info!("Renamed: {to:?}");
+            self.write(RenameRecord::Moved { from: from.to_owned(), to: to.to_owned() })
+        }
+    }
+
+    /// Append record to file
+    fn write(&mut self, record: RenameRecord) -> Result<(), Error> {
+        let mut line = serde_json::to_string(&record)?;
+        line.push('\n');
+        self.file.write_all(line.as_bytes())?;
+        self.file.flush()?;
+        self.records.push(record);
+        Ok(())
+    }
+
+    /// Revert everything done so far and remove the log
+    fn revert(&mut self) -> Vec<(PathBuf, Result<(), Error>)> {
+        let results = undo_records(std::mem::take(&mut self.records));
+        if results.iter().all(|(_, r)| r.is_ok()) {
+            std::fs::remove_file(&self.path).ok();
+        }
+        results
+    }
+
+    /// Load records from log file (or run ID)
+    pub fn load(log: &str) -> Result<Vec<RenameRecord>, Error> {
+        let path = match Path::new(log).exists() {
+            true => PathBuf::from(log),
+            false => Self::log_path(log)?
+        };
+        let reader = BufReader::new(File::open(&path)?);
+        let mut records = vec![];
+        for line in reader.lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            // Last line might be incomplete after crash
+            match serde_json::from_str(&line) {
+                Ok(r) => records.push(r),
+                Err(e) => warn!("Invalid rename log record in {}: {e}", path.display()),
+            }
+        }
+        Ok(records)
+    }
+
+    /// Undo all operations from log file (or run ID) in reverse order
+    pub fn undo(log: &str) -> Result<Vec<(PathBuf, Result<(), Error>)>, Error> {
+        let records = Self::load(log)?;
+        Ok(undo_records(records))
+    }
+}
+
+/// Single file operation
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", tag = "type")]
+pub enum RenameRecord {
+    Directory { path: PathBuf },
+    Overwritten { path: PathBuf, backup: PathBuf },
+    Moved { from: PathBuf, to: PathBuf },
+    Copied { from: PathBuf, to: PathBuf },
+}
+
+impl RenameRecord {
+    /// Original path of the affected file
+    pub fn path(&self) -> &Path {
+        match self {
+            RenameRecord::Directory { path } | RenameRecord::Overwritten { path, .. } => path,
+            RenameRecord::Moved { from, .. } => from,
+            RenameRecord::Copied { to, .. } => to,
+        }
+    }
+
+    /// Revert the operation
+    pub fn undo(&self) -> Result<(), Error> {
+        match self {
+            // Only if empty
+            RenameRecord::Directory { path } => {
+                if path.read_dir()?.next().is_none() {
+                    std::fs::remove_dir(path)?;
+                }
+                Ok(())
+            },
+            RenameRecord::Overwritten { path, backup } => move_file(backup, path),
+            RenameRecord::Moved { from, to } => {
+                if from.exists() && !is_case_rename(to, from) {
+                    return Err(anyhow!("File already exists: {from:?}"));
+                }
+                move_file(to, from)
+            },
+            RenameRecord::Copied { to, .. } => Ok(std::fs::remove_file(to)?),
+        }
+    }
+}
+
+/// Undo records in reverse order, directory errors are ignored
+fn undo_records(records: Vec<RenameRecord>) -> Vec<(PathBuf, Result<(), Error>)> {
+    let mut results = vec![];
+    for record in records.into_iter().rev() {
+        let result = record.undo();
+        match (&record, result) {
+            (RenameRecord::Directory { path }, Err(e)) => debug!("Failed removing dir {path:?}: {e}"),
+            (RenameRecord::Directory { .. }, Ok(_)) => {},
+            (_, result) => {
+                if let Err(e) = &result {
+                    error!("Failed reverting {record:?}: {e}");
+                }
+                results.push((record.path().to_owned(), result));
+            }
+        }
+    }
+    results
+}
+
+/// Rename file, case-only renames go through a temporary file and falls back to copy between filesystems
+fn move_file(from: &Path, to: &Path) -> Result<(), Error> {
+    if is_case_rename(from, to) {
+        let tmp = temp_path(from);
+        std::fs::rename(from, &tmp)?;
+        return Ok(std::fs::rename(&tmp, to)?);
+    }
+    if std::fs::rename(from, to).is_err() {
+        info!("Renaming failed, might be different FS, trying to copy.");
+        std::fs::copy(from, to)?;
+        if let Err(e) = std::fs::remove_file(from) {
+            warn!("Failed deleting {from:?}: {e}");
+        }
+    }
+    Ok(())
+}
+
+/// Paths differ only in filename case
+fn is_case_rename(from: &Path, to: &Path) -> bool {
+    from != to && from.parent() == to.parent() && from.to_string_lossy().to_lowercase() == to.to_string_lossy().to_lowercase()
+}
+
+#[test]
+fn rename_undo() {
+    use crate::collision::CollisionPolicy;
+
+    let dir = std::env::temp_dir().join(format!("onetagger-rename-undo-{}", onetagger_shared::timestamp!()));
+    std::fs::create_dir_all(&dir).unwrap();
+    for f in ["a.mp3", "b.mp3", "c.mp3", "d.mp3"] {
+        std::fs::write(dir.join(f), f).unwrap();
+    }
+    let files = vec![
+        (dir.join("a.mp3"), dir.join("b.mp3")),
+        (dir.join("b.mp3"), dir.join("a.mp3")),
+        (dir.join("c.mp3"), dir.join("sub/d.mp3")),
+        (dir.join("d.mp3"), dir.join("sub/d.mp3")),
+    ];
+    let plan = RenamePlan::new(&files, CollisionPolicy::Overwrite, false);
+    let mut log = RenameLog::open(dir.join("log.jsonl")).unwrap();
+    log.execute(&plan).unwrap();
+    assert_eq!(plan.renamed(&files).len(), 3);
+    assert_eq!(std::fs::read_to_string(dir.join("a.mp3")).unwrap(), "b.mp3");
+    assert_eq!(std::fs::read_to_string(dir.join("sub/d.mp3")).unwrap(), "c.mp3");
+    assert!(dir.join("d.mp3").exists());
+
+    let results = RenameLog::undo(&dir.join("log.jsonl").to_string_lossy()).unwrap();
+    assert!(results.iter().all(|(_, r)| r.is_ok()));
+    for f in ["a.mp3", "b.mp3", "c.mp3", "d.mp3"] {
+        assert_eq!(std::fs::read_to_string(dir.join(f)).unwrap(), f);
+    }
+    assert!(!dir.join("sub").exists());
+
+    std::fs::remove_dir_all(&dir).ok();
+}
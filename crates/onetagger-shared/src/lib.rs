@@ -39,6 +39,15 @@ lazy_static::lazy_static! {
 
 /// Setup onetagger logging and panic hooks
 pub fn setup() {
+    setup_logging(false);
+}
+
+/// Same as `setup`, but logs to stderr, so stdout can be used for machine-readable output
+pub fn setup_stderr() {
+    setup_logging(true);
+}
+
+fn setup_logging(stderr: bool) {
     // Fern logger setup
     let colors = ColoredLevelConfig::new()
         .trace(Color::White)
@@ -77,7 +86,10 @@ pub fn setup() {
                         message
                     ))
                 })
-                .chain(std::io::stdout())
+                .chain(match stderr {
+                    true => fern::Output::from(std::io::stderr()),
+                    false => fern::Output::from(std::io::stdout())
+                })
         )
         // Log file
         .chain(
@@ -0,0 +1,486 @@
+use anyhow::Error;
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use crate::{AudioFileFormat, Field, TagDate, CoverType, TagImpl, Picture, Lyrics};
+
+/// Item value types, stored in bits 1-2 of item flags
+const ITEM_TEXT: u32 = 0;
+const ITEM_BINARY: u32 = 1 << 1;
+const ITEM_TYPE_MASK: u32 = 0b110;
+/// Tag flags
+const TAG_HAS_HEADER: u32 = 1 << 31;
+const TAG_IS_HEADER: u32 = 1 << 29;
+
+const COVER_TYPES: [(&str, CoverType); 21] = [
+    ("Cover Art (Other)", CoverType::Other),
+    ("Cover Art (Png Icon)", CoverType::Icon),
+    ("Cover Art (Icon)", CoverType::OtherIcon),
+    ("Cover Art (Front)", CoverType::CoverFront),
+    ("Cover Art (Back)", CoverType::CoverBack),
+    ("Cover Art (Leaflet)", CoverType::Leaflet),
+    ("Cover Art (Media)", CoverType::Media),
+    ("Cover Art (Lead Artist)", CoverType::LeadArtist),
+    ("Cover Art (Artist)", CoverType::Artist),
+    ("Cover Art (Conductor)", CoverType::Conductor),
+    ("Cover Art (Band)", CoverType::Band),
+    ("Cover Art (Composer)", CoverType::Composer),
+    ("Cover Art (Lyricist)", CoverType::Lyricist),
+    ("Cover Art (Recording Location)", CoverType::RecordingLocation),
+    ("Cover Art (During Recording)", CoverType::DuringRecording),
+    ("Cover Art (During Performance)", CoverType::DuringPerformance),
+    ("Cover Art (Video Capture)", CoverType::ScreenCapture),
+    ("Cover Art (Fish)", CoverType::BrightFish),
+    ("Cover Art (Illustration)", CoverType::Illustration),
+    ("Cover Art (Band Logotype)", CoverType::BandLogo),
+    ("Cover Art (Publisher Logotype)", CoverType::PublisherLogo),
+];
+
+#[derive(Debug, Clone, PartialEq)]
+struct APEItem {
+    key: String,
+    flags: u32,
+    value: Vec<u8>,
+}
+
+impl APEItem {
+    fn is_text(&self) -> bool {
+        self.flags & ITEM_TYPE_MASK == ITEM_TEXT
+    }
+}
+
+/// APEv2 tag, used by WavPack, Monkey's Audio and Musepack
+pub struct APETag {
+    items: Vec<APEItem>,
+    separator: Option<String>,
+    pub format: AudioFileFormat,
+}
+
+impl APETag {
+    /// Load from file, empty tag if the file has none
+    pub fn load_file(path: impl AsRef<Path>) -> Result<APETag, Error> {
+        let format = AudioFileFormat::from_extension(&path.as_ref().extension().unwrap_or_default().to_string_lossy())
+            .ok_or(anyhow!("Unsupported format"))?;
+        let mut file = File::open(path)?;
+        let items = match Self::locate(&mut file)? {
+            Some(location) => {
+                file.seek(SeekFrom::Start(location.items))?;
+                let mut data = vec![0u8; location.items_len as usize];
+                file.read_exact(&mut data)?;
+                Self::parse_items(&data, location.count)?
+            },
+            None => vec![]
+        };
+        Ok(APETag { items, separator: None, format })
+    }
+
+    /// End of file without ID3v1 tag
+    fn end_without_id3v1(file: &mut File) -> Result<u64, Error> {
+        let len = file.seek(SeekFrom::End(0))?;
+        if len >= 128 {
+            let mut id3v1 = [0u8; 3];
+            file.seek(SeekFrom::Start(len - 128))?;
+            file.read_exact(&mut id3v1)?;
+            if &id3v1 == b"TAG" {
+                return Ok(len - 128);
+            }
+        }
+        Ok(len)
+    }
+
+    /// Find the tag at the end of file (before ID3v1)
+    fn locate(file: &mut File) -> Result<Option<TagLocation>, Error> {
+        let end = Self::end_without_id3v1(file)?;
+        if end < 32 {
+            return Ok(None);
+        }
+        let mut footer = [0u8; 32];
+        file.seek(SeekFrom::Start(end - 32))?;
+        file.read_exact(&mut footer)?;
+        if &footer[0..8] != b"APETAGEX" {
+            return Ok(None);
+        }
+        let u32_at = |i: usize| u32::from_le_bytes(footer[i..i + 4].try_into().unwrap());
+        // Size includes footer, but not header
+        let size = u32_at(12) as u64;
+        let flags = u32_at(20);
+        if size < 32 || size > end {
+            return Err(anyhow!("Invalid APE tag size"));
+        }
+        let items = end - size;
+        let start = match flags & TAG_HAS_HEADER != 0 {
+            true => items.checked_sub(32).ok_or(anyhow!("Invalid APE tag header"))?,
+            false => items
+        };
+        Ok(Some(TagLocation { start, end, items, items_len: size - 32, count: u32_at(16) }))
+    }
+
+    fn parse_items(data: &[u8], count: u32) -> Result<Vec<APEItem>, Error> {
+        let mut items = vec![];
+        let mut pos = 0;
+        for _ in 0..count {
+            if pos + 8 > data.len() {
+                break;
+            }
+            let len = u32::from_le_bytes(data[pos..pos + 4].try_into().unwrap()) as usize;
+            let flags = u32::from_le_bytes(data[pos + 4..pos + 8].try_into().unwrap());
+            pos += 8;
+            let key_len = data[pos..].iter().position(|b| *b == 0).ok_or(anyhow!("Invalid APE item key"))?;
+            let key = String::from_utf8_lossy(&data[pos..pos + key_len]).to_string();
+            pos += key_len + 1;
+            if pos + len > data.len() {
+                return Err(anyhow!("Invalid APE item length"));
+            }
+            items.push(APEItem { key, flags, value: data[pos..pos + len].to_vec() });
+            pos += len;
+        }
+        Ok(items)
+    }
+
+    /// Serialize with header and footer
+    fn serialize(&self) -> Vec<u8> {
+        let mut items = vec![];
+        for item in &self.items {
+            items.extend((item.value.len() as u32).to_le_bytes());
+            items.extend(item.flags.to_le_bytes());
+            items.extend(item.key.as_bytes());
+            items.push(0);
+            items.extend(&item.value);
+        }
+        let header = |flags: u32| {
+            let mut out = b"APETAGEX".to_vec();
+            out.extend(2000u32.to_le_bytes());
+            out.extend((items.len() as u32 + 32).to_le_bytes());
+            out.extend((self.items.len() as u32).to_le_bytes());
+            out.extend(flags.to_le_bytes());
+            out.extend([0u8; 8]);
+            out
+        };
+        let mut out = header(TAG_HAS_HEADER | TAG_IS_HEADER);
+        out.extend(&items);
+        out.extend(header(TAG_HAS_HEADER));
+        out
+    }
+
+    fn get_item(&self, key: &str) -> Option<&APEItem> {
+        self.items.iter().find(|i| i.key.eq_ignore_ascii_case(key))
+    }
+
+    /// Replace or add item
+    fn insert_item(&mut self, key: &str, flags: u32, value: Vec<u8>) {
+        let item = APEItem { key: key.to_string(), flags, value };
+        match self.items.iter_mut().find(|i| i.key.eq_ignore_ascii_case(key)) {
+            Some(i) => *i = item,
+            None => self.items.push(item)
+        }
+    }
+
+    /// Set date to tag
+    fn set_date_inner(&mut self, tag: &str, date: &TagDate, overwrite: bool) {
+        if overwrite || self.get_raw(tag).is_none() {
+            let v = match date.has_md() {
+                true => format!("{}-{:02}-{:02}", date.year, date.month.unwrap(), date.day.unwrap()),
+                false => date.year.to_string()
+            };
+            self.set_raw(tag, vec![v], true);
+        }
+    }
+
+    /// CoverType -> item key
+    fn cover_key(&self, cover_type: &CoverType) -> &'static str {
+        COVER_TYPES.iter().find(|(_, c)| c == cover_type).map(|i| i.0).unwrap_or("Cover Art (Other)")
+    }
+
+    /// Guess mime from image data
+    fn mime(data: &[u8]) -> &'static str {
+        match data {
+            [0x89, b'P', b'N', b'G', ..] => "image/png",
+            [b'G', b'I', b'F', ..] => "image/gif",
+            [b'B', b'M', ..] => "image/bmp",
+            _ => "image/jpeg"
+        }
+    }
+}
+
+/// Position of the tag in file
+struct TagLocation {
+    /// Start of the whole tag (header)
+    start: u64,
+    /// End of the footer
+    end: u64,
+    items: u64,
+    items_len: u64,
+    count: u32,
+}
+
+impl TagImpl for APETag {
+    fn save_file(&mut self, path: &Path) -> Result<(), Error> {
+        let mut file = OpenOptions::new().read(true).write(true).open(path)?;
+        // Strip old tag, but keep ID3v1
+        let (start, end) = match Self::locate(&mut file)? {
+            Some(location) => (location.start, location.end),
+            None => {
+                let end = Self::end_without_id3v1(&mut file)?;
+                (end, end)
+            }
+        };
+        let mut trailing = vec![];
+        file.seek(SeekFrom::Start(end))?;
+        file.read_to_end(&mut trailing)?;
+        file.set_len(start)?;
+        file.seek(SeekFrom::Start(start))?;
+        if !self.items.is_empty() {
+            file.write_all(&self.serialize())?;
+        }
+        file.write_all(&trailing)?;
+        Ok(())
+    }
+
+    fn set_separator(&mut self, separator: &str) {
+        if separator.is_empty() {
+            self.separator = None;
+        } else {
+            self.separator = Some(separator.replace("\\0", "\0"))
+        }
+    }
+
+    fn get_separator(&self) -> Option<String> {
+        self.separator.clone()
+    }
+
+    fn all_tags(&self) -> HashMap<String, Vec<String>> {
+        // Keys are case insensitive, known keys use the same case as `Field::ape`, others are uppercase
+        self.items.iter().filter(|i| i.is_text()).filter_map(|i| {
+            let key = Field::by_ape_key(&i.key).map(|f| f.ape().to_string()).unwrap_or(i.key.to_uppercase());
+            Some((key, self.get_raw(&i.key)?))
+        }).collect()
+    }
+
+    fn get_date(&self) -> Option<TagDate> {
+        let data = &self.get_raw("Year")?[0];
+        // YYYY-MM-DD
+        if data.len() >= 10 {
+            return Some(TagDate {
+                year: data[0..4].parse().ok()?,
+                month: data[5..7].parse().ok(),
+                day: data[8..10].parse().ok()
+            });
+        }
+        // YYYY
+        if data.len() >= 4 {
+            return Some(TagDate {
+                year: data[0..4].parse().ok()?,
+                month: None,
+                day: None
+            });
+        }
+        None
+    }
+
+    fn set_date(&mut self, date: &TagDate, overwrite: bool) {
+        self.set_date_inner("Year", date, overwrite);
+    }
+
+    fn set_publish_date(&mut self, date: &TagDate, overwrite: bool) {
+        self.set_date_inner("ORIGINALDATE", date, overwrite);
+    }
+
+    fn get_rating(&self) -> Option<u8> {
+        let rating = self.get_raw("RATING")?.first()?.parse::<i32>().ok()? / 20;
+        if rating <= 5 {
+            if rating == 0 {
+               return Some(1)
+            }
+            return Some(rating as u8)
+        }
+        None
+    }
+
+    fn set_rating(&mut self, rating: u8, overwrite: bool) {
+        if rating > 0 {
+            self.set_raw("RATING", vec![(rating * 20).to_string()], overwrite);
+        } else {
+            self.remove_raw("RATING");
+        }
+    }
+
+    fn set_art(&mut self, kind: CoverType, mime: &str, description: Option<&str>, data: Vec<u8>) {
+        // Description (usually filename), null, data
+        let filename = match mime.trim().to_lowercase().as_str() {
+            "image/png" => "cover.png",
+            _ => "cover.jpg"
+        };
+        let mut value = description.unwrap_or(filename).as_bytes().to_vec();
+        value.push(0);
+        value.extend(data);
+        self.insert_item(self.cover_key(&kind), ITEM_BINARY, value);
+    }
+
+    fn has_art(&self) -> bool {
+        self.items.iter().any(|i| COVER_TYPES.iter().any(|(k, _)| i.key.eq_ignore_ascii_case(k)))
+    }
+
+    fn get_art(&self) -> Vec<Picture> {
+        self.items.iter().filter_map(|i| {
+            let kind = COVER_TYPES.iter().find(|(k, _)| i.key.eq_ignore_ascii_case(k))?.1.clone();
+            let split = i.value.iter().position(|b| *b == 0)?;
+            let data = i.value[split + 1..].to_vec();
+            Some(Picture {
+                kind,
+                description: String::from_utf8_lossy(&i.value[..split]).to_string(),
+                mime: Self::mime(&data).to_string(),
+                data,
+            })
+        }).collect()
+    }
+
+    fn remove_art(&mut self, kind: CoverType) {
+        let key = self.cover_key(&kind);
+        self.remove_raw(key);
+    }
+
+    fn set_field(&mut self, field: Field, value: Vec<String>, overwrite: bool) {
+        // Total is stored together with the track number as "n/total"
+        if field == Field::TrackTotal {
+            if !overwrite && self.get_field(Field::TrackTotal).is_some() {
+                return;
+            }
+            let track = self.get_field(Field::TrackNumber).and_then(|v| v.first().cloned()).unwrap_or_default();
+            match value.first().and_then(|v| v.trim().parse::<u16>().ok()) {
+                Some(total) => self.set_track_number(&track, Some(total), true),
+                None if track.is_empty() => self.remove_raw(field.ape()),
+                None => self.set_raw(field.ape(), vec![track], true),
+            }
+            return;
+        }
+        self.set_raw(field.ape(), value, overwrite);
+    }
+
+    fn get_field(&self, field: Field) -> Option<Vec<String>> {
+        // Overrides, "n/total" format
+        if field == Field::TrackNumber || field == Field::TrackTotal {
+            let value = self.get_raw(field.ape())?.first()?.to_owned();
+            let mut split = value.split('/').map(|v| v.trim().to_string());
+            let value = match field {
+                Field::TrackNumber => split.next(),
+                _ => split.nth(1),
+            };
+            return value.filter(|v| !v.is_empty()).map(|v| vec![v]);
+        }
+        self.get_raw(field.ape())
+    }
+
+    fn set_raw(&mut self, tag: &str, value: Vec<String>, overwrite: bool) {
+        if overwrite || self.get_raw(tag).is_none() {
+            if value.is_empty() {
+                self.remove_raw(tag);
+                return;
+            }
+            // Multiple values are null separated
+            let value = value.join(self.separator.as_deref().unwrap_or("\0"));
+            self.insert_item(tag, ITEM_TEXT, value.into_bytes());
+        }
+    }
+
+    fn get_raw(&self, tag: &str) -> Option<Vec<String>> {
+        let item = self.get_item(tag).filter(|i| i.is_text())?;
+        let value = String::from_utf8_lossy(&item.value).to_string();
+        let mut values: Vec<String> = value.split('\0').map(String::from).collect();
+        // Separator override
+        if let (1, Some(separator)) = (values.len(), &self.separator) {
+            values = values[0].split(separator.as_str()).map(String::from).collect();
+        }
+        Some(values)
+    }
+
+    fn remove_raw(&mut self, tag: &str) {
+        self.items.retain(|i| !i.key.eq_ignore_ascii_case(tag));
+    }
+
+    fn set_lyrics(&mut self, lyrics: &Lyrics, synced: bool, overwrite: bool) {
+        if synced {
+            warn!("APE doesn't support synchronized lyrics!");
+            return;
+        }
+        self.set_raw("Lyrics", vec![lyrics.text()], overwrite);
+    }
+
+    fn set_track_number(&mut self, track_number: &str, track_total: Option<u16>, overwrite: bool) {
+        let value = match track_total {
+            Some(total) => format!("{track_number}/{total}"),
+            None => track_number.to_string()
+        };
+        self.set_field(Field::TrackNumber, vec![value], overwrite);
+    }
+
+    fn set_explicit(&mut self, explicit: bool) {
+        let mut comments: Vec<_> = self.get_raw("Comment").unwrap_or(vec![]).into_iter().filter(|i| i != "Explicit").collect();
+        if explicit {
+            comments.push("Explicit".to_string());
+        }
+        self.set_raw("Comment", comments, true);
+    }
+}
+
+/// Write, read back and replace the tag, audio data and ID3v1 stay intact
+#[test]
+fn ape_tag_roundtrip() {
+    let path = std::env::temp_dir().join("onetagger-ape-test.wv");
+    let mut audio = b"wvpk".to_vec();
+    audio.extend([1u8; 100]);
+    let mut id3v1 = b"TAG".to_vec();
+    id3v1.extend([0u8; 125]);
+    std::fs::write(&path, [audio.clone(), id3v1.clone()].concat()).unwrap();
+
+    let mut tag = APETag::load_file(&path).unwrap();
+    assert!(tag.all_tags().is_empty());
+    tag.set_field(Field::Title, vec!["Title".to_string()], true);
+    tag.set_field(Field::Artist, vec!["A".to_string(), "B".to_string()], true);
+    tag.set_rating(4, true);
+    tag.set_track_number("3", None, true);
+    tag.set_field(Field::TrackTotal, vec!["12".to_string()], true);
+    tag.set_art(CoverType::CoverFront, "image/png", None, vec![0x89, b'P', b'N', b'G']);
+    tag.save_file(&path).unwrap();
+
+    let mut tag = APETag::load_file(&path).unwrap();
+    assert_eq!(tag.get_raw("TITLE"), Some(vec!["Title".to_string()]));
+    assert_eq!(tag.get_field(Field::Artist), Some(vec!["A".to_string(), "B".to_string()]));
+    assert_eq!(tag.get_rating(), Some(4));
+    assert_eq!(tag.get_raw("Track"), Some(vec!["3/12".to_string()]));
+    assert_eq!(tag.get_field(Field::TrackNumber), Some(vec!["3".to_string()]));
+    assert_eq!(tag.get_field(Field::TrackTotal), Some(vec!["12".to_string()]));
+    assert_eq!(tag.get_art()[0].mime, "image/png");
+    tag.remove_art(CoverType::CoverFront);
+    tag.set_field(Field::Title, vec!["New".to_string()], true);
+    tag.save_file(&path).unwrap();
+
+    let data = std::fs::read(&path).unwrap();
+    assert!(data.starts_with(&audio) && data.ends_with(&id3v1));
+    let tag = APETag::load_file(&path).unwrap();
+    assert_eq!(tag.get_field(Field::Title), Some(vec!["New".to_string()]));
+    assert!(!tag.has_art());
+    std::fs::remove_file(&path).ok();
+}
+
+/// Frame names and fields resolve to the same APE keys
+#[test]
+fn ape_frame_names() {
+    use crate::FrameName;
+
+    let path = std::env::temp_dir().join("onetagger-ape-keys.ape");
+    std::fs::write(&path, b"MAC ").unwrap();
+    let mut tag = APETag::load_file(&path).unwrap();
+    let frame = FrameName::new("TPE2", "ALBUMARTIST", "aART").by_format(&AudioFileFormat::APE);
+    assert_eq!(frame, Field::AlbumArtist.by_format(&AudioFileFormat::APE));
+    tag.set_raw(&frame, vec!["Album Artist".to_string()], true);
+    tag.set_field(Field::Label, vec!["Label".to_string()], true);
+    tag.save_file(&path).unwrap();
+
+    let tag = APETag::load_file(&path).unwrap();
+    assert_eq!(tag.get_field(Field::AlbumArtist), Some(vec!["Album Artist".to_string()]));
+    assert_eq!(tag.get_raw(&FrameName::same("PUBLISHER").by_format(&AudioFileFormat::MPC)), Some(vec!["Label".to_string()]));
+    assert!(tag.all_tags().contains_key("Album Artist"));
+    assert_eq!(FrameName::same("CUSTOM").by_format(&AudioFileFormat::WV), "CUSTOM");
+    std::fs::remove_file(&path).ok();
+}
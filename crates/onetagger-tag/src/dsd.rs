@@ -0,0 +1,287 @@
+use anyhow::Error;
+use std::fs::{File, OpenOptions};
+use std::io::{BufReader, Cursor, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use std::time::Duration;
+use id3::{Tag, Version};
+
+/// DSF stores ID3v2 at the offset from the `DSD ` chunk
+/// DFF (DSDIFF) has an unofficial, but widely used `ID3 ` chunk
+pub(crate) fn read_dsd(path: impl AsRef<Path>) -> Result<Tag, Error> {
+    let mut file = BufReader::new(File::open(&path)?);
+    let tag = match is_dff(&path) {
+        true => {
+            let chunks = dff_chunks(&mut file)?;
+            match chunks.iter().find(|c| &c.id == b"ID3 ") {
+                Some(chunk) => read_id3(&mut file, chunk.offset, chunk.size)?,
+                None => Tag::new()
+            }
+        },
+        false => {
+            let header = DSFHeader::read(&mut file)?;
+            match header.metadata {
+                0 => Tag::new(),
+                offset => {
+                    let size = file.seek(SeekFrom::End(0))?.saturating_sub(offset);
+                    read_id3(&mut file, offset, size)?
+                }
+            }
+        }
+    };
+    Ok(tag)
+}
+
+/// Replace the tag in DSF/DFF file
+pub(crate) fn write_dsd(path: impl AsRef<Path>, tag: Tag, version: Version) -> Result<(), Error> {
+    let mut id3 = vec![];
+    tag.write_to(Cursor::new(&mut id3), version)?;
+    let mut file = OpenOptions::new().read(true).write(true).open(&path)?;
+
+    // DSF: Replace everything after the audio data and update the header
+    if !is_dff(&path) {
+        let header = DSFHeader::read(&mut file)?;
+        let end = header.data_end;
+        file.set_len(end)?;
+        file.seek(SeekFrom::Start(end))?;
+        file.write_all(&id3)?;
+        let total = end + id3.len() as u64;
+        file.seek(SeekFrom::Start(12))?;
+        file.write_all(&total.to_le_bytes())?;
+        file.write_all(&end.to_le_bytes())?;
+        return Ok(());
+    }
+
+    // DFF: ID3 chunk should be the last one
+    let chunks = dff_chunks(&mut file)?;
+    let audio_end = chunks.iter().filter(|c| &c.id != b"ID3 ").map(|c| c.offset + c.size + c.size % 2).max().unwrap_or(16);
+    if chunks.iter().any(|c| &c.id == b"ID3 " && c.offset < audio_end) {
+        return Err(anyhow!("DFF files with ID3 chunk before the audio data are not supported"));
+    }
+    file.set_len(audio_end)?;
+    file.seek(SeekFrom::Start(audio_end))?;
+    file.write_all(b"ID3 ")?;
+    file.write_all(&(id3.len() as u64).to_be_bytes())?;
+    file.write_all(&id3)?;
+    if id3.len() % 2 == 1 {
+        file.write_all(&[0])?;
+    }
+    let total = file.stream_position()?;
+    file.seek(SeekFrom::Start(4))?;
+    file.write_all(&(total - 12).to_be_bytes())?;
+    Ok(())
+}
+
+/// Get duration from the DSF/DFF header
+pub fn duration(path: impl AsRef<Path>) -> Result<Duration, Error> {
+    Ok(properties(path)?.duration)
+}
+
+/// Audio properties from the DSF/DFF header
+#[derive(Debug, Clone)]
+pub struct DSDProperties {
+    pub duration: Duration,
+    pub sample_rate: u32,
+    pub channels: u16,
+}
+
+/// Read audio properties from the DSF/DFF header
+pub fn properties(path: impl AsRef<Path>) -> Result<DSDProperties, Error> {
+    let mut file = BufReader::new(File::open(&path)?);
+    if !is_dff(&path) {
+        let header = DSFHeader::read(&mut file)?;
+        if header.sample_rate == 0 {
+            return Err(anyhow!("Invalid sample rate"));
+        }
+        return Ok(DSDProperties {
+            duration: Duration::from_secs_f64(header.samples as f64 / header.sample_rate as f64),
+            sample_rate: header.sample_rate,
+            channels: header.channels as u16
+        });
+    }
+
+    let chunks = dff_chunks(&mut file)?;
+    let prop = chunks.iter().find(|c| &c.id == b"PROP").ok_or(anyhow!("Missing PROP chunk"))?;
+    let mut data = vec![0u8; prop.size as usize];
+    file.seek(SeekFrom::Start(prop.offset))?;
+    file.read_exact(&mut data)?;
+    // PROP = "SND " + sub chunks
+    let (mut sample_rate, mut channels) = (0u32, 0u16);
+    let mut pos = 4;
+    while pos + 12 <= data.len() {
+        let id = &data[pos..pos + 4];
+        let size = u64::from_be_bytes(data[pos + 4..pos + 12].try_into()?) as usize;
+        let content = &data[pos + 12..(pos + 12 + size).min(data.len())];
+        match id {
+            b"FS  " if content.len() >= 4 => sample_rate = u32::from_be_bytes(content[0..4].try_into()?),
+            b"CHNL" if content.len() >= 2 => channels = u16::from_be_bytes(content[0..2].try_into()?),
+            _ => {}
+        }
+        pos += 12 + size + size % 2;
+    }
+    if sample_rate == 0 || channels == 0 {
+        return Err(anyhow!("Invalid DFF properties"));
+    }
+    // Uncompressed = 1 bit per sample
+    if let Some(dsd) = chunks.iter().find(|c| &c.id == b"DSD ") {
+        let duration = Duration::from_secs_f64(dsd.size as f64 * 8.0 / channels as f64 / sample_rate as f64);
+        return Ok(DSDProperties { duration, sample_rate, channels });
+    }
+    // DST compressed, FRTE = frame count & frame rate
+    let dst = chunks.iter().find(|c| &c.id == b"DST ").ok_or(anyhow!("Missing audio chunk"))?;
+    let mut frte = [0u8; 18];
+    file.seek(SeekFrom::Start(dst.offset))?;
+    file.read_exact(&mut frte)?;
+    if &frte[0..4] != b"FRTE" {
+        return Err(anyhow!("Missing FRTE chunk"));
+    }
+    let frames = u32::from_be_bytes(frte[12..16].try_into()?);
+    let rate = u16::from_be_bytes(frte[16..18].try_into()?).max(1);
+    Ok(DSDProperties { duration: Duration::from_secs_f64(frames as f64 / rate as f64), sample_rate, channels })
+}
+
+fn is_dff(path: impl AsRef<Path>) -> bool {
+    path.as_ref().extension().unwrap_or_default().eq_ignore_ascii_case("dff")
+}
+
+fn read_id3(file: &mut (impl Read + Seek), offset: u64, size: u64) -> Result<Tag, Error> {
+    let mut data = vec![];
+    file.seek(SeekFrom::Start(offset))?;
+    file.take(size).read_to_end(&mut data)?;
+    Ok(Tag::read_from2(Cursor::new(data)).unwrap_or_default())
+}
+
+/// Fields from the `DSD ` and `fmt ` chunks
+struct DSFHeader {
+    /// Offset of ID3, 0 = none
+    metadata: u64,
+    channels: u32,
+    sample_rate: u32,
+    /// Per channel
+    samples: u64,
+    /// End of the `data` chunk
+    data_end: u64,
+}
+
+impl DSFHeader {
+    fn read(file: &mut (impl Read + Seek)) -> Result<DSFHeader, Error> {
+        let mut header = [0u8; 80];
+        file.seek(SeekFrom::Start(0))?;
+        file.read_exact(&mut header)?;
+        if &header[0..4] != b"DSD " || &header[28..32] != b"fmt " {
+            return Err(anyhow!("Not a valid DSF file"));
+        }
+        let u64_at = |i: usize| u64::from_le_bytes(header[i..i + 8].try_into().unwrap());
+        // data chunk after fmt
+        let data_offset = 28 + u64_at(32);
+        let mut data = [0u8; 12];
+        file.seek(SeekFrom::Start(data_offset))?;
+        file.read_exact(&mut data)?;
+        if &data[0..4] != b"data" {
+            return Err(anyhow!("Missing DSF data chunk"));
+        }
+        Ok(DSFHeader {
+            metadata: u64_at(20),
+            channels: u32::from_le_bytes(header[52..56].try_into().unwrap()),
+            sample_rate: u32::from_le_bytes(header[56..60].try_into().unwrap()),
+            samples: u64_at(64),
+            data_end: data_offset + u64::from_le_bytes(data[4..12].try_into().unwrap()),
+        })
+    }
+}
+
+/// Top level DFF chunk
+struct DFFChunk {
+    id: [u8; 4],
+    /// Of the content
+    offset: u64,
+    size: u64,
+}
+
+fn dff_chunks(file: &mut (impl Read + Seek)) -> Result<Vec<DFFChunk>, Error> {
+    let mut header = [0u8; 16];
+    file.seek(SeekFrom::Start(0))?;
+    file.read_exact(&mut header)?;
+    if &header[0..4] != b"FRM8" || &header[12..16] != b"DSD " {
+        return Err(anyhow!("Not a valid DFF file"));
+    }
+    let len = file.seek(SeekFrom::End(0))?;
+    let end = (u64::from_be_bytes(header[4..12].try_into()?) + 12).min(len);
+    let mut chunks = vec![];
+    let mut offset = 16;
+    while offset + 12 <= end {
+        let mut chunk = [0u8; 12];
+        file.seek(SeekFrom::Start(offset))?;
+        file.read_exact(&mut chunk)?;
+        let size = u64::from_be_bytes(chunk[4..12].try_into()?);
+        chunks.push(DFFChunk { id: chunk[0..4].try_into()?, offset: offset + 12, size });
+        // Chunks are padded to even size
+        offset += 12 + size + size % 2;
+    }
+    Ok(chunks)
+}
+
+/// Synthetic DSF & DFF with 1 second of stereo DSD64
+#[test]
+fn dsd_tag_roundtrip() {
+    use id3::TagLike;
+
+    let rate: u32 = 2822400;
+    let audio = vec![0x69u8; (rate / 8 * 2) as usize];
+
+    // DSF
+    let mut dsf = b"DSD ".to_vec();
+    dsf.extend(28u64.to_le_bytes());
+    dsf.extend(0u64.to_le_bytes());
+    dsf.extend(0u64.to_le_bytes());
+    dsf.extend(b"fmt ");
+    dsf.extend(52u64.to_le_bytes());
+    for v in [1u32, 0, 2, 2, rate, 1] {
+        dsf.extend(v.to_le_bytes());
+    }
+    dsf.extend((rate as u64).to_le_bytes());
+    dsf.extend(4096u32.to_le_bytes());
+    dsf.extend(0u32.to_le_bytes());
+    dsf.extend(b"data");
+    dsf.extend((audio.len() as u64 + 12).to_le_bytes());
+    dsf.extend(&audio);
+
+    // DFF
+    let mut prop = b"SND ".to_vec();
+    prop.extend(b"FS  ");
+    prop.extend(4u64.to_be_bytes());
+    prop.extend(rate.to_be_bytes());
+    prop.extend(b"CHNL");
+    prop.extend(10u64.to_be_bytes());
+    prop.extend(2u16.to_be_bytes());
+    prop.extend(b"SLFTSRGT");
+    let mut dff = b"FRM8".to_vec();
+    dff.extend(((4 + 12 + prop.len() + 12 + audio.len()) as u64).to_be_bytes());
+    dff.extend(b"DSD PROP");
+    dff.extend((prop.len() as u64).to_be_bytes());
+    dff.extend(&prop);
+    dff.extend(b"DSD ");
+    dff.extend((audio.len() as u64).to_be_bytes());
+    dff.extend(&audio);
+
+    for (name, data) in [("onetagger-test.dsf", dsf), ("onetagger-test.dff", dff)] {
+        let path = std::env::temp_dir().join(name);
+        std::fs::write(&path, &data).unwrap();
+        assert_eq!(duration(&path).unwrap(), Duration::from_secs(1));
+        let mut tag = read_dsd(&path).unwrap();
+        assert!(tag.get("TLEN").is_none());
+        tag.set_title("Title 1");
+        write_dsd(&path, tag, Version::Id3v24).unwrap();
+        let mut tag = read_dsd(&path).unwrap();
+        assert_eq!(tag.title(), Some("Title 1"));
+        // Replaced, not appended
+        tag.set_title("Title 2");
+        write_dsd(&path, tag.clone(), Version::Id3v24).unwrap();
+        let written = std::fs::read(&path).unwrap();
+        let mut id3 = vec![];
+        tag.write_to(Cursor::new(&mut id3), Version::Id3v24).unwrap();
+        assert_eq!(written.len(), data.len() + id3.len() + if name.ends_with("dff") { 12 + id3.len() % 2 } else { 0 });
+        assert_eq!(read_dsd(&path).unwrap().title(), Some("Title 2"));
+        assert_eq!(duration(&path).unwrap(), Duration::from_secs(1));
+        std::fs::remove_file(&path).ok();
+    }
+}
@@ -1,10 +1,12 @@
 use std::collections::HashMap;
 use anyhow::Error;
 use std::path::Path;
+use std::sync::RwLock;
 use id3::{Version, Tag, Timestamp, Content, TagLike, Encoder, Frame, Encoding};
-use id3::frame::{Picture, PictureType, Comment, Lyrics, Popularimeter, ExtendedText, SynchronisedLyrics, TimestampFormat, SynchronisedLyricsType};
+use id3::frame::{Picture, PictureType, Comment, Lyrics, Popularimeter, ExtendedText, SynchronisedLyrics, TimestampFormat, SynchronisedLyricsType, EncapsulatedObject};
 use serde::{Serialize, Deserialize};
 use crate::{TagDate, CoverType, Field, TagImpl};
+use crate::wav::{Bext, DEFAULT_INFO_MAPPING};
 
 const COVER_TYPES: [(PictureType, CoverType); 21] = [
     (PictureType::Other, CoverType::Other),
@@ -32,7 +34,7 @@ const COVER_TYPES: [(PictureType, CoverType); 21] = [
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum ID3AudioFormat {
-    MP3, AIFF, WAV
+    MP3, AIFF, WAV, DSF, DFF
 }
 
 pub struct ID3Tag {
@@ -41,56 +43,64 @@ pub struct ID3Tag {
     pub id3_separator: String,
     pub id3v24: bool,
     pub comm_lang: String,
+    /// ID3 frame to RIFF INFO id (WAV only)
+    info_mapping: Vec<(String, String)>,
+    /// RIFF INFO chunks without ID3 equivalent
+    riff_info: Vec<(String, String)>,
+    /// RIFF INFO chunks as loaded from file
+    info: Vec<(String, String)>,
+    /// ID3 frames filled from RIFF INFO
+    info_frames: Vec<String>,
+    bext: Option<Bext>,
 }
 
 impl ID3Tag {
+    /// Wrap the rust-id3 tag with default settings
+    fn new(tag: Tag, format: ID3AudioFormat) -> ID3Tag {
+        ID3Tag {
+            id3v24: tag.version() == Version::Id3v24,
+            tag,
+            format,
+            id3_separator: String::from(", "),
+            comm_lang: "eng".to_string(),
+            info_mapping: default_info_mapping(),
+            riff_info: vec![],
+            info: vec![],
+            info_frames: vec![],
+            bext: None,
+        }
+    }
+
     // Read from file
     pub fn load_file(path: impl AsRef<Path>) -> Result<ID3Tag, Error> {
         let ext = path.as_ref().extension().unwrap_or_default().to_ascii_lowercase();
         // MP3
         if ext == "mp3" {
             let tag = Tag::read_from_path(path)?;
-            let version = tag.version();
-            return Ok(ID3Tag {
-                tag,
-                format: ID3AudioFormat::MP3,
-                id3_separator: String::from(", "),
-                id3v24: match version {
-                    Version::Id3v24 => true,
-                    _ => false
-                },
-                comm_lang: "eng".to_string()
-            }.into());
+            return Ok(ID3Tag::new(tag, ID3AudioFormat::MP3));
         }
         // AIFF
         if ext == "aif" || ext == "aiff" {
             let tag = Tag::read_from_path(path)?;
-            let version = tag.version();
-            return Ok(ID3Tag {
-                tag,
-                format: ID3AudioFormat::AIFF,
-                id3_separator: String::from(", "),
-                id3v24: match version {
-                    Version::Id3v24 => true,
-                    _ => false
-                },
-                comm_lang: "eng".to_string()
-            }.into());
+            return Ok(ID3Tag::new(tag, ID3AudioFormat::AIFF));
         }
         // WAV
         if ext == "wav" {
-            let tag = crate::wav::read_wav(path)?;
-            let version = tag.version();
-            return Ok(ID3Tag { 
-                tag,
-                format: ID3AudioFormat::WAV,
-                id3_separator: String::from(", "),
-                id3v24: match version {
-                    Version::Id3v24 => true,
-                    _ => false
-                },
-                comm_lang: "eng".to_string()
-            }.into());
+            let meta = crate::wav::read_wav(path)?;
+            let mut tag = ID3Tag::new(meta.id3, ID3AudioFormat::WAV);
+            tag.bext = meta.bext;
+            tag.info = meta.info;
+            tag.load_info();
+            return Ok(tag);
+        }
+        // DSD
+        if ext == "dsf" || ext == "dff" {
+            let tag = crate::dsd::read_dsd(&path)?;
+            let format = match ext == "dsf" {
+                true => ID3AudioFormat::DSF,
+                false => ID3AudioFormat::DFF
+            };
+            return Ok(ID3Tag::new(tag, format));
         }
 
 
@@ -105,6 +115,10 @@ impl ID3Tag {
             ID3AudioFormat::MP3
         } else if ext == "wav" {
             ID3AudioFormat::WAV
+        } else if ext == "dsf" {
+            ID3AudioFormat::DSF
+        } else if ext == "dff" {
+            ID3AudioFormat::DFF
         } else {
             ID3AudioFormat::AIFF
         };
@@ -113,13 +127,7 @@ impl ID3Tag {
             Ok(tag) => tag,
             Err(e) => {
                 warn!("Failed loading: {:?}, creating new tag. {:?}", path.as_ref(), e);
-                ID3Tag {
-                    tag: Tag::new(),
-                    format,
-                    id3_separator: String::from(", "),
-                    id3v24: true,
-                    comm_lang: "eng".to_string()
-                }
+                ID3Tag::new(Tag::with_version(Version::Id3v24), format)
             }
         }
     }
@@ -134,6 +142,114 @@ impl ID3Tag {
         self.comm_lang = lang;
     }
 
+    /// Set the ID3 frame to RIFF INFO mapping, the loaded INFO chunks are remapped as well
+    pub fn set_info_mapping(&mut self, mapping: Vec<(String, String)>) {
+        self.info_mapping = mapping;
+        for frame in std::mem::take(&mut self.info_frames) {
+            self.remove_info_frame(&frame);
+        }
+        self.load_info();
+    }
+
+    /// Broadcast Wave metadata
+    pub fn bext(&self) -> Option<&Bext> {
+        self.bext.as_ref()
+    }
+
+    pub fn set_bext(&mut self, bext: Option<Bext>) {
+        self.bext = bext;
+    }
+
+    /// Copy RIFF INFO to ID3 if missing, keep the unmapped ones
+    fn load_info(&mut self) {
+        self.riff_info.clear();
+        for (id, value) in self.info.clone() {
+            match self.info_mapping.iter().find(|(_, i)| i == &id).map(|(f, _)| f.to_string()) {
+                Some(frame) => if self.get_info_frame(&frame).is_none() {
+                    self.set_info_frame(&frame, value);
+                    self.info_frames.push(frame);
+                },
+                None => self.riff_info.push((id, value)),
+            }
+        }
+    }
+
+    /// RIFF INFO values to write
+    fn info_values(&self) -> Vec<(String, String)> {
+        let mut info: Vec<(String, String)> = self.info_mapping.iter().filter_map(|(frame, id)| {
+            Some((id.to_string(), self.get_info_frame(frame)?))
+        }).collect();
+        for (id, value) in &self.riff_info {
+            if !info.iter().any(|(i, _)| i == id) {
+                info.push((id.to_string(), value.to_string()));
+            }
+        }
+        info
+    }
+
+    /// Date is stored in different frames depending on version
+    fn get_info_frame(&self, frame: &str) -> Option<String> {
+        if frame == "TDRC" {
+            let date = self.get_date()?;
+            return Some(match (date.month, date.day) {
+                (Some(month), Some(day)) => format!("{}-{month:02}-{day:02}", date.year),
+                _ => date.year.to_string()
+            });
+        }
+        self.get_raw(frame).map(|v| v.join(&self.id3_separator))
+    }
+
+    fn set_info_frame(&mut self, frame: &str, value: String) {
+        if frame == "TDRC" {
+            if let Ok(timestamp) = value.trim().parse::<Timestamp>() {
+                self.set_date(&TagDate { year: timestamp.year, month: timestamp.month, day: timestamp.day }, false);
+            }
+            return;
+        }
+        self.set_raw(frame, vec![value], false);
+    }
+
+    fn remove_info_frame(&mut self, frame: &str) {
+        if frame == "TDRC" {
+            self.tag.remove_date_recorded();
+            self.tag.remove("TYER");
+            self.tag.remove("TDAT");
+            return;
+        }
+        self.remove_raw(frame);
+    }
+
+    /// Get all SYLT frames
+    pub fn get_synced_lyrics(&self) -> Vec<ID3SyncedLyrics> {
+        self.tag.synchronised_lyrics().map(|l| l.clone().into()).collect()
+    }
+
+    /// Replace all SYLT frames
+    pub fn set_synced_lyrics(&mut self, lyrics: Vec<ID3SyncedLyrics>) {
+        self.tag.remove_all_synchronised_lyrics();
+        for l in lyrics {
+            self.tag.add_frame(SynchronisedLyrics::from(l));
+        }
+    }
+
+    /// Get GEOB frame data by description
+    pub fn get_geob(&self, description: &str) -> Option<Vec<u8>> {
+        self.tag.encapsulated_objects().find(|o| o.description == description).map(|o| o.data.clone())
+    }
+
+    /// Replace or remove (`None`) GEOB frame
+    pub fn set_geob(&mut self, mime: &str, description: &str, data: Option<Vec<u8>>) {
+        self.tag.remove_encapsulated_object(Some(description), None, None, None);
+        if let Some(data) = data {
+            self.tag.add_frame(EncapsulatedObject {
+                mime_type: mime.to_string(),
+                filename: String::new(),
+                description: description.to_string(),
+                data
+            });
+        }
+    }
+
     // Read and write all comments
     pub fn get_comments(&self) -> Vec<ID3Comment> {
         self.tag.comments().map(|c| c.clone().into()).collect()
@@ -187,6 +303,25 @@ impl ID3Tag {
 
 }
 
+/// Default mapping as owned values
+/// Custom ID3 frame to RIFF INFO mapping for all newly loaded tags
+static INFO_MAPPING: RwLock<Option<Vec<(String, String)>>> = RwLock::new(None);
+
+/// Set the ID3 frame to RIFF INFO mapping used when loading WAV files, `None` = default
+pub fn set_default_info_mapping(mapping: Option<Vec<(String, String)>>) {
+    match INFO_MAPPING.write() {
+        Ok(mut m) => *m = mapping,
+        Err(e) => warn!("Failed setting RIFF INFO mapping: {e}"),
+    }
+}
+
+fn default_info_mapping() -> Vec<(String, String)> {
+    if let Some(mapping) = INFO_MAPPING.read().ok().and_then(|m| m.clone()) {
+        return mapping;
+    }
+    DEFAULT_INFO_MAPPING.iter().map(|(f, i)| (f.to_string(), i.to_string())).collect()
+}
+
 impl TagImpl for ID3Tag {
     // Write tag to file
     fn save_file(&mut self, path: &Path) -> Result<(), Error> {
@@ -221,7 +356,8 @@ impl TagImpl for ID3Tag {
                     .write_to_path(&self.tag, path)?;
             },
             ID3AudioFormat::AIFF => self.tag.write_to_path(path, version)?,
-            ID3AudioFormat::WAV => crate::wav::write_wav(path, self.tag.clone(), version)?,
+            ID3AudioFormat::WAV => crate::wav::write_wav(path, self.tag.clone(), version, &self.info_values(), self.bext.as_ref())?,
+            ID3AudioFormat::DSF | ID3AudioFormat::DFF => crate::dsd::write_dsd(path, self.tag.clone(), version)?,
         }
         
         Ok(())
@@ -243,6 +379,17 @@ impl TagImpl for ID3Tag {
         for extended in self.tag.extended_texts() {
             tags.insert(extended.description.to_string(), extended.value.split(&self.id3_separator).map(String::from).collect());
         }
+        // WAV specific
+        for (id, value) in &self.riff_info {
+            tags.insert(format!("INFO:{id}"), vec![value.to_string()]);
+        }
+        if let Some(bext) = &self.bext {
+            for field in crate::wav::BEXT_FIELDS {
+                if let Some(value) = bext.get(field) {
+                    tags.insert(format!("BEXT:{field}"), vec![value]);
+                }
+            }
+        }
         tags
     }
 
@@ -392,6 +539,23 @@ impl TagImpl for ID3Tag {
 
     // Set/Get by tag
     fn set_raw(&mut self, tag: &str, value: Vec<String>, overwrite: bool) {
+        // RIFF INFO & bext
+        if let Some(id) = tag.strip_prefix("INFO:") {
+            if overwrite || self.get_raw(tag).is_none() {
+                self.riff_info.retain(|(i, _)| i != id);
+                if !value.is_empty() {
+                    self.riff_info.push((id.to_string(), value.join(&self.id3_separator)));
+                }
+            }
+            return;
+        }
+        if let Some(field) = tag.strip_prefix("BEXT:") {
+            if overwrite || self.get_raw(tag).is_none() {
+                self.bext.get_or_insert_with(Bext::default).set(field, &value.join(&self.id3_separator));
+            }
+            return;
+        }
+
         // TXXX
         if tag.len() != 4 {
             if overwrite || self.get_raw(tag).is_none() {
@@ -457,6 +621,14 @@ impl TagImpl for ID3Tag {
 
     // Get raw TEXT field
     fn get_raw(&self, tag: &str) -> Option<Vec<String>> {
+        // RIFF INFO & bext
+        if let Some(id) = tag.strip_prefix("INFO:") {
+            return self.riff_info.iter().find(|(i, _)| i == id).map(|(_, v)| vec![v.to_string()]);
+        }
+        if let Some(field) = tag.strip_prefix("BEXT:") {
+            return self.bext.as_ref()?.get(field).map(|v| vec![v]);
+        }
+
         // Custom tag (TXXX)
         if tag.len() != 4 {
             if let Some(t) = self.tag.extended_texts().find(|t| t.description == tag) {
@@ -499,6 +671,17 @@ impl TagImpl for ID3Tag {
     }
 
     fn remove_raw(&mut self, tag: &str) {
+        if let Some(id) = tag.strip_prefix("INFO:") {
+            self.riff_info.retain(|(i, _)| i != id);
+            return;
+        }
+        if let Some(field) = tag.strip_prefix("BEXT:") {
+            if let Some(bext) = self.bext.as_mut() {
+                bext.set(field, "");
+            }
+            return;
+        }
+
         // TXXX
         if tag.len() != 4 {
             self.tag.remove_extended_text(Some(tag), None);
@@ -642,6 +825,55 @@ impl From<ID3Comment> for Comment {
     }
 }
 
+const SYLT_TYPES: [SynchronisedLyricsType; 7] = [
+    SynchronisedLyricsType::Other,
+    SynchronisedLyricsType::Lyrics,
+    SynchronisedLyricsType::Transcription,
+    SynchronisedLyricsType::PartName,
+    SynchronisedLyricsType::Event,
+    SynchronisedLyricsType::Chord,
+    SynchronisedLyricsType::Trivia,
+];
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ID3SyncedLyrics {
+    pub lang: String,
+    pub description: String,
+    /// Timestamps in MPEG frames instead of milliseconds
+    pub mpeg: bool,
+    /// Index of SynchronisedLyricsType
+    pub content_type: u8,
+    pub content: Vec<(u32, String)>,
+}
+
+impl From<SynchronisedLyrics> for ID3SyncedLyrics {
+    fn from(l: SynchronisedLyrics) -> Self {
+        Self {
+            lang: l.lang,
+            description: l.description,
+            mpeg: l.timestamp_format == TimestampFormat::Mpeg,
+            content_type: SYLT_TYPES.iter().position(|t| *t == l.content_type).unwrap_or(0) as u8,
+            content: l.content
+        }
+    }
+}
+
+impl From<ID3SyncedLyrics> for SynchronisedLyrics {
+    fn from(l: ID3SyncedLyrics) -> Self {
+        Self {
+            lang: l.lang,
+            timestamp_format: match l.mpeg {
+                true => TimestampFormat::Mpeg,
+                false => TimestampFormat::Ms
+            },
+            content_type: SYLT_TYPES.get(l.content_type as usize).copied().unwrap_or(SynchronisedLyricsType::Other),
+            description: l.description,
+            content: l.content
+        }
+    }
+}
+
 // Unsynchronized lyrics have exactly same schema as comment
 impl From<Lyrics> for ID3Comment {
     fn from(l: Lyrics) -> Self {
@@ -661,4 +893,63 @@ impl From<ID3Comment> for Lyrics {
             text: c.text
         }
     }
-}
\ No newline at end of file
+}
+
+#[test]
+fn wav_info_mapping() {
+    // WAV with RIFF INFO only, no id3 chunk
+    let mut list = b"INFO".to_vec();
+    for (id, value) in [("INAM", "Title\0"), ("IKEY", "Am\0\0"), ("ICRD", "2021\0\0")] {
+        list.extend(id.as_bytes());
+        list.extend((value.len() as u32).to_le_bytes());
+        list.extend(value.as_bytes());
+    }
+    let mut wave = b"WAVEfmt ".to_vec();
+    wave.extend(16u32.to_le_bytes());
+    wave.extend([1, 0, 2, 0, 0x44, 0xAC, 0, 0, 0x10, 0xB1, 2, 0, 4, 0, 16, 0]);
+    wave.extend(b"data");
+    wave.extend(0u32.to_le_bytes());
+    wave.extend(b"LIST");
+    wave.extend((list.len() as u32).to_le_bytes());
+    wave.extend(list);
+    let mut data = b"RIFF".to_vec();
+    data.extend((wave.len() as u32).to_le_bytes());
+    data.extend(wave);
+    let path = std::env::temp_dir().join("onetagger_wav_info_mapping.wav");
+    std::fs::write(&path, data).unwrap();
+
+    // Default mapping
+    let mut tag = ID3Tag::load_file(&path).unwrap();
+    let tags = tag.all_tags();
+    assert_eq!(tags.get("TIT2"), Some(&vec!["Title".to_string()]));
+    assert_eq!(tags.get("INFO:IKEY"), Some(&vec!["Am".to_string()]));
+    assert_eq!(tag.get_date().map(|d| d.year), Some(2021));
+
+    // Custom mapping is applied to the loaded INFO
+    tag.set_info_mapping(vec![("TKEY".to_string(), "IKEY".to_string())]);
+    let tags = tag.all_tags();
+    assert_eq!(tags.get("TKEY"), Some(&vec!["Am".to_string()]));
+    assert_eq!(tags.get("INFO:INAM"), Some(&vec!["Title".to_string()]));
+    assert_eq!(tags.get("INFO:ICRD"), Some(&vec!["2021".to_string()]));
+    assert!(!tags.contains_key("TIT2"));
+    assert!(!tags.contains_key("INFO:IKEY"));
+    assert!(tag.get_date().is_none());
+
+    // Round trip
+    tag.set_raw("TKEY", vec!["C#m".to_string()], true);
+    tag.set_raw("BEXT:Description", vec!["Interview".to_string()], true);
+    tag.save_file(&path).unwrap();
+    // New bext goes right after fmt
+    let data = std::fs::read(&path).unwrap();
+    let find = |id: &[u8]| data.windows(4).position(|w| w == id).unwrap();
+    assert_eq!(find(b"bext"), find(b"fmt ") + 8 + 16);
+    let meta = crate::wav::read_wav(&path).unwrap();
+    assert_eq!(meta.bext.unwrap().get("Description").as_deref(), Some("Interview"));
+    assert!(meta.info.contains(&("IKEY".to_string(), "C#m".to_string())));
+    assert!(meta.info.contains(&("INAM".to_string(), "Title".to_string())));
+    assert!(meta.info.contains(&("ICRD".to_string(), "2021".to_string())));
+    let tag = ID3Tag::load_file(&path).unwrap();
+    assert_eq!(tag.get_raw("TKEY"), Some(vec!["C#m".to_string()]));
+    assert_eq!(tag.get_raw("TIT2"), Some(vec!["Title".to_string()]));
+    std::fs::remove_file(&path).ok();
+}
@@ -23,18 +23,28 @@ pub mod mp4;
 #[cfg(feature = "tag")]
 pub mod vorbis;
 #[cfg(feature = "tag")]
-mod wav;
+pub mod wav;
+#[cfg(feature = "tag")]
+pub mod ape;
+#[cfg(feature = "tag")]
+pub mod dsd;
+#[cfg(feature = "tag")]
+pub mod serato;
+pub mod properties;
+
+pub use properties::AudioProperties;
 
 // Supported extensions
-pub static EXTENSIONS : [&'static str; 11] = ["mp3", "flac", "aif", "aiff", "m4a", 
-    "mp4", "wav", "ogg", "opus", "spx", "oga"];
+pub static EXTENSIONS : [&'static str; 16] = ["mp3", "flac", "aif", "aiff", "m4a", 
+    "mp4", "wav", "ogg", "opus", "spx", "oga", "wv", "ape", "mpc", "dsf", "dff"];
 
 #[cfg(feature = "tag")]
 pub enum Tag {
     FLAC(flac::FLACTag),
     ID3(id3::ID3Tag),
     MP4(mp4::MP4Tag),
-    Vorbis(vorbis::VorbisTag)
+    Vorbis(vorbis::VorbisTag),
+    APE(ape::APETag)
 }
 
 #[cfg(feature = "tag")]
@@ -55,6 +65,11 @@ impl Tag {
             return Ok(Tag::Vorbis(vorbis::VorbisTag::load_file(path)?));
         }
 
+        // APE
+        if ext == "wv" || ext == "ape" || ext == "mpc" {
+            return Ok(Tag::APE(ape::APETag::load_file(path)?));
+        }
+
         // ID3
         let tag = if allow_new {
             id3::ID3Tag::load_or_new(path)
@@ -71,6 +86,7 @@ impl Tag {
             Tag::ID3(tag) => tag.set_separator(&separators.id3),
             Tag::MP4(tag) => tag.set_separator(&separators.mp4),
             Tag::Vorbis(tag) => tag.set_separator(separators.vorbis.as_ref().unwrap_or(&String::new())),
+            Tag::APE(tag) => tag.set_separator(separators.vorbis.as_ref().unwrap_or(&String::new())),
         }
     }
 
@@ -81,6 +97,7 @@ impl Tag {
             Tag::ID3(tag) => Box::new(tag),
             Tag::MP4(tag) => Box::new(tag),
             Tag::Vorbis(tag) => Box::new(tag),
+            Tag::APE(tag) => Box::new(tag),
         }
     }
     pub fn tag_mut(&mut self) -> Box<&mut dyn TagImpl> {
@@ -88,7 +105,8 @@ impl Tag {
             Tag::FLAC(tag) => Box::new(tag),
             Tag::ID3(tag) => Box::new(tag),
             Tag::MP4(tag) => Box::new(tag),
-            Tag::Vorbis(tag) => Box::new(tag)
+            Tag::Vorbis(tag) => Box::new(tag),
+            Tag::APE(tag) => Box::new(tag)
         }
     }
 
@@ -100,9 +118,12 @@ impl Tag {
             Tag::ID3(id3) => match id3.format {
                 id3::ID3AudioFormat::MP3 => AudioFileFormat::MP3,
                 id3::ID3AudioFormat::AIFF => AudioFileFormat::AIFF,
-                id3::ID3AudioFormat::WAV => AudioFileFormat::WAV
+                id3::ID3AudioFormat::WAV => AudioFileFormat::WAV,
+                id3::ID3AudioFormat::DSF => AudioFileFormat::DSF,
+                id3::ID3AudioFormat::DFF => AudioFileFormat::DFF
             },
-            Tag::Vorbis(_) => AudioFileFormat::OGG
+            Tag::Vorbis(_) => AudioFileFormat::OGG,
+            Tag::APE(ape) => ape.format.clone()
         }
     }
 }
@@ -152,6 +173,11 @@ pub trait TagImpl {
     /// Track number is string because of platforms like discogs
     fn set_track_number(&mut self, track_number: &str, track_total: Option<u16>, overwrite: bool);
 
+    /// Set disc number
+    fn set_disc_number(&mut self, disc_number: u16, overwrite: bool) {
+        self.set_field(Field::DiscNumber, vec![disc_number.to_string()], overwrite);
+    }
+
     /// Set whether the track is explicit
     fn set_explicit(&mut self, explicit: bool);
 }
@@ -176,7 +202,11 @@ impl Default for TagSeparators {
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum AudioFileFormat {
-    FLAC, AIFF, MP3, MP4, WAV, OGG
+    FLAC, AIFF, MP3, MP4, WAV, OGG,
+    /// WavPack, Monkey's Audio and Musepack use APEv2 tags
+    WV, APE, MPC,
+    /// DSD, tagged with ID3
+    DSF, DFF
 }
 
 impl AudioFileFormat {
@@ -189,6 +219,11 @@ impl AudioFileFormat {
             "m4a" | "mp4" => Some(AudioFileFormat::MP4),
             "wav" => Some(AudioFileFormat::WAV),
             "ogg" | "opus" | "spx" | "oga" => Some(AudioFileFormat::OGG),
+            "wv" => Some(AudioFileFormat::WV),
+            "ape" => Some(AudioFileFormat::APE),
+            "mpc" => Some(AudioFileFormat::MPC),
+            "dsf" => Some(AudioFileFormat::DSF),
+            "dff" => Some(AudioFileFormat::DFF),
             _ => None
         }
     }
@@ -225,9 +260,11 @@ impl FrameName {
             AudioFileFormat::AIFF => self.id3.to_string(),
             AudioFileFormat::MP3 => self.id3.to_string(),
             AudioFileFormat::WAV => self.id3.to_string(),
+            AudioFileFormat::DSF | AudioFileFormat::DFF => self.id3.to_string(),
             AudioFileFormat::FLAC => self.vorbis.to_string(),
             AudioFileFormat::MP4 => self.mp4.to_string(),
-            AudioFileFormat::OGG => self.vorbis.to_string()
+            AudioFileFormat::OGG => self.vorbis.to_string(),
+            AudioFileFormat::WV | AudioFileFormat::APE | AudioFileFormat::MPC => Field::by_ape_key(&self.vorbis).map(|f| f.ape().to_string()).unwrap_or(self.vorbis.to_string())
         }
     }
 }
@@ -278,7 +315,7 @@ impl CoverType {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct TagDate {
     pub year: i32,
     pub month: Option<u8>,
@@ -322,8 +359,10 @@ impl Field {
             AudioFileFormat::AIFF => self.id3(),
             AudioFileFormat::WAV => self.id3(),
             AudioFileFormat::MP3 => self.id3(),
+            AudioFileFormat::DSF | AudioFileFormat::DFF => self.id3(),
             AudioFileFormat::MP4 => self.mp4(),
             AudioFileFormat::OGG => self.vorbis(),
+            AudioFileFormat::WV | AudioFileFormat::APE | AudioFileFormat::MPC => self.ape(),
         }
     }
 
@@ -375,6 +414,39 @@ impl Field {
         }
     }
 
+    /// Convert to APE item key
+    pub fn ape(&self) -> &'static str {
+        match self {
+            Field::Title => "Title",
+            Field::Artist => "Artist",
+            Field::AlbumArtist => "Album Artist",
+            Field::Album => "Album",
+            Field::Key => "INITIALKEY",
+            Field::BPM => "BPM",
+            Field::Genre => "Genre",
+            Field::Label => "Publisher",
+            Field::Style => "STYLE",
+            Field::ISRC => "ISRC",
+            Field::CatalogNumber => "CATALOGNUMBER",
+            Field::Version => "Subtitle",
+            Field::TrackNumber => "Track",
+            Field::Duration => "LENGTH",
+            Field::Remixer => "REMIXER",
+            Field::Mood => "MOOD",
+            Field::TrackTotal => "Track",
+            Field::DiscNumber => "Disc",
+        }
+    }
+
+    /// Get field with APE item key from Vorbis name or APE key in any case
+    pub fn by_ape_key(name: &str) -> Option<Field> {
+        // TRACKTOTAL is stored together with the track number
+        [
+            Field::Title, Field::Artist, Field::AlbumArtist, Field::Album, Field::Genre,
+            Field::Label, Field::Version, Field::TrackNumber, Field::DiscNumber
+        ].into_iter().find(|f| f.vorbis().eq_ignore_ascii_case(name) || f.ape().eq_ignore_ascii_case(name))
+    }
+
     /// Convert to MP4 frame name
     pub fn mp4(&self) -> &'static str {
         match self {
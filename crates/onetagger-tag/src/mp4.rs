@@ -248,18 +248,29 @@ impl TagImpl for MP4Tag {
     }
 
     fn set_field(&mut self, field: Field, value: Vec<String>, overwrite: bool) {
+        // Integer atoms, existing value is kept if invalid
+        if matches!(field, Field::BPM | Field::TrackNumber | Field::TrackTotal | Field::DiscNumber) {
+            let value = match value.first().and_then(|v| v.trim().parse::<f64>().ok()) {
+                Some(v) => v.round() as u16,
+                None => {
+                    warn!("Invalid {field:?} value, it won't be written: {value:?}");
+                    return;
+                }
+            };
+            match field {
+                Field::BPM if overwrite || self.tag.bpm().is_none() => self.tag.set_bpm(value),
+                Field::TrackNumber if overwrite || self.tag.track_number().is_none() => self.tag.set_track_number(value),
+                Field::TrackTotal if overwrite || self.tag.total_tracks().is_none() => self.tag.set_total_tracks(value),
+                Field::DiscNumber if overwrite || self.tag.disc_number().is_none() => self.tag.set_disc_number(value),
+                _ => {}
+            }
+            return;
+        }
+
         let ident = MP4Tag::field_to_ident(field.clone());
         if self.tag.data_of(&ident).next().is_none() || overwrite {
             self.tag.remove_data_of(&ident);
 
-            // Overrides
-            if field == Field::BPM {
-                if let Some(bpm) = value.first().map(|v| v.parse().ok()).flatten() {
-                    self.tag.set_bpm(bpm);
-                }
-                return;
-            }
-
             // Add each data separately
             if self.separator.is_empty() {
                 for v in value {
@@ -0,0 +1,83 @@
+use serde::{Serialize, Deserialize};
+use std::time::Duration;
+
+#[cfg(feature = "tag")]
+use anyhow::Error;
+#[cfg(feature = "tag")]
+use std::path::Path;
+
+/// Technical properties of the audio stream
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AudioProperties {
+    pub duration: Duration,
+    /// In kbps
+    pub bitrate: Option<u32>,
+    /// In Hz
+    pub sample_rate: Option<u32>,
+    pub bit_depth: Option<u8>,
+    pub channels: Option<u8>,
+    pub codec: String,
+    /// File size in bytes
+    pub size: u64,
+}
+
+#[cfg(feature = "tag")]
+impl AudioProperties {
+    /// Read properties from file
+    pub fn read(path: impl AsRef<Path>) -> Result<AudioProperties, Error> {
+        use lofty::file::{AudioFile, FileType, TaggedFileExt};
+
+        let size = std::fs::metadata(&path)?.len();
+        let extension = path.as_ref().extension().unwrap_or_default().to_string_lossy().to_lowercase();
+
+        // DSD, 1 bit per sample
+        if extension == "dsf" || extension == "dff" {
+            let properties = crate::dsd::properties(&path)?;
+            return Ok(AudioProperties {
+                duration: properties.duration,
+                bitrate: Some((properties.sample_rate as u64 * properties.channels as u64 / 1000) as u32),
+                sample_rate: Some(properties.sample_rate),
+                bit_depth: Some(1),
+                channels: Some(properties.channels as u8),
+                codec: "DSD".to_string(),
+                size
+            });
+        }
+
+        let file = lofty::read_from_path(&path)?;
+        let properties = file.properties();
+        let codec = match file.file_type() {
+            FileType::Mpeg => "MP3",
+            FileType::Flac => "FLAC",
+            // ALAC is the only MP4 codec with bit depth
+            FileType::Mp4 if properties.bit_depth().is_some() => "ALAC",
+            FileType::Mp4 | FileType::Aac => "AAC",
+            FileType::Aiff => "AIFF",
+            FileType::Wav => "WAV",
+            FileType::Vorbis => "Vorbis",
+            FileType::Opus => "Opus",
+            FileType::Speex => "Speex",
+            FileType::WavPack => "WavPack",
+            FileType::Ape => "APE",
+            FileType::Mpc => "Musepack",
+            _ => "Unknown"
+        };
+        // Estimate from size if missing
+        let duration = properties.duration();
+        let bitrate = properties.audio_bitrate()
+            .or(properties.overall_bitrate())
+            .filter(|b| *b > 0)
+            .or_else(|| (duration.as_millis() > 0).then(|| (size * 8 / duration.as_millis() as u64) as u32));
+
+        Ok(AudioProperties {
+            duration,
+            bitrate,
+            sample_rate: properties.sample_rate(),
+            bit_depth: properties.bit_depth(),
+            channels: properties.channels(),
+            codec: codec.to_string(),
+            size
+        })
+    }
+}
@@ -0,0 +1,382 @@
+use anyhow::Error;
+use std::path::Path;
+use base64::Engine;
+use base64::alphabet::STANDARD;
+use base64::engine::{GeneralPurpose, GeneralPurposeConfig, DecodePaddingMode};
+use serde::{Serialize, Deserialize};
+use crate::Tag;
+
+const MARKERS2: &str = "Serato Markers2";
+const BEATGRID: &str = "Serato BeatGrid";
+const GEOB_MIME: &str = "application/octet-stream";
+/// Serato pads the Markers2 data with zeroes to this size
+const MARKERS2_SIZE: usize = 470;
+/// Default loop color (ARGB)
+const LOOP_COLOR: [u8; 4] = [0x00, 0x27, 0xaa, 0xe1];
+
+/// Serato's base64 has no padding and non zero trailing bits
+const BASE64: GeneralPurpose = GeneralPurpose::new(&STANDARD, GeneralPurposeConfig::new()
+    .with_decode_padding_mode(DecodePaddingMode::Indifferent)
+    .with_decode_allow_trailing_bits(true)
+);
+
+/// Serato DJ cue points, loops and beatgrid
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SeratoMarkers {
+    pub cues: Vec<SeratoCue>,
+    pub loops: Vec<SeratoLoop>,
+    /// Track color (RGB)
+    pub color: Option<[u8; 3]>,
+    pub bpm_lock: bool,
+    pub beatgrid: Vec<SeratoBeatAnchor>,
+    /// Unknown Markers2 entries (such as FLIP), kept as is
+    #[serde(skip)]
+    other: Vec<(String, Vec<u8>)>,
+    #[serde(skip)]
+    beatgrid_footer: u8,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SeratoCue {
+    pub index: u8,
+    /// In milliseconds
+    pub position: u32,
+    /// RGB
+    pub color: [u8; 3],
+    pub label: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SeratoLoop {
+    pub index: u8,
+    /// In milliseconds
+    pub start: u32,
+    pub end: u32,
+    /// ARGB
+    pub color: [u8; 4],
+    pub locked: bool,
+    pub label: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SeratoBeatAnchor {
+    /// In seconds
+    pub position: f32,
+    /// Beats until the next anchor, None for the last one
+    pub beats: Option<u32>,
+    /// Only the last anchor has BPM
+    pub bpm: Option<f32>,
+}
+
+impl SeratoMarkers {
+    /// Read from tag, None if there is no Serato data
+    pub fn read(tag: &Tag) -> Option<SeratoMarkers> {
+        let markers2 = read_geob(tag, MARKERS2);
+        let beatgrid = read_geob(tag, BEATGRID);
+        if markers2.is_none() && beatgrid.is_none() {
+            return None;
+        }
+        let mut markers = SeratoMarkers::default();
+        if let Some(data) = markers2 {
+            if let Err(e) = markers.parse_markers2(&data) {
+                warn!("Failed parsing Serato Markers2: {e}");
+            }
+        }
+        if let Some(data) = beatgrid {
+            if let Err(e) = markers.parse_beatgrid(&data) {
+                warn!("Failed parsing Serato BeatGrid: {e}");
+            }
+        }
+        Some(markers)
+    }
+
+    /// Write into tag, will replace all existing markers
+    pub fn write(&self, tag: &mut Tag) {
+        let empty = self.cues.is_empty() && self.loops.is_empty() && self.color.is_none() && self.other.is_empty();
+        write_geob(tag, MARKERS2, match empty {
+            true => None,
+            false => Some(self.serialize_markers2())
+        });
+        write_geob(tag, BEATGRID, match self.beatgrid.is_empty() {
+            true => None,
+            false => Some(self.serialize_beatgrid())
+        });
+    }
+
+    /// Copy markers between files, returns false if source has no markers
+    pub fn copy(source: impl AsRef<Path>, target: impl AsRef<Path>) -> Result<bool, Error> {
+        let markers = match SeratoMarkers::read(&Tag::load_file(&source, false)?) {
+            Some(markers) => markers,
+            None => return Ok(false)
+        };
+        let mut tag = Tag::load_file(&target, true)?;
+        markers.write(&mut tag);
+        tag.tag_mut().save_file(target.as_ref())?;
+        Ok(true)
+    }
+
+    /// Is raw tag used for Serato markers
+    pub fn is_serato_tag(tag: &str) -> bool {
+        [MARKERS2, BEATGRID].iter().any(|d| tag == vorbis_name(d) || tag == mp4_name(d))
+    }
+
+    /// Markers2 = version, base64 of (version + entries)
+    fn parse_markers2(&mut self, data: &[u8]) -> Result<(), Error> {
+        let data = data.get(2..).ok_or(anyhow!("Invalid header"))?;
+        let end = data.iter().position(|b| *b == 0).unwrap_or(data.len());
+        let payload = decode_base64(&data[..end])?;
+        let mut reader = Reader { data: &payload, pos: 2 };
+        loop {
+            let name = reader.string();
+            if name.is_empty() {
+                break;
+            }
+            let len = reader.u32()? as usize;
+            let entry = reader.bytes(len)?;
+            let mut r = Reader { data: entry, pos: 0 };
+            match name.as_str() {
+                "COLOR" => {
+                    r.bytes(1)?;
+                    self.color = Some(r.bytes(3)?.try_into()?);
+                },
+                "CUE" => {
+                    r.bytes(1)?;
+                    let index = r.u8()?;
+                    let position = r.u32()?;
+                    r.bytes(1)?;
+                    let color = r.bytes(3)?.try_into()?;
+                    r.bytes(2)?;
+                    self.cues.push(SeratoCue { index, position, color, label: r.string() });
+                },
+                "LOOP" => {
+                    r.bytes(1)?;
+                    let index = r.u8()?;
+                    let start = r.u32()?;
+                    let end = r.u32()?;
+                    r.bytes(4)?;
+                    let color = r.bytes(4)?.try_into()?;
+                    r.bytes(1)?;
+                    let locked = r.u8()? != 0;
+                    self.loops.push(SeratoLoop { index, start, end, color, locked, label: r.string() });
+                },
+                "BPMLOCK" => self.bpm_lock = r.u8()? != 0,
+                _ => self.other.push((name, entry.to_vec())),
+            }
+        }
+        Ok(())
+    }
+
+    fn serialize_markers2(&self) -> Vec<u8> {
+        let mut entries: Vec<(String, Vec<u8>)> = vec![];
+        if let Some(color) = self.color {
+            entries.push(("COLOR".to_string(), [&[0][..], &color].concat()));
+        }
+        for cue in &self.cues {
+            let mut data = vec![0, cue.index];
+            data.extend(cue.position.to_be_bytes());
+            data.push(0);
+            data.extend(cue.color);
+            data.extend([0, 0]);
+            data.extend(cue.label.as_bytes());
+            data.push(0);
+            entries.push(("CUE".to_string(), data));
+        }
+        for l in &self.loops {
+            let mut data = vec![0, l.index];
+            data.extend(l.start.to_be_bytes());
+            data.extend(l.end.to_be_bytes());
+            data.extend([0xff; 4]);
+            data.extend(l.color);
+            data.extend([0, l.locked as u8]);
+            data.extend(l.label.as_bytes());
+            data.push(0);
+            entries.push(("LOOP".to_string(), data));
+        }
+        entries.extend(self.other.iter().cloned());
+        entries.push(("BPMLOCK".to_string(), vec![self.bpm_lock as u8]));
+
+        let mut payload = vec![1, 1];
+        for (name, data) in entries {
+            payload.extend(name.as_bytes());
+            payload.push(0);
+            payload.extend((data.len() as u32).to_be_bytes());
+            payload.extend(data);
+        }
+        payload.push(0);
+
+        // Serato replaces padding with A and wraps lines at 72 characters
+        let encoded = base64::engine::general_purpose::STANDARD.encode(payload).replace('=', "A");
+        let mut out = vec![1, 1];
+        for (i, line) in encoded.as_bytes().chunks(72).enumerate() {
+            if i > 0 {
+                out.push(b'\n');
+            }
+            out.extend(line);
+        }
+        if out.len() < MARKERS2_SIZE {
+            out.resize(MARKERS2_SIZE, 0);
+        } else {
+            out.push(0);
+        }
+        out
+    }
+
+    /// BeatGrid = version, anchor count, anchors, footer
+    fn parse_beatgrid(&mut self, data: &[u8]) -> Result<(), Error> {
+        let mut r = Reader { data, pos: 2 };
+        let count = r.u32()?;
+        for i in 0..count {
+            let position = f32::from_be_bytes(r.bytes(4)?.try_into()?);
+            // Last one has BPM instead of beat count
+            let value = r.bytes(4)?;
+            self.beatgrid.push(match i == count - 1 {
+                true => SeratoBeatAnchor { position, beats: None, bpm: Some(f32::from_be_bytes(value.try_into()?)) },
+                false => SeratoBeatAnchor { position, beats: Some(u32::from_be_bytes(value.try_into()?)), bpm: None },
+            });
+        }
+        self.beatgrid_footer = r.u8().unwrap_or(0);
+        Ok(())
+    }
+
+    fn serialize_beatgrid(&self) -> Vec<u8> {
+        let mut out = vec![1, 0];
+        out.extend((self.beatgrid.len() as u32).to_be_bytes());
+        for (i, anchor) in self.beatgrid.iter().enumerate() {
+            out.extend(anchor.position.to_be_bytes());
+            match i == self.beatgrid.len() - 1 {
+                true => out.extend(anchor.bpm.unwrap_or(0.0).to_be_bytes()),
+                false => out.extend(anchor.beats.unwrap_or(0).to_be_bytes()),
+            }
+        }
+        out.push(self.beatgrid_footer);
+        out
+    }
+}
+
+impl SeratoLoop {
+    pub fn new(index: u8, start: u32, end: u32) -> SeratoLoop {
+        SeratoLoop { index, start, end, color: LOOP_COLOR, locked: false, label: String::new() }
+    }
+}
+
+/// Vorbis comment name of Serato GEOB
+fn vorbis_name(description: &str) -> &'static str {
+    match description {
+        MARKERS2 => "SERATO_MARKERS_V2",
+        _ => "SERATO_BEATGRID",
+    }
+}
+
+/// MP4 freeform name of Serato GEOB
+fn mp4_name(description: &str) -> &'static str {
+    match description {
+        MARKERS2 => "com.serato.dj:markersv2",
+        _ => "com.serato.dj:beatgrid",
+    }
+}
+
+/// Get the GEOB data, FLAC and MP4 store base64 of the whole GEOB frame content
+fn read_geob(tag: &Tag, description: &str) -> Option<Vec<u8>> {
+    let name = match tag {
+        Tag::ID3(t) => return t.get_geob(description),
+        Tag::FLAC(_) | Tag::Vorbis(_) => vorbis_name(description),
+        Tag::MP4(_) => mp4_name(description),
+        Tag::APE(_) => return None,
+    };
+    let data = decode_base64(tag.tag().get_raw(name)?.join("").as_bytes()).ok()?;
+    // mime, filename, description are null terminated
+    let mut reader = Reader { data: &data, pos: 0 };
+    let (_mime, _filename, desc) = (reader.string(), reader.string(), reader.string());
+    if desc != description {
+        return None;
+    }
+    Some(data[reader.pos..].to_vec())
+}
+
+/// Set or remove the GEOB data
+fn write_geob(tag: &mut Tag, description: &str, data: Option<Vec<u8>>) {
+    let name = match tag {
+        Tag::ID3(t) => return t.set_geob(GEOB_MIME, description, data),
+        Tag::FLAC(_) | Tag::Vorbis(_) => vorbis_name(description),
+        Tag::MP4(_) => mp4_name(description),
+        Tag::APE(_) => return,
+    };
+    let data = match data {
+        Some(data) => data,
+        None => return tag.tag_mut().remove_raw(name)
+    };
+    let mut content = format!("{GEOB_MIME}\0\0{description}\0").into_bytes();
+    content.extend(data);
+    let encoded = base64::engine::general_purpose::STANDARD_NO_PAD.encode(content);
+    tag.tag_mut().set_raw(name, vec![encoded], true);
+}
+
+fn decode_base64(data: &[u8]) -> Result<Vec<u8>, Error> {
+    let mut data: Vec<u8> = data.iter().copied().filter(|c| c.is_ascii_alphanumeric() || *c == b'+' || *c == b'/').collect();
+    // Single leftover character can't be decoded
+    if data.len() % 4 == 1 {
+        data.pop();
+    }
+    Ok(BASE64.decode(data)?)
+}
+
+/// Big endian reader
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8], Error> {
+        let out = self.data.get(self.pos..self.pos + len).ok_or(anyhow!("Unexpected end of data"))?;
+        self.pos += len;
+        Ok(out)
+    }
+
+    fn u8(&mut self) -> Result<u8, Error> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u32(&mut self) -> Result<u32, Error> {
+        Ok(u32::from_be_bytes(self.bytes(4)?.try_into()?))
+    }
+
+    /// Null terminated
+    fn string(&mut self) -> String {
+        let rest = self.data.get(self.pos..).unwrap_or_default();
+        let len = rest.iter().position(|b| *b == 0).unwrap_or(rest.len());
+        self.pos += (len + 1).min(rest.len());
+        String::from_utf8_lossy(&rest[..len]).to_string()
+    }
+}
+
+#[test]
+fn serato_markers_roundtrip() {
+    let mut markers = SeratoMarkers {
+        cues: vec![
+            SeratoCue { index: 0, position: 1500, color: [0xcc, 0x00, 0x00], label: "Intro".to_string() },
+            SeratoCue { index: 3, position: 95_250, color: [0x00, 0xcc, 0x00], label: String::new() },
+        ],
+        loops: vec![SeratoLoop::new(0, 32_000, 40_000)],
+        color: Some([0xff, 0x99, 0xff]),
+        bpm_lock: true,
+        beatgrid: vec![
+            SeratoBeatAnchor { position: 0.05, beats: Some(64), bpm: None },
+            SeratoBeatAnchor { position: 30.05, beats: None, bpm: Some(128.0) },
+        ],
+        ..Default::default()
+    };
+    markers.other.push(("FLIP".to_string(), vec![0, 1, 2]));
+
+    let markers2 = markers.serialize_markers2();
+    assert_eq!(markers2.len(), MARKERS2_SIZE);
+    assert!(markers2[2..].split(|b| *b == 0).next().unwrap().split(|b| *b == b'\n').all(|l| l.len() <= 72));
+    let mut parsed = SeratoMarkers::default();
+    parsed.parse_markers2(&markers2).unwrap();
+    parsed.parse_beatgrid(&markers.serialize_beatgrid()).unwrap();
+    assert_eq!(parsed, markers);
+}
@@ -1,10 +1,8 @@
 use anyhow::Error;
 use std::io::{BufReader, BufWriter, Cursor, Seek, SeekFrom};
 use std::fs::File;
-use std::collections::HashMap;
 use std::path::Path;
-use id3::{Tag, TagLike, Frame, Version};
-use once_cell::sync::Lazy;
+use id3::{Tag, Version};
 use riff::{Chunk, ChunkId, LIST_ID, RIFF_ID, ChunkContents};
 
 /// RIFF `id3 ` chunk
@@ -14,21 +12,42 @@ static ID3_ID_2: ChunkId = ChunkId { value: [0x49, 0x44, 0x33, 0x20] };
 static WAVE_ID:  ChunkId = ChunkId { value: [0x57, 0x41, 0x56, 0x45] };
 static INFO_ID:  ChunkId = ChunkId { value: [0x49, 0x4E, 0x46, 0x4F] };
 
-/// ID3 Frame to RIFF ChunkID
-static ID3_RIFF: Lazy<HashMap<&'static str, ChunkId>> = Lazy::new(|| {
-    let mut m = HashMap::new();
-    m.insert("TIT2", ChunkId::new("INAM").unwrap());
-    m.insert("TALB", ChunkId::new("IPRD").unwrap());
-    m.insert("TPE1", ChunkId::new("IART").unwrap());
-    m.insert("COMM", ChunkId::new("ICMT").unwrap());
-    m.insert("TCON", ChunkId::new("IGNR").unwrap());
-    m.insert("TSRC", ChunkId::new("ISRC").unwrap());
-    m
-});
+/// Broadcast Wave `bext` chunk
+static BEXT_ID:  ChunkId = ChunkId { value: [0x62, 0x65, 0x78, 0x74] };
+/// WAVE `fmt ` chunk
+static FMT_ID:   ChunkId = ChunkId { value: [0x66, 0x6D, 0x74, 0x20] };
+
+/// Default ID3 frame to RIFF INFO mapping
+pub const DEFAULT_INFO_MAPPING: [(&str, &str); 16] = [
+    ("TIT2", "INAM"),
+    ("TALB", "IPRD"),
+    ("TPE1", "IART"),
+    ("COMM", "ICMT"),
+    ("TCON", "IGNR"),
+    ("TSRC", "ISRC"),
+    ("TDRC", "ICRD"),
+    ("TRCK", "ITRK"),
+    ("TCOP", "ICOP"),
+    ("TSSE", "ISFT"),
+    ("TCOM", "IMUS"),
+    ("TEXT", "IWRI"),
+    ("TENC", "ITCH"),
+    ("TPUB", "IPUB"),
+    ("TBPM", "IBPM"),
+    ("TLAN", "ILNG"),
+];
+
+/// Metadata of a WAV file
+pub(crate) struct WavMeta {
+    pub id3: Tag,
+    /// All RIFF INFO chunks (ID, text)
+    pub info: Vec<(String, String)>,
+    pub bext: Option<Bext>,
+}
 
 /// Write wav to path
-/// Will copy ID3 meta into RIFF INFO chunk
-pub(crate) fn write_wav(path: impl AsRef<Path>, tag: Tag, version: Version) -> Result<(), Error> {
+/// `info` replaces all the RIFF INFO chunks, `bext` the Broadcast Wave chunk
+pub(crate) fn write_wav(path: impl AsRef<Path>, tag: Tag, version: Version, info: &[(String, String)], bext: Option<&Bext>) -> Result<(), Error> {
     let mut file = BufReader::new(File::open(&path)?);
     let mut offset = 0;
     // Read all the chunks
@@ -87,14 +106,28 @@ pub(crate) fn write_wav(path: impl AsRef<Path>, tag: Tag, version: Version) -> R
 
     // Generate the RIFF chunk
     let mut riff_data = vec![];
+    let has_bext = riff_chunks.iter().any(|c| c.id() == BEXT_ID);
     for chunk in riff_chunks {
         // skip old ID3 chunk
         if chunk.id() == ID3_ID_1 || chunk.id() == ID3_ID_2 {
             continue;
         }
+        // Replace bext in place
+        if chunk.id() == BEXT_ID {
+            if let Some(bext) = bext {
+                riff_data.push(ChunkContents::Data(BEXT_ID.clone(), bext.data.clone()));
+                continue;
+            }
+        }
         // Passthru
-        let data = ChunkContents::Data(chunk.id(), chunk.read_contents(&mut file)?);
-        riff_data.push(data);
+        let id = chunk.id();
+        riff_data.push(ChunkContents::Data(id.clone(), chunk.read_contents(&mut file)?));
+        // New bext goes right after fmt, before the audio data
+        if id == FMT_ID && !has_bext {
+            if let Some(bext) = bext {
+                riff_data.push(ChunkContents::Data(BEXT_ID.clone(), bext.data.clone()));
+            }
+        }
     }
     // Add ID3 chunk
     let mut out = vec![];
@@ -102,20 +135,18 @@ pub(crate) fn write_wav(path: impl AsRef<Path>, tag: Tag, version: Version) -> R
     riff_data.push(ChunkContents::Data(ID3_ID_1.clone(), out));
     let riff_chunk = ChunkContents::Children(RIFF_ID.clone(), WAVE_ID.clone(), riff_data);
 
-    // Generate LIST chunk
+    // Generate LIST chunk, INFO strings are null terminated
     let mut list_data = vec![];
-    for frame in tag.frames() {
-        if let Some(chunk_id) = ID3_RIFF.get(frame.id()) {
-            if let Some(text) = frame.content().text() {
-                let data = ChunkContents::Data(chunk_id.clone(), format!("{text}").as_bytes().to_owned());
-                list_data.push(data);
-            }
+    for (id, value) in info {
+        match ChunkId::new(id) {
+            Ok(chunk_id) => list_data.push(ChunkContents::Data(chunk_id, format!("{value}\0").into_bytes())),
+            Err(_) => warn!("Invalid RIFF INFO id: {id}"),
         }
     }
-    
-    // Add original LIST chunks
+
+    // Add original non-INFO LIST chunks
     for chunk in list_chunks {
-        if list_data.iter().any(|c| matches!(c, ChunkContents::Data(id, _) if id == &chunk.id())) {
+        if is_info_id(&chunk.id()) {
             continue;
         }
         let data = ChunkContents::Data(chunk.id(), chunk.read_contents(&mut file)?);
@@ -131,8 +162,8 @@ pub(crate) fn write_wav(path: impl AsRef<Path>, tag: Tag, version: Version) -> R
     Ok(())
 }
 
-/// Read WAV from file, will copy missing tags from RIFF to ID3
-pub(crate) fn read_wav(path: impl AsRef<Path>) -> Result<Tag, Error> {
+/// Read ID3, RIFF INFO and bext from WAV file
+pub(crate) fn read_wav(path: impl AsRef<Path>) -> Result<WavMeta, Error> {
     let mut file = BufReader::new(File::open(path)?);
     let mut offset = 0;
     // Read all the chunks
@@ -169,19 +200,158 @@ pub(crate) fn read_wav(path: impl AsRef<Path>) -> Result<Tag, Error> {
 
     // Read ID3 using the new ID3 reader
     file.seek(SeekFrom::Start(0))?;
-    let mut id3 = Tag::read_from2(&mut file).unwrap_or(Tag::new());
-
-    // Copy tags from RIFF to ID3 if missing
-    for (frame_name, chunk_id) in ID3_RIFF.iter() {
-        if id3.get(frame_name).is_none() {
-            if let Some(chunk) = chunks.iter().find(|c| &c.id() == chunk_id) {
-                let data = chunk.read_contents(&mut file)?;
-                if let Ok(data) = String::from_utf8(data) {
-                    id3.add_frame(Frame::text(frame_name, data.replace("\0", "")));
+    let id3 = Tag::read_from2(&mut file).unwrap_or(Tag::new());
+
+    // INFO & bext
+    let mut info = vec![];
+    let mut bext = None;
+    for chunk in chunks {
+        if chunk.id() == BEXT_ID {
+            bext = Some(Bext { data: chunk.read_contents(&mut file)? });
+            continue;
+        }
+        if !is_info_id(&chunk.id()) {
+            continue;
+        }
+        let data = chunk.read_contents(&mut file)?;
+        let value = String::from_utf8_lossy(&data).trim_end_matches('\0').to_string();
+        if !value.is_empty() {
+            info.push((String::from_utf8_lossy(&chunk.id().value).to_string(), value));
+        }
+    }
+
+    Ok(WavMeta { id3, info, bext })
+}
+
+/// INFO chunk IDs are 4 uppercase alphanumeric characters starting with I
+fn is_info_id(id: &ChunkId) -> bool {
+    id.value[0] == b'I' && id.value.iter().all(|c| c.is_ascii_uppercase() || c.is_ascii_digit())
+}
+
+/// Text fields of bext chunk (name, offset, length)
+const BEXT_TEXT: [(&str, usize, usize); 5] = [
+    ("Description", 0, 256),
+    ("Originator", 256, 32),
+    ("OriginatorReference", 288, 32),
+    ("OriginationDate", 320, 10),
+    ("OriginationTime", 330, 8),
+];
+const BEXT_TIME_REFERENCE: usize = 338;
+const BEXT_VERSION: usize = 346;
+const BEXT_UMID: usize = 348;
+/// Size without the coding history
+const BEXT_SIZE: usize = 602;
+
+/// All the bext fields
+pub const BEXT_FIELDS: [&str; 8] = [
+    "Description", "Originator", "OriginatorReference", "OriginationDate", "OriginationTime",
+    "TimeReference", "UMID", "CodingHistory"
+];
+
+/// Broadcast Wave `bext` chunk (EBU Tech 3285), unknown fields are kept as is
+#[derive(Debug, Clone, PartialEq)]
+pub struct Bext {
+    data: Vec<u8>,
+}
+
+impl Default for Bext {
+    fn default() -> Self {
+        let mut data = vec![0u8; BEXT_SIZE];
+        // Version 1 = has UMID
+        data[BEXT_VERSION] = 1;
+        Bext { data }
+    }
+}
+
+impl Bext {
+    /// Get field by name (see `BEXT_FIELDS`), None if empty
+    pub fn get(&self, field: &str) -> Option<String> {
+        let value = match field {
+            "TimeReference" => match u64::from_le_bytes(self.bytes(BEXT_TIME_REFERENCE, 8).try_into().ok()?) {
+                0 => String::new(),
+                v => v.to_string()
+            },
+            "UMID" => match self.bytes(BEXT_UMID, 64).iter().all(|b| *b == 0) {
+                true => String::new(),
+                false => self.bytes(BEXT_UMID, 64).iter().map(|b| format!("{b:02X}")).collect()
+            },
+            "CodingHistory" => String::from_utf8_lossy(self.data.get(BEXT_SIZE..).unwrap_or_default()).to_string(),
+            _ => {
+                let (_, offset, len) = BEXT_TEXT.iter().find(|(n, _, _)| *n == field)?;
+                String::from_utf8_lossy(&self.bytes(*offset, *len)).to_string()
+            }
+        };
+        let value = value.trim_end_matches('\0').trim_end().to_string();
+        match value.is_empty() {
+            true => None,
+            false => Some(value)
+        }
+    }
+
+    /// Set field by name, empty value clears it
+    pub fn set(&mut self, field: &str, value: &str) {
+        if self.data.len() < BEXT_SIZE {
+            self.data.resize(BEXT_SIZE, 0);
+        }
+        match field {
+            "TimeReference" => {
+                let value = value.trim().parse::<u64>().unwrap_or(0);
+                self.data[BEXT_TIME_REFERENCE..BEXT_TIME_REFERENCE + 8].copy_from_slice(&value.to_le_bytes());
+            },
+            "UMID" => {
+                let value = value.trim();
+                let umid: Vec<u8> = (0..value.len() / 2).filter_map(|i| u8::from_str_radix(value.get(i * 2..i * 2 + 2)?, 16).ok()).collect();
+                if umid.len() * 2 != value.len() || umid.len() > 64 {
+                    warn!("Invalid bext UMID: {value}");
+                    return;
                 }
+                self.write(BEXT_UMID, 64, &umid);
+                if self.data[BEXT_VERSION] == 0 {
+                    self.data[BEXT_VERSION] = 1;
+                }
+            },
+            "CodingHistory" => {
+                self.data.truncate(BEXT_SIZE);
+                self.data.extend(value.as_bytes());
+            },
+            _ => match BEXT_TEXT.iter().find(|(n, _, _)| *n == field) {
+                Some((_, offset, len)) => self.write(*offset, *len, value.as_bytes()),
+                None => warn!("Unknown bext field: {field}"),
             }
         }
     }
 
-    Ok(id3)
-}
\ No newline at end of file
+    fn bytes(&self, offset: usize, len: usize) -> Vec<u8> {
+        let mut out = self.data.get(offset..).unwrap_or_default().iter().take(len).copied().collect::<Vec<_>>();
+        out.resize(len, 0);
+        out
+    }
+
+    /// Write and pad / truncate to length
+    fn write(&mut self, offset: usize, len: usize, value: &[u8]) {
+        let mut value = value.iter().take(len).copied().collect::<Vec<_>>();
+        value.resize(len, 0);
+        self.data[offset..offset + len].copy_from_slice(&value);
+    }
+}
+
+#[test]
+fn bext_fields() {
+    let mut bext = Bext::default();
+    assert_eq!(bext.get("Description"), None);
+    bext.set("Description", "Interview");
+    bext.set("OriginationDate", "2024-01-31");
+    bext.set("TimeReference", "158760000");
+    bext.set("UMID", "060A2B34");
+    bext.set("CodingHistory", "A=PCM,F=48000,W=24,M=stereo\r\n");
+    assert_eq!(bext.get("Description").as_deref(), Some("Interview"));
+    assert_eq!(bext.get("OriginationDate").as_deref(), Some("2024-01-31"));
+    assert_eq!(bext.get("TimeReference").as_deref(), Some("158760000"));
+    assert!(bext.get("UMID").unwrap().starts_with("060A2B34000000"));
+    assert_eq!(bext.get("CodingHistory").as_deref(), Some("A=PCM,F=48000,W=24,M=stereo"));
+    assert_eq!(bext.data.len(), BEXT_SIZE + 29);
+    // Truncated to field length
+    bext.set("Originator", &"x".repeat(40));
+    assert_eq!(bext.get("Originator").unwrap().len(), 32);
+    assert_eq!(bext.get("Description").as_deref(), Some("Interview"));
+}
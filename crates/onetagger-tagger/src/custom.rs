@@ -4,7 +4,7 @@ use log::{Record, Level, RecordBuilder};
 use crate::TrackMatch;
 
 /// Version of supported custom platform
-pub const CUSTOM_PLATFORM_COMPATIBILITY: i32 = 45;
+pub const CUSTOM_PLATFORM_COMPATIBILITY: i32 = 46;
 
 /// Logging from plugins
 #[no_mangle]
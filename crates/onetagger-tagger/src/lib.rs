@@ -7,8 +7,9 @@ use std::any::Any;
 use std::cmp::Ordering;
 use std::ops::Deref;
 use std::path::PathBuf;
+use std::sync::OnceLock;
 use std::time::Duration;
-use chrono::NaiveDate;
+use chrono::{Datelike, NaiveDate};
 use regex::Regex;
 use serde::{Serialize, Deserialize};
 use serde::de::DeserializeOwned;
@@ -23,7 +24,7 @@ const ATTRIBUTES_TO_REMOVE: [&'static str; 23] = ["(intro)", "(clean)", "(intro
     "(radio edit)", "(ck cut)", "(super cut)", "(mega cutz)", "(snip hitz)", "(jd live cut)", "(djcity intro)", "(vdj jd edit)"];
 
 // Re-export
-pub use onetagger_tag::{TagSeparators, FrameName, AudioFileFormat, Field, Lyrics, LyricsLine, LyricsLinePart};
+pub use onetagger_tag::{TagSeparators, FrameName, AudioFileFormat, Field, Lyrics, LyricsLine, LyricsLinePart, AudioProperties, TagDate};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -87,6 +88,30 @@ pub struct TaggerConfig {
     pub album_tagging_ratio: f32,
     /// Renamer template
     pub cover_filename: Option<String>,
+    /// Don't write anything, only report the changes which would be made
+    #[serde(default)]
+    pub dry_run: bool,
+    /// Write dry run changes into a report file in the `runs` folder
+    #[serde(default)]
+    pub dry_run_report: Option<DryRunReportFormat>,
+    /// Cache of platform API responses
+    #[serde(default)]
+    pub http_cache: HttpCacheConfig,
+    /// Write which platform supplied each tag into `1T_PROVENANCE`
+    #[serde(default)]
+    pub write_provenance: bool,
+    /// Per-tag platform priority. Platforms of the list are all queried and the final track is composed field by field
+    #[serde(default)]
+    pub tag_priority: HashMap<SupportedTag, Vec<String>>,
+    /// Weights of the match score components
+    #[serde(default)]
+    pub match_scoring: MatchScoringConfig,
+    /// Put low confidence matches into the review queue
+    #[serde(default)]
+    pub review: ReviewConfig,
+    /// Override the ID3 frame to RIFF INFO mapping of WAV files
+    #[serde(default)]
+    pub wav_info_mapping: Option<HashMap<String, String>>,
 
     /// Platform specific. Format: `{ platform: { custom_option: value }}`
     pub custom: PlatformTaggerConfig,
@@ -110,6 +135,11 @@ impl TaggerConfig {
         tags.iter().any(|t| self.tags.contains(t))
     }
 
+    /// Are tracks composed from all platforms by the tag priority
+    pub fn uses_tag_priority(&self) -> bool {
+        self.tag_priority.values().any(|p| !p.is_empty())
+    }
+
     /// Should this track be overwritten
     pub fn overwrite_tag(&self, tag: SupportedTag) -> bool {
         if self.overwrite {
@@ -169,7 +199,15 @@ impl Default for TaggerConfig {
             fetch_all_results: false,
             album_tagging: false,
             album_tagging_ratio: 0.5,
-            cover_filename: None
+            cover_filename: None,
+            dry_run: false,
+            dry_run_report: None,
+            http_cache: HttpCacheConfig::default(),
+            write_provenance: false,
+            tag_priority: HashMap::new(),
+            match_scoring: MatchScoringConfig::default(),
+            review: ReviewConfig::default(),
+            wav_info_mapping: None,
         }
     }
 }
@@ -207,6 +245,77 @@ pub struct SpotifyConfig {
 }
 
 
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum DryRunReportFormat {
+    Json, Csv
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HttpCacheConfig {
+    pub enabled: bool,
+    /// How long are responses valid, in seconds
+    pub ttl: u64,
+    /// Max size of the cache in MB
+    pub max_size: u64,
+}
+
+impl Default for HttpCacheConfig {
+    fn default() -> Self {
+        Self { enabled: false, ttl: 60 * 60 * 24 * 7, max_size: 512 }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MatchScoringConfig {
+    /// Use the weighted score as accuracy, otherwise only the title similarity is used
+    pub enabled: bool,
+    pub title: f64,
+    pub artists: f64,
+    pub remixers: f64,
+    pub version: f64,
+    pub duration: f64,
+    pub album: f64,
+    pub label: f64,
+    pub release_year: f64,
+    pub isrc: f64,
+}
+
+impl Default for MatchScoringConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            title: 1.0,
+            artists: 0.5,
+            remixers: 0.2,
+            version: 0.3,
+            duration: 0.3,
+            album: 0.2,
+            label: 0.1,
+            release_year: 0.1,
+            isrc: 1.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReviewConfig {
+    pub enabled: bool,
+    /// Fuzzy matches with lower accuracy are not written, but added to the review queue
+    pub threshold: f64,
+    /// How many candidates to keep per file
+    pub candidates: usize,
+}
+
+impl Default for ReviewConfig {
+    fn default() -> Self {
+        Self { enabled: false, threshold: 0.9, candidates: 5 }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub enum MultipleMatchesSort {
     Default,
@@ -328,6 +437,107 @@ impl Track {
 
         self
     }
+
+    /// Does the track have a value for the tag
+    pub fn has_tag(&self, tag: SupportedTag) -> bool {
+        match tag {
+            SupportedTag::Title => !self.title.is_empty(),
+            SupportedTag::Version => self.version.is_some(),
+            SupportedTag::Artist => !self.artists.is_empty(),
+            SupportedTag::AlbumArtist => !self.album_artists.is_empty(),
+            SupportedTag::Album => self.album.is_some(),
+            SupportedTag::Key => self.key.is_some(),
+            SupportedTag::BPM => self.bpm.is_some(),
+            SupportedTag::Label => self.label.is_some(),
+            SupportedTag::Genre => !self.genres.is_empty(),
+            SupportedTag::Style => !self.styles.is_empty(),
+            SupportedTag::ReleaseDate => self.release_date.is_some() || self.release_year.is_some(),
+            SupportedTag::PublishDate => self.publish_date.is_some() || self.publish_year.is_some(),
+            SupportedTag::URL => !self.url.is_empty(),
+            SupportedTag::OtherTags => !self.other.is_empty(),
+            SupportedTag::TrackId => self.track_id.is_some(),
+            SupportedTag::ReleaseId => self.release_id.is_some(),
+            SupportedTag::CatalogNumber => self.catalog_number.is_some(),
+            SupportedTag::Duration => self.duration.as_secs() > 0,
+            SupportedTag::Remixer => !self.remixers.is_empty(),
+            SupportedTag::ISRC => self.isrc.is_some(),
+            SupportedTag::Mood => self.mood.is_some(),
+            SupportedTag::DiscNumber => self.disc_number.is_some(),
+            SupportedTag::TrackNumber => self.track_number.is_some(),
+            SupportedTag::TrackTotal => self.track_number.is_some() && self.track_total.is_some(),
+            SupportedTag::SyncedLyrics | SupportedTag::UnsyncedLyrics => self.lyrics.is_some(),
+            SupportedTag::Explicit => self.explicit.is_some(),
+            SupportedTag::AlbumArt => self.art.is_some(),
+            SupportedTag::MetaTags => false,
+        }
+    }
+
+    /// Replace the value of tag with the one from other track
+    pub fn set_tag_from(&mut self, other: &Track, tag: SupportedTag) {
+        match tag {
+            SupportedTag::Title => self.title = other.title.clone(),
+            SupportedTag::Version => self.version = other.version.clone(),
+            SupportedTag::Artist => self.artists = other.artists.clone(),
+            SupportedTag::AlbumArtist => self.album_artists = other.album_artists.clone(),
+            SupportedTag::Album => self.album = other.album.clone(),
+            SupportedTag::Key => self.key = other.key.clone(),
+            SupportedTag::BPM => self.bpm = other.bpm,
+            SupportedTag::Label => self.label = other.label.clone(),
+            SupportedTag::Genre => self.genres = other.genres.clone(),
+            SupportedTag::Style => self.styles = other.styles.clone(),
+            SupportedTag::ReleaseDate => {
+                self.release_date = other.release_date;
+                self.release_year = other.release_year;
+            },
+            SupportedTag::PublishDate => {
+                self.publish_date = other.publish_date;
+                self.publish_year = other.publish_year;
+            },
+            SupportedTag::URL => self.url = other.url.clone(),
+            SupportedTag::OtherTags => self.other = other.other.clone(),
+            SupportedTag::CatalogNumber => self.catalog_number = other.catalog_number.clone(),
+            SupportedTag::Duration => self.duration = other.duration,
+            SupportedTag::Remixer => self.remixers = other.remixers.clone(),
+            SupportedTag::ISRC => self.isrc = other.isrc.clone(),
+            SupportedTag::Mood => self.mood = other.mood.clone(),
+            SupportedTag::DiscNumber => self.disc_number = other.disc_number,
+            SupportedTag::TrackNumber => self.track_number = other.track_number.clone(),
+            SupportedTag::TrackTotal => self.track_total = other.track_total,
+            SupportedTag::SyncedLyrics | SupportedTag::UnsyncedLyrics => self.lyrics = other.lyrics.clone(),
+            SupportedTag::Explicit => self.explicit = other.explicit,
+            SupportedTag::AlbumArt => {
+                self.art = other.art.clone();
+                self.thumbnail = other.thumbnail.clone();
+            },
+            // IDs are written under the name of the track's platform
+            SupportedTag::TrackId | SupportedTag::ReleaseId | SupportedTag::MetaTags => {},
+        }
+    }
+
+    /// Compose track from tracks of multiple platforms (in platform order). Tags in `priority` are taken from the first listed platform
+    /// which has them, other tags are merged like in `merge`. Returns the track and index of the source track of each of `tags`
+    pub fn compose(tracks: &[Track], tags: &[SupportedTag], priority: &HashMap<SupportedTag, Vec<String>>) -> Option<(Track, HashMap<SupportedTag, usize>)> {
+        let mut track = tracks.first()?.clone();
+        for t in &tracks[1..] {
+            track = track.merge(t.clone());
+        }
+
+        let mut sources = HashMap::new();
+        for tag in tags {
+            let prioritized = priority.get(tag).and_then(|platforms| platforms.iter()
+                .find_map(|p| tracks.iter().position(|t| &t.platform == p && t.has_tag(*tag))));
+            match prioritized {
+                Some(i) => {
+                    track.set_tag_from(&tracks[i], *tag);
+                    sources.insert(*tag, i);
+                },
+                None => if let Some(i) = tracks.iter().position(|t| t.has_tag(*tag)) {
+                    sources.insert(*tag, i);
+                }
+            }
+        }
+        Some((track, sources))
+    }
 }
 
 
@@ -337,23 +547,35 @@ impl Track {
 pub struct TrackMatch {
     pub accuracy: f64,
     pub track: Track,
-    pub reason: MatchReason
+    pub reason: MatchReason,
+    /// Breakdown of the accuracy
+    #[serde(default)]
+    pub score: Option<MatchScore>,
 }
 
 impl TrackMatch {
     /// Create new instance
     pub fn new(accuracy: f64, track: Track) -> TrackMatch {
-        TrackMatch { accuracy, track, reason: MatchReason::Fuzzy }
+        TrackMatch { accuracy, track, reason: MatchReason::Fuzzy, score: None }
     }
 
     /// Create new ISRC matched match
     pub fn new_isrc(track: Track) -> TrackMatch {
-        TrackMatch { accuracy: 1.0, track, reason: MatchReason::ISRC }
+        TrackMatch { accuracy: 1.0, track, reason: MatchReason::ISRC, score: None }
     }
 
     /// Create new ID matched match
     pub fn new_id(track: Track) -> TrackMatch {
-        TrackMatch { accuracy: 1.0, track, reason: MatchReason::ID }
+        TrackMatch { accuracy: 1.0, track, reason: MatchReason::ID, score: None }
+    }
+
+    /// Attach score breakdown. If scoring is enabled, fuzzy matches use the weighted total as accuracy
+    pub fn scored(mut self, score: MatchScore, config: &TaggerConfig) -> TrackMatch {
+        if config.match_scoring.enabled && self.reason == MatchReason::Fuzzy {
+            self.accuracy = score.total;
+        }
+        self.score = Some(score);
+        self
     }
 }
 
@@ -375,6 +597,80 @@ pub enum MatchReason {
     Album
 }
 
+/// Similarity of each component of the match (0 - 1), None if it couldn't be compared
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+#[repr(C)]
+pub struct MatchScore {
+    pub title: Option<f64>,
+    pub artists: Option<f64>,
+    pub remixers: Option<f64>,
+    pub version: Option<f64>,
+    pub duration: Option<f64>,
+    pub album: Option<f64>,
+    pub label: Option<f64>,
+    pub release_year: Option<f64>,
+    pub isrc: Option<f64>,
+    /// Weighted average of the available components
+    pub total: f64,
+}
+
+impl MatchScore {
+    /// Get (name, score, weight) of all components
+    pub fn components(&self, config: &MatchScoringConfig) -> Vec<(&'static str, Option<f64>, f64)> {
+        vec![
+            ("title", self.title, config.title),
+            ("artists", self.artists, config.artists),
+            ("remixers", self.remixers, config.remixers),
+            ("version", self.version, config.version),
+            ("duration", self.duration, config.duration),
+            ("album", self.album, config.album),
+            ("label", self.label, config.label),
+            ("releaseYear", self.release_year, config.release_year),
+            ("isrc", self.isrc, config.isrc),
+        ]
+    }
+
+    /// Calculate the weighted total
+    fn calculate_total(&mut self, config: &MatchScoringConfig) {
+        let (sum, weights) = self.components(config).into_iter()
+            .filter_map(|(_, score, weight)| score.filter(|_| weight > 0.0).map(|s| (s * weight, weight)))
+            .fold((0.0, 0.0), |(sum, weights), (s, w)| (sum + s, weights + w));
+        self.total = match weights > 0.0 {
+            true => sum / weights,
+            false => 0.0
+        };
+    }
+}
+
+/// Raw tag with the provenance JSON
+pub const PROVENANCE_TAG: &str = "1T_PROVENANCE";
+
+/// Which platform and match supplied a tag
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct TagProvenance {
+    pub platform: String,
+    pub track_id: Option<String>,
+    pub release_id: Option<String>,
+    pub accuracy: f64,
+    pub reason: MatchReason,
+    /// When was the tag written, `%Y-%m-%d %H:%M:%S` like `1T_TAGGEDDATE`
+    pub timestamp: String,
+}
+
+impl TagProvenance {
+    /// Parse provenance of all tags from raw tag value, invalid value = empty
+    pub fn parse(value: &[String]) -> HashMap<SupportedTag, TagProvenance> {
+        value.first().map(|v| serde_json::from_str(v).ok()).flatten().unwrap_or_default()
+    }
+
+    /// Serialize provenance of all tags into raw tag value
+    pub fn serialize(provenance: &HashMap<SupportedTag, TagProvenance>) -> Vec<String> {
+        vec![serde_json::to_string(provenance).unwrap_or_default()]
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 #[repr(C)]
 #[serde(untagged)]
@@ -587,7 +883,19 @@ pub struct AudioFileInfo {
     pub duration: Option<Duration>,
     pub track_number: Option<u16>,
     pub tagged: FileTaggedStatus,
-    pub tags: HashMap<String, Vec<String>>
+    pub tags: HashMap<String, Vec<String>>,
+    /// Parsed `1T_PROVENANCE` tag
+    #[serde(default)]
+    pub provenance: HashMap<SupportedTag, TagProvenance>,
+    /// Bitrate, sample rate etc, loaded only when needed
+    #[serde(default)]
+    pub properties: Option<AudioProperties>,
+    /// Rating read together with the tags
+    #[serde(default)]
+    pub rating: Option<u8>,
+    /// Date read together with the tags
+    #[serde(default)]
+    pub date: Option<TagDate>,
 }
 
 impl AudioFileInfo {
@@ -600,6 +908,28 @@ impl AudioFileInfo {
         Ok(self.title.as_ref().unwrap().as_str())
     }
 
+    /// Get values of field from the raw tags
+    pub fn tag_values(&self, field: Field) -> Vec<String> {
+        let name = field.by_format(&self.format);
+        self.tags.iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.iter().map(|v| v.trim().to_string()).filter(|v| !v.is_empty()).collect())
+            .unwrap_or_default()
+    }
+
+    /// Get first value of field from the raw tags
+    pub fn tag_value(&self, field: Field) -> Option<String> {
+        self.tag_values(field).into_iter().next()
+    }
+
+    /// Get release year from the raw tags
+    pub fn year(&self) -> Option<i16> {
+        ["DATE", "YEAR", "TDRC", "TYER", "©day"].iter()
+            .find_map(|k| self.tags.iter().find(|(t, _)| t.eq_ignore_ascii_case(k)))
+            .and_then(|(_, v)| v.first())
+            .and_then(|v| v.trim().get(0..4).and_then(|y| y.parse().ok()))
+    }
+
     /// Get first artist (or error shorthand)
     pub fn artist(&self) -> Result<&str, Error> {
         if self.artists.is_empty() {
@@ -728,7 +1058,7 @@ pub struct PlatformInfo {
 }
 
 /// All the different tags a platform can support
-#[derive(Debug, Clone, Serialize, Deserialize, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Serialize, Deserialize, Copy, PartialEq, Eq, Hash)]
 #[serde(rename_all = "camelCase")]
 #[repr(C)]
 pub enum SupportedTag {
@@ -1035,7 +1365,7 @@ impl MatchingUtils {
         let mut output = vec![];
         output.extend(
             MatchingUtils::match_track_exact_fallback(info, tracks, config, match_artist)
-            .into_iter().map(|t| TrackMatch::new(1.0, t))
+            .into_iter().map(|t| MatchingUtils::score_match(info, TrackMatch::new(1.0, t), config))
         );
         if !config.fetch_all_results && !output.is_empty() {
             return output;
@@ -1048,8 +1378,8 @@ impl MatchingUtils {
         };
         let clean_title = MatchingUtils::clean_title_matching(clean_title);
 
-        // Fuzzy match - value, track
-        let mut fuzz: Vec<(f64, &Track)> = vec![];
+        // Fuzzy match
+        let mut fuzz: Vec<TrackMatch> = vec![];
         for track in tracks {
             // Artist
             if match_artist {
@@ -1060,19 +1390,90 @@ impl MatchingUtils {
             // Match title
             let clean = MatchingUtils::clean_title_matching(&track.full_title());
             let l = normalized_levenshtein(&clean, &clean_title);
-            if l >= config.strictness {
-                fuzz.push((l, track));
+            let m = MatchingUtils::score_match(info, TrackMatch::new(l, track.to_owned()), config);
+            if m.accuracy >= config.strictness {
+                fuzz.push(m);
             }
         }
-        // Empty array
-        if fuzz.is_empty() {
-            return output;
-        }
 
-        output.extend(fuzz.into_iter().map(|(acc, track)| TrackMatch::new(acc, track.to_owned())));
+        output.extend(fuzz);
         output
     }
 
+    /// Attach score breakdown to the match, total is used for ranking only if scoring is enabled
+    pub fn score_match(info: &AudioFileInfo, m: TrackMatch, config: &TaggerConfig) -> TrackMatch {
+        let score = MatchingUtils::score_track(info, &m.track, config);
+        m.scored(score, config)
+    }
+
+    /// Compare each component of the track with the file and calculate weighted score
+    pub fn score_track(info: &AudioFileInfo, track: &Track, config: &TaggerConfig) -> MatchScore {
+        // Compare strings after cleanup
+        let similarity = |a: &str, b: &str| normalized_levenshtein(&Self::remove_special(&a.to_lowercase()), &Self::remove_special(&b.to_lowercase()));
+        // Version in brackets at the end of title
+        let title_version = |title: &str| -> Option<String> {
+            static RE: OnceLock<Regex> = OnceLock::new();
+            let re = RE.get_or_init(|| Regex::new(r"[\(\[]([^\(\[]+)[\)\]]\s*$").unwrap());
+            re.captures(title).map(|c| c[1].trim().to_string())
+        };
+
+        let mut score = MatchScore::default();
+        if let Some(title) = info.title.as_ref() {
+            score.title = Some(normalized_levenshtein(&Self::clean_title_matching(&track.full_title()), &Self::clean_title_matching(title)));
+        }
+        if !info.artists.is_empty() && !track.artists.is_empty() {
+            score.artists = Some(Self::artists_similarity(&info.artists, &track.artists));
+        }
+        let remixers = info.tag_values(Field::Remixer);
+        if !remixers.is_empty() && !track.remixers.is_empty() {
+            score.remixers = Some(Self::artists_similarity(&remixers, &track.remixers));
+        }
+
+        // Missing version = original
+        let version = info.tag_value(Field::Version).or(info.title.as_ref().and_then(|t| title_version(t)));
+        let track_version = track.version.clone().filter(|v| !v.trim().is_empty()).or(title_version(&track.title));
+        if version.is_some() || track_version.is_some() {
+            let original = "original mix".to_string();
+            score.version = Some(similarity(&version.unwrap_or(original.clone()), &track_version.unwrap_or(original)));
+        }
+
+        if let Some(duration) = info.duration.filter(|d| *d != Duration::ZERO && track.duration != Duration::ZERO) {
+            let diff = (duration.as_secs() as i64 - track.duration.as_secs() as i64).abs() as f64;
+            score.duration = Some((1.0 - diff / config.max_duration_difference.max(1) as f64).max(0.0));
+        }
+        if let (Some(a), Some(b)) = (info.tag_value(Field::Album), track.album.as_ref()) {
+            score.album = Some(similarity(&a, b));
+        }
+        if let (Some(a), Some(b)) = (info.tag_value(Field::Label), track.label.as_ref()) {
+            score.label = Some(similarity(&a, b));
+        }
+        let track_year = track.release_year.or(track.release_date.map(|d| d.year() as i16));
+        if let (Some(a), Some(b)) = (info.year(), track_year) {
+            score.release_year = Some(match (a - b).abs() {
+                0 => 1.0,
+                1 => 0.5,
+                _ => 0.0
+            });
+        }
+        if let (Some(a), Some(b)) = (info.isrc.as_ref(), track.isrc.as_ref()) {
+            score.isrc = Some(if a.trim().eq_ignore_ascii_case(b.trim()) { 1.0 } else { 0.0 });
+        }
+
+        score.calculate_total(&config.match_scoring);
+        score
+    }
+
+    /// Ratio of matching artists
+    fn artists_similarity(a: &Vec<String>, b: &Vec<String>) -> f64 {
+        let clean_a = Self::clean_artists(a);
+        let clean_b = Self::clean_artists(b);
+        let matched = clean_a.iter().filter(|a| clean_b.iter().any(|b| b == *a || b.contains(a.as_str()) || a.contains(b.as_str()))).count();
+        if matched == 0 {
+            return normalized_levenshtein(&clean_a.join(" "), &clean_b.join(" "));
+        }
+        matched as f64 / clean_a.len().max(clean_b.len()) as f64
+    }
+
     /// Sort matched tracks by accuracy or release dates
     pub fn sort_tracks(tracks: &mut Vec<TrackMatch>, config: &TaggerConfig) {
         match config.multiple_matches {
@@ -1126,3 +1527,80 @@ impl MatchingUtils {
         Ok(Duration::from_secs(seconds))
     }
 }
+
+/// Provenance roundtrip through the raw tag value
+#[test]
+fn tag_provenance_serialize() {
+    let provenance = HashMap::from([(SupportedTag::BPM, TagProvenance {
+        platform: "beatport".to_string(),
+        track_id: Some("1".to_string()),
+        release_id: None,
+        accuracy: 0.9,
+        reason: MatchReason::Fuzzy,
+        timestamp: "2024-01-01 00:00:00".to_string(),
+    })]);
+    let raw = TagProvenance::serialize(&provenance);
+    assert!(raw[0].contains("\"bpm\""));
+    assert_eq!(TagProvenance::parse(&raw), provenance);
+    assert!(TagProvenance::parse(&["invalid".to_string()]).is_empty());
+}
+
+/// Compose track by per-tag platform priority
+#[test]
+fn track_compose_priority() {
+    let beatport = Track { platform: "beatport".to_string(), bpm: Some(128), label: Some("BP Label".to_string()), ..Default::default() };
+    let discogs = Track { platform: "discogs".to_string(), bpm: Some(127), label: Some("Discogs Label".to_string()), catalog_number: Some("CAT1".to_string()), ..Default::default() };
+    let priority = HashMap::from([(SupportedTag::Label, vec!["discogs".to_string(), "beatport".to_string()])]);
+    let tags = vec![SupportedTag::BPM, SupportedTag::Label, SupportedTag::CatalogNumber, SupportedTag::ISRC];
+    let (track, sources) = Track::compose(&[beatport, discogs], &tags, &priority).unwrap();
+    assert_eq!(track.bpm, Some(128));
+    assert_eq!(track.label.as_deref(), Some("Discogs Label"));
+    assert_eq!(track.catalog_number.as_deref(), Some("CAT1"));
+    assert_eq!(sources, HashMap::from([(SupportedTag::BPM, 0), (SupportedTag::Label, 1), (SupportedTag::CatalogNumber, 1)]));
+    assert!(Track::compose(&[], &tags, &priority).is_none());
+}
+
+/// Weighted score ignores components which can't be compared
+#[test]
+fn match_score_breakdown() {
+    let info = AudioFileInfo {
+        title: Some("Song (Extended Mix)".to_string()),
+        artists: vec!["Artist A".to_string(), "Artist B".to_string()],
+        format: AudioFileFormat::FLAC,
+        path: PathBuf::from("song.flac"),
+        isrc: Some("USABC1234567".to_string()),
+        duration: None,
+        track_number: None,
+        tagged: FileTaggedStatus::Untagged,
+        tags: HashMap::from([("date".to_string(), vec!["2020-01-01".to_string()])]),
+        provenance: HashMap::new(),
+        properties: None,
+        rating: None,
+        date: None,
+    };
+    let track = Track {
+        title: "Song".to_string(),
+        version: Some("Extended Mix".to_string()),
+        artists: vec!["Artist A".to_string()],
+        isrc: Some("usabc1234567".to_string()),
+        release_year: Some(2019),
+        ..Default::default()
+    };
+    let score = MatchingUtils::score_track(&info, &track, &TaggerConfig::default());
+    assert_eq!(score.title, Some(1.0));
+    assert_eq!(score.artists, Some(0.5));
+    assert_eq!(score.version, Some(1.0));
+    assert_eq!(score.release_year, Some(0.5));
+    assert_eq!(score.isrc, Some(1.0));
+    assert!(score.album.is_none() && score.duration.is_none());
+    // (1.0 + 0.5 * 0.5 + 0.3 + 0.5 * 0.1 + 1.0) / (1.0 + 0.5 + 0.3 + 0.1 + 1.0)
+    assert!((score.total - 2.6 / 2.9).abs() < 1e-9);
+
+    // Breakdown is always attached, total drives ranking only when enabled
+    let mut config = TaggerConfig::default();
+    let m = &MatchingUtils::match_track(&info, &vec![track.clone()], &config, true)[0];
+    assert!(m.score.is_some());
+    assert_ne!(m.accuracy, m.score.as_ref().unwrap().total);
+    config.match_scoring.enabled = true;
+    assert!(MatchingUtils::match_track(&info, &vec![track], &config, true)[0].score.is_some());
+}
@@ -6,7 +6,7 @@ use walkdir::WalkDir;
 use dunce::canonicalize;
 
 use onetagger_tag::EXTENSIONS;
-use onetagger_playlist::{PLAYLIST_EXTENSIONS, get_files_from_playlist_file};
+use onetagger_playlist::{has_playlist_extension, get_files_from_playlist_file};
 
 pub struct FileBrowser {
     playlists: bool,
@@ -92,7 +92,7 @@ impl FileBrowser {
         // Filter extensions
         if !dir {
             // Playlist
-            if self.playlists && PLAYLIST_EXTENSIONS.iter().any(|e| filename.to_lowercase().ends_with(e)) {
+            if self.playlists && has_playlist_extension(&path) {
                 playlist = true;
             } else {
                 // Music files
@@ -8,6 +8,7 @@ use walkdir::WalkDir;
 use image::{ImageFormat, ImageReader};
 use serde::{Deserialize, Serialize};
 use onetagger_tag::{AudioFileFormat, Field, Tag, EXTENSIONS, TagSeparators};
+use onetagger_tag::serato::SeratoMarkers;
 use onetagger_playlist::{UIPlaylist, get_files_from_playlist_file};
 
 pub struct QuickTag {}
@@ -113,7 +114,8 @@ pub struct QuickTagFile {
     rating: u8,
     tags: HashMap<String, Vec<String>>,
     year: Option<i32>,
-    key: Option<String>
+    key: Option<String>,
+    serato: Option<SeratoMarkers>,
 }
 
 impl QuickTagFile {
@@ -136,8 +138,8 @@ impl QuickTagFile {
             all_tags.insert("USLT".to_string(), v);
         }
 
-        // Filter null bytes
-        let all_tags = all_tags.into_iter().map(|(k, v)| (k, v.into_iter().map(|v| v.replace("\0", "")).collect::<Vec<_>>())).collect();
+        // Filter null bytes and Serato binary data
+        let all_tags = all_tags.into_iter().filter(|(k, _)| !SeratoMarkers::is_serato_tag(k)).map(|(k, v)| (k, v.into_iter().map(|v| v.replace("\0", "")).collect::<Vec<_>>())).collect();
 
         Ok(QuickTagFile {
             path: path.as_ref().to_owned(),
@@ -152,7 +154,8 @@ impl QuickTagFile {
             },
             tags: all_tags,
             year: tag.get_date().map(|d| d.year),
-            key: tag.get_field(Field::Key).map(|f| f.first().map(String::from)).flatten()
+            key: tag.get_field(Field::Key).map(|f| f.first().map(String::from)).flatten(),
+            serato: SeratoMarkers::read(tag_wrap),
         })
     }
 
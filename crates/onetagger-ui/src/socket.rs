@@ -6,7 +6,7 @@ use std::sync::{Arc, Mutex};
 use std::path::{Path, PathBuf};
 use onetagger_renamer::ac::Autocomplete;
 use onetagger_renamer::docs::FullDocs;
-use onetagger_renamer::{Renamer, TemplateParser, RenamerConfig};
+use onetagger_renamer::{Renamer, TemplateParser, RenamerConfig, RenameLog, FilenameParser, FilenameTagsConfig};
 use serde_json::{Value, json};
 use serde::{Serialize, Deserialize};
 use dunce::canonicalize;
@@ -14,10 +14,14 @@ use onetagger_tag::{TagChanges, TagSeparators, Tag, Field};
 use onetagger_tagger::{TaggerConfig, AudioFileInfo, TrackMatch};
 use onetagger_autotag::{Tagger, AudioFileInfoImpl, TaggerConfigExt, AUTOTAGGER_PLATFORMS};
 use onetagger_autotag::audiofeatures::{AudioFeaturesConfig, AudioFeatures};
+use onetagger_autotag::journal::UndoJournal;
+use onetagger_autotag::review::ReviewQueue;
+use onetagger_autotag::duplicates::{Duplicates, DuplicatesConfig, DuplicateGroup};
+use onetagger_autotag::replaygain::{ReplayGain, ReplayGainConfig};
 use onetagger_platforms::spotify::Spotify;
 use onetagger_player::{AudioSources, AudioPlayer};
 use onetagger_shared::{Settings, COMMIT};
-use onetagger_playlist::{UIPlaylist, PLAYLIST_EXTENSIONS, get_files_from_playlist_file};
+use onetagger_playlist::{UIPlaylist, PlaylistFormat, has_playlist_extension, get_files_from_playlist_file};
 
 use crate::StartContext;
 use crate::quicktag::{QuickTag, QuickTagFile, QuickTagData};
@@ -40,11 +44,12 @@ enum Action {
     OpenFile { path: PathBuf },
     DeleteFiles { paths: Vec<String> },
     GetLog,
-    GeneratePlaylist { paths: Vec<String> },
+    GeneratePlaylist { paths: Vec<String>, format: Option<PlaylistFormat> },
 
     LoadPlatforms,
     StartTagging { config: TaggerConfigs, playlist: Option<UIPlaylist> },
     StopTagging,
+    UndoTagging { journal: String, paths: Option<Vec<PathBuf>> },
     ConfigCallback { config: Value, platform: String, id: String },
     RepoManifest,
     #[serde(rename_all = "camelCase")]
@@ -73,12 +78,25 @@ enum Action {
     RenamerSyntaxHighlight { template: String },
     RenamerAutocomplete { template: String },
     RenamerPreview { config: RenamerConfig },
+    RenamerCollisions { config: RenamerConfig },
     RenamerStart { config: RenamerConfig },
+    RenamerUndo { log: String },
+    FilenameTagsPreview { config: FilenameTagsConfig },
+    FilenameTagsWrite { config: FilenameTagsConfig },
 
     FolderBrowser { path: PathBuf, child: String, base: bool },
 
     ManualTag { config: TaggerConfig, path: PathBuf },
     ManualTagApply { matches: Vec<TrackMatch>, path: PathBuf, config: TaggerConfig },
+
+    ReviewQueue,
+    ReviewAccept { path: PathBuf, candidate: usize, config: TaggerConfig },
+    ReviewSkip { path: PathBuf },
+
+    FindDuplicates { config: DuplicatesConfig },
+    TrashDuplicates { groups: Vec<DuplicateGroup> },
+
+    ReplayGain { config: ReplayGainConfig },
 }
 
 
@@ -211,6 +229,27 @@ async fn send_socket_inner<D: Serialize>(ws: &mut WebSocket, json: D) -> Result<
     Ok(())
 }
 
+/// Send result of resolving review queue item
+async fn send_review_resolved(ws: &mut WebSocket, result: Result<(), Error>) {
+    let json = match result {
+        Ok(_) => json!({ "action": "reviewResolved", "status": "ok" }),
+        Err(e) => {
+            error!("Failed resolving review: {e}");
+            json!({ "action": "reviewResolved", "status": "error", "error": e.to_string() })
+        }
+    };
+    send_socket(ws, json).await.ok();
+}
+
+/// Use the Auto Tagger RIFF INFO mapping for all loaded WAV files (Tag Editor, Quick Tag, Renamer)
+fn apply_wav_info_mapping(settings: &Settings) {
+    let mapping = settings.ui.get("autoTaggerConfig")
+        .and_then(|c| c.get("wavInfoMapping"))
+        .and_then(|m| serde_json::from_value::<HashMap<String, String>>(m.clone()).ok())
+        .map(|m| m.into_iter().collect());
+    onetagger_tag::id3::set_default_info_mapping(mapping);
+}
+
 async fn handle_message(text: &str, websocket: &mut WebSocket, context: &mut SocketContext) -> Result<(), Error> {
     // Parse JSON
     let action: Action = serde_json::from_str(text)?;
@@ -220,9 +259,14 @@ async fn handle_message(text: &str, websocket: &mut WebSocket, context: &mut Soc
             send_socket(websocket, InitData::new(context.start_context.clone())).await.ok();
         },
         Action::Exit => std::process::exit(0),
-        Action::SaveSettings { settings } => Settings::from_ui(&settings).save()?,
+        Action::SaveSettings { settings } => {
+            let settings = Settings::from_ui(&settings);
+            apply_wav_info_mapping(&settings);
+            settings.save()?;
+        },
         Action::LoadSettings => match Settings::load() {
             Ok(settings) => {
+                apply_wav_info_mapping(&settings);
                 send_socket(websocket, json!({
                     "action": "loadSettings",
                     "settings": settings.ui
@@ -268,15 +312,27 @@ async fn handle_message(text: &str, websocket: &mut WebSocket, context: &mut Soc
         Action::OpenFile { path } => { opener::open(&path).ok(); },
         Action::DeleteFiles { paths } => { trash::delete_all(&paths)?; },
 
-        Action::GeneratePlaylist { paths } => {
-            let playlist = onetagger_playlist::create_m3u_playlist(&paths.into_iter().map(|i| i.into()).collect::<Vec<_>>());
-            if let Some(path) = tinyfiledialogs::save_file_dialog_with_filter(
+        Action::GeneratePlaylist { paths, format } => {
+            // Limit dialog to the requested format
+            let (filter, description) = match &format {
+                Some(format) => (format.extensions(), format!("Save playlist ({})", format.extensions().join(", "))),
+                None => (&["m3u", "m3u8", "pls", "xspf", "xml", "nml"][..], "Save playlist (M3U, PLS, XSPF, Rekordbox XML, Traktor NML)".to_string())
+            };
+            if let Some(mut path) = tinyfiledialogs::save_file_dialog_with_filter(
                 "Save playlist", 
                 &std::env::current_dir()?.to_string_lossy().to_string(), 
-                &["m3u", "m3u8"], 
-                "Save playlist"
+                filter, 
+                &description
             ) {
-                std::fs::write(&path, playlist)?;
+                // Add missing extension of the requested format
+                if let Some(format) = format {
+                    if PlaylistFormat::from_extension(&path).as_ref() != Some(&format) {
+                        path = format!("{path}.{}", format.extensions()[0]);
+                    }
+                }
+                let files = paths.into_iter().map(PathBuf::from).collect::<Vec<_>>();
+                let output = path.clone();
+                tokio::task::spawn_blocking(move || onetagger_playlist::export_playlist(&files, &output)).await??;
                 send_socket(websocket, json!({
                     "action": "notify",
                     "message": format!("Playlist saved to: {path}")
@@ -374,6 +430,21 @@ async fn handle_message(text: &str, websocket: &mut WebSocket, context: &mut Soc
         Action::StopTagging => {
             onetagger_autotag::STOP_TAGGING.store(true, Ordering::SeqCst);
         },
+        // Restore original tags from journal
+        Action::UndoTagging { journal, paths } => {
+            let results = tokio::task::spawn_blocking(move || {
+                UndoJournal::restore(&journal, paths.as_deref())
+            }).await??;
+            let failed = results.iter().filter_map(|(path, r)| r.as_ref().err().map(|e| json!({
+                "path": path,
+                "error": e.to_string()
+            }))).collect::<Vec<_>>();
+            send_socket(websocket, json!({
+                "action": "undoTagging",
+                "restored": results.len() - failed.len(),
+                "failed": failed
+            })).await.ok();
+        },
         Action::Waveform { path } => {
             let source = AudioSources::from_path(&path)?;
             let (waveform_rx, cancel_tx) = source.generate_waveform(180)?;
@@ -430,7 +501,7 @@ async fn handle_message(text: &str, websocket: &mut WebSocket, context: &mut Soc
             }
             // Path
             if let Some(path) = path {
-                if PLAYLIST_EXTENSIONS.iter().any(|e| path.to_lowercase().ends_with(e)) {
+                if has_playlist_extension(&path) {
                     data = QuickTag::load_files(get_files_from_playlist_file(&path)?, &separators)?;
                 } else {
                     data = QuickTag::load_files_path(
@@ -538,22 +609,84 @@ async fn handle_message(text: &str, websocket: &mut WebSocket, context: &mut Soc
         },
         // Generate new names but don't rename
         Action::RenamerPreview { config } => {
-            let mut renamer = Renamer::new(TemplateParser::parse(&config.template));
-            let files = AudioFileInfo::load_files_iter(&config.path, config.subfolders, None, None);
-            let files = renamer.generate(files.take(3), &config).unwrap_or(vec![]);
+            let (files, companions) = tokio::task::spawn_blocking(move || {
+                let mut renamer = Renamer::new(TemplateParser::parse(&config.template));
+                let files = AudioFileInfo::load_files_iter(&config.path, config.subfolders, None, None);
+                let files = renamer.generate(files.take(3), &config).unwrap_or(vec![]);
+                let companions = config.companions.resolve(&files);
+                (files, companions)
+            }).await?;
             send_socket(websocket, json!({
                 "action": "renamerPreview",
                 "files": files,
+                "companions": companions,
+            })).await.ok();
+        },
+        // Check all files for collisions
+        Action::RenamerCollisions { config } => {
+            let collisions = tokio::task::spawn_blocking(move || -> Result<_, Error> {
+                let mut renamer = Renamer::new(TemplateParser::parse(&config.template));
+                let files = AudioFileInfo::load_files_iter(&config.path, config.subfolders, None, None);
+                let files = renamer.generate(files, &config)?;
+                Ok(Renamer::plan(&files, &config).0.collisions)
+            }).await??;
+            send_socket(websocket, json!({
+                "action": "renamerCollisions",
+                "collisions": collisions,
             })).await.ok();
         },
         // Start renamer
         Action::RenamerStart { config } => {
-            let mut renamer = Renamer::new(TemplateParser::parse(&config.template));
-            let files = AudioFileInfo::load_files_iter(&config.path, config.subfolders, None, None);
-            let files = renamer.generate(files, &config)?;
-            renamer.rename(&files, &config)?;
+            let report = tokio::task::spawn_blocking(move || -> Result<_, Error> {
+                let mut renamer = Renamer::new(TemplateParser::parse(&config.template));
+                let files = AudioFileInfo::load_files_iter(&config.path, config.subfolders, None, None);
+                let files = renamer.generate(files, &config)?;
+                renamer.rename(&files, &config)
+            }).await??;
             send_socket(websocket, json!({
                 "action": "renamerDone",
+                "report": report
+            })).await.ok();
+        },
+        // Revert rename from log
+        Action::RenamerUndo { log } => {
+            let results = tokio::task::spawn_blocking(move || {
+                RenameLog::undo(&log)
+            }).await??;
+            let failed = results.iter().filter_map(|(path, r)| r.as_ref().err().map(|e| json!({
+                "path": path,
+                "error": e.to_string()
+            }))).collect::<Vec<_>>();
+            send_socket(websocket, json!({
+                "action": "renamerUndo",
+                "reverted": results.len() - failed.len(),
+                "failed": failed
+            })).await.ok();
+        },
+        // Parse tags from filenames
+        Action::FilenameTagsPreview { config } => {
+            let parser = FilenameParser::new(&config.template)?;
+            let files = AudioFileInfo::get_file_list(&config.path, config.subfolders);
+            send_socket(websocket, json!({
+                "action": "filenameTagsPreview",
+                "files": parser.preview(&files),
+            })).await.ok();
+        },
+        // Write tags parsed from filenames
+        Action::FilenameTagsWrite { config } => {
+            let parser = FilenameParser::new(&config.template)?;
+            let results = tokio::task::spawn_blocking(move || {
+                let files = AudioFileInfo::get_file_list(&config.path, config.subfolders);
+                parser.write_files(&files, &config)
+            }).await?;
+            let failed = results.iter().filter_map(|(path, r)| r.as_ref().err().map(|e| json!({
+                "path": path,
+                "error": e.to_string()
+            }))).collect::<Vec<_>>();
+            send_socket(websocket, json!({
+                "action": "filenameTagsWrite",
+                "written": results.len() - failed.len(),
+                "failed": failed
             })).await.ok();
         },
         // File browser list dir
@@ -639,6 +772,50 @@ async fn handle_message(text: &str, websocket: &mut WebSocket, context: &mut Soc
         },
 
 
+        // Low confidence matches waiting for review
+        Action::ReviewQueue => {
+            let items = tokio::task::spawn_blocking(|| ReviewQueue::open()?.list()).await??;
+            send_socket(websocket, json!({
+                "action": "reviewQueue",
+                "items": items
+            })).await.ok();
+        },
+        // Write the selected candidate
+        Action::ReviewAccept { path, candidate, config } => {
+            let result = tokio::task::spawn_blocking(move || ReviewQueue::open()?.accept(path, candidate, &config)).await?;
+            send_review_resolved(websocket, result).await;
+        },
+        // Leave the file untagged
+        Action::ReviewSkip { path } => {
+            let result = tokio::task::spawn_blocking(move || ReviewQueue::open()?.skip(path)).await?;
+            send_review_resolved(websocket, result).await;
+        },
+
+        // Duplicate finder
+        Action::FindDuplicates { config } => {
+            let groups = tokio::task::spawn_blocking(move || Duplicates::find(&config)).await??;
+            send_socket(websocket, json!({
+                "action": "duplicates",
+                "groups": groups
+            })).await.ok();
+        },
+        // Keep the best copy of each group
+        Action::TrashDuplicates { groups } => {
+            let trashed = tokio::task::spawn_blocking(move || Duplicates::trash(&groups)).await??;
+            send_socket(websocket, json!({
+                "action": "duplicatesTrashed",
+                "paths": trashed
+            })).await.ok();
+        },
+        // Loudness analysis
+        Action::ReplayGain { config } => {
+            let results = tokio::task::spawn_blocking(move || ReplayGain::run(&config)).await??;
+            send_socket(websocket, json!({
+                "action": "replayGain",
+                "results": results
+            })).await.ok();
+        },
+
         Action::RepoManifest => {
             send_socket(websocket, json!({
                 "action": "repoManifest",
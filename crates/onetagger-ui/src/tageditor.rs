@@ -8,6 +8,8 @@ use image::{GenericImageView, ImageReader};
 
 use onetagger_tag::{AudioFileFormat, CoverType, Picture, Tag};
 use onetagger_tag::id3::{ID3Comment, ID3Popularimeter};
+use onetagger_tag::serato::SeratoMarkers;
+use onetagger_tagger::{SupportedTag, TagProvenance, PROVENANCE_TAG};
 
 pub struct TagEditor {}
 
@@ -19,7 +21,7 @@ impl TagEditor {
         let id3_binary = ID3Binary::from_tag(&tag_wrap);
         // Load tags
         let tag = tag_wrap.tag();
-        let tags = tag.all_tags().iter().map(|(k, v)| {
+        let tags = tag.all_tags().iter().filter(|(k, _)| !SeratoMarkers::is_serato_tag(k)).map(|(k, v)| {
             (k.to_owned(), v.join(",").replace('\0', ""))
         }).collect();
 
@@ -32,12 +34,14 @@ impl TagEditor {
         }
 
         Ok(TagEditorFile {
+            provenance: tag.get_raw(PROVENANCE_TAG).map(|v| TagProvenance::parse(&v)).unwrap_or_default(),
             tags,
             filename: filename.to_owned(),
             format: tag_wrap.format(),
             path: path.as_ref().to_owned(),
             images,
-            id3: id3_binary
+            id3: id3_binary,
+            serato: SeratoMarkers::read(&tag_wrap),
         })
     }
 
@@ -70,7 +74,11 @@ pub struct TagEditorFile {
     pub format: AudioFileFormat,
     pub path: PathBuf,
     pub images: Vec<TagEditorImage>,
-    pub id3: Option<ID3Binary>
+    pub id3: Option<ID3Binary>,
+    /// Which platform supplied which tag
+    pub provenance: HashMap<SupportedTag, TagProvenance>,
+    /// Serato cue points, loops and beatgrid
+    pub serato: Option<SeratoMarkers>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]